@@ -1,6 +1,9 @@
-use cachegrab::{buffer, incremental::Incremental, memo, thunk, Dcg};
+use cachegrab::{
+    buffer, bulk::NodeSpec, incremental::Incremental, memo, sync::SyncDcg, thunk, Buffer, Dcg,
+};
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use rand::{prelude::SliceRandom, rngs::SmallRng, SeedableRng};
+use std::{sync::Mutex, thread};
 
 fn internals(c: &mut Criterion) {
     let dcg = Dcg::new();
@@ -193,6 +196,99 @@ fn filter_random_letter(c: &mut Criterion) {
     raw_group.finish();
 }
 
+// Builds a Var followed by `size - 1` Buffers, each after the first depending on the one before
+// it and the one halfway to it, one node at a time.
+fn naive_graph(size: usize) -> (Dcg, Buffer<u64>) {
+    let dcg = Dcg::new();
+    let root = dcg.var(1u64);
+    let mut nodes = vec![buffer!(dcg, root => root)];
+    for i in 1..size - 1 {
+        let (a, b) = (nodes[i - 1].clone(), nodes[i / 2].clone());
+        nodes.push(buffer!(dcg, (a, b) => a.wrapping_add(b)));
+    }
+    (dcg, nodes.pop().unwrap())
+}
+
+// Builds the same graph as `naive_graph` with `Dcg::build_bulk`.
+fn bulk_graph(size: usize) -> (Dcg, Buffer<u64>) {
+    let dcg = Dcg::new();
+    let mut specs = vec![NodeSpec::Var(1u64), NodeSpec::buffer(vec![0], |v| v[0])];
+    for i in 1..size - 1 {
+        specs.push(NodeSpec::buffer(vec![i, i / 2 + 1], |v: &[u64]| {
+            v[0].wrapping_add(v[1])
+        }));
+    }
+    let mut nodes = dcg.build_bulk(specs).unwrap();
+    let last = nodes.pop().unwrap().as_buffer().unwrap().clone();
+    (dcg, last)
+}
+
+fn bulk_construction(c: &mut Criterion) {
+    let sizes = [100, 1000, 10000];
+    let mut group = c.benchmark_group("Bulk Construction");
+
+    for size in sizes.iter() {
+        // Both variants declare every edge, so they must build identical graphs.
+        let (naive, naive_last) = naive_graph(*size);
+        let (bulk, bulk_last) = bulk_graph(*size);
+        assert_eq!(
+            (naive.node_count(), naive.edge_count(), naive_last.read()),
+            (bulk.node_count(), bulk.edge_count(), bulk_last.read())
+        );
+
+        group.bench_with_input(BenchmarkId::new("Naive", size), size, |b, &size| {
+            b.iter(|| black_box(naive_graph(size)))
+        });
+        group.bench_with_input(BenchmarkId::new("Bulk", size), size, |b, &size| {
+            b.iter(|| black_box(bulk_graph(size)))
+        });
+    }
+
+    group.finish();
+}
+
+fn sync_disjoint_writes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Sync Disjoint Writes");
+
+    // Each thread repeatedly writes its own Var and reads its own Buffer, whose recomputation
+    // takes a while. "Coarse Lock" holds one lock across each write and read, as the whole-graph
+    // RwLock did. With enough cores, "Fine-Grained" with 2 threads should take about as long as
+    // with 1, while "Coarse Lock" takes about twice as long.
+    for threads in [1, 2] {
+        let dcg = SyncDcg::new();
+        let pairs: Vec<_> = (0..threads)
+            .map(|_| {
+                let var = dcg.var(0u64);
+                let buffer = dcg.buffer(&[var.node()], {
+                    let var = var.clone();
+                    move || (0..2_000u64).fold(var.read(), |acc, i| acc.wrapping_mul(31) ^ i)
+                });
+                (var, buffer)
+            })
+            .collect();
+        let coarse = Mutex::new(());
+        for (name, lock) in [("Fine-Grained", None), ("Coarse Lock", Some(&coarse))] {
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, _| {
+                b.iter(|| {
+                    thread::scope(|scope| {
+                        for (var, buffer) in &pairs {
+                            scope.spawn(move || {
+                                for _ in 0..100 {
+                                    let _guard = lock.map(|lock| lock.lock().unwrap());
+                                    var.modify(|value| value + 1);
+                                    black_box(buffer.read());
+                                }
+                            });
+                        }
+                    })
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
 // fn depth_first_search() {
 //     let dcg = Dcg::new();
 //     let v = 100;
@@ -217,5 +313,11 @@ fn filter_random_letter(c: &mut Criterion) {
 //     println!("{:?}", graph);
 // }
 
-criterion_group!(benches, internals, filter_random_letter);
+criterion_group!(
+    benches,
+    internals,
+    filter_random_letter,
+    bulk_construction,
+    sync_disjoint_writes
+);
 criterion_main!(benches);
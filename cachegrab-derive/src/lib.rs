@@ -0,0 +1,85 @@
+#![warn(missing_docs)]
+
+//! Derive macros for `cachegrab`, re-exported by it with the `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields};
+
+/// Derives `into_cells` for a struct with named fields, turning each field into a `Var` of a
+/// generated `<Name>Cells` struct, which can write all its `Var`s at once with `set_all`.
+///
+/// Every field's type must be `PartialEq + Clone + 'static`.
+#[proc_macro_derive(DcgCells)]
+pub fn derive_dcg_cells(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    data.fields.span(),
+                    "DcgCells requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "DcgCells can only be derived for structs",
+            ))
+        }
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let cells = format_ident!("{}Cells", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let generics = &input.generics;
+    let names: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let vises = fields.iter().map(|field| &field.vis);
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let cells_doc = format!("The [`Var`](::cachegrab::Var)s created by `{name}::into_cells`.");
+
+    Ok(quote! {
+        #[doc = #cells_doc]
+        #vis struct #cells #generics #where_clause {
+            #(#vises #names: ::cachegrab::Var<#types>,)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Creates a [`Var`](::cachegrab::Var) in `dcg` for each field, holding its value.
+            #vis fn into_cells(self, dcg: &::cachegrab::Dcg) -> #cells #ty_generics {
+                #cells {
+                    #(#names: dcg.var(self.#names),)*
+                }
+            }
+        }
+
+        impl #impl_generics #cells #ty_generics #where_clause {
+            /// Writes each field of `values` into its [`Var`](::cachegrab::Var) together, by
+            /// [staging](::cachegrab::Dcg::stage) them and
+            /// [committing](::cachegrab::Dcg::commit_staged) every staged write, returning how
+            /// many [`Var`](::cachegrab::Var)s changed.
+            #vis fn set_all(&self, dcg: &::cachegrab::Dcg, values: #name #ty_generics) -> usize {
+                #(dcg.stage(&self.#names, values.#names);)*
+                dcg.commit_staged()
+            }
+
+            /// Returns the current value of every [`Var`](::cachegrab::Var), without reading
+            /// them.
+            #vis fn values(&self) -> #name #ty_generics {
+                #name {
+                    #(#names: ::cachegrab::incremental::Incremental::latest(&*self.#names),)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,279 @@
+//! Building many nodes at once from a list of [`NodeSpec`]s with [`Dcg::build_bulk`].
+//!
+//! Specs refer to their dependencies by their position in the list, so a graph generated from a
+//! schema can be described before any of its nodes exist, in any order.
+
+use std::{error, fmt, rc::Rc};
+
+use petgraph::{
+    algo::toposort,
+    graph::{DiGraph, NodeIndex},
+};
+
+use crate::{
+    incremental::Incremental, Buffer, Dcg, Event, Node, NodeKind, RawBuffer, RawThunk, RawVar, Var,
+};
+
+/// A node to be built by [`Dcg::build_bulk`].
+pub enum NodeSpec<T> {
+    /// A [`Var`] holding the value.
+    Var(T),
+    /// A [`Buffer`] computing its value from the values of its dependencies.
+    Buffer {
+        /// The positions of the dependencies' specs in the list, in the order their values are
+        /// passed to `f`.
+        dependencies: Vec<usize>,
+        /// Computes the [`Buffer`]'s value from its dependencies' values.
+        f: Compute<T>,
+    },
+}
+
+/// Computes a [`Buffer`]'s value from its dependencies' values.
+pub type Compute<T> = Box<dyn Fn(&[T]) -> T>;
+
+impl<T> NodeSpec<T> {
+    /// Returns a [`Buffer`](NodeSpec::Buffer) spec computing its value with `f`.
+    pub fn buffer<F>(dependencies: Vec<usize>, f: F) -> Self
+    where
+        F: Fn(&[T]) -> T + 'static,
+    {
+        NodeSpec::Buffer {
+            dependencies,
+            f: Box::new(f),
+        }
+    }
+
+    fn kind(&self) -> NodeKind {
+        match self {
+            NodeSpec::Var(_) => NodeKind::Var,
+            NodeSpec::Buffer { .. } => NodeKind::Buffer,
+        }
+    }
+
+    fn dependencies(&self) -> &[usize] {
+        match self {
+            NodeSpec::Var(_) => &[],
+            NodeSpec::Buffer { dependencies, .. } => dependencies,
+        }
+    }
+}
+
+/// A node built by [`Dcg::build_bulk`].
+pub enum BulkNode<T> {
+    /// Built from a [`NodeSpec::Var`].
+    Var(Var<T>),
+    /// Built from a [`NodeSpec::Buffer`].
+    Buffer(Buffer<T>),
+}
+
+impl<T: Clone + 'static> BulkNode<T> {
+    /// Returns the node.
+    pub fn node(&self) -> &Node {
+        match self {
+            BulkNode::Var(var) => var.node(),
+            BulkNode::Buffer(buffer) => buffer.node(),
+        }
+    }
+
+    /// Returns the node's value, [reading](Incremental::read) it.
+    pub fn read(&self) -> T {
+        match self {
+            BulkNode::Var(var) => var.read(),
+            BulkNode::Buffer(buffer) => buffer.read(),
+        }
+    }
+
+    /// Returns the [`Var`], if the node is one.
+    pub fn as_var(&self) -> Option<&Var<T>> {
+        match self {
+            BulkNode::Var(var) => Some(var),
+            BulkNode::Buffer(_) => None,
+        }
+    }
+
+    /// Returns the [`Buffer`], if the node is one.
+    pub fn as_buffer(&self) -> Option<&Buffer<T>> {
+        match self {
+            BulkNode::Var(_) => None,
+            BulkNode::Buffer(buffer) => Some(buffer),
+        }
+    }
+}
+
+impl<T> Clone for BulkNode<T> {
+    fn clone(&self) -> Self {
+        match self {
+            BulkNode::Var(var) => BulkNode::Var(var.clone()),
+            BulkNode::Buffer(buffer) => BulkNode::Buffer(buffer.clone()),
+        }
+    }
+}
+
+/// Errors reported by [`Dcg::build_bulk`], identifying specs by their position in the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkError {
+    /// A spec depends on a position past the end of the list.
+    UnknownDependency {
+        /// The depending spec.
+        spec: usize,
+        /// The position it depends on.
+        dependency: usize,
+    },
+    /// A spec depends on itself, directly or transitively.
+    Cycle {
+        /// A spec on the cycle.
+        spec: usize,
+    },
+}
+
+impl fmt::Display for BulkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BulkError::UnknownDependency { spec, dependency } => write!(
+                f,
+                "spec {} depends on spec {}, which does not exist",
+                spec, dependency
+            ),
+            BulkError::Cycle { spec } => write!(f, "spec {} depends on itself", spec),
+        }
+    }
+}
+
+impl error::Error for BulkError {}
+
+impl Dcg {
+    /// Builds a node for each of `specs`, returning them in the same order.
+    ///
+    /// Every spec is validated before anything is built, so on error the [`Dcg`] is left
+    /// untouched. Nodes are then added to the graph in one pass and their dependency edges in a
+    /// second, with capacity for both reserved up front, before the handles are created in
+    /// dependency order. Specs may come before their dependencies, but building is cheapest when
+    /// none do, as no sort is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{bulk::NodeSpec, Dcg};
+    ///
+    /// let dcg = Dcg::default();
+    /// let nodes = dcg
+    ///     .build_bulk(vec![
+    ///         NodeSpec::buffer(vec![1, 2], |values| values[0] + values[1]),
+    ///         NodeSpec::Var(1),
+    ///         NodeSpec::Var(2),
+    ///     ])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(nodes[0].read(), 3);
+    /// nodes[1].as_var().unwrap().write(10);
+    /// assert_eq!(nodes[0].read(), 12);
+    /// ```
+    pub fn build_bulk<T>(&self, specs: Vec<NodeSpec<T>>) -> Result<Vec<BulkNode<T>>, BulkError>
+    where
+        T: Clone + 'static,
+    {
+        let order = build_order(&specs)?;
+        let edges: Vec<_> = specs
+            .iter()
+            .enumerate()
+            .flat_map(|(spec, node)| {
+                node.dependencies()
+                    .iter()
+                    .map(move |&dependency| (dependency, spec))
+            })
+            .collect();
+
+        let indices: Vec<_> = {
+            let mut graph = self.inner.graph.borrow_mut();
+            let indices: Vec<_> = specs.iter().map(|_| graph.add_node(true)).collect();
+            for &(from, to) in &edges {
+                graph.add_edge(indices[from], indices[to], ());
+            }
+            indices
+        };
+        self.inner.kinds.borrow_mut().extend(
+            indices
+                .iter()
+                .zip(&specs)
+                .map(|(&idx, spec)| (idx, spec.kind())),
+        );
+
+        let mut specs: Vec<_> = specs.into_iter().map(Some).collect();
+        let mut built: Vec<Option<BulkNode<T>>> = vec![None; specs.len()];
+        for i in order {
+            let node = Node::at(self.inner.clone(), indices[i]);
+            built[i] = Some(match specs[i].take().expect("each spec is built once") {
+                NodeSpec::Var(value) => BulkNode::Var(Rc::new(RawVar::with_node(node, value))),
+                NodeSpec::Buffer { dependencies, f } => {
+                    let inputs: Vec<_> = dependencies
+                        .iter()
+                        .map(|&dependency| {
+                            built[dependency]
+                                .clone()
+                                .expect("dependencies are built first")
+                        })
+                        .collect();
+                    BulkNode::Buffer(RawBuffer::with_thunk(RawThunk::with_node(
+                        node,
+                        move || {
+                            let values: Vec<_> = inputs.iter().map(BulkNode::read).collect();
+                            f(&values)
+                        },
+                    )))
+                }
+            });
+        }
+        let built: Vec<_> = built
+            .into_iter()
+            .map(|node| node.expect("every spec is built"))
+            .collect();
+
+        for (idx, spec) in indices.iter().zip(&built) {
+            self.inner.emit(Event::NodeAdded {
+                idx: *idx,
+                kind: spec.node().kind(),
+            });
+        }
+        for (from, to) in edges {
+            self.inner.emit(Event::EdgeAdded {
+                from: indices[from],
+                to: indices[to],
+            });
+        }
+        if let Some(node) = built.first() {
+            node.node().debug_check();
+        }
+        Ok(built)
+    }
+}
+
+/// Returns the positions of `specs` in an order where each comes after its dependencies.
+fn build_order<T>(specs: &[NodeSpec<T>]) -> Result<Vec<usize>, BulkError> {
+    let mut ordered = true;
+    for (spec, node) in specs.iter().enumerate() {
+        for &dependency in node.dependencies() {
+            if dependency >= specs.len() {
+                return Err(BulkError::UnknownDependency { spec, dependency });
+            }
+            ordered &= dependency < spec;
+        }
+    }
+    // Generated specs usually come after their dependencies already.
+    if ordered {
+        return Ok((0..specs.len()).collect());
+    }
+    let mut graph = DiGraph::<(), ()>::with_capacity(specs.len(), 0);
+    for _ in specs {
+        graph.add_node(());
+    }
+    for (spec, node) in specs.iter().enumerate() {
+        for &dependency in node.dependencies() {
+            graph.add_edge(NodeIndex::new(dependency), NodeIndex::new(spec), ());
+        }
+    }
+    toposort(&graph, None)
+        .map(|order| order.into_iter().map(NodeIndex::index).collect())
+        .map_err(|cycle| BulkError::Cycle {
+            spec: cycle.node_id().index(),
+        })
+}
@@ -0,0 +1,84 @@
+//! Change classes: [`Var`] writes tagged with a class with [`RawVar::write_classed`], and
+//! [`Buffer`]s only dirtied by the classes they're sensitive to, created with
+//! [`Dcg::buffer_sensitive_to`].
+
+use crate::{incremental::Incremental, Buffer, Dcg, DcgError, RawVar};
+
+/// The class of plain writes, and the mask of nodes sensitive to every class: all of them.
+pub(crate) const ALL_CHANGES: u8 = u8::MAX;
+
+impl Dcg {
+    /// Like [`buffer`](Dcg::buffer), but only dirtied by changes whose
+    /// [class](RawVar::write_classed) intersects `mask`.
+    ///
+    /// Changes outside `mask` stop at the [`Buffer`]: it keeps its value, and its dependents are
+    /// only dirtied if they depend on the change through some other path. Plain writes belong
+    /// to every class, so always dirty it. Under
+    /// [`Versions`](crate::VerificationStrategy::Versions), the [`Buffer`] compares the versions
+    /// of its dependencies when read, so every change acts as if in every class.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// const HARD: u8 = 0b01;
+    /// const SOFT: u8 = 0b10;
+    ///
+    /// let dcg = Dcg::default();
+    /// let events = dcg.var(vec![1, 2]);
+    /// let analytics = dcg.buffer_sensitive_to(&events, HARD, {
+    ///     let events = events.clone();
+    ///     move || events.read().len()
+    /// });
+    ///
+    /// assert_eq!(analytics.read(), 2);
+    /// events.write_classed(vec![1, 2, 3], SOFT);
+    /// assert!(analytics.is_clean());
+    /// assert_eq!(analytics.read(), 2);
+    /// events.write_classed(vec![1], HARD);
+    /// assert_eq!(analytics.read(), 1);
+    /// ```
+    pub fn buffer_sensitive_to<P, F, T>(&self, params: &P, mask: u8, f: F) -> Buffer<T>
+    where
+        P: Incremental,
+        F: Fn() -> T + 'static,
+        T: Clone + 'static,
+    {
+        let buffer = self.buffer(params, f);
+        if mask != ALL_CHANGES {
+            self.inner
+                .sensitivities
+                .borrow_mut()
+                .insert(buffer.node().idx, mask);
+        }
+        buffer
+    }
+}
+
+impl<T> RawVar<T>
+where
+    T: PartialEq + Clone + 'static,
+{
+    /// Like [`write`](RawVar::write), but the change belongs to the classes in the `class`
+    /// bitmask, and only dirties dependents [sensitive](Dcg::buffer_sensitive_to) to one of them.
+    ///
+    /// [`write`](RawVar::write) is `write_classed` with every class.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`write`](RawVar::write); see
+    /// [`try_write_classed`](RawVar::try_write_classed).
+    pub fn write_classed(&self, new: T, class: u8) -> T {
+        self.try_write_classed(new, class)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`write_classed`](RawVar::write_classed), but returns an error instead of panicking,
+    /// as [`try_write`](RawVar::try_write) does.
+    pub fn try_write_classed(&self, new: T, class: u8) -> Result<T, DcgError> {
+        self.node.check_mutable()?;
+        self.validate(&new)?;
+        Ok(self.write_unvalidated(new, class).previous)
+    }
+}
@@ -0,0 +1,155 @@
+//! [`Clock`]s, and [`Buffer`]s that expire after a time to live, created with
+//! [`Dcg::memo_with_ttl`].
+
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{incremental::Incremental, Buffer, Dcg, DcgError, Node};
+
+/// A source of the current time, used by a [`Dcg`] to expire [`Buffer`]s created with
+/// [`Dcg::memo_with_ttl`] and to time [`Dcg::clean_for`].
+///
+/// [`Dcg`]s use [`SystemClock`] unless given another with [`Dcg::set_clock`], such as
+/// [`MockClock`](crate::testing::MockClock) in tests.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// A [`Clock`] reading the system time with [`Instant::now`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A [`Buffer`]'s time to live, and when its value was last computed.
+pub(crate) struct Expiry {
+    ttl: Duration,
+    computed_at: Option<Instant>,
+}
+
+impl Dcg {
+    /// Replaces the [`Clock`] used to expire [`Buffer`]s created with
+    /// [`memo_with_ttl`](Dcg::memo_with_ttl) and to time [`clean_for`](Dcg::clean_for).
+    pub fn set_clock(&self, clock: Rc<dyn Clock>) {
+        self.inner.clock.replace(Some(clock));
+    }
+
+    /// Returns the current time according to the [`Dcg`]'s [`Clock`].
+    pub(crate) fn now(&self) -> Instant {
+        let clock = self.inner.clock.borrow().clone();
+        clock.map_or_else(Instant::now, |clock| clock.now())
+    }
+
+    /// Creates a dirty [`Buffer`] of the value returned by `f`, adding incoming dependency edges
+    /// from `params`, whose value expires `ttl` after it was computed.
+    ///
+    /// Reading an expired [`Buffer`] dirties it and its dependents, then recomputes it, even if
+    /// none of `params` changed. This suits values derived from state outside the [`Dcg`], such
+    /// as the time or a rate read in `f`. Until it is read, an expired [`Buffer`] is not
+    /// [dirty](Incremental::is_dirty).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{rc::Rc, time::Duration};
+    ///
+    /// use cachegrab::{Dcg, incremental::Incremental, testing::MockClock};
+    ///
+    /// let dcg = Dcg::default();
+    /// let clock = Rc::new(MockClock::default());
+    /// dcg.set_clock(clock.clone());
+    /// let rate = dcg.memo_with_ttl(&(), || 1.25, Duration::from_secs(60));
+    /// let probe = dcg.probe(&rate);
+    ///
+    /// rate.read();
+    /// clock.advance(Duration::from_secs(59));
+    /// rate.read();
+    /// assert_eq!(probe.recomputations(), 1);
+    /// clock.advance(Duration::from_secs(1));
+    /// rate.read();
+    /// assert_eq!(probe.recomputations(), 2);
+    /// ```
+    pub fn memo_with_ttl<I, F, T>(&self, params: &I, f: F, ttl: Duration) -> Buffer<T>
+    where
+        I: Incremental,
+        F: Fn() -> T + 'static,
+        T: Clone + 'static,
+    {
+        let buffer = self.buffer(params, f);
+        self.inner.expiries.borrow_mut().insert(
+            buffer.node().idx,
+            Expiry {
+                ttl,
+                computed_at: None,
+            },
+        );
+        buffer
+    }
+
+    /// Expires the value of `node`, created with [`memo_with_ttl`](Dcg::memo_with_ttl), now,
+    /// dirtying it and its dependents. Returns `false`, doing nothing, if `node` has no time to
+    /// live.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`], or with
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) if called while a node is
+    /// being evaluated.
+    pub fn expire_now(&self, node: &Node) -> bool {
+        assert!(
+            Rc::ptr_eq(&node.inner, &self.inner),
+            "node must belong to the Dcg"
+        );
+        if let Some(evaluation) = self.inner.evaluating.borrow().last() {
+            panic!(
+                "{}",
+                DcgError::MutationDuringEvaluation {
+                    evaluating: evaluation.node,
+                    target: node.idx,
+                }
+            );
+        }
+        if !self.inner.expiries.borrow().contains_key(&node.idx) {
+            return false;
+        }
+        node.dirty_dependents();
+        node.debug_check();
+        true
+    }
+}
+
+impl Node {
+    /// Dirties the node and its dependents if its value has outlived its time to live.
+    pub(crate) fn expire_if_stale(&self) {
+        let dcg = Dcg {
+            inner: self.inner.clone(),
+        };
+        let expired = match self.inner.expiries.borrow().get(&self.idx) {
+            Some(Expiry {
+                ttl,
+                computed_at: Some(computed_at),
+            }) => dcg.now().saturating_duration_since(*computed_at) >= *ttl,
+            _ => false,
+        };
+        if expired {
+            self.dirty_dependents();
+        }
+    }
+
+    /// Records that the node's value was just computed, restarting its time to live.
+    pub(crate) fn restart_ttl(&self) {
+        let dcg = Dcg {
+            inner: self.inner.clone(),
+        };
+        if let Some(expiry) = self.inner.expiries.borrow_mut().get_mut(&self.idx) {
+            expiry.computed_at = Some(dcg.now());
+        }
+    }
+}
@@ -0,0 +1,705 @@
+//! Collection-valued cells whose dependents depend on parts of the collection, rather than all
+//! of it.
+//!
+//! A [`VecCell`] stores its elements in chunks, each held by a hidden [`Var`], so a node reading
+//! a range of elements only depends on, and is only dirtied by changes to, the chunks
+//! overlapping that range. Likewise, a [`MapCell`] holds each entry in a hidden [`Var`], with
+//! another tracking its set of keys. A [`Record`] groups [`Var`]s that are written together.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    ops::Range,
+    rc::Rc,
+};
+
+use crate::{
+    incremental::Incremental, stage_write, BatchReport, Buffer, Dcg, DcgError, Inner, Var,
+};
+
+/// The number of elements per chunk used by [`Dcg::vec_cell`].
+pub const DEFAULT_CHUNK_SIZE: usize = 64;
+
+/// Reference-counted [`RawVecCell`].
+pub type VecCell<T> = Rc<RawVecCell<T>>;
+
+/// A [`Vec`] whose elements are tracked in chunks, as created by [`Dcg::vec_cell`].
+pub struct RawVecCell<T> {
+    inner: Rc<Inner>,
+    chunk_size: usize,
+    /// Chunk `i` holds elements `i * chunk_size..(i + 1) * chunk_size`. Chunks past the end are
+    /// empty, created for ranges read beyond it.
+    chunks: RefCell<Vec<Var<Vec<T>>>>,
+    /// The number of chunks, so nodes reading every chunk learn of new ones.
+    chunk_count: Var<usize>,
+}
+
+impl Dcg {
+    /// Creates a [`VecCell`] holding `initial`, in chunks of [`DEFAULT_CHUNK_SIZE`] elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let cell = dcg.vec_cell((0..1000).collect::<Vec<i64>>());
+    /// let head = dcg.memo_over_range(&cell, 0..10, |head| head.iter().sum::<i64>());
+    /// let tail = dcg.memo_over_range(&cell, 990..1000, |tail| tail.iter().sum::<i64>());
+    ///
+    /// assert_eq!((head.read(), tail.read()), (45, 9945));
+    /// cell.set_elem(995, 0);
+    /// assert!(head.is_clean());
+    /// assert_eq!(tail.read(), 8950);
+    /// ```
+    pub fn vec_cell<T>(&self, initial: Vec<T>) -> VecCell<T>
+    where
+        T: PartialEq + Clone + 'static,
+    {
+        self.vec_cell_with_chunk_size(initial, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates a [`VecCell`] holding `initial`, in chunks of `chunk_size` elements. Smaller
+    /// chunks dirty fewer dependents per change, at the cost of more nodes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn vec_cell_with_chunk_size<T>(&self, initial: Vec<T>, chunk_size: usize) -> VecCell<T>
+    where
+        T: PartialEq + Clone + 'static,
+    {
+        assert!(chunk_size > 0, "chunks must hold at least one element");
+        let chunks: Vec<_> = initial
+            .chunks(chunk_size)
+            .map(|chunk| self.var(chunk.to_vec()))
+            .collect();
+        Rc::new(RawVecCell {
+            inner: self.inner.clone(),
+            chunk_size,
+            chunk_count: self.var(chunks.len()),
+            chunks: RefCell::new(chunks),
+        })
+    }
+
+    /// Creates a dirty [`Buffer`] of `f` applied to the elements of `cell` in `range`, which is
+    /// only dirtied by changes to the chunks overlapping `range`.
+    ///
+    /// Elements of `range` beyond the end of `cell` are left out, and included once they are
+    /// [pushed](RawVecCell::push).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` belongs to another [`Dcg`].
+    pub fn memo_over_range<T, U, F>(
+        &self,
+        cell: &VecCell<T>,
+        range: Range<usize>,
+        f: F,
+    ) -> Buffer<U>
+    where
+        T: PartialEq + Clone + 'static,
+        U: Clone + 'static,
+        F: Fn(&[T]) -> U + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&cell.inner, &self.inner),
+            "vec cells must belong to the Dcg"
+        );
+        let size = cell.chunk_size;
+        let first = range.start / size;
+        let chunks: Vec<_> = if range.is_empty() {
+            Vec::new()
+        } else {
+            cell.reserve_chunks(range.end.div_ceil(size));
+            cell.chunks.borrow()[first..range.end.div_ceil(size)].to_vec()
+        };
+        self.buffer(&chunks.clone(), move || {
+            let mut elements = Vec::with_capacity(range.len());
+            for (offset, chunk) in chunks.iter().enumerate() {
+                let start = (first + offset) * size;
+                let chunk = chunk.read();
+                let from = range.start.saturating_sub(start).min(chunk.len());
+                let to = (range.end - start).min(chunk.len());
+                elements.extend_from_slice(&chunk[from..to]);
+            }
+            f(&elements)
+        })
+    }
+}
+
+impl Dcg {
+    /// Creates a dirty [`Buffer`] of `f` applied to every element of `cell`, in order.
+    ///
+    /// The [`Buffer`] remembers each element's image, so when it is recomputed `f` is only
+    /// applied to elements whose value changed. Elements shifted by
+    /// [`remove`](RawVecCell::remove) count as changed wherever the new value at a position
+    /// differs from the old.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let cell = dcg.vec_cell(vec![1, 2, 3]);
+    /// let doubled = dcg.map_collection(&cell, |x| x * 2);
+    ///
+    /// assert_eq!(doubled.read(), vec![2, 4, 6]);
+    /// cell.push(4);
+    /// assert_eq!(doubled.read(), vec![2, 4, 6, 8]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` belongs to another [`Dcg`].
+    pub fn map_collection<T, U, F>(&self, cell: &VecCell<T>, f: F) -> Buffer<Vec<U>>
+    where
+        T: PartialEq + Clone + 'static,
+        U: Clone + 'static,
+        F: Fn(&T) -> U + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&cell.inner, &self.inner),
+            "vec cells must belong to the Dcg"
+        );
+        let cell = cell.clone();
+        let cache = RefCell::default();
+        self.dynamic_buffer(move || {
+            cell.patch(&cache, &f)
+                .into_iter()
+                .map(|(_, image)| image)
+                .collect()
+        })
+    }
+
+    /// Creates a dirty [`Buffer`] of the elements of `cell` satisfying `predicate`, in order.
+    ///
+    /// Like [`map_collection`](Dcg::map_collection), `predicate` is only applied to elements
+    /// whose value changed since the [`Buffer`] was last computed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let cell = dcg.vec_cell(vec![1, 2, 3, 4]);
+    /// let even = dcg.filter_collection(&cell, |x| x % 2 == 0);
+    ///
+    /// assert_eq!(even.read(), vec![2, 4]);
+    /// cell.set_elem(0, 6);
+    /// assert_eq!(even.read(), vec![6, 2, 4]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` belongs to another [`Dcg`].
+    pub fn filter_collection<T, F>(&self, cell: &VecCell<T>, predicate: F) -> Buffer<Vec<T>>
+    where
+        T: PartialEq + Clone + 'static,
+        F: Fn(&T) -> bool + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&cell.inner, &self.inner),
+            "vec cells must belong to the Dcg"
+        );
+        let cell = cell.clone();
+        let cache = RefCell::default();
+        self.dynamic_buffer(move || {
+            cell.patch(&cache, &predicate)
+                .into_iter()
+                .filter_map(|(element, kept)| kept.then_some(element))
+                .collect()
+        })
+    }
+}
+
+impl<T: PartialEq + Clone + 'static> RawVecCell<T> {
+    /// Creates empty chunks until there are at least `count`.
+    fn reserve_chunks(&self, count: usize) {
+        let dcg = Dcg {
+            inner: self.inner.clone(),
+        };
+        let mut chunks = self.chunks.borrow_mut();
+        if chunks.len() >= count {
+            return;
+        }
+        while chunks.len() < count {
+            chunks.push(dcg.var(Vec::new()));
+        }
+        drop(chunks);
+        self.chunk_count.write(count);
+    }
+
+    /// Reads every chunk, applying `f` to each element whose value differs from the one at its
+    /// position when last read, and reusing the result from `cache` for the others.
+    fn patch<V, F>(&self, cache: &RefCell<Vec<(Vec<T>, Vec<V>)>>, f: F) -> Vec<(T, V)>
+    where
+        V: Clone,
+        F: Fn(&T) -> V,
+    {
+        let chunks = self.chunks.borrow()[..self.chunk_count.read()].to_vec();
+        let mut cache = cache.borrow_mut();
+        cache.resize_with(chunks.len(), Default::default);
+        let mut patched = Vec::new();
+        for (chunk, (sources, images)) in chunks.iter().zip(cache.iter_mut()) {
+            let elements = chunk.read();
+            let updated: Vec<_> = elements
+                .iter()
+                .enumerate()
+                .map(|(i, element)| match sources.get(i) {
+                    Some(source) if source == element => images[i].clone(),
+                    _ => f(element),
+                })
+                .collect();
+            patched.extend(elements.iter().cloned().zip(updated.iter().cloned()));
+            *sources = elements;
+            *images = updated;
+        }
+        patched
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        let full = chunks
+            .iter()
+            .take_while(|chunk| chunk.value.borrow().len() == self.chunk_size)
+            .count();
+        full * self.chunk_size
+            + chunks
+                .get(full)
+                .map_or(0, |chunk| chunk.value.borrow().len())
+    }
+
+    /// Returns `true` if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the element at `index`, if any, without recording a read.
+    pub fn get(&self, index: usize) -> Option<T> {
+        let chunks = self.chunks.borrow();
+        let chunk = chunks.get(index / self.chunk_size)?;
+        let element = chunk.value.borrow().get(index % self.chunk_size).cloned();
+        element
+    }
+
+    /// Returns a copy of the elements, without recording a read.
+    pub fn to_vec(&self) -> Vec<T> {
+        self.chunks
+            .borrow()
+            .iter()
+            .flat_map(|chunk| chunk.latest())
+            .collect()
+    }
+
+    /// Replaces the element at `index` with `value`, returning the previous element. Only
+    /// dependents of the element's chunk are dirtied, and only if the element changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds, or if called while a node is being evaluated.
+    pub fn set_elem(&self, index: usize, value: T) -> T {
+        let len = self.len();
+        assert!(
+            index < len,
+            "index {} out of bounds for length {}",
+            index,
+            len
+        );
+        let chunk = self.chunks.borrow()[index / self.chunk_size].clone();
+        let mut elements = chunk.latest();
+        let old = std::mem::replace(&mut elements[index % self.chunk_size], value);
+        chunk.write(elements);
+        old
+    }
+
+    /// Appends `value`, dirtying only dependents of the last chunk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated.
+    pub fn push(&self, value: T) {
+        let len = self.len();
+        self.reserve_chunks(len / self.chunk_size + 1);
+        let chunk = self.chunks.borrow()[len / self.chunk_size].clone();
+        let mut elements = chunk.latest();
+        elements.push(value);
+        chunk.write(elements);
+    }
+
+    /// Removes and returns the element at `index`, shifting later elements down. Dependents of
+    /// the chunks from `index`'s onwards are dirtied if their elements changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds, or if called while a node is being evaluated.
+    pub fn remove(&self, index: usize) -> T {
+        let mut elements = self.to_vec();
+        let removed = elements.remove(index);
+        let chunks = self.chunks.borrow().clone();
+        for (i, chunk) in chunks.iter().enumerate().skip(index / self.chunk_size) {
+            let start = (i * self.chunk_size).min(elements.len());
+            let end = ((i + 1) * self.chunk_size).min(elements.len());
+            chunk.write(elements[start..end].to_vec());
+        }
+        removed
+    }
+}
+
+/// Reference-counted [`RawMapCell`].
+pub type MapCell<K, T> = Rc<RawMapCell<K, T>>;
+
+/// A [`HashMap`] whose entries are tracked individually, as created by [`Dcg::map_cell`].
+pub struct RawMapCell<K, T> {
+    inner: Rc<Inner>,
+    /// The [`Var`] of every key present or depended on, holding [`None`] while it is absent.
+    entries: RefCell<HashMap<K, Var<Option<T>>>>,
+    keys: Var<HashSet<K>>,
+}
+
+impl Dcg {
+    /// Creates a [`MapCell`] holding `initial`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let prices = dcg.map_cell(HashMap::from([("apple", 3), ("pear", 4)]));
+    /// let apple = dcg.memo_on_key(&prices, "apple", |price| price.copied());
+    /// let count = dcg.memo_on_keys(&prices, |keys| keys.len());
+    ///
+    /// assert_eq!((apple.read(), count.read()), (Some(3), 2));
+    /// prices.set_key("pear", 5);
+    /// assert!(apple.is_clean() && count.is_clean());
+    /// prices.insert("plum", 2);
+    /// assert!(apple.is_clean());
+    /// assert_eq!(count.read(), 3);
+    /// ```
+    pub fn map_cell<K, T>(&self, initial: HashMap<K, T>) -> MapCell<K, T>
+    where
+        K: Eq + Hash + Clone + 'static,
+        T: PartialEq + Clone + 'static,
+    {
+        let keys = self.var(initial.keys().cloned().collect());
+        let entries = initial
+            .into_iter()
+            .map(|(key, value)| (key, self.var(Some(value))))
+            .collect();
+        Rc::new(RawMapCell {
+            inner: self.inner.clone(),
+            entries: RefCell::new(entries),
+            keys,
+        })
+    }
+
+    /// Creates a dirty [`Buffer`] of `f` applied to the value of `key` in `map`, or [`None`] if
+    /// it is absent, which is only dirtied when that value changes, is inserted or is removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` belongs to another [`Dcg`].
+    pub fn memo_on_key<K, T, U, F>(&self, map: &MapCell<K, T>, key: K, f: F) -> Buffer<U>
+    where
+        K: Eq + Hash + Clone + 'static,
+        T: PartialEq + Clone + 'static,
+        U: Clone + 'static,
+        F: Fn(Option<&T>) -> U + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&map.inner, &self.inner),
+            "map cells must belong to the Dcg"
+        );
+        let entry = map.entry(key);
+        self.buffer(&entry.clone(), move || f(entry.read().as_ref()))
+    }
+
+    /// Creates a dirty [`Buffer`] of `f` applied to the keys of `map`, which is only dirtied when
+    /// a key is inserted or removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` belongs to another [`Dcg`].
+    pub fn memo_on_keys<K, T, U, F>(&self, map: &MapCell<K, T>, f: F) -> Buffer<U>
+    where
+        K: Eq + Hash + Clone + 'static,
+        U: Clone + 'static,
+        F: Fn(&HashSet<K>) -> U + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&map.inner, &self.inner),
+            "map cells must belong to the Dcg"
+        );
+        let keys = map.keys.clone();
+        self.buffer(&keys.clone(), move || f(&keys.read()))
+    }
+
+    /// Creates a dirty [`dynamic`](Dcg::dynamic_buffer) [`Buffer`] of `f` applied to the whole
+    /// of `map`, which is dirtied by any change to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` belongs to another [`Dcg`].
+    pub fn memo_on_map<K, T, U, F>(&self, map: &MapCell<K, T>, f: F) -> Buffer<U>
+    where
+        K: Eq + Hash + Clone + 'static,
+        T: PartialEq + Clone + 'static,
+        U: Clone + 'static,
+        F: Fn(&HashMap<K, T>) -> U + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&map.inner, &self.inner),
+            "map cells must belong to the Dcg"
+        );
+        let map = map.clone();
+        self.dynamic_buffer(move || {
+            let keys = map.keys.read();
+            let entries = map.entries.borrow();
+            let values = keys
+                .into_iter()
+                .filter_map(|key| {
+                    let value = entries[&key].read()?;
+                    Some((key, value))
+                })
+                .collect();
+            f(&values)
+        })
+    }
+}
+
+impl<K, T> RawMapCell<K, T>
+where
+    K: Eq + Hash + Clone + 'static,
+    T: PartialEq + Clone + 'static,
+{
+    /// Returns the [`Var`] of `key`, creating an empty one if there is none.
+    fn entry(&self, key: K) -> Var<Option<T>> {
+        let dcg = Dcg {
+            inner: self.inner.clone(),
+        };
+        self.entries
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| dcg.var(None))
+            .clone()
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.keys.value.borrow().len()
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if there is an entry for `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.keys.value.borrow().contains(key)
+    }
+
+    /// Returns the value of `key`, if any, without recording a read.
+    pub fn get(&self, key: &K) -> Option<T> {
+        self.entries.borrow().get(key)?.latest()
+    }
+
+    /// Inserts `value` for `key`, returning the previous value, if any. Dependents of `key` are
+    /// dirtied if its value changed, and dependents of the keys if `key` was absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated.
+    pub fn insert(&self, key: K, value: T) -> Option<T> {
+        let old = self.entry(key.clone()).write(Some(value));
+        if old.is_none() {
+            self.keys.modify(|keys| {
+                let mut keys = keys.clone();
+                keys.insert(key);
+                keys
+            });
+        }
+        old
+    }
+
+    /// Replaces the value of the existing entry for `key`, returning the previous value. Only
+    /// dependents of `key` are dirtied, and only if its value changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no entry for `key`, or if called while a node is being evaluated.
+    pub fn set_key(&self, key: K, value: T) -> T {
+        assert!(self.contains_key(&key), "no entry for the key");
+        self.insert(key, value)
+            .expect("existing entries have a value")
+    }
+
+    /// Removes the entry for `key`, returning its value, if any. Dependents of `key` and of the
+    /// keys are dirtied if there was an entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated.
+    pub fn remove(&self, key: &K) -> Option<T> {
+        let entry = self.entries.borrow().get(key)?.clone();
+        let old = entry.write(None);
+        if old.is_some() {
+            self.keys.modify(|keys| {
+                let mut keys = keys.clone();
+                keys.remove(key);
+                keys
+            });
+        }
+        old
+    }
+}
+
+/// A fixed number of [`Var`]s written together, as created by [`Dcg::record`].
+///
+/// Nodes depend on individual [components](Record::component), so writing the whole record only
+/// dirties dependents of the components that changed.
+pub struct Record<T> {
+    inner: Rc<Inner>,
+    components: Vec<Var<T>>,
+}
+
+impl<T> Clone for Record<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            components: self.components.clone(),
+        }
+    }
+}
+
+impl Dcg {
+    /// Creates a [`Record`] with a component [`Var`] holding each of `values`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let position = dcg.record(vec![0.0, 0.0, 0.0]);
+    /// let y = position.component(1).clone();
+    /// let height = buffer!(dcg, y => y * 2.0);
+    ///
+    /// assert_eq!(height.read(), 0.0);
+    /// assert_eq!(position.set_all(vec![1.0, 0.0, 2.0]), Ok(2));
+    /// assert!(height.is_clean());
+    /// assert_eq!(position.get_all(), vec![1.0, 0.0, 2.0]);
+    /// assert!(position.set_all(vec![1.0]).is_err());
+    /// ```
+    pub fn record<T>(&self, values: Vec<T>) -> Record<T>
+    where
+        T: PartialEq + Clone + 'static,
+    {
+        Record {
+            inner: self.inner.clone(),
+            components: values.into_iter().map(|value| self.var(value)).collect(),
+        }
+    }
+}
+
+/// Collects values into a [`Record`] in a new [`Dcg`], which its components keep alive.
+///
+/// # Examples
+///
+/// ```
+/// use cachegrab::{collections::Record, incremental::Incremental};
+///
+/// let record: Record<_> = (1..=3).collect();
+///
+/// assert_eq!(record.get_all(), vec![1, 2, 3]);
+/// assert_eq!(record.component(0).read(), 1);
+/// ```
+impl<T: PartialEq + Clone + 'static> FromIterator<T> for Record<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let dcg = Dcg::default();
+        Record {
+            components: dcg.extend_vars(values),
+            inner: dcg.inner,
+        }
+    }
+}
+
+impl<T: PartialEq + Clone + 'static> Record<T> {
+    /// Returns the [`Var`] of component `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn component(&self, i: usize) -> &Var<T> {
+        &self.components[i]
+    }
+
+    /// Returns the number of components.
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns `true` if there are no components.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Returns every component's value, without recording a read.
+    pub fn get_all(&self) -> Vec<T> {
+        self.components
+            .iter()
+            .map(|component| component.latest())
+            .collect()
+    }
+
+    /// Writes each of `values` into its component as one update, like
+    /// [`commit_staged`](Dcg::commit_staged), and returns how many components changed.
+    ///
+    /// Returns [`LengthMismatch`](DcgError::LengthMismatch), writing nothing, if there isn't
+    /// exactly one value per component, or [`ValidationFailed`](DcgError::ValidationFailed), also
+    /// writing nothing, if a value is rejected by its component's
+    /// [validator](crate::RawVar::add_validator).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated.
+    pub fn set_all(&self, values: Vec<T>) -> Result<usize, DcgError> {
+        self.set_all_report(values).map(|report| report.changed)
+    }
+
+    /// Like [`set_all`](Record::set_all), but returns the combined effect of the writes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated.
+    pub fn set_all_report(&self, values: Vec<T>) -> Result<BatchReport, DcgError> {
+        if values.len() != self.components.len() {
+            return Err(DcgError::LengthMismatch {
+                expected: self.components.len(),
+                actual: values.len(),
+            });
+        }
+        let writes = self
+            .components
+            .iter()
+            .zip(values)
+            .map(|(component, value)| {
+                (
+                    component.node.idx,
+                    stage_write(Rc::downgrade(component), value),
+                )
+            })
+            .collect();
+        let dcg = Dcg {
+            inner: self.inner.clone(),
+        };
+        dcg.apply_report(writes)
+    }
+}
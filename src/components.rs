@@ -0,0 +1,99 @@
+//! Splitting a [`Dcg`] into independent parts with [`Dcg::components`].
+
+use std::{collections::HashMap, rc::Rc};
+
+use petgraph::{
+    graph::NodeIndex,
+    unionfind::UnionFind,
+    visit::{EdgeRef, IntoEdgeReferences, NodeIndexable},
+};
+
+use crate::{Dcg, Graph, Node};
+
+/// A [`Dcg`]'s weakly connected components, cached until its structure changes.
+pub(crate) struct Components {
+    /// Each component's nodes, in index order, components ordered by their first node.
+    members: Vec<Vec<NodeIndex>>,
+    /// The component of each node.
+    of: HashMap<NodeIndex, usize>,
+}
+
+impl Components {
+    fn new(graph: &Graph) -> Self {
+        let mut sets = UnionFind::new(graph.node_bound());
+        for edge in graph.edge_references() {
+            sets.union(edge.source().index(), edge.target().index());
+        }
+        let mut members: Vec<Vec<NodeIndex>> = Vec::new();
+        let mut of = HashMap::new();
+        let mut ids = HashMap::new();
+        for idx in graph.node_indices() {
+            let id = *ids.entry(sets.find(idx.index())).or_insert_with(|| {
+                members.push(Vec::new());
+                members.len() - 1
+            });
+            members[id].push(idx);
+            of.insert(idx, id);
+        }
+        Self { members, of }
+    }
+}
+
+impl Dcg {
+    /// Returns the cached [`Components`], computing them if the structure has changed.
+    fn cached_components(&self) -> Rc<Components> {
+        let mut cached = self.inner.components.borrow_mut();
+        cached
+            .get_or_insert_with(|| Rc::new(Components::new(&self.inner.graph.borrow())))
+            .clone()
+    }
+
+    /// Returns the [`Dcg`]'s weakly connected components: groups of nodes such that no node
+    /// depends, even transitively, on a node in another group.
+    ///
+    /// Components are numbered by their position in the returned [`Vec`], in order of their
+    /// lowest node index, and each lists its nodes in index order. The result is cached until a
+    /// node or dependency is added or removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let c = buffer!(dcg, a => a + 1);
+    ///
+    /// assert_eq!(
+    ///     dcg.components(),
+    ///     vec![vec![a.node().index(), c.node().index()], vec![b.node().index()]]
+    /// );
+    /// assert_eq!(dcg.component_of(c.node()), 0);
+    /// ```
+    pub fn components(&self) -> Vec<Vec<NodeIndex>> {
+        self.cached_components().members.clone()
+    }
+
+    /// Returns the number of `node`'s component in [`components`](Dcg::components).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn component_of(&self, node: &Node) -> usize {
+        assert!(
+            Rc::ptr_eq(&node.inner, &self.inner),
+            "node must belong to the Dcg"
+        );
+        self.cached_components().of[&node.idx]
+    }
+
+    /// Returns the nodes in component `id`, or an empty [`Vec`] if there is no such component.
+    pub(crate) fn component(&self, id: usize) -> Vec<NodeIndex> {
+        self.cached_components()
+            .members
+            .get(id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
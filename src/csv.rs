@@ -0,0 +1,115 @@
+//! Rendering a [`Dcg`]'s nodes and edges as CSV tables.
+
+use std::{borrow::Cow, io};
+
+use crate::{Dcg, NodeKind};
+
+/// Quotes `field` if it contains a delimiter, quote or line break.
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+impl Dcg {
+    /// Renders every node as a row of a CSV table with the columns
+    /// `index,kind,name,dirty,cached,value`.
+    ///
+    /// `cached` is only set for [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s, and
+    /// `value` only for nodes registered with `show_value`. Fields are quoted as described in
+    /// [RFC 4180](https://www.rfc-editor.org/rfc/rfc4180).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// a.node().set_name("a");
+    /// a.show_value();
+    ///
+    /// assert_eq!(
+    ///     dcg.to_csv(),
+    ///     "index,kind,name,dirty,cached,value\n0,Var,a,true,,1\n1,Buffer,,true,false,\n"
+    /// );
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv = Vec::new();
+        self.write_csv(&mut csv)
+            .expect("writing to a Vec doesn't fail");
+        String::from_utf8(csv).expect("the table is built from strings")
+    }
+
+    /// Writes the table rendered by [`to_csv`](Dcg::to_csv) to `w`.
+    pub fn write_csv(&self, mut w: impl io::Write) -> io::Result<()> {
+        let (nodes, _) = self.describe(true, usize::MAX);
+        writeln!(w, "index,kind,name,dirty,cached,value")?;
+        for node in nodes {
+            let cached = match node.kind {
+                NodeKind::Memo | NodeKind::Buffer => node.cached.to_string(),
+                NodeKind::Var | NodeKind::Thunk => String::new(),
+            };
+            writeln!(
+                w,
+                "{},{:?},{},{},{},{}",
+                node.idx.index(),
+                node.kind,
+                escape(node.name.as_deref().unwrap_or_default()),
+                node.dirty,
+                cached,
+                escape(node.value.as_deref().unwrap_or_default())
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Renders every dependency as a row of a CSV table with the columns
+    /// `from,to,dirty,weak,label`, where `dirty` is whether the dependent is dirty, `weak` whether
+    /// the dependency is [weak](Dcg::add_weak_dependency) and `label` is the dependency's
+    /// [label](Dcg::label_edge), if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let c = buffer!(dcg, (a, b) => a - b);
+    /// dcg.label_edge(a.node(), c.node(), "minuend");
+    ///
+    /// assert_eq!(
+    ///     dcg.edges_to_csv(),
+    ///     "from,to,dirty,weak,label\n0,2,true,false,minuend\n1,2,true,false,\n"
+    /// );
+    /// ```
+    pub fn edges_to_csv(&self) -> String {
+        let mut csv = Vec::new();
+        self.write_edges_csv(&mut csv)
+            .expect("writing to a Vec doesn't fail");
+        String::from_utf8(csv).expect("the table is built from strings")
+    }
+
+    /// Writes the table rendered by [`edges_to_csv`](Dcg::edges_to_csv) to `w`.
+    pub fn write_edges_csv(&self, mut w: impl io::Write) -> io::Result<()> {
+        let (_, edges) = self.describe(false, 0);
+        writeln!(w, "from,to,dirty,weak,label")?;
+        for edge in edges {
+            writeln!(
+                w,
+                "{},{},{},{},{}",
+                edge.from.index(),
+                edge.to.index(),
+                edge.dirty,
+                edge.weak,
+                escape(edge.label.as_deref().unwrap_or_default())
+            )?;
+        }
+        Ok(())
+    }
+}
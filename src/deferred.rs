@@ -0,0 +1,139 @@
+//! [`Buffer`]s created before their dependencies exist with [`Dcg::deferred_buffer`], and wired
+//! up later with [`Dcg::finalize_dependencies`].
+
+use std::rc::Rc;
+
+use petgraph::algo::has_path_connecting;
+
+use crate::{incremental::Incremental, Buffer, Dcg, DcgError, Node, RawBuffer};
+
+impl Dcg {
+    /// Creates a dirty [`Buffer`] storing `f`, with its dependencies to be declared later by
+    /// [`finalize_dependencies`](Dcg::finalize_dependencies).
+    ///
+    /// This allows forward references: other nodes can depend on the [`Buffer`] before the nodes
+    /// it reads exist. Until it is finalized, the [`Buffer`] can't be read:
+    /// [`read`](Incremental::read) panics and [`try_read`](RawBuffer::try_read) returns
+    /// [`Unfinalized`](DcgError::Unfinalized), rather than computing a value no write would ever
+    /// dirty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, DcgError, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let total = dcg.deferred_buffer({
+    ///     let parts = dcg.var(vec![1, 2]);
+    ///     move || parts.read().iter().sum::<i32>()
+    /// });
+    /// let doubled = buffer!(dcg, total => total * 2);
+    /// assert_eq!(
+    ///     total.try_read(),
+    ///     Err(DcgError::Unfinalized { target: total.node().index() })
+    /// );
+    /// ```
+    pub fn deferred_buffer<F, T>(&self, f: F) -> Buffer<T>
+    where
+        F: Fn() -> T + 'static,
+        T: Clone + 'static,
+    {
+        let buffer = self.buffer(&(), f);
+        self.inner
+            .unfinalized
+            .borrow_mut()
+            .insert(buffer.node().idx);
+        buffer
+    }
+
+    /// Adds incoming dependency edges from `dependencies` to `node`, created by
+    /// [`deferred_buffer`](Dcg::deferred_buffer), allowing it to be read.
+    ///
+    /// `node` has never been read, so it and its dependents are still dirty and compute their
+    /// values from the new dependencies when next read.
+    ///
+    /// Returns [`AlreadyFinalized`](DcgError::AlreadyFinalized) if `node` wasn't created by
+    /// [`deferred_buffer`](Dcg::deferred_buffer) or has already been finalized, or
+    /// [`Cycle`](DcgError::Cycle) if one of `dependencies` depends on `node`, or
+    /// [`StaleHandle`](DcgError::StaleHandle) if one of them was removed. No edges are added if
+    /// an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(2);
+    /// let doubled = dcg.deferred_buffer({
+    ///     let a = a.clone();
+    ///     move || a.read() * 2
+    /// });
+    ///
+    /// dcg.finalize_dependencies(doubled.node(), &a).unwrap();
+    /// assert_eq!(doubled.read(), 4);
+    /// a.write(3);
+    /// assert_eq!(doubled.read(), 6);
+    /// assert!(dcg.finalize_dependencies(doubled.node(), &a).is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn finalize_dependencies<P>(&self, node: &Node, dependencies: &P) -> Result<(), DcgError>
+    where
+        P: Incremental,
+    {
+        assert!(
+            Rc::ptr_eq(&node.inner, &self.inner),
+            "node must belong to the Dcg"
+        );
+        if !self.inner.unfinalized.borrow().contains(&node.idx) {
+            return Err(DcgError::AlreadyFinalized { target: node.idx });
+        }
+        for dependency in dependencies.nodes() {
+            dependency.check_current()?;
+        }
+        {
+            let graph = self.inner.graph.borrow();
+            for dependency in dependencies.nodes() {
+                if has_path_connecting(&*graph, node.idx, dependency.idx, None) {
+                    return Err(DcgError::Cycle {
+                        from: dependency.idx,
+                        to: node.idx,
+                    });
+                }
+            }
+        }
+        node.add_dependencies(dependencies);
+        self.inner.unfinalized.borrow_mut().remove(&node.idx);
+        node.debug_check();
+        Ok(())
+    }
+}
+
+impl Node {
+    /// Returns [`Unfinalized`](DcgError::Unfinalized) if the node is a
+    /// [deferred](Dcg::deferred_buffer) [`Buffer`] whose dependencies haven't been finalized.
+    pub(crate) fn check_finalized(&self) -> Result<(), DcgError> {
+        if self.inner.unfinalized.borrow().contains(&self.idx) {
+            Err(DcgError::Unfinalized { target: self.idx })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: Clone + 'static> RawBuffer<T> {
+    /// Like [`read`](Incremental::read), but returns [`Unfinalized`](DcgError::Unfinalized)
+    /// instead of panicking if the [`Buffer`] was created by
+    /// [`deferred_buffer`](Dcg::deferred_buffer) and its dependencies haven't been finalized, and
+    /// an error rather than evaluating it if it depends on anything that can't be read yet; see
+    /// [`Dcg::is_ready`]. Returns [`StaleHandle`](DcgError::StaleHandle) if the [`Buffer`] was a
+    /// [watch](Dcg::watch_expr) since removed.
+    pub fn try_read(&self) -> Result<T, DcgError> {
+        self.node().check_current()?;
+        self.node().check_ready()?;
+        Ok(self.read())
+    }
+}
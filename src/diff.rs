@@ -0,0 +1,187 @@
+//! Structural diffs between two [`Dcg`]s with [`diff`], or between two [`SavedDcg`]s with
+//! [`diff_saved`].
+//!
+//! Nodes are matched by [name](crate::Node::set_name), falling back to their index for unnamed
+//! nodes, so two graphs built by the same code compare equal regardless of unrelated nodes
+//! created in between. Values are compared as they are [saved](Dcg::to_saved), so only nodes
+//! registered with `persist` contribute value differences.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde_json::Value;
+
+use crate::{
+    persist::{SavedDcg, SavedNode},
+    Dcg, NodeKind,
+};
+
+/// The differences between two graphs, as returned by [`diff`]. Nodes are identified by their
+/// name, or `#` followed by their index if unnamed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphDiff {
+    /// Nodes only in the first graph, sorted.
+    pub removed_nodes: Vec<String>,
+    /// Nodes only in the second graph, sorted.
+    pub added_nodes: Vec<String>,
+    /// `(dependency, dependent)` pairs only in the first graph, sorted.
+    pub removed_edges: Vec<(String, String)>,
+    /// `(dependency, dependent)` pairs only in the second graph, sorted.
+    pub added_edges: Vec<(String, String)>,
+    /// [`Var`](crate::Var)s in both graphs whose saved values differ, sorted by node.
+    pub changed_values: Vec<ValueChange>,
+    /// [`Buffer`](crate::Buffer)s and [`Memo`](crate::Memo)s in both graphs whose saved caches
+    /// differ, sorted by node.
+    pub changed_caches: Vec<ValueChange>,
+}
+
+/// A node whose saved state differs between two graphs, in a [`GraphDiff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueChange {
+    /// The node's name, or `#` followed by its index if unnamed.
+    pub node: String,
+    /// The node's state in the first graph, or `None` if it wasn't persisted.
+    pub before: Option<Value>,
+    /// The node's state in the second graph, or `None` if it wasn't persisted.
+    pub after: Option<Value>,
+}
+
+impl GraphDiff {
+    /// Returns `true` if the graphs had no differences.
+    pub fn is_empty(&self) -> bool {
+        *self == GraphDiff::default()
+    }
+}
+
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for node in &self.removed_nodes {
+            writeln!(f, "- node {}", node)?;
+        }
+        for node in &self.added_nodes {
+            writeln!(f, "+ node {}", node)?;
+        }
+        for (from, to) in &self.removed_edges {
+            writeln!(f, "- edge {} -> {}", from, to)?;
+        }
+        for (from, to) in &self.added_edges {
+            writeln!(f, "+ edge {} -> {}", from, to)?;
+        }
+        for (what, changes) in [
+            ("value", &self.changed_values),
+            ("cache", &self.changed_caches),
+        ] {
+            for change in changes {
+                writeln!(
+                    f,
+                    "~ {} {}: {} -> {}",
+                    what,
+                    change.node,
+                    show(&change.before),
+                    show(&change.after)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn show(value: &Option<Value>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "unsaved".to_string(), Value::to_string)
+}
+
+/// Returns the differences between `a` and `b`, comparing their [saved](Dcg::to_saved) forms.
+///
+/// # Examples
+///
+/// ```
+/// use cachegrab::{Dcg, buffer, diff::diff};
+///
+/// let build = |start| {
+///     let dcg = Dcg::default();
+///     let a = dcg.var(start);
+///     a.node().set_name("a");
+///     a.persist();
+///     let b = buffer!(dcg, a => a + 1);
+///     b.node().set_name("b");
+///     (dcg, b)
+/// };
+///
+/// let (one, two) = (build(1), build(2));
+/// assert!(diff(&one.0, &build(1).0).is_empty());
+/// assert_eq!(diff(&one.0, &two.0).to_string(), "~ value a: 1 -> 2\n");
+/// ```
+pub fn diff(a: &Dcg, b: &Dcg) -> GraphDiff {
+    diff_saved(&a.to_saved(), &b.to_saved())
+}
+
+/// Returns the differences between two [`SavedDcg`]s, like [`diff`].
+pub fn diff_saved(a: &SavedDcg, b: &SavedDcg) -> GraphDiff {
+    let (a_nodes, a_edges) = (keyed_nodes(a), keyed_edges(a));
+    let (b_nodes, b_edges) = (keyed_nodes(b), keyed_edges(b));
+
+    let mut diff = GraphDiff {
+        removed_nodes: only_in(&a_nodes, &b_nodes),
+        added_nodes: only_in(&b_nodes, &a_nodes),
+        removed_edges: a_edges.difference(&b_edges).cloned().collect(),
+        added_edges: b_edges.difference(&a_edges).cloned().collect(),
+        ..GraphDiff::default()
+    };
+    for (key, before) in &a_nodes {
+        let after = match b_nodes.get(key) {
+            Some(after) if after.value != before.value => after,
+            _ => continue,
+        };
+        let change = ValueChange {
+            node: key.clone(),
+            before: before.value.clone(),
+            after: after.value.clone(),
+        };
+        match before.kind {
+            NodeKind::Var => diff.changed_values.push(change),
+            NodeKind::Buffer | NodeKind::Memo => diff.changed_caches.push(change),
+            NodeKind::Thunk => {}
+        }
+    }
+    diff
+}
+
+/// Returns the node's name, or `#` followed by its index if unnamed.
+fn key(saved: &SavedDcg, index: usize) -> String {
+    saved.nodes[index]
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("#{}", index))
+}
+
+fn keyed_nodes(saved: &SavedDcg) -> BTreeMap<String, &SavedNode> {
+    (0..saved.nodes.len())
+        .map(|index| (key(saved, index), &saved.nodes[index]))
+        .collect()
+}
+
+fn keyed_edges(saved: &SavedDcg) -> BTreeSet<(String, String)> {
+    saved
+        .edges
+        .iter()
+        .map(|&(from, to)| (key(saved, from), key(saved, to)))
+        .collect()
+}
+
+fn only_in(
+    nodes: &BTreeMap<String, &SavedNode>,
+    other: &BTreeMap<String, &SavedNode>,
+) -> Vec<String> {
+    nodes
+        .keys()
+        .filter(|key| !other.contains_key(*key))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,301 @@
+//! Rendering a [`Dcg`] in the [DOT](https://graphviz.org/doc/info/lang.html) language.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    rc::Rc,
+};
+
+use petgraph::{
+    graph::NodeIndex,
+    Direction::{self, Incoming, Outgoing},
+};
+
+use crate::{
+    export::{EdgeDescription, NodeDescription},
+    Dcg, Node, NodeKind,
+};
+
+/// The direction in which a rendered graph's edges point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankDir {
+    /// Dependencies above their dependents.
+    #[default]
+    TopToBottom,
+    /// Dependencies left of their dependents.
+    LeftToRight,
+    /// Dependencies below their dependents.
+    BottomToTop,
+    /// Dependencies right of their dependents.
+    RightToLeft,
+}
+
+impl RankDir {
+    fn as_str(self) -> &'static str {
+        match self {
+            RankDir::TopToBottom => "TB",
+            RankDir::LeftToRight => "LR",
+            RankDir::BottomToTop => "BT",
+            RankDir::RightToLeft => "RL",
+        }
+    }
+}
+
+/// Controls how [`Dcg::to_dot_with`] renders a [`Dcg`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Whether to include the values of nodes registered with `show_value`. Defaults to `true`.
+    pub values: bool,
+    /// The number of characters after which values are truncated. Defaults to 32.
+    pub max_value_len: usize,
+    /// The direction of the graph's edges. Defaults to [`RankDir::TopToBottom`].
+    pub rankdir: RankDir,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        DotOptions {
+            values: true,
+            max_value_len: 32,
+            rankdir: RankDir::default(),
+        }
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Dcg {
+    /// Renders the [`Dcg`] in the DOT language with the default [`DotOptions`].
+    ///
+    /// [`Var`](crate::Var)s are drawn as boxes, [`Thunk`](crate::Thunk)s as ellipses and
+    /// [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s as double ellipses. Nodes are labelled
+    /// with their index, their [name](crate::Node::set_name) and, if registered with `show_value`,
+    /// their current value. Edges are labelled with their [label](Dcg::label_edge), if any,
+    /// edges into dirty nodes are drawn red and dashed, and
+    /// [weak dependencies](Dcg::add_weak_dependency) are drawn dashed with a hollow arrowhead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// a.show_value();
+    ///
+    /// assert_eq!(
+    ///     dcg.to_dot(),
+    ///     r#"digraph {
+    ///     rankdir=TB;
+    ///     0 [label="0\n1", shape=box];
+    ///     1 [label="1", shape=ellipse, peripheries=2];
+    ///     0 -> 1 [color=red, style=dashed];
+    /// }
+    /// "#
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(DotOptions::default())
+    }
+
+    /// Renders the [`Dcg`] in the DOT language. See [`to_dot`](Dcg::to_dot).
+    pub fn to_dot_with(&self, opts: DotOptions) -> String {
+        let (nodes, edges) = self.describe(opts.values, opts.max_value_len);
+        render(nodes, edges, &opts, &HashMap::new())
+    }
+
+    /// Renders the neighbourhood of `center` in the DOT language with the default
+    /// [`DotOptions`]: the nodes it depends on within `upstream_depth` hops, and the nodes
+    /// depending on it within `downstream_depth` hops.
+    ///
+    /// Nodes with dependencies or dependents left out are drawn dotted, with a dotted edge to or
+    /// from an `…` marker on each side that was trimmed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b + 1);
+    ///
+    /// assert_eq!(
+    ///     dcg.to_dot_around(c.node(), 1, 1),
+    ///     r#"digraph {
+    ///     rankdir=TB;
+    ///     1 [label="1", shape=ellipse, peripheries=2, style=dotted];
+    ///     trimmed_above_1 [label="…", shape=plaintext];
+    ///     trimmed_above_1 -> 1 [style=dotted];
+    ///     2 [label="2", shape=ellipse, peripheries=2];
+    ///     1 -> 2 [color=red, style=dashed];
+    /// }
+    /// "#
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `center` belongs to another [`Dcg`].
+    pub fn to_dot_around(
+        &self,
+        center: &Node,
+        upstream_depth: usize,
+        downstream_depth: usize,
+    ) -> String {
+        self.to_dot_around_with(
+            center,
+            upstream_depth,
+            downstream_depth,
+            DotOptions::default(),
+            |_| true,
+        )
+    }
+
+    /// Renders the neighbourhood of `center` in the DOT language, like
+    /// [`to_dot_around`](Dcg::to_dot_around), leaving out nodes other than `center` for which
+    /// `filter` returns `false`, along with the nodes only reachable through them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `center` belongs to another [`Dcg`].
+    pub fn to_dot_around_with(
+        &self,
+        center: &Node,
+        upstream_depth: usize,
+        downstream_depth: usize,
+        opts: DotOptions,
+        filter: impl Fn(NodeIndex) -> bool,
+    ) -> String {
+        assert!(
+            Rc::ptr_eq(&center.inner, &self.inner),
+            "node must belong to the Dcg"
+        );
+        let mut included = HashSet::from([center.idx]);
+        let mut trimmed = HashMap::new();
+        {
+            let graph = self.inner.graph.borrow();
+            for (direction, depth) in [(Incoming, upstream_depth), (Outgoing, downstream_depth)] {
+                let mut frontier = vec![center.idx];
+                for _ in 0..depth {
+                    let mut next = Vec::new();
+                    for idx in frontier {
+                        for neighbour in graph.neighbors_directed(idx, direction) {
+                            if filter(neighbour) && included.insert(neighbour) {
+                                next.push(neighbour);
+                            }
+                        }
+                    }
+                    frontier = next;
+                }
+            }
+            for &idx in &included {
+                for direction in [Incoming, Outgoing] {
+                    if graph
+                        .neighbors_directed(idx, direction)
+                        .any(|neighbour| !included.contains(&neighbour))
+                    {
+                        trimmed.entry(idx).or_insert_with(Vec::new).push(direction);
+                    }
+                }
+            }
+        }
+        let (nodes, edges) = self.describe(opts.values, opts.max_value_len);
+        let nodes = nodes
+            .into_iter()
+            .filter(|node| included.contains(&node.idx))
+            .collect();
+        let edges = edges
+            .into_iter()
+            .filter(|edge| included.contains(&edge.from) && included.contains(&edge.to))
+            .collect();
+        render(nodes, edges, &opts, &trimmed)
+    }
+}
+
+/// Renders `nodes` and `edges`, marking the nodes in `trimmed` as having neighbours in each
+/// listed direction left out.
+fn render(
+    nodes: Vec<NodeDescription>,
+    edges: Vec<EdgeDescription>,
+    opts: &DotOptions,
+    trimmed: &HashMap<NodeIndex, Vec<Direction>>,
+) -> String {
+    let mut dot = String::new();
+    writeln!(dot, "digraph {{").unwrap();
+    writeln!(dot, "    rankdir={};", opts.rankdir.as_str()).unwrap();
+    for node in nodes {
+        let shape = match node.kind {
+            NodeKind::Var => "shape=box",
+            NodeKind::Thunk => "shape=ellipse",
+            NodeKind::Memo | NodeKind::Buffer => "shape=ellipse, peripheries=2",
+        };
+        let mut label = node.idx.index().to_string();
+        if let Some(name) = node.name {
+            write!(label, "\\n{}", escape(&name)).unwrap();
+        }
+        if let Some(value) = node.value {
+            write!(label, "\\n{}", escape(&value)).unwrap();
+        }
+        let directions = trimmed.get(&node.idx).map_or(&[][..], Vec::as_slice);
+        let style = if directions.is_empty() {
+            ""
+        } else {
+            ", style=dotted"
+        };
+        let idx = node.idx.index();
+        writeln!(
+            dot,
+            "    {} [label=\"{}\", {}{}];",
+            idx, label, shape, style
+        )
+        .unwrap();
+        for direction in directions {
+            let (side, from, to) = match direction {
+                Incoming => ("above", format!("trimmed_above_{}", idx), idx.to_string()),
+                Outgoing => ("below", idx.to_string(), format!("trimmed_below_{}", idx)),
+            };
+            writeln!(
+                dot,
+                "    trimmed_{}_{} [label=\"…\", shape=plaintext];",
+                side, idx
+            )
+            .unwrap();
+            writeln!(dot, "    {} -> {} [style=dotted];", from, to).unwrap();
+        }
+    }
+    for edge in edges {
+        let mut attributes = Vec::new();
+        if let Some(label) = edge.label {
+            attributes.push(format!("label=\"{}\"", escape(&label)));
+        }
+        if edge.dirty {
+            attributes.push("color=red".to_owned());
+        }
+        if edge.dirty || edge.weak {
+            attributes.push("style=dashed".to_owned());
+        }
+        if edge.weak {
+            attributes.push("arrowhead=empty".to_owned());
+        }
+        let style = if attributes.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", attributes.join(", "))
+        };
+        writeln!(
+            dot,
+            "    {} -> {}{};",
+            edge.from.index(),
+            edge.to.index(),
+            style
+        )
+        .unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+}
@@ -0,0 +1,81 @@
+//! Recomputing [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s as soon as they are
+//! dirtied, chosen per node with [`Dcg::set_policy`].
+
+use std::rc::Rc;
+
+use crate::{Dcg, Node, RecomputePolicy};
+
+impl Dcg {
+    /// Sets when `node`, a [`Memo`](crate::Memo) or [`Buffer`](crate::Buffer), recomputes after
+    /// being dirtied.
+    ///
+    /// [`Eager`](RecomputePolicy::Eager) nodes dirtied by a [`Var`](crate::Var) write, or by a
+    /// [commit](Dcg::commit_staged) of several, are recomputed in topological order before the
+    /// write returns, after the [`Var`](crate::Var)s' observers, so their hooks and watchers fire
+    /// during the write.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, RecomputePolicy, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// dcg.set_policy(b.node(), RecomputePolicy::Eager);
+    ///
+    /// a.write(2);
+    /// assert!(b.is_clean());
+    /// assert_eq!(b.last_computed(), Some(3));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`], or is not a [`Memo`](crate::Memo) or
+    /// [`Buffer`](crate::Buffer).
+    pub fn set_policy(&self, node: &Node, policy: RecomputePolicy) {
+        assert!(
+            Rc::ptr_eq(&node.inner, &self.inner),
+            "node must belong to the Dcg"
+        );
+        assert!(
+            self.inner.refreshers.borrow().contains_key(&node.idx),
+            "only memos and buffers have a recompute policy"
+        );
+        let mut eager = self.inner.eager.borrow_mut();
+        match policy {
+            RecomputePolicy::OnDemand => eager.remove(&node.idx),
+            RecomputePolicy::Eager => eager.insert(node.idx),
+        };
+    }
+
+    /// Returns `node`'s [`RecomputePolicy`], set with [`set_policy`](Dcg::set_policy).
+    pub fn policy(&self, node: &Node) -> RecomputePolicy {
+        if self.inner.eager.borrow().contains(&node.idx) {
+            RecomputePolicy::Eager
+        } else {
+            RecomputePolicy::OnDemand
+        }
+    }
+}
+
+impl Node {
+    /// Recomputes every dirty [`Eager`](RecomputePolicy::Eager) node, after a write.
+    pub(crate) fn refresh_eager(&self) {
+        let dirty: Vec<_> = {
+            let eager = self.inner.eager.borrow();
+            eager
+                .iter()
+                .copied()
+                .filter(|&idx| self.inner.is_dirty(idx))
+                .collect()
+        };
+        if dirty.is_empty() {
+            return;
+        }
+        Dcg {
+            inner: self.inner.clone(),
+        }
+        .refresh_where(|idx| dirty.contains(&idx));
+    }
+}
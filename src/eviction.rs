@@ -0,0 +1,130 @@
+//! Bounding how many [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s hold cached values
+//! with [`Dcg::set_cache_capacity`].
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::graph::NodeIndex;
+
+use crate::{Dcg, Node};
+
+/// When each cached node was last used.
+#[derive(Default)]
+pub(crate) struct Recency {
+    tick: u64,
+    used: HashMap<NodeIndex, u64>,
+    /// Clean nodes whose caches were evicted and haven't been repopulated since.
+    evicted: HashSet<NodeIndex>,
+}
+
+impl Recency {
+    /// Forgets when the removed node at `idx` was last used.
+    pub(crate) fn forget(&mut self, idx: NodeIndex) {
+        self.used.remove(&idx);
+        self.evicted.remove(&idx);
+    }
+
+    /// Returns `true` if the node at `idx` has had its cache evicted since it was last used.
+    pub(crate) fn is_evicted(&self, idx: NodeIndex) -> bool {
+        self.evicted.contains(&idx)
+    }
+}
+
+impl Dcg {
+    /// Limits how many [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s may hold cached
+    /// values at once to `max_entries`, evicting the least recently used caches beyond it.
+    ///
+    /// Reading a node's cache, or populating it, counts as using it. An evicted node stays clean,
+    /// and so do its dependents, as its value hasn't changed: it is recomputed the next time it
+    /// is read. `usize::MAX`, the default, disables eviction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, a => a + 2);
+    ///
+    /// dcg.set_cache_capacity(1);
+    /// assert_eq!((b.read(), c.read()), (2, 3));
+    /// assert!(!b.node().is_cached() && c.node().is_cached());
+    /// assert!(b.is_clean());
+    /// ```
+    pub fn set_cache_capacity(&self, max_entries: usize) {
+        self.inner.cache_capacity.set(Some(max_entries));
+        self.evict_caches(None);
+    }
+
+    /// Returns the limit set by [`set_cache_capacity`](Dcg::set_cache_capacity).
+    pub fn cache_capacity(&self) -> usize {
+        self.inner.cache_capacity.get().unwrap_or(usize::MAX)
+    }
+
+    /// Evicts least recently used caches, other than `keep`'s and those of nodes being
+    /// evaluated, until at most the capacity remain.
+    fn evict_caches(&self, keep: Option<NodeIndex>) {
+        let capacity = self.cache_capacity();
+        while self.inner.cached.borrow().len() > capacity {
+            let evaluating: Vec<_> = self
+                .inner
+                .evaluating
+                .borrow()
+                .iter()
+                .map(|evaluation| evaluation.node)
+                .collect();
+            let refreshers = self.inner.refreshers.borrow();
+            let recency = self.inner.recency.borrow();
+            let lru = self
+                .inner
+                .cached
+                .borrow()
+                .iter()
+                .copied()
+                .filter(|&idx| {
+                    Some(idx) != keep && !evaluating.contains(&idx) && refreshers.contains_key(&idx)
+                })
+                .min_by_key(|idx| (recency.used.get(idx).copied().unwrap_or_default(), *idx));
+            let Some(lru) = lru else {
+                break;
+            };
+            let refresher = refreshers[&lru].clone();
+            drop((refreshers, recency));
+            refresher.forget();
+            // Dropped handles can't forget their own cache.
+            Node::at(self.inner.clone(), lru).forget_cached();
+            self.inner.recency.borrow_mut().evicted.insert(lru);
+        }
+    }
+}
+
+impl Node {
+    /// Returns `true` if the node is a [`Memo`](crate::Memo) or [`Buffer`](crate::Buffer)
+    /// holding a cached value.
+    pub fn is_cached(&self) -> bool {
+        self.inner.cached.borrow().contains(&self.idx)
+    }
+
+    /// Records that the node's cache was just used.
+    pub(crate) fn touch_cache(&self) {
+        let mut recency = self.inner.recency.borrow_mut();
+        recency.tick += 1;
+        let tick = recency.tick;
+        recency.used.insert(self.idx, tick);
+        recency.evicted.remove(&self.idx);
+    }
+
+    /// Forgets when the node's cache was last used, as it no longer has one.
+    pub(crate) fn untouch_cache(&self) {
+        self.inner.recency.borrow_mut().used.remove(&self.idx);
+    }
+
+    /// Evicts other caches if the node's newly populated cache exceeded the capacity.
+    pub(crate) fn evict_beyond_capacity(&self) {
+        Dcg {
+            inner: self.inner.clone(),
+        }
+        .evict_caches(Some(self.idx));
+    }
+}
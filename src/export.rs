@@ -0,0 +1,194 @@
+//! Describing a [`Dcg`] for rendering in text formats such as [DOT](crate::dot) and
+//! [Mermaid](crate::mermaid).
+
+use std::{collections::HashMap, rc::Rc};
+
+use petgraph::{
+    graph::NodeIndex,
+    visit::{EdgeRef, IntoEdgeReferences},
+};
+
+use crate::{Dcg, Node, NodeKind, StaleMemo};
+
+/// A node as rendered by an export.
+pub(crate) struct NodeDescription {
+    pub(crate) idx: NodeIndex,
+    pub(crate) kind: NodeKind,
+    pub(crate) name: Option<String>,
+    pub(crate) dirty: bool,
+    /// Whether the node is a [`Memo`](crate::Memo) or [`Buffer`](crate::Buffer) holding a value.
+    pub(crate) cached: bool,
+    /// The node's value, if registered with `show_value`, requested and still available.
+    pub(crate) value: Option<String>,
+}
+
+/// A dependency as rendered by an export.
+pub(crate) struct EdgeDescription {
+    pub(crate) from: NodeIndex,
+    pub(crate) to: NodeIndex,
+    /// Whether the dependent is dirty.
+    pub(crate) dirty: bool,
+    /// The label given with [`Dcg::label_edge`], if any.
+    pub(crate) label: Option<String>,
+    /// Whether the dependency was added with [`Dcg::add_weak_dependency`].
+    pub(crate) weak: bool,
+}
+
+/// Shortens `value` to `max_len` characters, marking it as truncated.
+fn truncate(value: String, max_len: usize) -> String {
+    match value.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value,
+    }
+}
+
+impl Dcg {
+    /// Labels the dependency of `to` on `from`, for example with the parameter it feeds, to be
+    /// shown by exports such as [`to_dot`](Dcg::to_dot). Returns `false`, labelling nothing, if
+    /// `to` doesn't depend on `from`.
+    ///
+    /// Labels are dropped along with their dependency, such as when a
+    /// [dynamic](Dcg::dynamic_buffer) node stops reading `from`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    ///
+    /// assert!(dcg.label_edge(a.node(), b.node(), "addend"));
+    /// assert!(!dcg.label_edge(b.node(), a.node(), "addend"));
+    /// assert_eq!(dcg.edge_label(a.node(), b.node()).as_deref(), Some("addend"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` belongs to another [`Dcg`].
+    pub fn label_edge(&self, from: &Node, to: &Node, label: impl Into<String>) -> bool {
+        assert!(
+            Rc::ptr_eq(&from.inner, &self.inner) && Rc::ptr_eq(&to.inner, &self.inner),
+            "labelled nodes must belong to the Dcg"
+        );
+        if !self.inner.graph.borrow().contains_edge(from.idx, to.idx) {
+            return false;
+        }
+        self.inner
+            .edge_labels
+            .borrow_mut()
+            .insert((from.idx, to.idx), label.into());
+        true
+    }
+
+    /// Returns the label given to the dependency of `to` on `from` with
+    /// [`label_edge`](Dcg::label_edge), if any.
+    pub fn edge_label(&self, from: &Node, to: &Node) -> Option<String> {
+        self.inner
+            .edge_labels
+            .borrow()
+            .get(&(from.idx, to.idx))
+            .cloned()
+    }
+
+    /// Returns every dirty [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer), in index order,
+    /// with its possibly out of date value, without recomputing anything.
+    ///
+    /// Nodes that have never been evaluated, or whose caches were emptied, are included as not
+    /// [`cached`](StaleMemo::cached). A [`Buffer`](crate::Buffer)'s value itself is available
+    /// from its handle with [`last_computed`](crate::RawBuffer::last_computed).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// b.show_value();
+    ///
+    /// b.read();
+    /// a.write(2);
+    /// let stale = dcg.stale_memos();
+    /// assert_eq!(stale.len(), 1);
+    /// assert_eq!(stale[0].value.as_deref(), Some("2"));
+    /// assert_eq!(b.last_computed(), Some(2));
+    /// ```
+    pub fn stale_memos(&self) -> Vec<StaleMemo> {
+        let (nodes, _) = self.describe(true, usize::MAX);
+        nodes
+            .into_iter()
+            .filter(|node| node.dirty && matches!(node.kind, NodeKind::Memo | NodeKind::Buffer))
+            .map(|node| StaleMemo {
+                idx: node.idx,
+                kind: node.kind,
+                name: node.name,
+                cached: node.cached,
+                value: node.value,
+            })
+            .collect()
+    }
+
+    /// Describes every node and edge in index order, including values truncated to
+    /// `max_value_len` characters if `values` is set.
+    pub(crate) fn describe(
+        &self,
+        values: bool,
+        max_value_len: usize,
+    ) -> (Vec<NodeDescription>, Vec<EdgeDescription>) {
+        let mut labels = HashMap::new();
+        if values {
+            let value_labels: Vec<_> = self
+                .inner
+                .value_labels
+                .borrow()
+                .iter()
+                .map(|(&node, label)| (node, label.clone()))
+                .collect();
+            for (node, label) in value_labels {
+                if let Some(value) = label() {
+                    labels.insert(node, truncate(value, max_value_len));
+                }
+            }
+        }
+
+        let graph = self.inner.graph.borrow();
+        let kinds = self.inner.kinds.borrow();
+        let names = self.inner.names.borrow();
+        let cached = self.inner.cached.borrow();
+        let nodes = graph
+            .node_indices()
+            .map(|idx| NodeDescription {
+                idx,
+                kind: kinds[&idx],
+                name: names.get(&idx).cloned(),
+                dirty: self.inner.is_dirty(idx),
+                cached: cached.contains(&idx),
+                value: labels.remove(&idx),
+            })
+            .collect();
+        let edge_labels = self.inner.edge_labels.borrow();
+        let mut edges: Vec<_> = graph
+            .edge_references()
+            .map(|edge| EdgeDescription {
+                from: edge.source(),
+                to: edge.target(),
+                dirty: self.inner.is_dirty(edge.target()),
+                label: edge_labels.get(&(edge.source(), edge.target())).cloned(),
+                weak: false,
+            })
+            .collect();
+        let mut weak_edges: Vec<_> = self.inner.weak_edges.borrow().iter().copied().collect();
+        weak_edges.sort();
+        edges.extend(weak_edges.into_iter().map(|(from, to)| EdgeDescription {
+            from,
+            to,
+            dirty: self.inner.is_dirty(to),
+            label: None,
+            weak: true,
+        }));
+        (nodes, edges)
+    }
+}
@@ -0,0 +1,145 @@
+//! Hashing a [`Dcg`]'s structure with [`Dcg::fingerprint`].
+
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+
+use crate::{Dcg, NodeKind};
+
+/// A 64-bit FNV-1a hash. Unlike [`DefaultHasher`](std::collections::hash_map::DefaultHasher), its
+/// output is specified, so fingerprints can be compared across builds and platforms.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn usize(&mut self, n: usize) {
+        self.bytes(&(n as u64).to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.bytes(s.as_bytes());
+    }
+}
+
+impl Dcg {
+    /// Returns a hash of the [`Dcg`]'s structure: each node's kind and
+    /// [name](crate::Node::set_name), in creation order, and its dependencies and
+    /// [weak dependencies](Dcg::add_weak_dependency).
+    ///
+    /// Values and dirtiness are excluded, so the fingerprint only changes when the topology
+    /// does. Dependencies are sorted before hashing, so the order they were added in doesn't
+    /// matter. The hash function is fixed, so two [`Dcg`]s built the same way have the same
+    /// fingerprint in any run, which makes it suitable for detecting accidental changes to code
+    /// building a graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let build = || {
+    ///     let dcg = Dcg::default();
+    ///     let a = dcg.var(1);
+    ///     let b = buffer!(dcg, a => a + 1);
+    ///     (dcg, a, b)
+    /// };
+    /// let (first, _, _) = build();
+    /// let (second, a, b) = build();
+    /// assert_eq!(first.fingerprint(), second.fingerprint());
+    ///
+    /// a.write(2);
+    /// assert_eq!(first.fingerprint(), second.fingerprint());
+    /// b.node().set_name("b");
+    /// assert_ne!(first.fingerprint(), second.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        self.structure_hash().0
+    }
+
+    /// Like [`fingerprint`](Dcg::fingerprint), but also hashing the values of nodes registered
+    /// with `persist`, as they are [saved](Dcg::to_saved), for keying caches of whole graphs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::Dcg;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// a.persist();
+    /// let before = dcg.fingerprint_with_values();
+    ///
+    /// a.write(2);
+    /// assert_ne!(dcg.fingerprint_with_values(), before);
+    /// a.write(1);
+    /// assert_eq!(dcg.fingerprint_with_values(), before);
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn fingerprint_with_values(&self) -> u64 {
+        let mut hash = self.structure_hash();
+        for node in self.to_saved().nodes {
+            match node.value {
+                Some(value) => {
+                    hash.bytes(&[1]);
+                    hash.str(&value.to_string());
+                }
+                None => hash.bytes(&[0]),
+            }
+        }
+        hash.0
+    }
+
+    fn structure_hash(&self) -> Fnv {
+        let mut hash = Fnv::new();
+        let graph = self.inner.graph.borrow();
+        let kinds = self.inner.kinds.borrow();
+        let names = self.inner.names.borrow();
+
+        hash.usize(graph.node_count());
+        for idx in graph.node_indices() {
+            hash.bytes(&[match kinds[&idx] {
+                NodeKind::Var => 0,
+                NodeKind::Thunk => 1,
+                NodeKind::Memo => 2,
+                NodeKind::Buffer => 3,
+            }]);
+            match names.get(&idx) {
+                Some(name) => {
+                    hash.bytes(&[1]);
+                    hash.str(name);
+                }
+                None => hash.bytes(&[0]),
+            }
+        }
+
+        let mut edges: Vec<_> = graph
+            .edge_references()
+            .map(|edge| (edge.source().index(), edge.target().index()))
+            .collect();
+        let mut weak_edges: Vec<_> = self
+            .inner
+            .weak_edges
+            .borrow()
+            .iter()
+            .map(|(from, to)| (from.index(), to.index()))
+            .collect();
+        for edges in [&mut edges, &mut weak_edges] {
+            edges.sort_unstable();
+            hash.usize(edges.len());
+            for &(from, to) in edges.iter() {
+                hash.usize(from);
+                hash.usize(to);
+            }
+        }
+        hash
+    }
+}
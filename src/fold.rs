@@ -0,0 +1,221 @@
+//! Freezing [`Var`](crate::Var)s and folding the nodes computed only from frozen
+//! [`Var`](crate::Var)s into constants with [`Dcg::fold_constants`], or turning a single
+//! [`Buffer`] into one with [`Dcg::retire`].
+
+use std::collections::HashSet;
+
+use petgraph::{
+    algo::toposort,
+    graph::NodeIndex,
+    Direction::{Incoming, Outgoing},
+};
+
+use crate::{
+    incremental::Incremental, partial::reach, Buffer, Dcg, DcgError, Event, Node, NodeKind, RawVar,
+};
+
+impl Node {
+    /// Returns `true` if the node is a frozen [`Var`](crate::Var) or a folded constant.
+    pub fn is_frozen(&self) -> bool {
+        self.inner.frozen.borrow().contains(&self.idx)
+    }
+
+    /// Returns [`Frozen`](DcgError::Frozen) if the node must not be written because it is frozen.
+    pub(crate) fn check_unfrozen(&self) -> Result<(), DcgError> {
+        if self.is_frozen() {
+            Err(DcgError::Frozen { target: self.idx })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T> RawVar<T> {
+    /// Freezes the [`Var`](crate::Var), so its value never changes again and
+    /// [`fold_constants`](Dcg::fold_constants) can fold its dependents.
+    ///
+    /// Later writes return [`Frozen`](DcgError::Frozen), or panic for the non-`try_` methods.
+    /// Values [staged](Dcg::stage) or [fed](Dcg::feed) to the [`Var`](crate::Var) are discarded.
+    pub fn freeze(&self) {
+        let inner = &self.node.inner;
+        inner.frozen.borrow_mut().insert(self.node.idx);
+        inner
+            .staged
+            .borrow_mut()
+            .retain(|(staged, _)| *staged != self.node.idx);
+    }
+}
+
+impl Dcg {
+    /// Evaluates every [`Memo`](crate::Memo) and [`Buffer`] whose dependencies are
+    /// all, transitively, [frozen](RawVar::freeze) [`Var`](crate::Var)s, and turns them into
+    /// constants, returning how many nodes were folded.
+    ///
+    /// A folded node keeps its value, but loses its dependency edges, so it is never dirtied or
+    /// visited by traversals of its former dependencies again. It is itself
+    /// [frozen](Node::is_frozen), so later calls can fold its dependents. [`Thunk`](crate::Thunk)s
+    /// have no cached value to keep, so they are not folded, but nodes depending on frozen
+    /// [`Var`](crate::Var)s through them are. Nodes depending on any unfrozen
+    /// [`Var`](crate::Var) are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let config = dcg.var(2);
+    /// let input = dcg.var(1);
+    /// let scale = buffer!(dcg, config => config * 10);
+    /// let scaled = buffer!(dcg, (scale, input) => scale * input);
+    ///
+    /// config.freeze();
+    /// assert_eq!(dcg.fold_constants(), 1);
+    /// assert!(scale.node().is_frozen());
+    /// assert_eq!(dcg.edge_count(), 2);
+    /// assert_eq!(scaled.read(), 20);
+    /// assert!(config.try_write(3).is_err());
+    /// ```
+    pub fn fold_constants(&self) -> usize {
+        let order = {
+            let graph = self.inner.graph.borrow();
+            toposort(&*graph, None).expect("a Dcg's dependencies are acyclic")
+        };
+        let mut constant = self.inner.frozen.borrow().clone();
+        let mut folded = 0;
+        for idx in order {
+            if constant.contains(&idx) || self.inner.dynamic.borrow().contains(&idx) {
+                continue;
+            }
+            let dependencies: HashSet<_> = self
+                .inner
+                .graph
+                .borrow()
+                .neighbors_directed(idx, Incoming)
+                .collect();
+            if dependencies.is_empty() || !dependencies.is_subset(&constant) {
+                continue;
+            }
+            let refresher = self.inner.refreshers.borrow().get(&idx).cloned();
+            match refresher {
+                Some(refresher) => {
+                    if !refresher.refresh() {
+                        continue;
+                    }
+                    self.detach(idx);
+                    self.inner.frozen.borrow_mut().insert(idx);
+                    folded += 1;
+                }
+                None if self.inner.kinds.borrow().get(&idx) == Some(&NodeKind::Thunk) => {}
+                None => continue,
+            }
+            constant.insert(idx);
+        }
+        folded
+    }
+
+    /// Evaluates `buffer` and turns it into a constant holding the result, returning how many
+    /// nodes upstream of it were pruned.
+    ///
+    /// Like a [folded](Dcg::fold_constants) node, the retired [`Buffer`] is
+    /// [frozen](Node::is_frozen) and loses its dependency edges, so writes to its former
+    /// dependencies no longer dirty it or its dependents, which keep reading the result through
+    /// their handles as before. Its closure is dropped, releasing the handles it captured.
+    ///
+    /// If `prune` is set, the [`Memo`](crate::Memo)s and [`Buffer`]s upstream that are left with
+    /// no dependents, no [name](Node::set_name), and no handle anywhere else lose their
+    /// dependency edges too, cascading up the graph, so writes stop reaching them. The graph
+    /// doesn't track handles to [`Var`](crate::Var)s and [`Thunk`](crate::Thunk)s, so those are
+    /// never pruned, and neither is anything they depend on.
+    ///
+    /// Returns [`Frozen`](DcgError::Frozen) if `buffer` is already frozen, or
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) if called while a node is
+    /// being evaluated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let input = dcg.var(2);
+    /// let squared = buffer!(dcg, input => input * input);
+    /// let config = buffer!(dcg, squared => squared + 1);
+    /// let scaled = buffer!(dcg, config => config * 10);
+    /// drop(squared);
+    ///
+    /// assert_eq!(dcg.retire(&config, true), Ok(1));
+    /// assert_eq!(dcg.edge_count(), 1);
+    /// input.write(3);
+    /// assert!(scaled.is_dirty());
+    /// assert_eq!(scaled.read(), 50);
+    /// assert!(dcg.retire(&config, true).is_err());
+    /// ```
+    pub fn retire<T>(&self, buffer: &Buffer<T>, prune: bool) -> Result<usize, DcgError>
+    where
+        T: Clone + 'static,
+    {
+        let node = buffer.node();
+        node.check_mutable()?;
+        let value = buffer.read();
+        let upstream = reach(&self.inner.graph.borrow(), &[node.idx], Incoming);
+        drop(buffer.thunk.f.replace(Box::new(move || value.clone())));
+        self.detach(node.idx);
+        self.inner.frozen.borrow_mut().insert(node.idx);
+        if !prune {
+            return Ok(0);
+        }
+
+        let order = {
+            let graph = self.inner.graph.borrow();
+            toposort(&*graph, None).expect("a Dcg's dependencies are acyclic")
+        };
+        let mut pruned = 0;
+        for idx in order.into_iter().rev().filter(|idx| upstream.contains(idx)) {
+            let prunable = self
+                .inner
+                .graph
+                .borrow()
+                .neighbors_directed(idx, Outgoing)
+                .next()
+                .is_none()
+                && !self.inner.names.borrow().contains_key(&idx)
+                && self
+                    .inner
+                    .refreshers
+                    .borrow()
+                    .get(&idx)
+                    .is_some_and(|refresher| refresher.is_dropped());
+            if prunable {
+                self.detach(idx);
+                self.inner.refreshers.borrow_mut().remove(&idx);
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Removes the dependency edges and [weak dependencies](Dcg::add_weak_dependency) into
+    /// `idx`.
+    pub(crate) fn detach(&self, idx: NodeIndex) {
+        let mut dependencies: Vec<_> = self
+            .inner
+            .graph
+            .borrow()
+            .neighbors_directed(idx, Incoming)
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+        self.inner
+            .graph
+            .borrow_mut()
+            .retain_edges(|graph, edge| graph.edge_endpoints(edge).map(|(_, to)| to) != Some(idx));
+        self.inner
+            .weak_edges
+            .borrow_mut()
+            .retain(|&(_, to)| to != idx);
+        for from in dependencies {
+            self.inner.emit(Event::EdgeRemoved { from, to: idx });
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! [`AsyncBuffer`]s, whose values are computed by [`Future`]s, and cleaning a [`Dcg`] without
+//! blocking an executor with [`Dcg::clean_all_yielding`].
+//!
+//! An [`AsyncBuffer`] is a [`Buffer`](crate::Buffer) whose closure returns a [`Future`]: the
+//! closure itself runs synchronously, reading its dependencies, and the returned [`Future`] is
+//! awaited by [`read_async`](RawAsyncBuffer::read_async). Concurrent reads of a dirty
+//! [`AsyncBuffer`] share one in-flight computation.
+
+use std::{
+    cell::RefCell,
+    future::{poll_fn, Future},
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Wake, Waker},
+};
+
+use petgraph::{graph::NodeIndex, Direction::Incoming};
+
+use crate::{incremental::Incremental, partial::reach, Dcg, Node, NodeKind};
+
+/// What a call to [`Dcg::clean_all_yielding`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanSummary {
+    /// The number of nodes evaluated.
+    pub cleaned: usize,
+    /// The number of times the clean yielded to the executor.
+    pub yields: usize,
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// Reference-counted [`RawAsyncBuffer`].
+pub type AsyncBuffer<T> = Rc<RawAsyncBuffer<T>>;
+
+impl Dcg {
+    /// Creates a dirty [`AsyncBuffer`], adding incoming dependency edges from `params` and storing
+    /// `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    /// # use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     let mut future = pin!(future);
+    /// #     loop {
+    /// #         if let Poll::Ready(value) = future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+    /// #             return value;
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.async_buffer(&a, {
+    ///     let a = a.clone();
+    ///     move || {
+    ///         let a = a.read();
+    ///         Box::pin(async move { a + 1 })
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(block_on(b.read_async()), 2);
+    /// a.write(2);
+    /// assert_eq!(block_on(b.read_async()), 3);
+    /// ```
+    pub fn async_buffer<P, F, T>(&self, params: &P, f: F) -> AsyncBuffer<T>
+    where
+        P: Incremental,
+        F: Fn() -> BoxFuture<T> + 'static,
+    {
+        for node in params.nodes() {
+            node.assert_current();
+        }
+        let node = Node::new(self, NodeKind::Buffer);
+        node.add_dependencies(params);
+        Rc::new(RawAsyncBuffer {
+            f: Box::new(f),
+            node,
+            buffered: RefCell::default(),
+            in_flight: RefCell::default(),
+        })
+    }
+
+    /// Evaluates every dirty [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer), in the order
+    /// of [`prefetch`](Dcg::prefetch), yielding to the executor after every `every` evaluations.
+    ///
+    /// This keeps a single-threaded event loop responsive while a large graph is cleaned. Other
+    /// tasks may write [`Var`](crate::Var)s while the clean is suspended: the dirty nodes are
+    /// gathered again after each yield, so the future only completes once every node is clean.
+    /// If writes never stop, neither does the clean.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, future::CleanSummary, incremental::Incremental};
+    /// # use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     let mut future = pin!(future);
+    /// #     loop {
+    /// #         if let Poll::Ready(value) = future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+    /// #             return value;
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 2);
+    ///
+    /// assert_eq!(
+    ///     block_on(dcg.clean_all_yielding(1)),
+    ///     CleanSummary { cleaned: 2, yields: 1 }
+    /// );
+    /// assert!(c.is_clean());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    pub fn clean_all_yielding(&self, every: usize) -> impl Future<Output = CleanSummary> + '_ {
+        assert!(every > 0, "cannot yield after every 0 evaluations");
+        async move {
+            let mut summary = CleanSummary {
+                cleaned: 0,
+                yields: 0,
+            };
+            let mut since_yield = 0;
+            loop {
+                let mut candidates = self.dirty_refreshable();
+                if candidates.is_empty() {
+                    return summary;
+                }
+                self.prioritize(&mut candidates);
+                let before = summary.cleaned;
+                for idx in candidates {
+                    if since_yield == every {
+                        break;
+                    }
+                    if self.refresh(idx) {
+                        summary.cleaned += 1;
+                        since_yield += 1;
+                    }
+                }
+                if since_yield < every {
+                    // Only dropped nodes are left dirty if nothing was evaluated.
+                    if summary.cleaned == before {
+                        return summary;
+                    }
+                } else if !self.dirty_refreshable().is_empty() {
+                    YieldNow(false).await;
+                    summary.yields += 1;
+                    since_yield = 0;
+                }
+            }
+        }
+    }
+}
+
+/// A future that is pending the first time it is polled, waking its task immediately.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// A [`Buffer`](crate::Buffer) computed by a [`Future`].
+pub struct RawAsyncBuffer<T> {
+    f: Box<dyn Fn() -> BoxFuture<T>>,
+    node: Node,
+    buffered: RefCell<Option<T>>,
+    in_flight: RefCell<Option<Rc<Shared<T>>>>,
+}
+
+impl<T> RawAsyncBuffer<T> {
+    /// Returns the [`AsyncBuffer`]'s [`Node`].
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    /// Returns whether the [`AsyncBuffer`] is dirty.
+    pub fn is_dirty(&self) -> bool {
+        self.node.is_dirty()
+    }
+
+    /// Returns the [versions](Dcg::version) of the [`Var`](crate::Var)s the [`AsyncBuffer`]
+    /// depends on, directly or through other nodes, in index order.
+    fn input_versions(&self) -> Vec<(NodeIndex, u64)> {
+        let inner = &self.node.inner;
+        let mut vars: Vec<_> = reach(&inner.graph.borrow(), &[self.node.idx], Incoming)
+            .into_iter()
+            .filter(|idx| inner.kinds.borrow().get(idx) == Some(&NodeKind::Var))
+            .collect();
+        vars.sort();
+        let versions = inner.versions.borrow();
+        vars.into_iter()
+            .map(|idx| (idx, versions.get(&idx).copied().unwrap_or_default()))
+            .collect()
+    }
+}
+
+impl<T: Clone> RawAsyncBuffer<T> {
+    /// Returns the buffered value if the [`AsyncBuffer`] is clean, or else awaits a new one.
+    ///
+    /// If the [`AsyncBuffer`] is already being recomputed, this awaits the same computation
+    /// instead of starting another. The new value is only buffered, and the [`AsyncBuffer`]
+    /// cleaned, if no [`Var`](crate::Var) it depends on, directly or through other nodes, changed
+    /// while it was computed. Writes to other [`Var`](crate::Var)s don't affect it.
+    pub async fn read_async(&self) -> T {
+        if !self.node.is_dirty() {
+            if let Some(value) = self.buffered.borrow().clone() {
+                self.node.record_cache_read(true);
+                return value;
+            }
+        }
+        let existing = self.in_flight.borrow().clone();
+        let shared = match existing {
+            Some(shared) => shared,
+            None => {
+                let inputs = self.input_versions();
+                let future = (self.f)();
+                let shared = Rc::new(Shared {
+                    future: RefCell::new(Some(future)),
+                    output: RefCell::default(),
+                    wakers: Arc::default(),
+                    inputs,
+                });
+                self.in_flight.replace(Some(shared.clone()));
+                shared
+            }
+        };
+
+        let value = poll_fn(|cx| shared.poll(cx)).await;
+        let finished = self
+            .in_flight
+            .borrow()
+            .as_ref()
+            .is_some_and(|in_flight| Rc::ptr_eq(in_flight, &shared));
+        if finished {
+            self.in_flight.replace(None);
+            self.node.record_cache_read(false);
+            if shared.inputs == self.input_versions() {
+                self.buffered.replace(Some(value.clone()));
+                self.node.bump_version();
+                self.node.mark_cached();
+                self.node.clean();
+            }
+        }
+        value
+    }
+}
+
+/// A computation awaited by every concurrent [`RawAsyncBuffer::read_async`].
+struct Shared<T> {
+    future: RefCell<Option<BoxFuture<T>>>,
+    output: RefCell<Option<T>>,
+    wakers: Arc<Wakers>,
+    /// The [`input_versions`](RawAsyncBuffer::input_versions) when the computation started.
+    inputs: Vec<(NodeIndex, u64)>,
+}
+
+impl<T: Clone> Shared<T> {
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<T> {
+        if let Some(value) = &*self.output.borrow() {
+            return Poll::Ready(value.clone());
+        }
+        self.wakers.register(cx.waker());
+        let waker = Waker::from(self.wakers.clone());
+        let mut future = self.future.borrow_mut();
+        let poll = future
+            .as_mut()
+            .expect("a pending computation has a future")
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker));
+        if let Poll::Ready(value) = &poll {
+            *future = None;
+            self.output.replace(Some(value.clone()));
+            self.wakers.clone().wake();
+        }
+        poll
+    }
+}
+
+/// Wakes every task awaiting a [`Shared`] computation.
+#[derive(Default)]
+struct Wakers(Mutex<Vec<Waker>>);
+
+impl Wakers {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl Wake for Wakers {
+    fn wake(self: Arc<Self>) {
+        let wakers = std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
@@ -13,9 +13,9 @@ use crate::Node;
 /// `I` is either:
 ///
 /// - **clean**: no dependencies have changed, reading `I` yields the same
-/// [`Output`](Incremental::Output) as last read.
+///   [`Output`](Incremental::Output) as last read.
 /// - **dirty**: a dependency has changed, reading `I` computes a different
-/// [`Output`](Incremental::Output).
+///   [`Output`](Incremental::Output).
 ///
 /// `I`'s state can be interrogated using [`is_dirty`](Incremental::is_dirty) and
 /// [`is_clean`](Incremental::is_clean).
@@ -24,7 +24,7 @@ use crate::Node;
 ///
 /// - [`read`](Incremental::read): cleans `I`'s [`Node`]s and returns `I`'s most up-to-date value.
 /// - [`latest`](Incremental::read): returns `I`s most up-to-date value without affecting the
-/// DCG.
+///   DCG.
 pub trait Incremental {
     /// The type returned when reading or reading a node.
     type Output;
@@ -32,20 +32,37 @@ pub trait Incremental {
     /// Computes the [`Incremental`]'s most recent value, cleans its nodes and returns the computed
     /// value.
     ///
-    /// The default implementation stores the result of [`latest`](Incremental::latest), cleans the
-    /// [`Incremental`]'s [`Node`]'s and returns the result.
+    /// The default implementation records the read against the node currently being evaluated (see
+    /// [`Dcg::unused_dependencies`](crate::Dcg::unused_dependencies)), stores the result of
+    /// [`latest`](Incremental::latest), cleans the [`Incremental`]'s [`Node`]'s and returns the
+    /// result. In debug builds, the DCG's invariants are checked after outermost reads (see
+    /// [`Dcg::check_invariants`](crate::Dcg::check_invariants)). It panics with
+    /// [`StaleHandle`](crate::DcgError::StaleHandle) if any of the [`Node`]s has been removed.
     ///
     /// The default implementation should not be overriden unless non-standard behaviour is
     /// required.
     fn read(&self) -> Self::Output {
+        let nodes = self.nodes();
+        #[cfg(feature = "tracing")]
+        let _span = match nodes[..] {
+            [node] => Some(tracing::debug_span!("read", node = node.index().index()).entered()),
+            _ => None,
+        };
+        for node in &nodes {
+            node.assert_current();
+            node.record_read();
+        }
         let value = self.latest();
         if self.is_dirty() {
-            for node in self.nodes() {
+            for node in &nodes {
                 if node.is_dirty() {
                     node.clean();
                 }
             }
         }
+        if let Some(node) = nodes.first() {
+            node.debug_check();
+        }
         value
     }
 
@@ -123,12 +140,35 @@ where
     }
 }
 
+impl<A, T, const N: usize> Incremental for [A; N]
+where
+    A: Incremental<Output = T>,
+{
+    type Output = [T; N];
+
+    fn latest(&self) -> Self::Output {
+        self.each_ref().map(Incremental::read)
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.iter().any(Incremental::is_dirty)
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        self.iter().flat_map(Incremental::nodes).collect()
+    }
+}
+
 impl<T, O> Incremental for Rc<T>
 where
     T: Incremental<Output = O>,
 {
     type Output = O;
 
+    fn read(&self) -> Self::Output {
+        self.as_ref().read()
+    }
+
     fn latest(&self) -> Self::Output {
         self.as_ref().read()
     }
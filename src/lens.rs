@@ -0,0 +1,156 @@
+//! [`Lens`]es: [`Buffer`]s derived from a [`Var`] that can be written through.
+//!
+//! A [`Lens`] computes its value from its source [`Var`] with a `forward` function, and writes
+//! to it by mapping the written value back with a `backward` function. The two functions should
+//! be inverses for reads to round-trip writes.
+
+use std::rc::Rc;
+
+use crate::{incremental::Incremental, Buffer, Dcg, DcgError, Node, Var};
+
+/// Reference-counted [`RawLens`].
+pub type Lens<S, T> = Rc<RawLens<S, T>>;
+
+impl Dcg {
+    /// Creates a dirty [`Lens`] over `source`, buffering `forward` of `source`'s value, and
+    /// writing through to `source` with `backward`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let celsius = dcg.var(100.0);
+    /// let fahrenheit = dcg.lens(&celsius, |c| c * 9.0 / 5.0 + 32.0, |f| (f - 32.0) * 5.0 / 9.0);
+    ///
+    /// assert_eq!(fahrenheit.read(), 212.0);
+    /// fahrenheit.write(32.0);
+    /// assert_eq!(celsius.read(), 0.0);
+    /// assert_eq!(fahrenheit.read(), 32.0);
+    /// ```
+    pub fn lens<S, T, F, B>(&self, source: &Var<S>, forward: F, backward: B) -> Lens<S, T>
+    where
+        S: Clone + 'static,
+        T: Clone + 'static,
+        F: Fn(&S) -> T + 'static,
+        B: Fn(&T) -> S + 'static,
+    {
+        self.lens_with(source, Rc::new(forward), Rc::new(backward))
+    }
+
+    fn lens_with<S, T>(
+        &self,
+        source: &Var<S>,
+        forward: Rc<dyn Fn(&S) -> T>,
+        backward: Rc<dyn Fn(&T) -> S>,
+    ) -> Lens<S, T>
+    where
+        S: Clone + 'static,
+        T: Clone + 'static,
+    {
+        let buffer = self.buffer(source, {
+            let (source, forward) = (source.clone(), forward.clone());
+            move || forward(&source.read())
+        });
+        Rc::new(RawLens {
+            buffer,
+            source: source.clone(),
+            forward,
+            backward,
+        })
+    }
+}
+
+/// A [`Buffer`] of a function of a [`Var`], which can be written through to the [`Var`].
+pub struct RawLens<S, T> {
+    buffer: Buffer<T>,
+    source: Var<S>,
+    forward: Rc<dyn Fn(&S) -> T>,
+    backward: Rc<dyn Fn(&T) -> S>,
+}
+
+impl<S, T> RawLens<S, T> {
+    /// Returns the [`Lens`]'s [`Node`].
+    pub fn node(&self) -> &Node {
+        self.buffer.node()
+    }
+
+    /// Returns the [`Var`] the [`Lens`] reads and writes.
+    pub fn source(&self) -> &Var<S> {
+        &self.source
+    }
+}
+
+impl<S: Clone + 'static, T: Clone + 'static> RawLens<S, T> {
+    /// Creates a [`Lens`] over this one's source that applies `forward` after this [`Lens`]'s
+    /// forward function, and `backward` before its backward function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let cents = dcg.var(250);
+    /// let dollars = dcg.lens(&cents, |c| *c as f64 / 100.0, |d| (d * 100.0) as i32);
+    /// let label = dollars.lens(|d| format!("${d:.2}"), |s| s[1..].parse().unwrap());
+    ///
+    /// assert_eq!(label.read(), "$2.50");
+    /// label.write("$4.00".to_owned());
+    /// assert_eq!(cents.read(), 400);
+    /// ```
+    pub fn lens<U, F, B>(&self, forward: F, backward: B) -> Lens<S, U>
+    where
+        U: Clone + 'static,
+        F: Fn(&T) -> U + 'static,
+        B: Fn(&U) -> T + 'static,
+    {
+        let (inner_forward, inner_backward) = (self.forward.clone(), self.backward.clone());
+        let dcg = Dcg {
+            inner: self.source.node.inner.clone(),
+        };
+        dcg.lens_with(
+            &self.source,
+            Rc::new(move |source| forward(&inner_forward(source))),
+            Rc::new(move |value| inner_backward(&backward(value))),
+        )
+    }
+}
+
+impl<S: PartialEq + Clone, T> RawLens<S, T> {
+    /// Writes `backward` of `value` into the source [`Var`], returning the source's previous
+    /// value. Dirties the source's dependents, including the [`Lens`], as
+    /// [`write`](crate::RawVar::write) does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated; see [`try_write`](RawLens::try_write).
+    pub fn write(&self, value: T) -> S {
+        self.source.write((self.backward)(&value))
+    }
+
+    /// Like [`write`](RawLens::write), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) instead of writing if
+    /// called while a node is being evaluated, or [`Frozen`](DcgError::Frozen) if the source is
+    /// frozen.
+    pub fn try_write(&self, value: T) -> Result<S, DcgError> {
+        self.source.try_write((self.backward)(&value))
+    }
+}
+
+impl<S, T: Clone + 'static> Incremental for RawLens<S, T> {
+    type Output = T;
+
+    fn latest(&self) -> Self::Output {
+        self.buffer.latest()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.buffer.is_dirty()
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        self.buffer.nodes()
+    }
+}
@@ -129,27 +129,918 @@
 //! let area = buffer!(dcg, radius => PI * radius * radius);        // radius used here
 //! let circumference = buffer!(dcg, radius => 2. * PI * radius);   // ... and here
 //! ```
+//!
+//! # Features
+//!
+//! - `tracing`: instruments the [`Dcg`] with [`tracing`](https://docs.rs/tracing) at `debug`
+//!   level: a `read` span per node read, a `write` span per [`Var`] change recording how many
+//!   nodes were `dirtied`, and a `recomputed` event with the `elapsed` time per closure execution.
+//! - `serde`: saves a [`Dcg`]'s topology and values and rebuilds it from them, or exports its
+//!   current values as JSON. See `persist`.
+//! - `timings`: measures every closure execution, reported by `Dcg::timings`.
+//! - `async`: adds `Buffer`s computed by futures. See `future`.
+//! - `arbitrary`: generates random [`Dcg`]s for property testing. See `testing::arbitrary`.
+//! - `derive`: adds `#[derive(DcgCells)]`, turning each field of a struct into a [`Var`].
 
 use petgraph::{
+    algo::toposort,
     dot::Dot,
-    graph::{DiGraph, NodeIndex},
-    visit::{depth_first_search, Control, DfsEvent},
+    graph::NodeIndex,
+    stable_graph::StableDiGraph,
+    visit::{depth_first_search, Control, DfsEvent, EdgeRef},
+    Direction::Incoming,
 };
 
+// Lets the `DcgCells` derive's `::cachegrab` paths resolve in this crate's own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as cachegrab;
+
+#[cfg(feature = "derive")]
+pub use cachegrab_derive::DcgCells;
 #[doc(hidden)]
 pub use paste::paste;
-use std::{cell::RefCell, collections::HashMap, fmt, hash::Hash, rc::Rc};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    error, fmt,
+    hash::Hash,
+    ops::Deref,
+    rc::{self, Rc},
+    sync::mpsc,
+    thread,
+};
+pub mod bulk;
+mod classes;
+pub mod clock;
+pub mod collections;
+mod components;
+mod csv;
+mod deferred;
+#[cfg(feature = "serde")]
+pub mod diff;
+pub mod dot;
+mod eager;
+mod eviction;
+mod export;
+mod fingerprint;
+mod fold;
+#[cfg(feature = "async")]
+pub mod future;
 pub mod incremental;
+pub mod lens;
+mod link;
+pub mod mermaid;
+pub mod named;
+mod partial;
+mod pending;
+#[cfg(feature = "serde")]
+pub mod persist;
+pub mod plan;
+pub mod reader;
+mod schedule;
+pub mod settle;
+mod sinks;
+pub mod sync;
+mod tags;
+pub mod testing;
+pub mod trace;
+pub mod undo;
+mod values;
+mod verify;
+pub mod visit;
+pub mod watch;
+mod weak;
+use classes::ALL_CHANGES;
 use incremental::Incremental;
 
-type Graph = DiGraph<bool, ()>;
+type Graph = StableDiGraph<bool, ()>;
 
 /// Creates- and stores dependencies between- data and compute nodes in an incremental computation.
 #[derive(Default)]
 pub struct Dcg {
-    graph: Rc<RefCell<Graph>>,
+    inner: Rc<Inner>,
+}
+
+/// State shared between a [`Dcg`] and its [`Node`]s.
+#[derive(Default)]
+struct Inner {
+    graph: RefCell<Graph>,
+    kinds: RefCell<HashMap<NodeIndex, NodeKind>>,
+    names: RefCell<HashMap<NodeIndex, String>>,
+    #[cfg(feature = "serde")]
+    persisted: RefCell<HashMap<NodeIndex, Rc<dyn persist::Persist>>>,
+    value_labels: RefCell<HashMap<NodeIndex, ValueLabel>>,
+    /// See [`Dcg::label_edge`].
+    edge_labels: RefCell<HashMap<(NodeIndex, NodeIndex), String>>,
+    /// The `(dependency, dependent)` pairs added by [`Dcg::add_weak_dependency`], which aren't
+    /// edges of the graph.
+    weak_edges: RefCell<HashSet<(NodeIndex, NodeIndex)>>,
+    /// The [`Eager`](RecomputePolicy::Eager) nodes.
+    eager: RefCell<HashSet<NodeIndex>>,
+    /// See [`Dcg::with_strategy`].
+    strategy: Cell<VerificationStrategy>,
+    /// The versions of each node's inputs when it was last cleaned; see [`Dcg::provenance`].
+    verified: RefCell<HashMap<NodeIndex, HashMap<NodeIndex, u64>>>,
+    /// Whether each node was found dirty under [`Versions`](VerificationStrategy::Versions), and
+    /// the revision it was checked at.
+    checked: RefCell<HashMap<NodeIndex, (u64, bool)>>,
+    /// Incremented whenever a write or a change to the graph may dirty any node under
+    /// [`Versions`](VerificationStrategy::Versions), discarding every `checked` answer.
+    revision: Cell<u64>,
+    /// The number of nodes checked under [`Versions`](VerificationStrategy::Versions).
+    #[cfg(test)]
+    version_checks: Cell<u64>,
+    /// The events of each [`Dcg::trace`] in progress, innermost last.
+    traces: RefCell<Vec<Vec<trace::TraceEvent>>>,
+    /// The [`Memo`]s and [`Buffer`]s holding at least one value.
+    cached: RefCell<HashSet<NodeIndex>>,
+    /// The error from each [`Dcg::buffer_or`]'s latest evaluation, as a `RefCell<Option<E>>`.
+    errors: RefCell<HashMap<NodeIndex, Rc<dyn Any>>>,
+    /// The nodes whose dependencies are replaced by what they read in each evaluation.
+    dynamic: RefCell<HashSet<NodeIndex>>,
+    /// The nodes with each tag; see [`Dcg::tag`].
+    tags: RefCell<HashMap<String, HashSet<NodeIndex>>>,
+    /// Cleared whenever a node or dependency is added or removed.
+    components: RefCell<Option<Rc<components::Components>>>,
+    /// Incremented whenever a node or dependency is added or removed.
+    generation: Cell<u64>,
+    /// See [`Dcg::set_skip_thunk_sinks`].
+    skip_thunk_sinks: Cell<bool>,
+    /// The nodes writes dirty while skipping [`Thunk`] sinks, cleared whenever a node, dependency
+    /// or [`on_dirtied`](Dcg::on_dirtied) hook is added or removed.
+    observed: RefCell<Option<Rc<HashSet<NodeIndex>>>>,
+    /// The clean [`Thunk`]s writes skipped, from which dirtiness is yet to propagate.
+    skipped: RefCell<HashSet<NodeIndex>>,
+    /// The frozen [`Var`]s and folded constants; see [`Dcg::fold_constants`].
+    frozen: RefCell<HashSet<NodeIndex>>,
+    /// [Deferred](Dcg::deferred_buffer) nodes whose dependencies are yet to be finalized.
+    unfinalized: RefCell<HashSet<NodeIndex>>,
+    /// [Pending](Dcg::pending_var) [`Var`]s yet to be fulfilled.
+    pending: RefCell<HashSet<NodeIndex>>,
+    /// The change classes each [`Buffer`] is [sensitive to](Dcg::buffer_sensitive_to), if not
+    /// every class.
+    sensitivities: RefCell<HashMap<NodeIndex, u8>>,
+    /// The nodes whose latest value was transformed by an [interceptor](RawThunk::intercept).
+    intercepted: RefCell<HashSet<NodeIndex>>,
+    /// See [`Dcg::track_stability`].
+    stability: RefCell<HashMap<NodeIndex, testing::StabilityCheck>>,
+    /// Every [`Memo`] and [`Buffer`], for [`Dcg::clean_until`] and [`Dcg::clean_depth`].
+    refreshers: RefCell<HashMap<NodeIndex, Rc<dyn partial::Refresh>>>,
+    /// Set by [`Dcg::set_clock`]; [`clock::SystemClock`] if [`None`].
+    clock: RefCell<Option<Rc<dyn clock::Clock>>>,
+    /// The [`Buffer`]s created by [`Dcg::memo_with_ttl`].
+    expiries: RefCell<HashMap<NodeIndex, clock::Expiry>>,
+    /// Set by [`Dcg::set_cache_capacity`]; unbounded if [`None`].
+    cache_capacity: Cell<Option<usize>>,
+    recency: RefCell<eviction::Recency>,
+    /// The generation of each index whose node was removed, bumped on every removal; 0 if
+    /// absent.
+    generations: RefCell<HashMap<NodeIndex, u32>>,
+    /// The closures executed during the current [`Dcg::read_with_budget`], if any.
+    budget: Cell<Option<usize>>,
+    next_id: Cell<usize>,
+    recompute_hooks: RefCell<Vec<RecomputeHook>>,
+    dirtied_hooks: RefCell<HashMap<NodeIndex, Vec<NodeHook>>>,
+    subscribers: RefCell<Vec<(SubscriptionId, Sink)>>,
+    feeds: RefCell<Vec<Feed>>,
+    /// See [`Dcg::link`].
+    links: RefCell<Vec<link::Link>>,
+    staged: RefCell<Vec<(NodeIndex, Staged)>>,
+    /// Values received by [`Dcg::try_pump`] that couldn't be written, to be retried.
+    unpumped: RefCell<Vec<(NodeIndex, Staged)>>,
+    /// See [`Dcg::current_tick`].
+    tick: Cell<u64>,
+    /// The writes [scheduled](Dcg::schedule) for each tick.
+    scheduled: RefCell<BTreeMap<u64, Vec<(NodeIndex, Staged)>>>,
+    cache_stats: RefCell<HashMap<NodeIndex, CacheStats>>,
+    costs: RefCell<HashMap<NodeIndex, (u64, u64)>>,
+    #[cfg(feature = "timings")]
+    timings: RefCell<HashMap<NodeIndex, (std::time::Duration, u64)>>,
+    audit: RefCell<AuditLog>,
+    evaluating: RefCell<Vec<Evaluation>>,
+    last_reads: RefCell<HashMap<NodeIndex, HashSet<NodeIndex>>>,
+    unchecked: Cell<bool>,
+    /// Incremented whenever a [`Var`] changes.
+    version: Cell<u64>,
+    /// The number of times each node's value has changed; see [`Dcg::version`].
+    versions: RefCell<HashMap<NodeIndex, u64>>,
+    undo: RefCell<undo::UndoLog>,
+}
+
+impl Inner {
+    /// Returns an identifier unique within the [`Dcg`].
+    fn next_id(&self) -> usize {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    /// Registers `label` to render the value of `node` in exports such as [`Dcg::to_dot`].
+    fn show_value(&self, node: NodeIndex, label: impl Fn() -> Option<String> + 'static) {
+        self.value_labels.borrow_mut().insert(node, Rc::new(label));
+    }
+
+    /// Sends `event` to every subscriber, without holding any borrows.
+    fn emit(&self, event: Event) {
+        if let Event::NodeAdded { .. }
+        | Event::NodeRemoved { .. }
+        | Event::EdgeAdded { .. }
+        | Event::EdgeRemoved { .. } = event
+        {
+            self.structure_changed();
+        }
+        match event {
+            Event::EdgeRemoved { from, to } => {
+                self.edge_labels.borrow_mut().remove(&(from, to));
+            }
+            Event::Recomputed { idx } => self.trace(trace::TraceEvent::Evaluated(idx)),
+            Event::NodeCleaned { idx } => self.trace(trace::TraceEvent::Cleaned(idx)),
+            _ => {}
+        }
+        if self.subscribers.borrow().is_empty() {
+            return;
+        }
+        let sinks: Vec<_> = self
+            .subscribers
+            .borrow()
+            .iter()
+            .map(|(_, sink)| sink.clone())
+            .collect();
+        for sink in sinks {
+            (sink.borrow_mut())(event);
+        }
+    }
+
+    /// Discards everything derived from the graph's structure.
+    fn structure_changed(&self) {
+        self.components.take();
+        self.observed.take();
+        self.new_revision();
+        self.generation.set(self.generation.get() + 1);
+    }
+
+    /// Returns every node's height: 0 for nodes without dependencies, or else one more than the
+    /// greatest height among its dependencies.
+    fn heights(&self) -> HashMap<NodeIndex, usize> {
+        let graph = self.graph.borrow();
+        let mut heights = HashMap::new();
+        for idx in toposort(&*graph, None).expect("a Dcg's dependencies are acyclic") {
+            let height = graph
+                .neighbors_directed(idx, Incoming)
+                .map(|dependency| heights[&dependency] + 1)
+                .max()
+                .unwrap_or(0);
+            heights.insert(idx, height);
+        }
+        heights
+    }
+
+    fn check_invariants(&self) -> Result<(), Vec<String>> {
+        let observed = self.peek_observed();
+        let graph = self.graph.borrow();
+        let mut violations = Vec::new();
+
+        if let Err(cycle) = toposort(&*graph, None) {
+            violations.push(format!(
+                "node {} is part of a dependency cycle",
+                cycle.node_id().index()
+            ));
+        }
+
+        let mut evaluating = HashSet::new();
+        for evaluation in self.evaluating.borrow().iter() {
+            if graph.node_weight(evaluation.node).is_none() {
+                violations.push(format!(
+                    "unknown node {} is being evaluated",
+                    evaluation.node.index()
+                ));
+            } else if !evaluating.insert(evaluation.node) {
+                violations.push(format!(
+                    "node {} is being evaluated re-entrantly",
+                    evaluation.node.index()
+                ));
+            }
+        }
+
+        let mut last_reads: Vec<_> = self.last_reads.borrow().clone().into_iter().collect();
+        last_reads.sort_by_key(|(node, _)| *node);
+        for (node, reads) in last_reads {
+            let dirty = match graph.node_weight(node) {
+                Some(dirty) => *dirty,
+                None => {
+                    violations.push(format!("reads recorded for unknown node {}", node.index()));
+                    continue;
+                }
+            };
+            let mut reads: Vec<_> = reads.into_iter().collect();
+            reads.sort();
+            for read in reads {
+                match graph.node_weight(read) {
+                    None => violations.push(format!(
+                        "node {} read unknown node {}",
+                        node.index(),
+                        read.index()
+                    )),
+                    Some(true)
+                        if !dirty
+                            && self.strategy.get() == VerificationStrategy::DirtyFlags
+                            && graph.contains_edge(read, node)
+                            && !self.sensitivities.borrow().contains_key(&node)
+                            && observed
+                                .as_ref()
+                                .is_none_or(|observed| observed.contains(&node)) =>
+                    {
+                        violations.push(format!(
+                            "node {} is clean but its dependency {} is dirty",
+                            node.index(),
+                            read.index()
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let kinds = self.kinds.borrow();
+        let cached = self.cached.borrow();
+        let recency = self.recency.borrow();
+        for idx in graph.node_indices() {
+            if !graph[idx]
+                && matches!(kinds.get(&idx), Some(NodeKind::Memo | NodeKind::Buffer))
+                && !cached.contains(&idx)
+                && !recency.is_evicted(idx)
+            {
+                violations.push(format!(
+                    "node {} is clean but has no cached value",
+                    idx.index()
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// A node's in-progress evaluation and the nodes it has read so far.
+struct Evaluation {
+    node: NodeIndex,
+    reads: HashSet<NodeIndex>,
+}
+
+/// Returned by [`read_with_budget`](Dcg::read_with_budget) when a read needs more recomputations
+/// than it was allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// The number of closures executed before the read was aborted.
+    pub recomputed: usize,
+    /// The node whose closure would have exceeded the budget.
+    pub stopped_at: NodeIndex,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "recomputation budget exceeded after {} recomputations at node {}",
+            self.recomputed,
+            self.stopped_at.index()
+        )
+    }
+}
+
+impl error::Error for BudgetExceeded {}
+
+/// Errors reported by [`Dcg`] operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DcgError {
+    /// A [`Var`] was written while a node was being evaluated.
+    ///
+    /// Dirtying in the middle of an evaluation would leave the evaluating node's dirtiness
+    /// ill-defined, so such writes are rejected.
+    MutationDuringEvaluation {
+        /// The node whose closure was executing.
+        evaluating: NodeIndex,
+        /// The [`Var`] that was written.
+        target: NodeIndex,
+    },
+    /// A [frozen](RawVar::freeze) [`Var`] was written.
+    Frozen {
+        /// The [`Var`] that was written.
+        target: NodeIndex,
+    },
+    /// A [`Var`] write was [scheduled](Dcg::schedule) for a tick already passed.
+    ScheduledInPast {
+        /// The [`Var`] the write was scheduled for.
+        target: NodeIndex,
+        /// The tick the write was scheduled at.
+        at: u64,
+        /// The [current tick](Dcg::current_tick).
+        current: u64,
+    },
+    /// A [`Record`](collections::Record) was written with the wrong number of values.
+    LengthMismatch {
+        /// The number of components.
+        expected: usize,
+        /// The number of values written.
+        actual: usize,
+    },
+    /// A [deferred](Dcg::deferred_buffer) [`Buffer`] was read before its dependencies were
+    /// [finalized](Dcg::finalize_dependencies).
+    Unfinalized {
+        /// The [`Buffer`] that was read.
+        target: NodeIndex,
+    },
+    /// The dependencies of a node not awaiting them were
+    /// [finalized](Dcg::finalize_dependencies).
+    AlreadyFinalized {
+        /// The node being finalized.
+        target: NodeIndex,
+    },
+    /// A dependency was declared that would make a node depend on itself.
+    Cycle {
+        /// The dependency.
+        from: NodeIndex,
+        /// The dependent.
+        to: NodeIndex,
+    },
+    /// A [pending](Dcg::pending_var) [`Var`] was read or written before being
+    /// [fulfilled](Dcg::fulfill).
+    Pending {
+        /// The pending [`Var`].
+        target: NodeIndex,
+    },
+    /// A [`Var`] not awaiting its value was [fulfilled](Dcg::fulfill).
+    NotPending {
+        /// The [`Var`] being fulfilled.
+        target: NodeIndex,
+    },
+    /// A value written to a [`Var`] was rejected by one of its
+    /// [validators](RawVar::add_validator).
+    ValidationFailed {
+        /// The [`Var`] that was written.
+        target: NodeIndex,
+        /// The validator's message.
+        message: String,
+    },
+    /// [`Dcg::settle`] ran out of rounds with nodes still changing.
+    Unsettled {
+        /// The nodes that changed in the last round, in index order.
+        changing: Vec<NodeIndex>,
+    },
+    /// A handle was used after its node was removed, as its [watch](Dcg::watch_expr) was
+    /// dropped.
+    ///
+    /// Reading or writing through the handle, or creating a node depending on it, panics with
+    /// this error, which `try_` methods return instead.
+    StaleHandle {
+        /// The removed node's index, which may since name another node.
+        target: NodeIndex,
+    },
+}
+
+impl fmt::Display for DcgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DcgError::MutationDuringEvaluation { evaluating, target } => write!(
+                f,
+                "cannot mutate node {} while node {} is being evaluated",
+                target.index(),
+                evaluating.index()
+            ),
+            DcgError::Frozen { target } => {
+                write!(f, "cannot mutate frozen node {}", target.index())
+            }
+            DcgError::ScheduledInPast {
+                target,
+                at,
+                current,
+            } => write!(
+                f,
+                "cannot schedule a write to node {} at tick {}, before the current tick {}",
+                target.index(),
+                at,
+                current
+            ),
+            DcgError::LengthMismatch { expected, actual } => write!(
+                f,
+                "expected {} values, one per component, but got {}",
+                expected, actual
+            ),
+            DcgError::Unfinalized { target } => write!(
+                f,
+                "cannot read node {} before its dependencies are finalized",
+                target.index()
+            ),
+            DcgError::AlreadyFinalized { target } => write!(
+                f,
+                "node {} is not awaiting its dependencies",
+                target.index()
+            ),
+            DcgError::Cycle { from, to } => write!(
+                f,
+                "node {} cannot depend on node {}, which depends on it",
+                to.index(),
+                from.index()
+            ),
+            DcgError::Pending { target } => {
+                write!(f, "node {} is still awaiting its value", target.index())
+            }
+            DcgError::NotPending { target } => {
+                write!(f, "node {} is not awaiting a value", target.index())
+            }
+            DcgError::ValidationFailed { target, message } => {
+                write!(f, "cannot write node {}: {}", target.index(), message)
+            }
+            DcgError::Unsettled { changing } => {
+                let changing: Vec<_> = changing.iter().map(|idx| idx.index().to_string()).collect();
+                write!(f, "nodes {} are still changing", changing.join(", "))
+            }
+            DcgError::StaleHandle { target } => {
+                write!(f, "node {} was removed from its Dcg", target.index())
+            }
+        }
+    }
+}
+
+impl error::Error for DcgError {}
+
+/// How often a [`Memo`] or [`Buffer`] served a cached value rather than executing its closure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads that executed the closure.
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the fraction of reads served from the cache, or [`None`] if there were no reads.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            None
+        } else {
+            Some(self.hits as f64 / reads as f64)
+        }
+    }
+}
+
+/// A dirty [`Memo`] or [`Buffer`], as returned by [`Dcg::stale_memos`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleMemo {
+    /// The node.
+    pub idx: NodeIndex,
+    /// Whether the node is a [`Memo`] or a [`Buffer`].
+    pub kind: NodeKind,
+    /// The node's [name](Node::set_name), if any.
+    pub name: Option<String>,
+    /// Whether the node holds a cached value, which may be out of date.
+    pub cached: bool,
+    /// The node's possibly out of date value, if registered with `show_value`: a [`Buffer`]'s
+    /// buffered value, or the number of results a [`Memo`] has cached.
+    pub value: Option<String>,
+}
+
+/// How far a call to [`Dcg::clean_for`] got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanProgress {
+    /// The number of nodes evaluated.
+    pub cleaned: usize,
+    /// The number of [`Memo`]s and [`Buffer`]s still dirty.
+    pub remaining: usize,
+}
+
+/// The effect of a [`Var`] write, as returned by [`RawVar::write_report`].
+///
+/// Under [`VerificationStrategy::Versions`], writes dirty no dependents, so nothing is reported
+/// dirtied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeReport<T> {
+    /// The [`Var`]'s value before the write.
+    pub previous: T,
+    /// Whether the value changed. Nothing is dirtied otherwise.
+    pub changed: bool,
+    /// The number of dependencies into the nodes the write dirtied.
+    pub edges_dirtied: usize,
+    /// The [`Memo`]s and [`Buffer`]s the write dirtied, sorted.
+    pub memos_invalidated: Vec<NodeIndex>,
+}
+
+/// The combined effect of [`Var`] writes applied together, as returned by
+/// [`Dcg::commit_staged_report`] and
+/// [`Record::set_all_report`](collections::Record::set_all_report).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    /// The number of [`Var`]s whose value changed.
+    pub changed: usize,
+    /// The number of dependencies into the nodes the writes dirtied.
+    pub edges_dirtied: usize,
+    /// The [`Memo`]s and [`Buffer`]s the writes dirtied, sorted.
+    pub memos_invalidated: Vec<NodeIndex>,
+}
+
+/// The dirtying caused by one [`Var`] write, recorded while [auditing](Dcg::enable_audit).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Increases by one with every entry recorded by the [`Dcg`].
+    pub seq: u64,
+    /// The written [`Var`].
+    pub origin: NodeIndex,
+    /// The `(dependency, dependent)` edges along which clean nodes were dirtied.
+    pub edges: Vec<(NodeIndex, NodeIndex)>,
+}
+
+impl AuditEntry {
+    fn dirtied(&self, node: NodeIndex) -> bool {
+        self.edges.iter().any(|&(_, to)| to == node)
+    }
+}
+
+/// The most recent [`AuditEntry`]s, and when each node was cleaned relative to them.
+struct AuditLog {
+    enabled: bool,
+    capacity: usize,
+    next_seq: u64,
+    entries: VecDeque<AuditEntry>,
+    /// The `next_seq` at each node's previous and most recent clean.
+    cleaned: HashMap<NodeIndex, (u64, u64)>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog {
+            enabled: false,
+            capacity: 256,
+            next_seq: 0,
+            entries: VecDeque::new(),
+            cleaned: HashMap::new(),
+        }
+    }
+}
+
+impl AuditLog {
+    fn record(&mut self, origin: NodeIndex, edges: Vec<(NodeIndex, NodeIndex)>) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(AuditEntry {
+            seq: self.next_seq,
+            origin,
+            edges,
+        });
+        self.next_seq += 1;
+    }
+}
+
+/// The kinds of [`Dcg`] node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeKind {
+    /// A [`Var`].
+    Var,
+    /// A [`Thunk`].
+    Thunk,
+    /// A [`Memo`].
+    Memo,
+    /// A [`Buffer`].
+    Buffer,
+}
+
+/// When a [`Memo`] or [`Buffer`] recomputes after being dirtied, as set by [`Dcg::set_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RecomputePolicy {
+    /// When it is next read.
+    #[default]
+    OnDemand,
+    /// Immediately, before the write dirtying it returns.
+    Eager,
+}
+
+/// How a [`Dcg`] decides which nodes are dirty, chosen with [`Dcg::with_strategy`].
+///
+/// Both strategies produce the same values. [`Versions`](VerificationStrategy::Versions) may
+/// evaluate nodes in a different order, and avoids recomputing [`Buffer`]s over [`Buffer`]s that
+/// [compare values](RawBuffer::compare_values) and recomputed an equal one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VerificationStrategy {
+    /// Writes mark every dependent dirty, so checking whether a node is dirty is a lookup.
+    #[default]
+    DirtyFlags,
+    /// Writes only bump the written [`Var`]'s version, and checking whether a node is dirty
+    /// compares the versions of its dependencies, transitively, with those it last read.
+    ///
+    /// Only the nodes written, expired or invalidated themselves are reported
+    /// [dirtied](Event::NodeDirtied), or call their [`on_dirtied`](Dcg::on_dirtied) hooks, and
+    /// [`Dcg::check_invariants`] doesn't check that clean nodes have no dirty dependencies.
+    Versions,
+}
+
+/// Changes to a [`Dcg`], reported to sinks registered with [`subscribe`](Dcg::subscribe).
+///
+/// Events only carry node indices, never values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A node was created.
+    NodeAdded {
+        /// The new node.
+        idx: NodeIndex,
+        /// The new node's kind.
+        kind: NodeKind,
+    },
+    /// A node was removed, as its [watch](Dcg::watch_expr) was dropped.
+    ///
+    /// The next node created takes `idx`. A [`NodeIndex`] kept from before this event, e.g. from
+    /// an earlier event or a snapshot, then names that node in index-based APIs such as
+    /// [`Dcg::kind`], so subscribers keying state by index should discard it here. Handles to
+    /// the removed node are generational instead, and return
+    /// [`StaleHandle`](DcgError::StaleHandle) once it is removed.
+    NodeRemoved {
+        /// The removed node, which the next node created reuses.
+        idx: NodeIndex,
+    },
+    /// A dependency was added.
+    EdgeAdded {
+        /// The dependency.
+        from: NodeIndex,
+        /// The dependent.
+        to: NodeIndex,
+    },
+    /// A dependency was removed, as a [`dynamic_buffer`](Dcg::dynamic_buffer) stopped reading it
+    /// or its dependent was [folded](Dcg::fold_constants) or [retired](Dcg::retire).
+    EdgeRemoved {
+        /// The dependency.
+        from: NodeIndex,
+        /// The dependent.
+        to: NodeIndex,
+    },
+    /// A [`Var`]'s value was changed. Followed by [`NodeDirtied`](Event::NodeDirtied) for every
+    /// node dirtied as a result.
+    VarWritten {
+        /// The [`Var`].
+        idx: NodeIndex,
+    },
+    /// A clean node was dirtied.
+    NodeDirtied {
+        /// The dirtied node.
+        idx: NodeIndex,
+    },
+    /// A node executed its closure.
+    Recomputed {
+        /// The recomputed node.
+        idx: NodeIndex,
+    },
+    /// A dirty node was cleaned.
+    NodeCleaned {
+        /// The cleaned node.
+        idx: NodeIndex,
+    },
+}
+
+/// Identifies a sink registered with [`subscribe`](Dcg::subscribe).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(usize);
+
+type Sink = Rc<RefCell<dyn FnMut(Event)>>;
+
+/// Stages a write of the latest value received for a [`Var`] by [`Dcg::feed`], if any, or
+/// returns [`None`] once the [`Var`] or the sending half is gone.
+type Feed = Box<dyn FnMut() -> Option<Option<(NodeIndex, Staged)>>>;
+
+/// A [`Var`] write staged by [`Dcg::stage`].
+type Staged = Box<dyn StagedWrite>;
+
+/// A [`Var`] write to be applied by [`Dcg::apply_report`], validated before any write is applied.
+trait StagedWrite {
+    /// Returns [`ValidationFailed`](DcgError::ValidationFailed) if one of the [`Var`]'s
+    /// validators rejects the value.
+    fn validate(&self) -> Result<(), DcgError>;
+
+    /// Applies the write, returning it if the [`Var`] changed.
+    fn apply(self: Box<Self>) -> Option<Box<dyn Applied>>;
+}
+
+/// A value to be written into a [`Var`], unless it has been dropped or frozen since.
+struct StagedValue<T> {
+    var: rc::Weak<RawVar<T>>,
+    value: T,
+}
+
+impl<T: PartialEq + Clone + 'static> StagedWrite for StagedValue<T> {
+    fn validate(&self) -> Result<(), DcgError> {
+        match self.var.upgrade() {
+            Some(var) if !var.node.is_frozen() => var.validate(&self.value),
+            _ => Ok(()),
+        }
+    }
+
+    fn apply(self: Box<Self>) -> Option<Box<dyn Applied>> {
+        let var = self.var.upgrade()?;
+        if var.node.is_frozen() || *var.value.borrow() == self.value {
+            return None;
+        }
+        let before = var.node.before_edit();
+        Some(Box::new(Swap::new(var, self.value, before)))
+    }
+}
+
+/// Stages a write of `value` into `var`, to be applied by [`Dcg::apply_report`].
+fn stage_write<T>(var: rc::Weak<RawVar<T>>, value: T) -> Staged
+where
+    T: PartialEq + Clone + 'static,
+{
+    Box::new(StagedValue { var, value })
+}
+
+/// A [`Var`] write applied by [`Dcg::commit_staged`] whose dependents are yet to be dirtied.
+trait Applied {
+    fn node(&self) -> &Node;
+
+    /// See [`Swap::commit`].
+    fn commit(&mut self);
+
+    /// Calls the [`Var`]'s observers and watchers.
+    fn notify(&self);
+}
+
+/// A [`Var`] write whose new value is in place, with every copy of a value the write needs
+/// already made, so [`commit`](Swap::commit) cannot panic even if `T`'s [`Clone`] can.
+///
+/// Dropping an uncommitted [`Swap`] puts the old value back, so a panic before the commit leaves
+/// the [`Var`] as it was.
+struct Swap<V: Deref<Target = RawVar<T>>, T> {
+    var: V,
+    old: Option<T>,
+    edit: Option<undo::Edit>,
+    recorded: Option<T>,
+    committed: bool,
+}
+
+impl<V: Deref<Target = RawVar<T>>, T> Swap<V, T> {
+    /// Replaces `var`'s value with `new`, given its value from [`Node::before_edit`].
+    fn new(var: V, new: T, before: Option<Box<dyn Any>>) -> Self {
+        let recorded = var.history.borrow().copy(&new);
+        let old = var.value.replace(new);
+        let mut swap = Swap {
+            var,
+            old: Some(old),
+            edit: None,
+            recorded,
+            committed: false,
+        };
+        swap.edit = swap.var.node.after_edit(before);
+        swap
+    }
+
+    /// Records the write in the undo log and history and bumps the [`Var`]'s version.
+    fn commit(&mut self) {
+        self.var.node.record_edit(self.edit.take());
+        if let Some(recorded) = self.recorded.take() {
+            self.var.history.borrow_mut().push(recorded);
+        }
+        self.var.node.bump_version();
+        self.committed = true;
+    }
+
+    fn old(&self) -> &T {
+        self.old
+            .as_ref()
+            .expect("a swap holds its old value until dropped")
+    }
+}
+
+impl<V: Deref<Target = RawVar<T>>, T> Drop for Swap<V, T> {
+    fn drop(&mut self) {
+        if let (false, Some(old)) = (self.committed, self.old.take()) {
+            self.var.value.replace(old);
+        }
+    }
+}
+
+impl<T: Clone> Applied for Swap<Var<T>, T> {
+    fn node(&self) -> &Node {
+        &self.var.node
+    }
+
+    fn commit(&mut self) {
+        Swap::commit(self);
+    }
+
+    fn notify(&self) {
+        self.var.notify(self.old());
+    }
 }
 
+/// Renders a node's value, or [`None`] once its handle has been dropped.
+type ValueLabel = Rc<dyn Fn() -> Option<String>>;
+
 /// Refines the concept of a shared [`RawVar`].
 pub type Var<T> = Rc<RawVar<T>>;
 
@@ -163,6 +1054,11 @@ pub type Memo<A, T> = Rc<RawMemo<A, T>>;
 pub type Buffer<T> = Rc<RawBuffer<T>>;
 
 impl Dcg {
+    /// Creates an empty [`Dcg`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     /// Creates a dirty [`Var`], containing `value`.
     ///
     /// The [`Var`] starts dirty as it has never been read.
@@ -180,16 +1076,44 @@ impl Dcg {
     /// assert!(a.is_clean());
     /// ```
     pub fn var<T>(&self, value: T) -> Var<T> {
-        Rc::new(RawVar {
-            value: RefCell::new(value),
-            node: Node::new(self),
-        })
+        Rc::new(RawVar::with_node(Node::new(self, NodeKind::Var), value))
+    }
+
+    /// Creates a dirty [`Var`] containing each of `values`, returning them in iteration order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let vars = dcg.extend_vars(1..=3);
+    ///
+    /// assert_eq!(dcg.len(), 3);
+    /// assert_eq!(vars[2].read(), 3);
+    /// ```
+    pub fn extend_vars<T, I>(&self, values: I) -> Vec<Var<T>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let values = values.into_iter();
+        let (lower, _) = values.size_hint();
+        self.inner.kinds.borrow_mut().reserve(lower);
+        values.map(|value| self.var(value)).collect()
     }
 
     /// Creates a dirty [`Thunk`], adding incoming dependency edges from `params` and storing `f`.
     ///
     /// The [`Thunk`] starts dirty as it has never been read.
     ///
+    /// `params` can be any [`Incremental`]: a single node, a tuple, an array or a [`Vec`] of nodes.
+    /// Passing `()` (or an empty array or [`Vec`]) creates a node without dependencies, just like
+    /// [`thunk!`] with a lone `expr`.
+    ///
+    /// A [`Thunk`] keeps no value to compare against, so its dependents treat it as changed
+    /// whenever it is dirtied: [`Buffer`]s re-compute, and [`Memo`]s re-execute it to look its
+    /// output up in their cache.
+    ///
     /// If buffering behaviour is desired, use [`buffer`](Dcg::buffer) or [`buffer!`] instead.
     ///
     /// If full caching behaviour is desired, use [`memo`](Dcg::memo) or [`memo!`] instead.
@@ -232,13 +1156,23 @@ impl Dcg {
         P: Incremental,
         F: Fn() -> T + 'static,
     {
-        Rc::new(RawThunk::new(self, params, f))
+        Rc::new(RawThunk::new(self, NodeKind::Thunk, params, f))
     }
 
     /// Creates a dirty [`Memo`], adding incoming dependency edges from `params` and storing `f`.
     ///
     /// The [`Memo`] starts dirty as it has never been read.
     ///
+    /// `params` can be any [`Incremental`]: a single node, a tuple, an array or a [`Vec`] of nodes.
+    /// Passing `()` (or an empty array or [`Vec`]) creates a node without dependencies, just like
+    /// [`memo!`] with a lone `expr`.
+    ///
+    /// The [`Memo`] caches `f`'s results by the values of `params`. When read while dirty, it
+    /// re-reads `params`, executing any [`Thunk`]s among them, and only executes `f` if their
+    /// values have no cached result. So a [`Thunk`] dependency whose output is unaffected by a
+    /// write doesn't cause `f` to execute, although the [`Memo`]'s [`version`](Dcg::version) is
+    /// still bumped unless it [compares values](RawMemo::compare_values).
+    ///
     /// If non-caching behaviour is desired, use [`thunk`](Dcg::thunk) or [`thunk!`] instead.
     ///
     /// If buffering behaviour is desired, use [`buffer`](Dcg::buffer) or [`buffer!`] instead.
@@ -279,20 +1213,35 @@ impl Dcg {
     pub fn memo<P, F, A, T>(&self, params: P, f: F) -> Memo<A, T>
     where
         P: Incremental<Output = A> + 'static,
-        A: Eq + Hash,
+        A: Eq + Hash + 'static,
         F: Fn() -> T + 'static,
+        T: Clone + 'static,
     {
-        Rc::new(RawMemo {
-            thunk: RawThunk::new(self, &params, f),
+        let memo = Rc::new(RawMemo {
+            thunk: RawThunk::new(self, NodeKind::Memo, &params, f),
             params: Box::new(params),
             cache: RefCell::default(),
-        })
+            capacity: Cell::default(),
+            uses: Cell::default(),
+            hooks: RefCell::default(),
+            compare: Cell::default(),
+            last: RefCell::default(),
+        });
+        self.inner
+            .refreshers
+            .borrow_mut()
+            .insert(memo.node().idx, Rc::new(Rc::downgrade(&memo)));
+        memo
     }
 
     /// Creates a dirty [`Buffer`], adding incoming dependency edges from `params` and storing `f`.
     ///
     /// The [`Buffer`] starts dirty as it has never been read.
     ///
+    /// `params` can be any [`Incremental`]: a single node, a tuple, an array or a [`Vec`] of nodes.
+    /// Passing `()` (or an empty array or [`Vec`]) creates a node without dependencies, just like
+    /// [`buffer!`] with a lone `expr`.
+    ///
     /// If non-caching behaviour is desired, use [`thunk`](Dcg::thunk) or [`thunk!`] instead.
     ///
     /// If full caching behaviour is desired, use [`memo`](Dcg::memo) or [`memo!`] instead.
@@ -334,280 +1283,2771 @@ impl Dcg {
     where
         P: Incremental,
         F: Fn() -> T + 'static,
+        T: Clone + 'static,
     {
-        Rc::new(RawBuffer {
-            thunk: RawThunk::new(self, params, f),
-            buffered: RefCell::default(),
-        })
+        RawBuffer::with_thunk(RawThunk::new(self, NodeKind::Buffer, params, f))
     }
-}
-
-impl fmt::Debug for Dcg {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", Dot::new(&*self.graph.borrow()))
-    }
-}
-
-/// A handle for a node in a [`Dcg`].
-pub struct Node {
-    graph: Rc<RefCell<Graph>>,
-    idx: NodeIndex,
-}
 
-impl Node {
-    fn new(dcg: &Dcg) -> Self {
-        Self {
-            graph: dcg.graph.clone(),
-            idx: dcg.graph.borrow_mut().add_node(true),
-        }
+    /// Creates a dirty [`Buffer`] whose dependencies are exactly the nodes `f` read in its most
+    /// recent execution, rather than declared up front.
+    ///
+    /// Writes to nodes `f` didn't read last time don't dirty the [`Buffer`]. Any node whose value
+    /// decides what `f` reads is itself read, so it remains a dependency.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let flag = dcg.var(true);
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let choice = dcg.dynamic_buffer({
+    ///     let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+    ///     move || if flag.read() { a.read() } else { b.read() }
+    /// });
+    ///
+    /// assert_eq!(choice.read(), 1);
+    /// b.write(3);
+    /// assert!(choice.is_clean());
+    /// flag.write(false);
+    /// assert_eq!(choice.read(), 3);
+    /// ```
+    pub fn dynamic_buffer<F, T>(&self, f: F) -> Buffer<T>
+    where
+        F: Fn() -> T + 'static,
+        T: Clone + 'static,
+    {
+        let buffer = self.buffer(&(), f);
+        self.inner.dynamic.borrow_mut().insert(buffer.node().idx);
+        buffer
     }
 
-    fn add_dependencies<P>(&self, params: &P)
+    /// Creates a dirty [`Buffer`] of the value returned by fallible `f`, adding incoming
+    /// dependency edges from `params`.
+    ///
+    /// When `f` fails, the [`Buffer`] holds the value of its last successful evaluation, or
+    /// `fallback` if it has never succeeded, so dependents always see a usable value. The error
+    /// can be retrieved with [`last_error`](Dcg::last_error) until `f` next succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let input = dcg.var("12");
+    /// let parsed = dcg.buffer_or(&input, {
+    ///     let input = input.clone();
+    ///     move || input.read().parse::<i32>()
+    /// }, 0);
+    ///
+    /// assert_eq!(parsed.read(), 12);
+    /// input.write("twelve");
+    /// assert_eq!(parsed.read(), 12);
+    /// assert!(dcg.last_error::<std::num::ParseIntError>(parsed.node()).is_some());
+    /// ```
+    pub fn buffer_or<P, F, T, E>(&self, params: &P, f: F, fallback: T) -> Buffer<T>
     where
         P: Incremental,
+        F: Fn() -> Result<T, E> + 'static,
+        T: Clone + 'static,
+        E: 'static,
     {
-        let mut graph = self.graph.borrow_mut();
-        for node in params.nodes() {
-            graph.add_edge(node.idx, self.idx, ());
-        }
+        let last_good = RefCell::new(fallback);
+        let error = Rc::new(RefCell::new(None));
+        let buffer = self.buffer(params, {
+            let error = error.clone();
+            move || match f() {
+                Ok(value) => {
+                    error.replace(None);
+                    last_good.replace(value.clone());
+                    value
+                }
+                Err(err) => {
+                    error.replace(Some(err));
+                    last_good.borrow().clone()
+                }
+            }
+        });
+        self.inner
+            .errors
+            .borrow_mut()
+            .insert(buffer.node().idx, error);
+        buffer
     }
 
-    fn clean(&self) {
-        self.graph.borrow_mut()[self.idx] = false;
+    /// Returns the error from `node`'s latest evaluation, if `node` was created by
+    /// [`buffer_or`](Dcg::buffer_or) with error type `E` and that evaluation failed.
+    pub fn last_error<E: Clone + 'static>(&self, node: &Node) -> Option<E> {
+        let errors = self.inner.errors.borrow();
+        let error = errors
+            .get(&node.idx)?
+            .downcast_ref::<RefCell<Option<E>>>()?;
+        let error = error.borrow().clone();
+        error
     }
 
-    fn is_dirty(&self) -> bool {
-        self.graph.borrow()[self.idx]
+    /// Returns the kind of the node at `idx`, or [`None`] if the [`Dcg`] has no such node.
+    pub fn kind(&self, idx: NodeIndex) -> Option<NodeKind> {
+        self.inner.kinds.borrow().get(&idx).copied()
     }
 
-    /// Dirties the node's transitive dependents.
-    /// A DFS from the node gathers clean edges, pruning already dirty ones, and dirties them.
-    fn dirty_dependents(&self) {
-        let mut dependents = Vec::new();
-        {
-            let graph = self.graph.borrow();
-            depth_first_search(&*graph, Some(self.idx), |event| {
-                if let DfsEvent::Discover(n, _) = event {
-                    if graph[n] {
-                        return Control::Prune::<()>;
-                    }
-                    dependents.push(n);
-                }
-                Control::Continue::<()>
-            });
-        }
+    /// Returns the number of nodes in the [`Dcg`].
+    pub fn node_count(&self) -> usize {
+        self.inner.graph.borrow().node_count()
+    }
 
-        let mut graph = self.graph.borrow_mut();
-        for node in dependents {
-            graph[node] = true;
-        }
+    /// Returns the number of nodes in the [`Dcg`], like [`node_count`](Dcg::node_count).
+    pub fn len(&self) -> usize {
+        self.node_count()
     }
-}
 
-/// Data-storing [`Dcg`] node.
-pub struct RawVar<T> {
-    value: RefCell<T>,
-    node: Node,
-}
+    /// Returns `true` if the [`Dcg`] has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-impl<T: PartialEq> RawVar<T> {
-    /// Writes a value into the [`Var`] and dirties its dependents if necessary.
-    ///
-    /// If `new` is equal to its current value, `new` is simply returned.
-    /// Otherwise, `new` will be swapped with the current value, the [`Var`] and its dependents will be
-    /// dirtied and the old value will be returned.
+    /// Returns the number of dependencies in the [`Dcg`].
+    pub fn edge_count(&self) -> usize {
+        self.inner.graph.borrow().edge_count()
+    }
+
+    /// Returns whether `to` depends on `from`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use cachegrab::{Dcg, incremental::Incremental};
+    /// use cachegrab::{Dcg, buffer};
     ///
     /// let dcg = Dcg::default();
     /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
     ///
-    /// // Ensures `a` is clean
-    /// a.read();
+    /// assert!(dcg.contains_edge(a.node(), b.node()));
+    /// assert!(!dcg.contains_edge(b.node(), a.node()));
+    /// ```
+    pub fn contains_edge(&self, from: &Node, to: &Node) -> bool {
+        self.inner.graph.borrow().contains_edge(from.idx, to.idx)
+    }
+
+    /// Calls `f` with the [`Dcg`]'s underlying graph, whose node weights are the nodes' dirty
+    /// flags.
     ///
-    /// // `a` remains clean due to writing the same value
-    /// assert_eq!(a.write(1), 1);
-    /// assert!(a.is_clean());
-    /// assert_eq!(a.read(), 1);
+    /// `f` must not create or write nodes in the [`Dcg`], which panics.
+    ///
+    /// # Examples
     ///
-    /// // `a` dirtied due to writing a different value
-    /// assert_eq!(a.write(2), 1);
-    /// assert!(a.is_dirty());
-    /// assert_eq!(a.read(), 2);
     /// ```
-    pub fn write(&self, new: T) -> T {
-        if *self.value.borrow() == new {
-            new
-        } else {
-            self.node.dirty_dependents();
-            self.value.replace(new)
-        }
+    /// use cachegrab::{Dcg, buffer};
+    /// use petgraph::algo::toposort;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    ///
+    /// let order = dcg.with_graph(|graph| toposort(graph, None).unwrap());
+    /// assert_eq!(order, vec![a.node().index(), b.node().index()]);
+    /// ```
+    pub fn with_graph<R>(&self, f: impl FnOnce(&StableDiGraph<bool, ()>) -> R) -> R {
+        f(&self.inner.graph.borrow())
     }
 
-    /// Modifies the value in the [`Var`], returning the value before modification and dirtying the node and
-    /// its transitive dependents if the new value differs from the old value.
+    /// Calls `f` with mutable access to the [`Dcg`]'s underlying graph.
+    ///
+    /// This is an escape hatch: nothing stops `f` from breaking the [`Dcg`]'s invariants, e.g. by
+    /// removing nodes, adding cycles or cleaning nodes whose dependencies are dirty, after which
+    /// reads may return stale values or panic. Check the result with
+    /// [`check_invariants`](Dcg::check_invariants). `f` must not use the [`Dcg`] itself, which
+    /// panics.
+    pub fn with_graph_mut<R>(&self, f: impl FnOnce(&mut StableDiGraph<bool, ()>) -> R) -> R {
+        self.inner.structure_changed();
+        f(&mut self.inner.graph.borrow_mut())
+    }
+
+    /// [`read`](Incremental::read)s `node`, aborting if more than `max_recomputes` closures would
+    /// be executed.
+    ///
+    /// Every [`Thunk`] execution, [`Memo`] cache miss and [`Buffer`] re-computation counts towards
+    /// the budget. The dirty [`Memo`]s and [`Buffer`]s `node` depends on are evaluated first, in
+    /// topological order, and the read is abandoned before the first one that would start once
+    /// the budget is spent: results computed so far stay cached and clean, while everything that
+    /// was not finished stays dirty, so a later read resumes correctly.
+    ///
+    /// The budget is checked between nodes, so the [`Thunk`]s read by the last node evaluated,
+    /// which execute along with it, may take the count past `max_recomputes`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use cachegrab::{Dcg, incremental::Incremental};
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
     ///
     /// let dcg = Dcg::default();
     /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b + 1);
     ///
-    /// // Ensure `a` is clean
-    /// a.read();
-    ///
-    /// // `a` remains clean due to modify producing same value
-    /// assert_eq!(a.modify(|x| *x), 1);
-    /// assert!(a.is_clean());
-    /// assert_eq!(a.read(), 1);
+    /// let exceeded = dcg.read_with_budget(&c, 1).unwrap_err();
+    /// assert_eq!(exceeded.recomputed, 1);
+    /// assert_eq!(exceeded.stopped_at, c.node().index());
+    /// assert!(b.is_clean());
+    /// assert!(c.is_dirty());
     ///
-    /// // `a` dirtied due to modify producing different value
-    /// assert_eq!(a.modify(|x| *x + 1), 1);
-    /// assert!(a.is_dirty());
-    /// assert_eq!(a.read(), 2);
+    /// assert_eq!(dcg.read_with_budget(&c, 2), Ok(3));
     /// ```
-    pub fn modify<F>(&self, f: F) -> T
+    pub fn read_with_budget<I>(
+        &self,
+        node: &I,
+        max_recomputes: usize,
+    ) -> Result<I::Output, BudgetExceeded>
     where
-        F: FnOnce(&mut T) -> T,
+        I: Incremental,
     {
-        let old_value = self.value.replace_with(f);
-        if old_value != *self.value.borrow() {
-            self.node.dirty_dependents();
+        let outer = self.inner.budget.replace(Some(0));
+        let result = self.read_within(node, max_recomputes);
+        let recomputed = self.inner.budget.replace(outer).unwrap_or_default();
+        if let Some(outer) = outer {
+            self.inner.budget.set(Some(outer + recomputed));
         }
-        old_value
+        result
     }
-}
-
-/// Naively re-computing [`Dcg`] node.
-pub struct RawThunk<T> {
-    f: Box<dyn Fn() -> T>,
-    node: Node,
-}
 
-impl<T> RawThunk<T> {
-    fn new<P, F>(dcg: &Dcg, params: &P, f: F) -> Self
+    /// Evaluates `node` and its dirty dependencies bottom-up for
+    /// [`read_with_budget`](Dcg::read_with_budget), stopping before any closure once
+    /// `max_recomputes` have executed.
+    fn read_within<I>(&self, node: &I, max_recomputes: usize) -> Result<I::Output, BudgetExceeded>
     where
-        P: Incremental,
-        F: Fn() -> T + 'static,
+        I: Incremental,
     {
-        let node = Node::new(dcg);
-        node.add_dependencies(params);
-        Self {
-            f: Box::new(f),
-            node,
+        let targets: Vec<_> = node.nodes().iter().map(|node| node.idx).collect();
+        let order: Vec<_> = {
+            let graph = self.inner.graph.borrow();
+            let mut needed = partial::reach(&graph, &targets, Incoming);
+            needed.extend(&targets);
+            toposort(&*graph, None)
+                .expect("a Dcg's dependencies are acyclic")
+                .into_iter()
+                .filter(|idx| needed.contains(idx))
+                .collect()
+        };
+        let check = |stopped_at| {
+            let recomputed = self.inner.budget.get().unwrap_or_default();
+            if recomputed < max_recomputes {
+                Ok(())
+            } else {
+                Err(BudgetExceeded {
+                    recomputed,
+                    stopped_at,
+                })
+            }
+        };
+
+        for idx in order {
+            let refreshable = self.inner.refreshers.borrow().contains_key(&idx);
+            if refreshable && self.inner.is_dirty(idx) {
+                check(idx)?;
+                self.refresh(idx);
+            }
         }
+        let pending = {
+            let kinds = self.inner.kinds.borrow();
+            targets
+                .into_iter()
+                .find(|idx| kinds[idx] == NodeKind::Thunk || self.inner.is_dirty(*idx))
+        };
+        if let Some(idx) = pending {
+            check(idx)?;
+        }
+        Ok(node.read())
     }
-}
 
-/// [`RawThunk`] that caches all its previous values.
-pub struct RawMemo<A, T>
-where
-    A: Eq + Hash,
-{
-    thunk: RawThunk<T>,
-    params: Box<dyn Incremental<Output = A>>,
-    cache: RefCell<HashMap<A, T>>,
-}
-
-/// [`RawThunk`] that buffers its previous value.
-pub struct RawBuffer<T> {
-    thunk: RawThunk<T>,
-    buffered: RefCell<Option<T>>,
-}
+    /// Returns a closure [`read`](Incremental::read)ing `node` on every call, for passing a
+    /// node's value to APIs taking an `impl Fn() -> T`.
+    ///
+    /// The closure holds a handle to `node`, so it can outlive the borrow of the [`Dcg`]. Reads
+    /// go through `node`'s cache, as any other read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a * 2);
+    /// let doubled = dcg.as_fn(&b);
+    ///
+    /// assert_eq!(std::iter::repeat_with(&doubled).take(2).collect::<Vec<_>>(), vec![2, 2]);
+    /// a.write(5);
+    /// assert_eq!(doubled(), 10);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn as_fn<I>(&self, node: &Rc<I>) -> impl Fn() -> I::Output + 'static
+    where
+        I: Incremental + 'static,
+    {
+        assert!(
+            node.nodes()
+                .iter()
+                .all(|node| Rc::ptr_eq(&node.inner, &self.inner)),
+            "nodes must belong to the Dcg"
+        );
+        let node = node.clone();
+        move || node.read()
+    }
 
-impl<T: Clone> Incremental for RawVar<T> {
-    type Output = T;
+    /// Registers `hook` to be called with a node's index and freshly computed value whenever any
+    /// node in the [`Dcg`] executes its closure, i.e. whenever a [`Thunk`] is read, a [`Memo`]
+    /// misses its cache or a [`Buffer`] re-computes.
+    ///
+    /// Nodes compute values of different types, so the value is passed as [`Any`], to be
+    /// downcast to the node's type. Hooks run after the closure returns, with no borrows of the
+    /// [`Dcg`] held.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let recomputed = Rc::new(RefCell::new(Vec::new()));
+    /// let recomputed_clone = recomputed.clone();
+    /// dcg.on_any_recompute(move |node, value| {
+    ///     recomputed_clone
+    ///         .borrow_mut()
+    ///         .push((node, value.downcast_ref::<i32>().copied()))
+    /// });
+    ///
+    /// b.read();
+    /// b.read();
+    /// assert_eq!(*recomputed.borrow(), vec![(b.node().index(), Some(2))]);
+    /// ```
+    pub fn on_any_recompute<F>(&self, hook: F)
+    where
+        F: FnMut(NodeIndex, &dyn Any) + 'static,
+    {
+        self.inner
+            .recompute_hooks
+            .borrow_mut()
+            .push(Rc::new(RefCell::new(hook)));
+    }
 
-    fn latest(&self) -> Self::Output {
-        self.value.borrow().clone()
+    /// Registers `hook` to be called with the index of the written [`Var`] whenever `node` is
+    /// dirtied.
+    ///
+    /// Hooks are only called when `node` goes from clean to dirty, not when an already dirty
+    /// `node` would be dirtied again. They run after the write's dirtying completes, with no
+    /// borrows of the [`Dcg`] held, which makes them a convenient place for a breakpoint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let sources = Rc::new(RefCell::new(Vec::new()));
+    /// let sources_clone = sources.clone();
+    /// dcg.on_dirtied(b.node(), move |source| sources_clone.borrow_mut().push(source));
+    ///
+    /// b.read();
+    /// a.write(2);
+    /// assert_eq!(*sources.borrow(), vec![a.node().index()]);
+    /// ```
+    pub fn on_dirtied<F>(&self, node: &Node, hook: F)
+    where
+        F: FnMut(NodeIndex) + 'static,
+    {
+        self.inner
+            .dirtied_hooks
+            .borrow_mut()
+            .entry(node.idx)
+            .or_default()
+            .push(Rc::new(RefCell::new(hook)));
+        self.inner.observed.take();
     }
 
-    fn is_dirty(&self) -> bool {
-        self.node.is_dirty()
+    /// Returns `node`'s cache hits and misses since it was created or the [`Dcg`]'s statistics
+    /// were last [`reset`](Dcg::reset_stats).
+    ///
+    /// Only [`Memo`]s and [`Buffer`]s have caches; other nodes report no reads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, CacheStats, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    ///
+    /// b.read();
+    /// b.read();
+    /// assert_eq!(dcg.cache_stats(b.node()), CacheStats { hits: 1, misses: 1 });
+    /// ```
+    pub fn cache_stats(&self, node: &Node) -> CacheStats {
+        self.inner
+            .cache_stats
+            .borrow()
+            .get(&node.idx)
+            .copied()
+            .unwrap_or_default()
     }
 
-    fn nodes(&self) -> Vec<&Node> {
-        vec![&self.node]
+    /// Returns `node`'s version: the number of times its value has changed.
+    ///
+    /// A [`Var`]'s version is bumped by every [`write`](RawVar::write) or
+    /// [`modify`](RawVar::modify) that changes its value. A [`Memo`]'s or [`Buffer`]'s version is
+    /// bumped whenever it is re-evaluated after being dirtied, or, once it
+    /// [`compares values`](RawBuffer::compare_values), only when that produces a different value.
+    /// [`Thunk`]s hold no value and stay at version 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a / 10);
+    /// b.compare_values();
+    ///
+    /// b.read();
+    /// let seen = dcg.version(b.node());
+    /// a.write(2);
+    /// b.read();
+    /// assert_eq!(dcg.version(a.node()), 1);
+    /// assert!(!dcg.changed_since(b.node(), seen));
+    /// ```
+    pub fn version(&self, node: &Node) -> u64 {
+        node.version()
     }
-}
 
-impl<T> Incremental for RawThunk<T> {
-    type Output = T;
+    /// Returns `true` if `node`'s [`version`](Dcg::version) is newer than `version`.
+    pub fn changed_since(&self, node: &Node, version: u64) -> bool {
+        node.version() > version
+    }
 
-    fn latest(&self) -> Self::Output {
-        (self.f)()
+    /// Returns `true` if `node`'s latest computed value was transformed by an
+    /// [interceptor](RawThunk::intercept), complementing its [provenance](Dcg::provenance).
+    pub fn is_intercepted(&self, node: &Node) -> bool {
+        self.inner.intercepted.borrow().contains(&node.idx)
     }
 
-    fn is_dirty(&self) -> bool {
-        self.node.is_dirty()
+    /// Returns the sum of every node's [`cache_stats`](Dcg::cache_stats).
+    pub fn cache_stats_all(&self) -> CacheStats {
+        self.inner
+            .cache_stats
+            .borrow()
+            .values()
+            .fold(CacheStats::default(), |total, stats| CacheStats {
+                hits: total.hits + stats.hits,
+                misses: total.misses + stats.misses,
+            })
     }
 
-    fn nodes(&self) -> Vec<&Node> {
-        vec![&self.node]
+    /// Resets every node's [`cache_stats`](Dcg::cache_stats) to zero.
+    pub fn reset_stats(&self) {
+        self.inner.cache_stats.borrow_mut().clear();
     }
-}
 
-impl<A, T> Incremental for RawMemo<A, T>
-where
-    A: Eq + Hash,
-    T: Clone,
-{
-    type Output = T;
+    /// Enables or disables recording an [`AuditEntry`] for every [`Var`] write that dirties
+    /// nodes, to be queried with [`explain`](Dcg::explain).
+    ///
+    /// Auditing is disabled by default. Disabling it discards the recorded entries.
+    pub fn enable_audit(&self, enabled: bool) {
+        let mut audit = self.inner.audit.borrow_mut();
+        audit.enabled = enabled;
+        if !enabled {
+            audit.entries.clear();
+            audit.cleaned.clear();
+        }
+    }
 
-    fn latest(&self) -> Self::Output {
-        let args = self.params.latest();
-        let mut cache = self.cache.borrow_mut();
-        if let Some(result) = cache.get(&args) {
-            result.clone()
-        } else {
-            let missed = self.thunk.read();
-            cache.insert(args, missed.clone());
-            missed
+    /// Sets the number of [`AuditEntry`]s kept, discarding the oldest entries beyond it.
+    ///
+    /// Defaults to 256.
+    pub fn set_audit_capacity(&self, capacity: usize) {
+        let mut audit = self.inner.audit.borrow_mut();
+        audit.capacity = capacity;
+        while audit.entries.len() > capacity {
+            audit.entries.pop_front();
         }
     }
 
-    fn is_dirty(&self) -> bool {
-        self.thunk.is_dirty()
+    /// Returns the recorded [`AuditEntry`]s explaining why `node` is dirty or, if it is clean,
+    /// why it was last recomputed.
+    ///
+    /// Entries are only available while [auditing](Dcg::enable_audit) and until they are
+    /// discarded for [capacity](Dcg::set_audit_capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// dcg.enable_audit(true);
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let c = buffer!(dcg, (a, b) => a + b);
+    ///
+    /// c.read();
+    /// b.write(3);
+    /// c.read();
+    ///
+    /// let causes = dcg.explain(c.node());
+    /// assert_eq!(causes.len(), 1);
+    /// assert_eq!(causes[0].origin, b.node().index());
+    /// ```
+    pub fn explain(&self, node: &Node) -> Vec<AuditEntry> {
+        let audit = self.inner.audit.borrow();
+        let (previous, last) = audit.cleaned.get(&node.idx).copied().unwrap_or_default();
+        let (from, to) = if node.is_dirty() {
+            (last, u64::MAX)
+        } else {
+            (previous, last)
+        };
+        audit
+            .entries
+            .iter()
+            .filter(|entry| (from..to).contains(&entry.seq) && entry.dirtied(node.idx))
+            .cloned()
+            .collect()
     }
 
-    fn nodes(&self) -> Vec<&Node> {
-        self.thunk.nodes()
+    /// Returns the total time spent executing each node's closure and how many times it was
+    /// executed, most expensive first.
+    ///
+    /// Only nodes that have executed their closure are included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    ///
+    /// b.read();
+    /// let timings = dcg.timings();
+    /// assert_eq!(timings.len(), 1);
+    /// assert_eq!((timings[0].0, timings[0].2), (b.node().index(), 1));
+    /// ```
+    #[cfg(feature = "timings")]
+    pub fn timings(&self) -> Vec<(NodeIndex, std::time::Duration, u64)> {
+        let mut timings: Vec<_> = self
+            .inner
+            .timings
+            .borrow()
+            .iter()
+            .map(|(&node, &(elapsed, count))| (node, elapsed, count))
+            .collect();
+        timings.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        timings
     }
-}
 
-impl<T: Clone> Incremental for RawBuffer<T> {
-    type Output = T;
+    /// Returns the total cost of each node's values, as measured by the function registered with
+    /// `set_cost`, and how many values were measured, most expensive first.
+    ///
+    /// Only nodes with a cost function that have executed their closure are included. Unlike
+    /// `timings`, costs are deterministic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let n = dcg.var(3);
+    /// let range = buffer!(dcg, n => (0..n).collect::<Vec<_>>());
+    /// range.set_cost(|range| range.len() as u64);
+    ///
+    /// range.read();
+    /// n.write(5);
+    /// range.read();
+    /// assert_eq!(dcg.costs(), vec![(range.node().index(), 8, 2)]);
+    /// ```
+    pub fn costs(&self) -> Vec<(NodeIndex, u64, u64)> {
+        let mut costs: Vec<_> = self
+            .inner
+            .costs
+            .borrow()
+            .iter()
+            .map(|(&node, &(cost, count))| (node, cost, count))
+            .collect();
+        costs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        costs
+    }
 
-    fn latest(&self) -> Self::Output {
-        if self.is_dirty() || self.buffered.borrow().is_none() {
-            self.buffered.replace(Some(self.thunk.read()));
-        }
-        self.buffered.borrow().clone().unwrap()
+    /// Registers `sink` to receive every [`Event`] occurring in the [`Dcg`] from now on.
+    ///
+    /// Sinks are called in registration order, with no borrows of the [`Dcg`] held. When there are
+    /// no subscribers, no events are produced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, Event, NodeKind};
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// let dcg = Dcg::default();
+    /// let events = Rc::new(RefCell::new(Vec::new()));
+    /// let events_clone = events.clone();
+    /// let id = dcg.subscribe(move |event| events_clone.borrow_mut().push(event));
+    ///
+    /// let a = dcg.var(1);
+    /// dcg.unsubscribe(id);
+    /// dcg.var(2);
+    /// assert_eq!(
+    ///     *events.borrow(),
+    ///     vec![Event::NodeAdded { idx: a.node().index(), kind: NodeKind::Var }]
+    /// );
+    /// ```
+    pub fn subscribe<F>(&self, sink: F) -> SubscriptionId
+    where
+        F: FnMut(Event) + 'static,
+    {
+        let id = SubscriptionId(self.inner.next_id());
+        self.inner
+            .subscribers
+            .borrow_mut()
+            .push((id, Rc::new(RefCell::new(sink))));
+        id
     }
 
-    fn is_dirty(&self) -> bool {
-        self.thunk.is_dirty()
+    /// Registers `rx` to supply new values for `var` whenever [`pump`](Dcg::pump) is called.
+    ///
+    /// The [`Dcg`] does not keep `var` alive; the feed is removed once `var` is dropped or every
+    /// sender of `rx` is.
+    pub fn feed<T>(&self, var: &Var<T>, rx: mpsc::Receiver<T>)
+    where
+        T: PartialEq + Clone + 'static,
+    {
+        let idx = var.node.idx;
+        let var = Rc::downgrade(var);
+        self.inner.feeds.borrow_mut().push(Box::new(move || {
+            if var.strong_count() == 0 {
+                return None;
+            }
+            let mut latest = None;
+            let connected = loop {
+                match rx.try_recv() {
+                    Ok(value) => latest = Some(value),
+                    Err(mpsc::TryRecvError::Empty) => break true,
+                    Err(mpsc::TryRecvError::Disconnected) => break false,
+                }
+            };
+            match latest {
+                Some(value) => Some(Some((idx, stage_write(var.clone(), value)))),
+                None => connected.then_some(None),
+            }
+        }));
     }
 
-    fn nodes(&self) -> Vec<&Node> {
-        vec![&self.thunk.node]
+    /// Writes the most recent value received by each [`feed`](Dcg::feed) into its [`Var`],
+    /// discarding older ones, and returns how many [`Var`]s changed.
+    ///
+    /// Feeds with nothing received leave their [`Var`] untouched. The values are written together
+    /// as by [`commit_staged`](Dcg::commit_staged), so observers only run once every [`Var`] has
+    /// been written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    /// use std::{sync::mpsc, thread};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(0);
+    /// let (tx, rx) = mpsc::channel();
+    /// dcg.feed(&a, rx);
+    ///
+    /// thread::spawn(move || {
+    ///     tx.send(1).unwrap();
+    ///     tx.send(2).unwrap();
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// assert_eq!(dcg.pump(), 1);
+    /// assert_eq!(a.read(), 2);
+    /// assert_eq!(dcg.pump(), 0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated and a value has been received, or if a
+    /// received value is rejected by a [validator](RawVar::add_validator). See
+    /// [`try_pump`](Dcg::try_pump).
+    pub fn pump(&self) -> usize {
+        self.try_pump().unwrap_or_else(|err| panic!("{}", err))
     }
-}
 
-/// Ergonomic [`Thunk`] creation.
-///
-/// The first argument is the [`Dcg`] in which the [`Thunk`] will be created.
-///
-/// The second argument specifies how the [`Thunk`] will generate values.
-/// It can be:
-///
-/// - An [`Incremental`]'s `ident`.
-/// - Of the form `params => expr` where
+    /// Like [`pump`](Dcg::pump), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) or
+    /// [`ValidationFailed`](DcgError::ValidationFailed) instead of panicking.
+    ///
+    /// On error no value is written, and the received values are kept to be written by the next
+    /// pump, unless a newer value is received for the same [`Var`] by then.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    /// use std::sync::mpsc;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(0);
+    /// a.add_validator(|value| if *value < 10 { Ok(()) } else { Err("too big".to_string()) });
+    /// let (tx, rx) = mpsc::channel();
+    /// dcg.feed(&a, rx);
+    ///
+    /// tx.send(20).unwrap();
+    /// assert!(dcg.try_pump().is_err());
+    /// assert!(dcg.try_pump().is_err());
+    /// tx.send(5).unwrap();
+    /// assert_eq!(dcg.try_pump(), Ok(1));
+    /// assert_eq!(a.read(), 5);
+    /// ```
+    pub fn try_pump(&self) -> Result<usize, DcgError> {
+        let mut writes = self.inner.unpumped.take();
+        let mut feeds = self.inner.feeds.take();
+        feeds.retain_mut(|feed| match feed() {
+            Some(write) => {
+                if let Some((idx, write)) = write {
+                    writes.retain(|(unpumped, _)| *unpumped != idx);
+                    writes.push((idx, write));
+                }
+                true
+            }
+            None => false,
+        });
+        {
+            let mut registered = self.inner.feeds.borrow_mut();
+            feeds.append(&mut registered);
+            *registered = feeds;
+        }
+        let result = self.try_apply_report(&mut writes);
+        if result.is_err() {
+            self.inner.unpumped.replace(writes);
+        }
+        result.map(|report| report.changed)
+    }
+
+    /// Stages a write of `value` into `var`, to be applied by
+    /// [`commit_staged`](Dcg::commit_staged). Replaces any write already staged for `var`.
+    ///
+    /// Unlike [`write`](RawVar::write), staging may be done while nodes are being evaluated:
+    /// reads before the commit still see `var`'s current value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    ///
+    /// // Swaps `a` and `b`.
+    /// dcg.stage(&a, b.read());
+    /// dcg.stage(&b, a.read());
+    /// assert_eq!((a.read(), b.read()), (1, 2));
+    /// assert_eq!(dcg.commit_staged(), 2);
+    /// assert_eq!((a.read(), b.read()), (2, 1));
+    /// ```
+    pub fn stage<T>(&self, var: &Var<T>, value: T)
+    where
+        T: PartialEq + Clone + 'static,
+    {
+        let idx = var.node.idx;
+        let write = stage_write(Rc::downgrade(var), value);
+        let mut staged = self.inner.staged.borrow_mut();
+        staged.retain(|(staged, _)| *staged != idx);
+        staged.push((idx, write));
+    }
+
+    /// Applies every write [staged](Dcg::stage) since the last commit, returning how many
+    /// [`Var`]s changed.
+    ///
+    /// Every value is written and every dependent dirtied before any observer or watcher is
+    /// called, so none of them sees some staged writes applied and others not. They are then
+    /// called in topological order of their [`Var`]s, ties broken by creation order. The writes
+    /// are recorded as a single [undoable](Dcg::undo) step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated and a write is staged, or if a staged
+    /// value is rejected by a [validator](RawVar::add_validator), in which case none of the
+    /// staged writes are applied. See [`try_commit_staged`](Dcg::try_commit_staged).
+    pub fn commit_staged(&self) -> usize {
+        self.commit_staged_report().changed
+    }
+
+    /// Like [`commit_staged`](Dcg::commit_staged), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) or
+    /// [`ValidationFailed`](DcgError::ValidationFailed) instead of panicking.
+    ///
+    /// On error none of the staged writes are applied, and all of them stay staged, to be
+    /// retried or [discarded](Dcg::discard_staged).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, DcgError, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// b.add_validator(|value| if *value > 0 { Ok(()) } else { Err("not positive".to_string()) });
+    ///
+    /// dcg.stage(&a, 10);
+    /// dcg.stage(&b, 0);
+    /// assert_eq!(
+    ///     dcg.try_commit_staged(),
+    ///     Err(DcgError::ValidationFailed {
+    ///         target: b.node().index(),
+    ///         message: "not positive".to_string(),
+    ///     })
+    /// );
+    /// assert_eq!((a.read(), b.read()), (1, 2));
+    ///
+    /// dcg.stage(&b, 20);
+    /// assert_eq!(dcg.try_commit_staged(), Ok(2));
+    /// assert_eq!((a.read(), b.read()), (10, 20));
+    /// ```
+    pub fn try_commit_staged(&self) -> Result<usize, DcgError> {
+        self.try_commit_staged_report().map(|report| report.changed)
+    }
+
+    /// Like [`commit_staged`](Dcg::commit_staged), but returns the combined effect of the writes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{BatchReport, Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let sum = buffer!(dcg, (a, b) => a + b);
+    /// sum.read();
+    ///
+    /// dcg.stage(&a, 3);
+    /// dcg.stage(&b, 2);
+    /// assert_eq!(
+    ///     dcg.commit_staged_report(),
+    ///     BatchReport {
+    ///         changed: 1,
+    ///         edges_dirtied: 2,
+    ///         memos_invalidated: vec![sum.node().index()],
+    ///     }
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated and a write is staged, or if a staged
+    /// value is rejected by a [validator](RawVar::add_validator).
+    pub fn commit_staged_report(&self) -> BatchReport {
+        self.try_commit_staged_report()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`try_commit_staged`](Dcg::try_commit_staged), but returns the combined effect of the
+    /// writes.
+    pub fn try_commit_staged_report(&self) -> Result<BatchReport, DcgError> {
+        let mut staged = self.inner.staged.take();
+        let result = self.try_apply_report(&mut staged);
+        if result.is_err() {
+            // Writes staged since, e.g. by a validator, replace the kept ones.
+            let mut current = self.inner.staged.borrow_mut();
+            staged.retain(|(idx, _)| current.iter().all(|(staged, _)| staged != idx));
+            staged.append(&mut current);
+            *current = staged;
+        }
+        result
+    }
+
+    /// Applies `writes` as one glitch-free update, returning how many [`Var`]s changed, or the
+    /// first [`ValidationFailed`](DcgError::ValidationFailed) error, having applied none of them.
+    /// See [`commit_staged`](Dcg::commit_staged).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated and `writes` isn't empty.
+    fn apply_report(&self, mut writes: Vec<(NodeIndex, Staged)>) -> Result<BatchReport, DcgError> {
+        self.check_applicable(&writes)
+            .unwrap_or_else(|err| panic!("{}", err));
+        self.try_apply_report(&mut writes)
+    }
+
+    /// Returns [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) if called while a
+    /// node is being evaluated and `writes` isn't empty.
+    fn check_applicable(&self, writes: &[(NodeIndex, Staged)]) -> Result<(), DcgError> {
+        match (self.inner.evaluating.borrow().last(), writes.first()) {
+            (Some(evaluation), Some((target, _))) => Err(DcgError::MutationDuringEvaluation {
+                evaluating: evaluation.node,
+                target: *target,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like [`apply_report`](Dcg::apply_report), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) instead of panicking.
+    /// `writes` is emptied if they're applied, and left as it was on error.
+    fn try_apply_report(
+        &self,
+        writes: &mut Vec<(NodeIndex, Staged)>,
+    ) -> Result<BatchReport, DcgError> {
+        self.check_applicable(writes)?;
+        for (_, write) in writes.iter() {
+            write.validate()?;
+        }
+        // A panicking clone drops the writes swapped so far, putting their old values back.
+        let mut applied = Vec::new();
+        for (_, write) in writes.drain(..) {
+            applied.extend(write.apply());
+        }
+        // Nothing from here until the callbacks can panic, so the writes are applied in full.
+        self.group_edits(|| {
+            for write in &mut applied {
+                write.commit();
+            }
+        });
+        if applied.len() > 1 {
+            let heights = self.inner.heights();
+            applied.sort_by_key(|write| (heights[&write.node().idx], write.node().idx));
+        }
+        let dirtied: Vec<_> = applied
+            .iter()
+            .map(|write| write.node().mark_dependents())
+            .collect();
+        let mut report = BatchReport {
+            changed: applied.len(),
+            ..BatchReport::default()
+        };
+        for (write, dirtied) in applied.iter().zip(&dirtied) {
+            let (edges, caches) = write.node().summarize_dirtied(dirtied);
+            report.edges_dirtied += edges;
+            report.memos_invalidated.extend(caches);
+        }
+        report.memos_invalidated.sort();
+        for (write, dirtied) in applied.iter().zip(&dirtied) {
+            let node = write.node();
+            node.inner.emit(Event::VarWritten { idx: node.idx });
+            node.announce_dirtied(dirtied);
+        }
+        if let Some(write) = applied.first() {
+            write.node().debug_check();
+        }
+        for write in &applied {
+            write.notify();
+        }
+        if let Some(write) = applied.first() {
+            write.node().refresh_eager();
+        }
+        Ok(report)
+    }
+
+    /// Discards every write [staged](Dcg::stage) since the last commit.
+    pub fn discard_staged(&self) {
+        self.inner.staged.borrow_mut().clear();
+    }
+
+    /// Removes the sink registered as `id`, returning `true` if it was subscribed.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.inner.subscribers.borrow_mut();
+        let before = subscribers.len();
+        subscribers.retain(|(subscriber, _)| *subscriber != id);
+        subscribers.len() != before
+    }
+
+    /// Checks that the [`Dcg`]'s internal bookkeeping is consistent, returning a description of
+    /// every violation found.
+    ///
+    /// The following invariants are checked:
+    ///
+    /// - Dependencies form a directed acyclic graph.
+    /// - No node is being evaluated re-entrantly.
+    /// - A clean node's dependencies that were read during its most recent evaluation are clean;
+    ///   dirtying relies on this to stop at already dirty nodes.
+    /// - Read records only refer to nodes in the [`Dcg`].
+    /// - A clean [`Memo`] or [`Buffer`] holds a cached value, unless it was evicted by
+    ///   [`set_cache_capacity`](Dcg::set_cache_capacity).
+    ///
+    /// In debug builds this is asserted after every [`write`](RawVar::write),
+    /// [`modify`](RawVar::modify) and outermost [`read`](Incremental::read), unless disabled with
+    /// [`set_debug_checks`](Dcg::set_debug_checks).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    ///
+    /// b.read();
+    /// a.write(2);
+    /// assert_eq!(dcg.check_invariants(), Ok(()));
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), Vec<String>> {
+        self.inner.check_invariants()
+    }
+
+    /// Enables or disables the invariant checks performed in debug builds.
+    ///
+    /// Checks are enabled by default; disabling them is useful for large graphs, where checking
+    /// after every operation is slow. See [`check_invariants`](Dcg::check_invariants).
+    pub fn set_debug_checks(&self, enabled: bool) {
+        self.inner.unchecked.set(!enabled);
+    }
+
+    /// Returns the dependencies of `node` that were not read during its most recent evaluation.
+    ///
+    /// A dependency is only considered read if it was [`read`](Incremental::read) while `node`'s
+    /// closure was executing. Nodes that have never been evaluated report no unused dependencies.
+    ///
+    /// This is purely diagnostic: over-declared dependencies are not removed, they just cause
+    /// `node` to be dirtied more often than necessary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental, thunk};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let t = thunk!(dcg, (a; b) => a * 2);
+    ///
+    /// t.read();
+    /// assert_eq!(dcg.unused_dependencies(t.node()), vec![b.node().index()]);
+    /// ```
+    pub fn unused_dependencies(&self, node: &Node) -> Vec<NodeIndex> {
+        self.unused_dependencies_of(node.idx)
+    }
+
+    /// Returns every `(dependency, dependent)` pair where `dependency` was not read during
+    /// `dependent`'s most recent evaluation.
+    ///
+    /// See [`unused_dependencies`](Dcg::unused_dependencies).
+    pub fn audit(&self) -> Vec<(NodeIndex, NodeIndex)> {
+        let nodes: Vec<_> = self.inner.graph.borrow().node_indices().collect();
+        nodes
+            .into_iter()
+            .flat_map(|node| {
+                self.unused_dependencies_of(node)
+                    .into_iter()
+                    .map(move |dependency| (dependency, node))
+            })
+            .collect()
+    }
+
+    fn unused_dependencies_of(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        let last_reads = self.inner.last_reads.borrow();
+        let reads = match last_reads.get(&idx) {
+            Some(reads) => reads,
+            None => return Vec::new(),
+        };
+        let mut unused: Vec<_> = self
+            .inner
+            .graph
+            .borrow()
+            .neighbors_directed(idx, Incoming)
+            .filter(|dependency| !reads.contains(dependency))
+            .collect();
+        unused.sort();
+        unused.dedup();
+        unused
+    }
+}
+
+impl fmt::Debug for Dcg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", Dot::new(&*self.inner.graph.borrow()))
+    }
+}
+
+/// A handle for a node in a [`Dcg`].
+pub struct Node {
+    inner: Rc<Inner>,
+    idx: NodeIndex,
+    /// The generation of `idx` when the node was created.
+    generation: u32,
+}
+
+impl Node {
+    fn new(dcg: &Dcg, kind: NodeKind) -> Self {
+        let idx = dcg.inner.graph.borrow_mut().add_node(true);
+        dcg.inner.kinds.borrow_mut().insert(idx, kind);
+        dcg.inner.emit(Event::NodeAdded { idx, kind });
+        Self::at(dcg.inner.clone(), idx)
+    }
+
+    /// Returns a handle for the node currently at `idx`.
+    fn at(inner: Rc<Inner>, idx: NodeIndex) -> Self {
+        let generation = inner
+            .generations
+            .borrow()
+            .get(&idx)
+            .copied()
+            .unwrap_or_default();
+        Self {
+            inner,
+            idx,
+            generation,
+        }
+    }
+
+    /// Returns [`StaleHandle`](DcgError::StaleHandle) if the node has been removed.
+    pub(crate) fn check_current(&self) -> Result<(), DcgError> {
+        let generations = self.inner.generations.borrow();
+        match generations.get(&self.idx) {
+            Some(&generation) if generation != self.generation => {
+                Err(DcgError::StaleHandle { target: self.idx })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Panics with [`StaleHandle`](DcgError::StaleHandle) if the node has been removed.
+    pub(crate) fn assert_current(&self) {
+        if let Err(err) = self.check_current() {
+            panic!("{}", err);
+        }
+    }
+
+    /// Returns the node's index in its [`Dcg`].
+    pub fn index(&self) -> NodeIndex {
+        self.idx
+    }
+
+    /// Names the node, replacing any previous name.
+    ///
+    /// Names label the node in exports such as [`Dcg::to_dot`], and identify it when saving and
+    /// rebuilding a [`Dcg`].
+    pub fn set_name(&self, name: impl Into<String>) {
+        self.inner.names.borrow_mut().insert(self.idx, name.into());
+    }
+
+    /// Returns the node's name, if it has been given one with [`set_name`](Node::set_name).
+    pub fn name(&self) -> Option<String> {
+        self.inner.names.borrow().get(&self.idx).cloned()
+    }
+
+    /// Returns whether the node is a [`Var`], [`Thunk`], [`Memo`] or [`Buffer`].
+    pub fn kind(&self) -> NodeKind {
+        self.inner.kinds.borrow()[&self.idx]
+    }
+
+    fn add_dependencies<P>(&self, params: &P)
+    where
+        P: Incremental,
+    {
+        let dependencies: Vec<_> = params.nodes().iter().map(|node| node.idx).collect();
+        self.add_dependency_indices(dependencies);
+    }
+
+    fn add_dependency_indices(&self, dependencies: Vec<NodeIndex>) {
+        {
+            let mut graph = self.inner.graph.borrow_mut();
+            for &dependency in &dependencies {
+                graph.add_edge(dependency, self.idx, ());
+            }
+        }
+        for from in dependencies {
+            self.inner.emit(Event::EdgeAdded { from, to: self.idx });
+        }
+    }
+
+    /// Replaces the node's dependencies with `dependencies`.
+    fn set_dependencies(&self, dependencies: &HashSet<NodeIndex>) {
+        let (removed, added) = {
+            let mut graph = self.inner.graph.borrow_mut();
+            let mut existing: Vec<_> = graph
+                .edges_directed(self.idx, Incoming)
+                .map(|edge| (edge.id(), edge.source()))
+                .collect();
+            // Removing an edge moves the last edge into its index, so remove from the back.
+            existing.sort_by_key(|&(edge, _)| std::cmp::Reverse(edge));
+            let mut removed = Vec::new();
+            for &(edge, from) in &existing {
+                if !dependencies.contains(&from) {
+                    graph.remove_edge(edge);
+                    removed.push(from);
+                }
+            }
+            let mut added: Vec<_> = dependencies
+                .iter()
+                .copied()
+                .filter(|&from| !existing.iter().any(|&(_, source)| source == from))
+                .collect();
+            added.sort();
+            for &from in &added {
+                graph.add_edge(from, self.idx, ());
+            }
+            (removed, added)
+        };
+        for from in removed {
+            self.inner.emit(Event::EdgeRemoved { from, to: self.idx });
+        }
+        for from in added {
+            self.inner.emit(Event::EdgeAdded { from, to: self.idx });
+        }
+    }
+
+    fn clean(&self) {
+        self.inner.graph.borrow_mut()[self.idx] = false;
+        self.record_verified();
+        let mut audit = self.inner.audit.borrow_mut();
+        if audit.enabled {
+            let now = audit.next_seq;
+            let cleaned = audit.cleaned.entry(self.idx).or_default();
+            *cleaned = (cleaned.1, now);
+        }
+        drop(audit);
+        self.inner.emit(Event::NodeCleaned { idx: self.idx });
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.inner.is_dirty(self.idx)
+    }
+
+    /// Runs `f` as the node's evaluation, recording which nodes it reads.
+    ///
+    /// Nested evaluations of the same node (a [`RawMemo`] evaluating its params, then its thunk)
+    /// share one record. Evaluations that unwind leave the previous record untouched.
+    fn evaluate<R>(&self, f: impl FnOnce() -> R) -> R {
+        let current = self.inner.evaluating.borrow().last().map(|e| e.node);
+        if current == Some(self.idx) {
+            return f();
+        }
+        self.inner.evaluating.borrow_mut().push(Evaluation {
+            node: self.idx,
+            reads: HashSet::new(),
+        });
+        let _guard = EvaluationGuard(self);
+        f()
+    }
+
+    /// Returns an error if the node was removed or is frozen, or if any node is being evaluated,
+    /// as the node must not be mutated then.
+    fn check_mutable(&self) -> Result<(), DcgError> {
+        self.check_current()?;
+        self.check_unfrozen()?;
+        self.check_fulfilled()?;
+        match self.inner.evaluating.borrow().last() {
+            Some(evaluation) => Err(DcgError::MutationDuringEvaluation {
+                evaluating: evaluation.node,
+                target: self.idx,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Records that the node's cache holds a value.
+    fn mark_cached(&self) {
+        self.inner.cached.borrow_mut().insert(self.idx);
+        self.touch_cache();
+        self.evict_beyond_capacity();
+    }
+
+    /// Records that the node's cache has been emptied.
+    fn forget_cached(&self) {
+        self.inner.cached.borrow_mut().remove(&self.idx);
+        self.untouch_cache();
+    }
+
+    /// Returns the node's [`version`](Dcg::version).
+    fn version(&self) -> u64 {
+        self.inner
+            .versions
+            .borrow()
+            .get(&self.idx)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Records that the node's value has changed.
+    fn bump_version(&self) {
+        *self
+            .inner
+            .versions
+            .borrow_mut()
+            .entry(self.idx)
+            .or_default() += 1;
+        self.inner.forget_checked(self.idx);
+    }
+
+    /// Counts a read of the node's cache towards its [`CacheStats`].
+    fn record_cache_read(&self, hit: bool) {
+        self.touch_cache();
+        if hit {
+            self.inner.trace(trace::TraceEvent::CacheHit(self.idx));
+        }
+        let mut cache_stats = self.inner.cache_stats.borrow_mut();
+        let stats = cache_stats.entry(self.idx).or_default();
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+    }
+
+    /// Records that the node was read by the node currently being evaluated, if any.
+    fn record_read(&self) {
+        if let Some(evaluation) = self.inner.evaluating.borrow_mut().last_mut() {
+            evaluation.reads.insert(self.idx);
+        }
+    }
+
+    /// Panics if the [`Dcg`]'s invariants are violated, in debug builds and outside evaluations.
+    fn debug_check(&self) {
+        if cfg!(debug_assertions)
+            && !self.inner.unchecked.get()
+            && self.inner.evaluating.borrow().is_empty()
+        {
+            if let Err(violations) = self.inner.check_invariants() {
+                panic!("Dcg invariants violated:\n{}", violations.join("\n"));
+            }
+        }
+    }
+
+    /// Counts one recomputation towards the active budget, if any.
+    fn spend(&self) {
+        if let Some(recomputed) = self.inner.budget.get() {
+            self.inner.budget.set(Some(recomputed + 1));
+        }
+    }
+
+    /// Returns the number of dependencies into `dirtied` nodes other than this one, and the
+    /// [`Memo`]s and [`Buffer`]s among them, sorted.
+    fn summarize_dirtied(&self, dirtied: &[NodeIndex]) -> (usize, Vec<NodeIndex>) {
+        let graph = self.inner.graph.borrow();
+        let kinds = self.inner.kinds.borrow();
+        let dirtied = dirtied.iter().filter(|&&idx| idx != self.idx);
+        let edges = dirtied
+            .clone()
+            .map(|&idx| graph.edges_directed(idx, Incoming).count())
+            .sum();
+        let mut caches: Vec<_> = dirtied
+            .copied()
+            .filter(|idx| matches!(kinds[idx], NodeKind::Memo | NodeKind::Buffer))
+            .collect();
+        caches.sort();
+        (edges, caches)
+    }
+
+    /// Dirties the node's transitive dependents, returning the nodes dirtied, including the node
+    /// itself if it was clean.
+    fn dirty_dependents(&self) -> Vec<NodeIndex> {
+        let dependents = self.mark_dependents();
+        self.announce_dirtied(&dependents);
+        dependents
+    }
+
+    /// Sets the dirty flags of the node's transitive dependents, without running any callbacks,
+    /// returning the nodes dirtied.
+    fn mark_dependents(&self) -> Vec<NodeIndex> {
+        self.mark_dependents_classed(ALL_CHANGES)
+    }
+
+    /// Like [`mark_dependents`](Node::mark_dependents), for a change of the given
+    /// [class](RawVar::write_classed).
+    /// A DFS from the node gathers clean edges, pruning already dirty ones,
+    /// [insensitive](Dcg::buffer_sensitive_to) ones and [skipped](Dcg::set_skip_thunk_sinks)
+    /// [`Thunk`]s, and dirties them. Once any node is insensitive to some class, a clean node can
+    /// depend on a dirty one, so the DFS continues through already dirty nodes.
+    fn mark_dependents_classed(&self, class: u8) -> Vec<NodeIndex> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "write",
+            node = self.idx.index(),
+            dirtied = tracing::field::Empty
+        )
+        .entered();
+        self.inner.version.set(self.inner.version.get() + 1);
+        self.inner.new_revision();
+        let auditing = self.inner.audit.borrow().enabled;
+        let versions = self.inner.strategy.get() == VerificationStrategy::Versions;
+        let mut dependents = Vec::new();
+        let mut edges = Vec::new();
+        let observed = self.inner.observed();
+        let mut skipped = Vec::new();
+        {
+            let graph = self.inner.graph.borrow();
+            let sensitivities = self.inner.sensitivities.borrow();
+            let classed = !sensitivities.is_empty();
+            let unobserved = |n: NodeIndex| {
+                n != self.idx
+                    && observed
+                        .as_ref()
+                        .is_some_and(|observed| !observed.contains(&n))
+            };
+            let insensitive = |n: NodeIndex| {
+                (n != self.idx && sensitivities.get(&n).is_some_and(|mask| mask & class == 0))
+                    || unobserved(n)
+            };
+            depth_first_search(&*graph, Some(self.idx), |event| match event {
+                DfsEvent::Discover(n, _) => {
+                    if unobserved(n) {
+                        if !graph[n] && !versions {
+                            skipped.push(n);
+                        }
+                        return Control::Prune::<()>;
+                    }
+                    if insensitive(n) {
+                        return Control::Prune;
+                    }
+                    if graph[n] {
+                        return if classed && !versions {
+                            Control::Continue
+                        } else {
+                            Control::Prune
+                        };
+                    }
+                    dependents.push(n);
+                    if versions {
+                        // Dependents find out by comparing versions.
+                        return Control::Prune;
+                    }
+                    Control::Continue
+                }
+                DfsEvent::TreeEdge(from, to) if auditing && !graph[to] && !insensitive(to) => {
+                    edges.push((from, to));
+                    Control::Continue
+                }
+                _ => Control::Continue,
+            });
+        }
+        if !edges.is_empty() {
+            self.inner.audit.borrow_mut().record(self.idx, edges);
+        }
+        if !skipped.is_empty() {
+            self.inner.skipped.borrow_mut().extend(skipped);
+        }
+
+        {
+            let mut graph = self.inner.graph.borrow_mut();
+            for &node in &dependents {
+                graph[node] = true;
+            }
+        }
+        #[cfg(feature = "tracing")]
+        span.record("dirtied", dependents.len());
+        dependents
+    }
+
+    /// Emits events for, and calls the hooks of, the nodes dirtied by
+    /// [`mark_dependents`](Node::mark_dependents).
+    fn announce_dirtied(&self, dirtied: &[NodeIndex]) {
+        for &idx in dirtied {
+            self.inner.emit(Event::NodeDirtied { idx });
+            let hooks = self.inner.dirtied_hooks.borrow().get(&idx).cloned();
+            for hook in hooks.into_iter().flatten() {
+                (hook.borrow_mut())(self.idx);
+            }
+        }
+    }
+}
+
+/// Ends a [`Node::evaluate`], even if the evaluation unwinds.
+struct EvaluationGuard<'a>(&'a Node);
+
+impl Drop for EvaluationGuard<'_> {
+    fn drop(&mut self) {
+        let evaluation = self.0.inner.evaluating.borrow_mut().pop();
+        if let Some(evaluation) = evaluation {
+            if !thread::panicking() {
+                if self.0.inner.dynamic.borrow().contains(&evaluation.node) {
+                    let strong = evaluation
+                        .reads
+                        .iter()
+                        .copied()
+                        .filter(|&read| !self.0.weakly_depends_on(read))
+                        .collect();
+                    self.0.set_dependencies(&strong);
+                }
+                self.0
+                    .inner
+                    .last_reads
+                    .borrow_mut()
+                    .insert(evaluation.node, evaluation.reads);
+            }
+        }
+    }
+}
+
+type Hook<T> = Rc<RefCell<dyn FnMut(&T)>>;
+
+type NodeHook = Rc<RefCell<dyn FnMut(NodeIndex)>>;
+
+type RecomputeHook = Rc<RefCell<dyn FnMut(NodeIndex, &dyn Any)>>;
+
+/// Compares a node's old and new values, stored so nodes without `T: PartialEq` bounds can.
+type Compare<T> = fn(&T, &T) -> bool;
+
+/// Sends a changed value to a receiver returned by `watch`, returning whether it is still alive.
+type Watcher<T> = Box<dyn FnMut(&T) -> bool>;
+
+/// Passes `value` to each of `watchers`, dropping those whose receiver has been dropped.
+fn send_to_watchers<T>(watchers: &RefCell<Vec<Watcher<T>>>, value: &T) {
+    watchers.borrow_mut().retain_mut(|watcher| watcher(value));
+}
+
+/// Calls each of `hooks` with `value`, without holding a borrow of `hooks` so they may register
+/// further hooks.
+fn call_hooks<T>(hooks: &RefCell<Vec<Hook<T>>>, value: &T) {
+    let hooks = hooks.borrow().clone();
+    for hook in hooks {
+        (hook.borrow_mut())(value);
+    }
+}
+
+/// The most recent values produced by a node, recorded once enabled by `record_history`.
+struct History<T> {
+    capacity: usize,
+    values: VecDeque<T>,
+    /// Set while recording; stored so nodes without `T: Clone` bounds can record.
+    clone: Option<fn(&T) -> T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History {
+            capacity: 0,
+            values: VecDeque::new(),
+            clone: None,
+        }
+    }
+}
+
+impl<T> History<T> {
+    fn record(&mut self, value: &T) {
+        if let Some(value) = self.copy(value) {
+            self.push(value);
+        }
+    }
+
+    /// Returns a copy of `value` to [`push`](History::push) if recording.
+    fn copy(&self, value: &T) -> Option<T> {
+        self.clone.map(|clone| clone(value))
+    }
+
+    fn push(&mut self, value: T) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+}
+
+impl<T: Clone> History<T> {
+    fn enable(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.clone = if capacity == 0 { None } else { Some(T::clone) };
+        while self.values.len() > capacity {
+            self.values.pop_front();
+        }
+    }
+
+    fn values(&self) -> Vec<T> {
+        self.values.iter().cloned().collect()
+    }
+}
+
+/// Identifies a callback registered with [`observe`](RawVar::observe).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(usize);
+
+type Observer<T> = Rc<RefCell<dyn FnMut(&T, &T)>>;
+
+/// Data-storing [`Dcg`] node.
+pub struct RawVar<T> {
+    value: RefCell<T>,
+    node: Node,
+    observers: RefCell<Vec<(ObserverId, Observer<T>)>>,
+    watchers: RefCell<Vec<Watcher<T>>>,
+    history: RefCell<History<T>>,
+    validators: RefCell<Vec<Validator<T>>>,
+}
+
+type Validator<T> = Rc<dyn Fn(&T) -> Result<(), String>>;
+
+impl<T> RawVar<T> {
+    /// Creates a [`Var`] containing `value` for the already added `node`.
+    fn with_node(node: Node, value: T) -> Self {
+        Self {
+            value: RefCell::new(value),
+            node,
+            observers: RefCell::default(),
+            watchers: RefCell::default(),
+            history: RefCell::default(),
+            validators: RefCell::default(),
+        }
+    }
+
+    /// Returns the [`Var`]'s [`Node`].
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    /// Registers `callback` to be called with the old and new values whenever the [`Var`]'s value
+    /// is changed by [`write`](RawVar::write) or [`modify`](RawVar::modify).
+    ///
+    /// Callbacks run in registration order, after the [`Var`]'s dependents have been dirtied and
+    /// with no borrows held, so they may freely read and write [`Dcg`] nodes. Writes that leave
+    /// the value unchanged do not call observers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::Dcg;
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let changes = Rc::new(RefCell::new(Vec::new()));
+    /// let changes_clone = changes.clone();
+    /// let id = a.observe(move |old, new| changes_clone.borrow_mut().push((*old, *new)));
+    ///
+    /// a.write(2);
+    /// a.write(2);
+    /// a.unobserve(id);
+    /// a.write(3);
+    /// assert_eq!(*changes.borrow(), vec![(1, 2)]);
+    /// ```
+    pub fn observe<F>(&self, callback: F) -> ObserverId
+    where
+        F: FnMut(&T, &T) + 'static,
+    {
+        let id = ObserverId(self.node.inner.next_id());
+        self.observers
+            .borrow_mut()
+            .push((id, Rc::new(RefCell::new(callback))));
+        id
+    }
+
+    /// Removes the callback registered as `id`, returning `true` if it was registered with this
+    /// [`Var`].
+    ///
+    /// Removing a callback while observers are being called takes effect from the next change.
+    pub fn unobserve(&self, id: ObserverId) -> bool {
+        let mut observers = self.observers.borrow_mut();
+        let before = observers.len();
+        observers.retain(|(observer, _)| *observer != id);
+        observers.len() != before
+    }
+
+    /// Registers `validator` to be consulted before every value written to the [`Var`], by
+    /// [`write`](RawVar::write), [`modify`](RawVar::modify), [`Dcg::commit_staged`],
+    /// [`Record::set_all`](collections::Record::set_all) and [`Dcg::restore_cells`].
+    ///
+    /// A value is only written if every validator accepts it, in registration order. A rejected
+    /// write leaves the [`Var`] and its dependents untouched, and
+    /// [`try_write`](RawVar::try_write) returns [`ValidationFailed`](DcgError::ValidationFailed)
+    /// with the first rejection's message. Values restored by [`Dcg::undo`] and [`Dcg::redo`]
+    /// were accepted when first written, so they aren't validated again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, DcgError, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let percent = dcg.var(50);
+    /// percent.add_validator(|value| {
+    ///     if (0..=100).contains(value) {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(format!("{} is not a percentage", value))
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(
+    ///     percent.try_write(120),
+    ///     Err(DcgError::ValidationFailed {
+    ///         target: percent.node().index(),
+    ///         message: "120 is not a percentage".to_string(),
+    ///     })
+    /// );
+    /// assert_eq!(percent.try_write(80), Ok(50));
+    /// assert_eq!(percent.read(), 80);
+    /// ```
+    pub fn add_validator<F>(&self, validator: F)
+    where
+        F: Fn(&T) -> Result<(), String> + 'static,
+    {
+        self.validators.borrow_mut().push(Rc::new(validator));
+    }
+
+    /// Returns [`ValidationFailed`](DcgError::ValidationFailed) if a validator rejects `value`.
+    fn validate(&self, value: &T) -> Result<(), DcgError> {
+        let validators = self.validators.borrow().clone();
+        for validator in validators {
+            validator(value).map_err(|message| DcgError::ValidationFailed {
+                target: self.node.idx,
+                message,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Debug + 'static> RawVar<T> {
+    /// Includes the [`Var`]'s current value, formatted with [`Debug`](fmt::Debug), in its label
+    /// in exports such as [`Dcg::to_dot`].
+    pub fn show_value(self: &Rc<Self>) {
+        let var = Rc::downgrade(self);
+        self.node.inner.show_value(self.node.idx, move || {
+            var.upgrade().map(|var| format!("{:?}", var.value.borrow()))
+        });
+    }
+}
+
+impl<T: Clone> RawVar<T> {
+    /// Returns the [`Var`]'s value without [reading](Incremental::read) it: a dirty [`Var`] stays
+    /// dirty, and no node being evaluated comes to depend on it.
+    pub fn value(&self) -> T {
+        self.value.borrow().clone()
+    }
+
+    /// Starts recording the last `capacity` values written to the [`Var`], discarding the
+    /// oldest recorded values beyond it.
+    ///
+    /// Recording is disabled by default, and disabled again by a `capacity` of 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::Dcg;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(0);
+    /// a.record_history(3);
+    ///
+    /// for i in 1..=5 {
+    ///     a.write(i);
+    /// }
+    /// assert_eq!(a.history(), vec![3, 4, 5]);
+    /// ```
+    pub fn record_history(&self, capacity: usize) {
+        self.history.borrow_mut().enable(capacity);
+    }
+
+    /// Returns the recorded values, oldest first. See [`record_history`](RawVar::record_history).
+    pub fn history(&self) -> Vec<T> {
+        self.history.borrow().values()
+    }
+
+    /// Calls the [`Var`]'s observers with `old` and its current value, then sends it to its
+    /// watchers.
+    fn notify(&self, old: &T) {
+        let observers: Vec<_> = self
+            .observers
+            .borrow()
+            .iter()
+            .map(|(_, observer)| observer.clone())
+            .collect();
+        if observers.is_empty() && self.watchers.borrow().is_empty() {
+            return;
+        }
+        let new = self.value.borrow().clone();
+        for observer in observers {
+            (observer.borrow_mut())(old, &new);
+        }
+        send_to_watchers(&self.watchers, &new);
+    }
+}
+
+impl<T: Clone + 'static> RawVar<T> {
+    /// Returns a [`Receiver`](mpsc::Receiver) sent the [`Var`]'s new value whenever it is changed
+    /// by [`write`](RawVar::write) or [`modify`](RawVar::modify).
+    ///
+    /// Writes that leave the value unchanged send nothing. The [`Var`] stops sending once the
+    /// receiver is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::Dcg;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let rx = a.watch();
+    ///
+    /// a.write(2);
+    /// a.write(2);
+    /// a.write(3);
+    /// assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+    /// ```
+    pub fn watch(&self) -> mpsc::Receiver<T> {
+        let (tx, rx) = mpsc::channel();
+        self.watchers
+            .borrow_mut()
+            .push(Box::new(move |value: &T| tx.send(value.clone()).is_ok()));
+        rx
+    }
+}
+
+impl<T: PartialEq + Clone> RawVar<T> {
+    /// Writes a value into the [`Var`] and dirties its dependents if necessary.
+    ///
+    /// If `new` is equal to its current value, `new` is simply returned.
+    /// Otherwise, `new` will be swapped with the current value, the [`Var`] and its dependents will be
+    /// dirtied and the old value will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    ///
+    /// // Ensures `a` is clean
+    /// a.read();
+    ///
+    /// // `a` remains clean due to writing the same value
+    /// assert_eq!(a.write(1), 1);
+    /// assert!(a.is_clean());
+    /// assert_eq!(a.read(), 1);
+    ///
+    /// // `a` dirtied due to writing a different value
+    /// assert_eq!(a.write(2), 1);
+    /// assert!(a.is_dirty());
+    /// assert_eq!(a.read(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated, if the [`Var`] is
+    /// [frozen](RawVar::freeze), or if `new` is rejected by a
+    /// [validator](RawVar::add_validator); see [`try_write`](RawVar::try_write).
+    pub fn write(&self, new: T) -> T {
+        self.try_write(new).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`write`](RawVar::write), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) instead of writing if
+    /// called while a node is being evaluated, [`Frozen`](DcgError::Frozen) if the [`Var`] is
+    /// frozen, or [`ValidationFailed`](DcgError::ValidationFailed) if `new` is rejected by a
+    /// [validator](RawVar::add_validator).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, DcgError, incremental::Incremental, thunk};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let t = thunk!(dcg, (; a) => a.try_write(2));
+    ///
+    /// assert_eq!(
+    ///     t.read(),
+    ///     Err(DcgError::MutationDuringEvaluation {
+    ///         evaluating: t.node().index(),
+    ///         target: a.node().index(),
+    ///     })
+    /// );
+    /// assert_eq!(a.read(), 1);
+    /// ```
+    pub fn try_write(&self, new: T) -> Result<T, DcgError> {
+        self.try_write_report(new).map(|report| report.previous)
+    }
+
+    /// Like [`write`](RawVar::write), but returns the effect of the write.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{ChangeReport, Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 2);
+    /// c.read();
+    ///
+    /// assert_eq!(
+    ///     a.write_report(2),
+    ///     ChangeReport {
+    ///         previous: 1,
+    ///         changed: true,
+    ///         edges_dirtied: 2,
+    ///         memos_invalidated: vec![b.node().index(), c.node().index()],
+    ///     }
+    /// );
+    /// assert!(!a.write_report(2).changed);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated, if the [`Var`] is
+    /// [frozen](RawVar::freeze), or if `new` is rejected by a
+    /// [validator](RawVar::add_validator); see [`try_write_report`](RawVar::try_write_report).
+    pub fn write_report(&self, new: T) -> ChangeReport<T> {
+        self.try_write_report(new)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`write_report`](RawVar::write_report), but returns an error instead of panicking, as
+    /// [`try_write`](RawVar::try_write) does.
+    pub fn try_write_report(&self, new: T) -> Result<ChangeReport<T>, DcgError> {
+        self.node.check_mutable()?;
+        self.validate(&new)?;
+        Ok(self.write_unvalidated(new, ALL_CHANGES))
+    }
+
+    /// Writes `new` as a change of the given [class](RawVar::write_classed), without consulting
+    /// the validators or checking the [`Var`] is mutable.
+    fn write_unvalidated(&self, new: T, class: u8) -> ChangeReport<T> {
+        if *self.value.borrow() == new {
+            ChangeReport {
+                previous: new,
+                changed: false,
+                edges_dirtied: 0,
+                memos_invalidated: Vec::new(),
+            }
+        } else {
+            let before = self.node.before_edit();
+            self.replace_value(new, before, class)
+        }
+    }
+
+    /// Replaces the value with `new`, which differs from it, given its value from
+    /// [`Node::before_edit`], dirtying the node's dependents and then running callbacks.
+    ///
+    /// Every clone of a value is made before the graph is touched, and every callback runs after
+    /// the write is complete, so a panicking [`Clone`] leaves the [`Dcg`] as it was, and a
+    /// panicking callback leaves the write fully applied.
+    fn replace_value(&self, new: T, before: Option<Box<dyn Any>>, class: u8) -> ChangeReport<T> {
+        let mut swap = Swap::new(self, new, before);
+        swap.commit();
+        let old = swap
+            .old
+            .take()
+            .expect("an uncommitted swap holds its old value");
+        let dirtied = self.node.mark_dependents_classed(class);
+        let (edges_dirtied, memos_invalidated) = self.node.summarize_dirtied(&dirtied);
+
+        self.node
+            .inner
+            .emit(Event::VarWritten { idx: self.node.idx });
+        self.node.announce_dirtied(&dirtied);
+        self.node.debug_check();
+        self.notify(&old);
+        self.node.refresh_eager();
+        ChangeReport {
+            previous: old,
+            changed: true,
+            edges_dirtied,
+            memos_invalidated,
+        }
+    }
+
+    /// Modifies the value in the [`Var`], returning the value before modification and dirtying the node and
+    /// its transitive dependents if the new value differs from the old value.
+    ///
+    /// `f` is given a copy of the value, and the [`Var`] is written with the value it returns.
+    /// Until then the [`Var`] is untouched, so if `f` panics, nothing changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    ///
+    /// // Ensure `a` is clean
+    /// a.read();
+    ///
+    /// // `a` remains clean due to modify producing same value
+    /// assert_eq!(a.modify(|x| *x), 1);
+    /// assert!(a.is_clean());
+    /// assert_eq!(a.read(), 1);
+    ///
+    /// // `a` dirtied due to modify producing different value
+    /// assert_eq!(a.modify(|x| *x + 1), 1);
+    /// assert!(a.is_dirty());
+    /// assert_eq!(a.read(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated, if the [`Var`] is
+    /// [frozen](RawVar::freeze), or if the new value is rejected by a
+    /// [validator](RawVar::add_validator); see [`try_modify`](RawVar::try_modify).
+    pub fn modify<F>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut T) -> T,
+    {
+        self.try_modify(f).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`modify`](RawVar::modify), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) instead of modifying if
+    /// called while a node is being evaluated, [`Frozen`](DcgError::Frozen) if the [`Var`] is
+    /// frozen, or [`ValidationFailed`](DcgError::ValidationFailed) if the new value is rejected by
+    /// a [validator](RawVar::add_validator).
+    pub fn try_modify<F>(&self, f: F) -> Result<T, DcgError>
+    where
+        F: FnOnce(&mut T) -> T,
+    {
+        self.node.check_mutable()?;
+        let mut value = self.value.borrow().clone();
+        let new = f(&mut value);
+        self.validate(&new)?;
+        if new == *self.value.borrow() {
+            return Ok(new);
+        }
+        let before = self.node.before_edit();
+        Ok(self.replace_value(new, before, ALL_CHANGES).previous)
+    }
+}
+
+/// Naively re-computing [`Dcg`] node.
+pub struct RawThunk<T> {
+    /// Replaced by a constant when the node is [retired](Dcg::retire).
+    f: RefCell<Box<dyn Fn() -> T>>,
+    node: Node,
+    history: RefCell<History<T>>,
+    cost: RefCell<Option<Cost<T>>>,
+    hooks: RefCell<Vec<Hook<T>>>,
+    interceptors: RefCell<Vec<(InterceptorId, Interceptor<T>)>>,
+}
+
+type Cost<T> = Rc<dyn Fn(&T) -> u64>;
+
+/// Identifies a transformation registered with [`intercept`](RawThunk::intercept).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InterceptorId(usize);
+
+type Interceptor<T> = Rc<dyn Fn(T) -> T>;
+
+impl<T> RawThunk<T> {
+    fn new<P, F>(dcg: &Dcg, kind: NodeKind, params: &P, f: F) -> Self
+    where
+        P: Incremental,
+        F: Fn() -> T + 'static,
+    {
+        for node in params.nodes() {
+            node.assert_current();
+        }
+        let node = Node::new(dcg, kind);
+        node.add_dependencies(params);
+        Self::with_node(node, f)
+    }
+
+    /// Creates a [`Thunk`] computing its value with `f` for the already added `node`.
+    fn with_node<F>(node: Node, f: F) -> Self
+    where
+        F: Fn() -> T + 'static,
+    {
+        Self {
+            f: RefCell::new(Box::new(f)),
+            node,
+            history: RefCell::default(),
+            cost: RefCell::default(),
+            hooks: RefCell::default(),
+            interceptors: RefCell::default(),
+        }
+    }
+
+    /// Returns the [`Thunk`]'s [`Node`].
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    /// Measures each value computed by the [`Thunk`] with `cost`, to be reported by
+    /// [`Dcg::costs`]. Replaces any previous cost function.
+    pub fn set_cost<F>(&self, cost: F)
+    where
+        F: Fn(&T) -> u64 + 'static,
+    {
+        self.cost.replace(Some(Rc::new(cost)));
+    }
+
+    /// Registers `hook` to be called with every value the [`Thunk`] computes, which it does on
+    /// every read.
+    ///
+    /// Hooks run with no borrows of the [`Dcg`] held.
+    pub fn on_recompute<F>(&self, hook: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.hooks.borrow_mut().push(Rc::new(RefCell::new(hook)));
+    }
+
+    /// Registers `f` to transform every value the [`Thunk`] computes before it is returned,
+    /// returning an id to [remove](RawThunk::remove_interceptor) it with.
+    ///
+    /// Interceptors run in registration order, each given the previous one's result, as part of
+    /// the evaluation: nodes they read become dependencies, and they must not write to the
+    /// [`Dcg`]. Everything downstream, including [`Memo`] and [`Buffer`] caches, history, costs
+    /// and [`on_recompute`](RawThunk::on_recompute) hooks, only sees the transformed value.
+    /// Whether a node's latest value was transformed is reported by [`Dcg::is_intercepted`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let price = dcg.var(10.0f64);
+    /// let taxed = buffer!(dcg, price => price * 1.175);
+    /// let round = taxed.intercept(|value| (value * 100.0).round() / 100.0);
+    ///
+    /// assert_eq!(taxed.read(), 11.75);
+    /// price.write(9.99);
+    /// assert_eq!(taxed.read(), 11.74);
+    ///
+    /// taxed.remove_interceptor(round);
+    /// price.write(10.01);
+    /// assert!(taxed.read() > 11.76);
+    /// ```
+    pub fn intercept<F>(&self, f: F) -> InterceptorId
+    where
+        F: Fn(T) -> T + 'static,
+    {
+        let id = InterceptorId(self.node.inner.next_id());
+        self.interceptors.borrow_mut().push((id, Rc::new(f)));
+        id
+    }
+
+    /// Removes the interceptor registered as `id`, returning `true` if it was registered with
+    /// this node.
+    ///
+    /// Values already computed keep their transformation; the next one computed is returned
+    /// untransformed.
+    pub fn remove_interceptor(&self, id: InterceptorId) -> bool {
+        let mut interceptors = self.interceptors.borrow_mut();
+        let before = interceptors.len();
+        interceptors.retain(|(interceptor, _)| *interceptor != id);
+        interceptors.len() != before
+    }
+
+    /// Computes a value, passing it through the interceptors.
+    fn compute(&self) -> T {
+        let value = (self.f.borrow())();
+        let interceptors: Vec<_> = self
+            .interceptors
+            .borrow()
+            .iter()
+            .map(|(_, interceptor)| interceptor.clone())
+            .collect();
+        let mut intercepted = self.node.inner.intercepted.borrow_mut();
+        if interceptors.is_empty() {
+            if !intercepted.is_empty() {
+                intercepted.remove(&self.node.idx);
+            }
+            return value;
+        }
+        intercepted.insert(self.node.idx);
+        drop(intercepted);
+        interceptors
+            .into_iter()
+            .fold(value, |value, interceptor| interceptor(value))
+    }
+}
+
+impl<T: Clone> RawThunk<T> {
+    /// Starts recording the last `capacity` values computed by the [`Thunk`], discarding the
+    /// oldest recorded values beyond it.
+    ///
+    /// Recording is disabled by default, and disabled again by a `capacity` of 0.
+    pub fn record_history(&self, capacity: usize) {
+        self.history.borrow_mut().enable(capacity);
+    }
+
+    /// Returns the recorded values, oldest first. See
+    /// [`record_history`](RawThunk::record_history).
+    pub fn history(&self) -> Vec<T> {
+        self.history.borrow().values()
+    }
+}
+
+/// [`RawThunk`] that caches all its previous values.
+pub struct RawMemo<A, T>
+where
+    A: Eq + Hash,
+{
+    thunk: RawThunk<T>,
+    params: Box<dyn Incremental<Output = A>>,
+    /// Each cached value, with when it was last used.
+    cache: RefCell<HashMap<A, (T, u64)>>,
+    /// Set by `remember`.
+    capacity: Cell<Option<usize>>,
+    /// Incremented whenever a cached value is used.
+    uses: Cell<u64>,
+    hooks: RefCell<Vec<Hook<T>>>,
+    /// Set by `compare_values`.
+    compare: Cell<Option<Compare<T>>>,
+    /// The last value produced, kept while comparing values.
+    last: RefCell<Option<T>>,
+}
+
+impl<A, T> RawMemo<A, T>
+where
+    A: Eq + Hash,
+{
+    /// Returns the [`Memo`]'s [`Node`].
+    pub fn node(&self) -> &Node {
+        &self.thunk.node
+    }
+
+    /// Returns the value cached for `args` without recomputing it, even if it is out of date, or
+    /// [`None`] if none is cached. Peeking doesn't count as using the cache.
+    pub fn cached(&self, args: &A) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.cache
+            .borrow()
+            .get(args)
+            .map(|(value, _)| value.clone())
+    }
+
+    /// Starts recording the last `capacity` values computed by the [`Memo`] on cache misses,
+    /// discarding the oldest recorded values beyond it.
+    ///
+    /// Recording is disabled by default, and disabled again by a `capacity` of 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental, memo};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let m = memo!(dcg, a => a * 10);
+    /// m.record_history(8);
+    ///
+    /// m.read();
+    /// a.write(2);
+    /// m.read();
+    /// a.write(1);
+    /// m.read(); // cache hit
+    /// assert_eq!(m.history(), vec![10, 20]);
+    /// ```
+    pub fn record_history(&self, capacity: usize)
+    where
+        T: Clone,
+    {
+        self.thunk.record_history(capacity);
+    }
+
+    /// Returns the recorded values, oldest first. See
+    /// [`record_history`](RawMemo::record_history).
+    pub fn history(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.thunk.history()
+    }
+
+    /// Measures each value computed by the [`Memo`] on cache misses with `cost`, to be reported
+    /// by [`Dcg::costs`]. Replaces any previous cost function.
+    pub fn set_cost<F>(&self, cost: F)
+    where
+        F: Fn(&T) -> u64 + 'static,
+    {
+        self.thunk.set_cost(cost);
+    }
+
+    /// Registers `f` to transform every value the [`Memo`] computes on cache misses before it is
+    /// cached, as [`RawThunk::intercept`] does. Cache hits return the transformed value as
+    /// cached, without transforming it again.
+    pub fn intercept<F>(&self, f: F) -> InterceptorId
+    where
+        F: Fn(T) -> T + 'static,
+    {
+        self.thunk.intercept(f)
+    }
+
+    /// Removes the interceptor registered as `id`, as [`RawThunk::remove_interceptor`] does.
+    /// Values already cached keep their transformation.
+    pub fn remove_interceptor(&self, id: InterceptorId) -> bool {
+        self.thunk.remove_interceptor(id)
+    }
+
+    /// Keeps only the values of the `k` most recently used argument configurations in the
+    /// [`Memo`]'s cache, discarding the least recently used beyond it.
+    ///
+    /// By default every configuration is kept. Either way, when the arguments revert to a
+    /// remembered configuration, e.g. a [`Var`] set to `x`, then `y`, then back to `x`, the
+    /// [`Memo`] restores the cached value rather than executing its closure. With
+    /// [`compare_values`](RawMemo::compare_values), its [`version`](Dcg::version) only changes if
+    /// the restored value differs from the last one read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental, memo};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let m = memo!(dcg, a => a * 10);
+    /// m.remember(2);
+    /// let probe = dcg.probe(&m);
+    ///
+    /// for value in [1, 2, 1, 3, 2] {
+    ///     a.write(value);
+    ///     m.read();
+    /// }
+    /// assert_eq!(probe.values(), vec![10, 20, 30, 20]);
+    /// ```
+    pub fn remember(&self, k: usize) {
+        self.capacity.set(Some(k));
+        self.evict();
+    }
+
+    /// Discards the least recently used cached values beyond the [`Memo`]'s capacity.
+    fn evict(&self) {
+        let Some(capacity) = self.capacity.get() else {
+            return;
+        };
+        let mut cache = self.cache.borrow_mut();
+        while cache.len() > capacity {
+            let oldest = cache.values().map(|(_, used)| *used).min();
+            cache.retain(|_, (_, used)| Some(*used) != oldest);
+        }
+    }
+
+    /// Registers `hook` to be called with the freshly computed value whenever the [`Memo`] misses
+    /// its cache and executes its closure.
+    ///
+    /// Hooks are not called on cache hits. They run after the value has been cached, with no
+    /// borrows of the [`Dcg`] held.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental, memo};
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let m = memo!(dcg, a => a * 10);
+    /// let computed = Rc::new(RefCell::new(Vec::new()));
+    /// let computed_clone = computed.clone();
+    /// m.on_recompute(move |value| computed_clone.borrow_mut().push(*value));
+    ///
+    /// m.read();
+    /// a.write(2);
+    /// m.read();
+    /// a.write(1);
+    /// m.read(); // cache hit
+    /// assert_eq!(*computed.borrow(), vec![10, 20]);
+    /// ```
+    pub fn on_recompute<F>(&self, hook: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.hooks.borrow_mut().push(Rc::new(RefCell::new(hook)));
+    }
+
+    /// Only bumps the [`Memo`]'s [`version`](Dcg::version) when it produces a value different
+    /// from the last one, rather than whenever it is re-evaluated after being dirtied.
+    ///
+    /// The [`Memo`] keeps a copy of the last value it produced to compare against.
+    pub fn compare_values(&self)
+    where
+        T: PartialEq,
+    {
+        self.compare.set(Some(T::eq));
+    }
+}
+
+impl<A, T> RawMemo<A, T>
+where
+    A: Eq + Hash + 'static,
+    T: 'static,
+{
+    /// Includes the number of values cached by the [`Memo`] in its label in exports such as
+    /// [`Dcg::to_dot`].
+    pub fn show_value(self: &Rc<Self>) {
+        let memo = Rc::downgrade(self);
+        self.thunk
+            .node
+            .inner
+            .show_value(self.thunk.node.idx, move || {
+                memo.upgrade()
+                    .map(|memo| format!("{} cached", memo.cache.borrow().len()))
+            });
+    }
+}
+
+/// [`RawThunk`] that buffers its previous value.
+pub struct RawBuffer<T> {
+    thunk: RawThunk<T>,
+    buffered: RefCell<Option<T>>,
+    hooks: RefCell<Vec<Hook<T>>>,
+    watchers: RefCell<Vec<Watcher<T>>>,
+    /// Set by `compare_values`.
+    compare: Cell<Option<Compare<T>>>,
+}
+
+impl<T> RawBuffer<T> {
+    /// Creates a [`Buffer`] buffering the values computed by `thunk`.
+    fn with_thunk(thunk: RawThunk<T>) -> Buffer<T>
+    where
+        T: Clone + 'static,
+    {
+        let buffer = Rc::new(RawBuffer {
+            thunk,
+            buffered: RefCell::default(),
+            hooks: RefCell::default(),
+            watchers: RefCell::default(),
+            compare: Cell::default(),
+        });
+        buffer
+            .thunk
+            .node
+            .inner
+            .refreshers
+            .borrow_mut()
+            .insert(buffer.node().idx, Rc::new(Rc::downgrade(&buffer)));
+        buffer
+    }
+
+    /// Returns the [`Buffer`]'s [`Node`].
+    pub fn node(&self) -> &Node {
+        &self.thunk.node
+    }
+
+    /// Returns the [`Buffer`]'s buffered value without recomputing it, even if it is out of date.
+    pub fn last_computed(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.buffered.borrow().clone()
+    }
+
+    /// Starts recording the last `capacity` values computed by the [`Buffer`], discarding the
+    /// oldest recorded values beyond it.
+    ///
+    /// Recording is disabled by default, and disabled again by a `capacity` of 0.
+    pub fn record_history(&self, capacity: usize)
+    where
+        T: Clone,
+    {
+        self.thunk.record_history(capacity);
+    }
+
+    /// Returns the recorded values, oldest first. See
+    /// [`record_history`](RawBuffer::record_history).
+    pub fn history(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.thunk.history()
+    }
+
+    /// Measures each value computed by the [`Buffer`] with `cost`, to be reported by
+    /// [`Dcg::costs`]. Replaces any previous cost function.
+    pub fn set_cost<F>(&self, cost: F)
+    where
+        F: Fn(&T) -> u64 + 'static,
+    {
+        self.thunk.set_cost(cost);
+    }
+
+    /// Registers `f` to transform every value the [`Buffer`] re-computes before it is buffered,
+    /// as [`RawThunk::intercept`] does. Reusing the buffered value doesn't transform it again.
+    pub fn intercept<F>(&self, f: F) -> InterceptorId
+    where
+        F: Fn(T) -> T + 'static,
+    {
+        self.thunk.intercept(f)
+    }
+
+    /// Removes the interceptor registered as `id`, as [`RawThunk::remove_interceptor`] does.
+    /// The buffered value keeps its transformation until the [`Buffer`] next re-computes.
+    pub fn remove_interceptor(&self, id: InterceptorId) -> bool {
+        self.thunk.remove_interceptor(id)
+    }
+
+    /// Registers `hook` to be called with the freshly computed value whenever the [`Buffer`]
+    /// re-computes.
+    ///
+    /// Hooks are not called when the buffered value is reused. They run after the value has been
+    /// buffered, with no borrows of the [`Dcg`] held, but before the [`Buffer`] is cleaned: they
+    /// must not read the [`Buffer`] itself.
+    pub fn on_recompute<F>(&self, hook: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.hooks.borrow_mut().push(Rc::new(RefCell::new(hook)));
+    }
+
+    /// Only bumps the [`Buffer`]'s [`version`](Dcg::version) when it re-computes a value
+    /// different from the one it buffered before, rather than whenever it re-computes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a % 2);
+    /// let c = buffer!(dcg, a => a % 2);
+    /// c.compare_values();
+    ///
+    /// for i in [1, 3, 5] {
+    ///     a.write(i);
+    ///     b.read();
+    ///     c.read();
+    /// }
+    /// assert_eq!(dcg.version(b.node()), 3);
+    /// assert_eq!(dcg.version(c.node()), 1);
+    /// ```
+    pub fn compare_values(&self)
+    where
+        T: PartialEq,
+    {
+        self.compare.set(Some(T::eq));
+    }
+}
+
+impl<T: PartialEq + Clone + 'static> RawBuffer<T> {
+    /// Returns a [`Receiver`](mpsc::Receiver) sent the [`Buffer`]'s value whenever it
+    /// re-computes a value different from the one it buffered before.
+    ///
+    /// Values are sent as the [`Buffer`] is read, not when its dependencies change. The
+    /// [`Buffer`] stops sending once the receiver is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a / 10);
+    /// let rx = b.watch();
+    ///
+    /// b.read();
+    /// a.write(2);
+    /// b.read();
+    /// a.write(20);
+    /// b.read();
+    /// assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![0, 2]);
+    /// ```
+    pub fn watch(&self) -> mpsc::Receiver<T> {
+        let (tx, rx) = mpsc::channel();
+        let mut last = self.buffered.borrow().clone();
+        self.watchers.borrow_mut().push(Box::new(move |value: &T| {
+            if last.as_ref() == Some(value) {
+                return true;
+            }
+            last = Some(value.clone());
+            tx.send(value.clone()).is_ok()
+        }));
+        rx
+    }
+}
+
+impl<T: fmt::Debug + 'static> RawBuffer<T> {
+    /// Includes the [`Buffer`]'s buffered value, formatted with [`Debug`](fmt::Debug), in its label
+    /// in exports such as [`Dcg::to_dot`].
+    pub fn show_value(self: &Rc<Self>) {
+        let buffer = Rc::downgrade(self);
+        self.thunk
+            .node
+            .inner
+            .show_value(self.thunk.node.idx, move || {
+                buffer
+                    .upgrade()
+                    .map(|buffer| match &*buffer.buffered.borrow() {
+                        Some(value) => format!("{:?}", value),
+                        None => "empty".to_owned(),
+                    })
+            });
+    }
+}
+
+impl<T: Clone> Incremental for RawVar<T> {
+    type Output = T;
+
+    fn latest(&self) -> Self::Output {
+        if let Err(err) = self.node.check_fulfilled() {
+            panic!("{}", err);
+        }
+        self.value.borrow().clone()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.node.is_dirty()
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        vec![&self.node]
+    }
+}
+
+impl<T: 'static> Incremental for RawThunk<T> {
+    type Output = T;
+
+    fn latest(&self) -> Self::Output {
+        self.node.spend();
+        #[cfg(any(feature = "tracing", feature = "timings"))]
+        let start = std::time::Instant::now();
+        let value = self.node.evaluate(|| self.compute());
+        #[cfg(any(feature = "tracing", feature = "timings"))]
+        let elapsed = start.elapsed();
+        self.history.borrow_mut().record(&value);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(node = self.node.idx.index(), ?elapsed, "recomputed");
+        #[cfg(feature = "timings")]
+        {
+            let mut timings = self.node.inner.timings.borrow_mut();
+            let timing = timings.entry(self.node.idx).or_default();
+            timing.0 += elapsed;
+            timing.1 += 1;
+        }
+        let cost = self.cost.borrow().clone();
+        if let Some(cost) = cost {
+            let cost = cost(&value);
+            let mut costs = self.node.inner.costs.borrow_mut();
+            let total = costs.entry(self.node.idx).or_default();
+            total.0 += cost;
+            total.1 += 1;
+        }
+        self.node
+            .inner
+            .emit(Event::Recomputed { idx: self.node.idx });
+        let hooks = self.node.inner.recompute_hooks.borrow().clone();
+        for hook in hooks {
+            (hook.borrow_mut())(self.node.idx, &value);
+        }
+        call_hooks(&self.hooks, &value);
+        value
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.node.is_dirty()
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        vec![&self.node]
+    }
+}
+
+impl<A, T> Incremental for RawMemo<A, T>
+where
+    A: Eq + Hash,
+    T: Clone + 'static,
+{
+    type Output = T;
+
+    fn latest(&self) -> Self::Output {
+        let dirty = self.thunk.node.is_dirty();
+        let value = self.thunk.node.evaluate(|| {
+            let args = self.params.latest();
+            let used = self.uses.get() + 1;
+            self.uses.set(used);
+            if let Some((result, last_used)) = self.cache.borrow_mut().get_mut(&args) {
+                *last_used = used;
+                self.thunk.node.record_cache_read(true);
+                return result.clone();
+            }
+            let missed = self.thunk.latest();
+            self.thunk.node.record_cache_read(false);
+            self.cache.borrow_mut().insert(args, (missed.clone(), used));
+            self.evict();
+            self.thunk.node.mark_cached();
+            call_hooks(&self.hooks, &missed);
+            missed
+        });
+        if dirty {
+            match self.compare.get() {
+                Some(eq) => {
+                    let mut last = self.last.borrow_mut();
+                    if !last.as_ref().is_some_and(|last| eq(last, &value)) {
+                        self.thunk.node.bump_version();
+                        *last = Some(value.clone());
+                    }
+                }
+                None => self.thunk.node.bump_version(),
+            }
+        }
+        value
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.thunk.is_dirty()
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        self.thunk.nodes()
+    }
+}
+
+impl<T: Clone + 'static> Incremental for RawBuffer<T> {
+    type Output = T;
+
+    fn latest(&self) -> Self::Output {
+        if let Err(err) = self.thunk.node.check_finalized() {
+            panic!("{}", err);
+        }
+        self.thunk.node.expire_if_stale();
+        self.thunk.node.verify_dependencies();
+        if self.is_dirty() || self.buffered.borrow().is_none() {
+            let value = self.thunk.latest();
+            self.thunk.node.restart_ttl();
+            self.thunk.node.record_cache_read(false);
+            let old = self.buffered.replace(Some(value.clone()));
+            let unchanged = match (self.compare.get(), &old) {
+                (Some(eq), Some(old)) => eq(old, &value),
+                _ => false,
+            };
+            if !unchanged {
+                self.thunk.node.bump_version();
+            }
+            self.thunk.node.mark_cached();
+            call_hooks(&self.hooks, &value);
+            send_to_watchers(&self.watchers, &value);
+            return value;
+        }
+        self.thunk.node.record_cache_read(true);
+        self.buffered.borrow().clone().unwrap()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.thunk.is_dirty()
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        vec![&self.thunk.node]
+    }
+}
+
+/// Ergonomic [`Thunk`] creation.
+///
+/// The first argument is the [`Dcg`] in which the [`Thunk`] will be created.
+///
+/// The second argument specifies how the [`Thunk`] will generate values.
+/// It can be:
+///
+/// - An [`Incremental`]'s `ident`.
+/// - Of the form `params => expr` where
 ///     - `params` is either
 ///         - `(reads; unreads)` where `read` and `unread` are `,`-separated lists of [`Incremental`] `ident`s.
 ///         - `read` - an [`Incremental`]'s `ident`.
@@ -618,12 +4058,142 @@ impl<T: Clone> Incremental for RawBuffer<T> {
 /// - An `expr` (ideally not referencing an [`Incremental`]).
 ///
 /// ```
-/// use cachegrab::{Dcg, incremental::Incremental, thunk};
+/// use cachegrab::{Dcg, incremental::Incremental, thunk};
+///
+/// let dcg = Dcg::default();
+/// let numerator = dcg.var(1);
+/// let denominator = dcg.var(1);
+/// let safe_div = thunk!(dcg, (denominator; numerator) => {
+///     if denominator == 0 {
+///         None
+///     } else {
+///         Some(numerator.read() / denominator)
+///     }
+/// });
+///
+/// assert_eq!(safe_div.read(), Some(1));
+/// denominator.write(0);
+/// // numerator doesn't have to be- and isn't- executed!
+/// assert_eq!(safe_div.read(), None);
+/// ```
+#[macro_export]
+macro_rules! thunk {
+    ($dcg:ident, ($($read:ident),*; $($unread:ident),*) => $f:expr) => {{
+        $crate::paste! {
+            $(let [<$read _clone>] = std::rc::Rc::clone(&$read);)*
+            $(let $unread = std::rc::Rc::clone(&$unread);)*
+            $dcg.thunk(&($(std::rc::Rc::clone(&$read),)* $(std::rc::Rc::clone(&$unread)),*), move || {
+                $(let $read = $crate::incremental::Incremental::read(&*[<$read _clone>]);)*
+                $f
+            })
+        }
+    }};
+    ($dcg:ident, $read:ident) => {
+        thunk!($dcg, ($read) => $read)
+    };
+    ($dcg:ident, $read:ident => $f:expr) => {
+        thunk!($dcg, ($read) => $f)
+    };
+    ($dcg:ident, ($($read:ident),*) => $f:expr) => {
+        thunk!($dcg, ($($read),*;) => $f)
+    };
+    ($dcg:ident, $f:expr) => {
+        thunk!($dcg, (;) => $f)
+    };
+}
+
+/// Ergonomic [`Memo`] creation.
+///
+/// The first argument is the [`Dcg`] in which the [`Memo`] will be created.
+///
+/// The second argument specifies how the [`Memo`] will generate values.
+/// It can be:
+///
+/// - An [`Incremental`]'s `ident`; the [`Incremental`] is simply (`read`)[`Incremental::read`].
+/// - Of the form `params => expr` where
+///     - `params` can be
+///         - `(reads; unreads)` where `read` and `unread` are `,`-separated lists of [`Incremental`] `ident`s.
+///         - `read` - an [`Incremental`]'s `ident`.
+///         - `(reads)` where `reads` is a `,`-separated list of [`Incremental`] `ident`s.
+///     - `expr` is an expression that treats:
+///         - `read` params as if they were (`read`)[`Incremental::read`].
+///         - `unread` params as normal.
+/// - An `expr` (ideally not referencing an [`Incremental`]).
+///
+/// # Examples
+///
+/// ```
+/// use cachegrab::{Dcg, incremental::Incremental, memo};
+///
+/// let dcg = Dcg::default();
+/// let numerator = dcg.var(1);
+/// let denominator = dcg.var(1);
+/// let safe_div = memo!(dcg, (denominator; numerator) => {
+///     if denominator == 0 {
+///         None
+///     } else {
+///         Some(numerator.read() / denominator)
+///     }
+/// });
+///
+/// assert_eq!(safe_div.read(), Some(1));
+/// denominator.write(0);
+/// // numerator doesn't have to be- and isn't- executed!
+/// assert_eq!(safe_div.read(), None);
+/// ```
+#[macro_export]
+macro_rules! memo {
+    ($dcg:ident, ($($read:ident),*; $($unread:ident),*) => $f:expr) => {{
+        $crate::paste! {
+            $(let [<$read _clone>] = std::rc::Rc::clone(&$read);)*
+            $(let $unread = std::rc::Rc::clone(&$unread);)*
+            $dcg.memo(($(std::rc::Rc::clone(&$read),)* $(std::rc::Rc::clone(&$unread)),*), move || {
+                $(let $read = $crate::incremental::Incremental::read(&*[<$read _clone>]);)*
+                $f
+            })
+        }
+    }};
+    ($dcg:ident, $read:ident) => {
+        memo!($dcg, ($read) => $read)
+    };
+    ($dcg:ident, $read:ident => $f:expr) => {
+        memo!($dcg, ($read) => $f)
+    };
+    ($dcg:ident, ($($read:ident),*) => $f:expr) => {
+        memo!($dcg, ($($read),*;) => $f)
+    };
+    ($dcg:ident, $f:expr) => {
+        memo!($dcg, (;) => $f)
+    };
+}
+
+/// Ergonomic [`Buffer`] creation.
+///
+/// The first argument is the [`Dcg`] in which the [`Buffer`] will be created.
+///
+/// The second argument specifies how the [`Buffer`] will generate values.
+/// It can be:
+///
+/// - An [`Incremental`]'s `ident`; the [`Incremental`] is simply (`read`)[`Incremental::read`].
+/// - Of the form `params => expr` where
+///     - `params` can be
+///         - `(reads; unreads)` where `read` and `unread` are `,`-separated lists of [`Incremental`] `ident`s.
+///         - `read` - an [`Incremental`]'s `ident`.
+///         - `(reads)` where `reads` is a `,`-separated list of [`Incremental`] `ident`s.
+///     - `expr` is an expression that treats:
+///         - `read` params as if they were (`read`)[`Incremental::read`].
+///         - `unread` params as normal.
+/// - An `expr` (ideally not referencing an [`Incremental`]).
+///
+/// # Examples
+///
+/// ```
+/// use cachegrab::{Dcg, incremental::Incremental, buffer};
 ///
 /// let dcg = Dcg::default();
 /// let numerator = dcg.var(1);
 /// let denominator = dcg.var(1);
-/// let safe_div = thunk!(dcg, (denominator; numerator) => {
+/// let safe_div = buffer!(dcg, (denominator; numerator) => {
 ///     if denominator == 0 {
 ///         None
 ///     } else {
@@ -637,395 +4207,5001 @@ impl<T: Clone> Incremental for RawBuffer<T> {
 /// assert_eq!(safe_div.read(), None);
 /// ```
 #[macro_export]
-macro_rules! thunk {
+macro_rules! buffer {
     ($dcg:ident, ($($read:ident),*; $($unread:ident),*) => $f:expr) => {{
         $crate::paste! {
             $(let [<$read _clone>] = std::rc::Rc::clone(&$read);)*
             $(let $unread = std::rc::Rc::clone(&$unread);)*
-            $dcg.thunk(&($(std::rc::Rc::clone(&$read),)* $(std::rc::Rc::clone(&$unread)),*), move || {
+            $dcg.buffer(&($(std::rc::Rc::clone(&$read),)* $(std::rc::Rc::clone(&$unread)),*), move || {
                 $(let $read = $crate::incremental::Incremental::read(&*[<$read _clone>]);)*
                 $f
             })
         }
-    }};
-    ($dcg:ident, $read:ident) => {
-        thunk!($dcg, ($read) => $read)
-    };
-    ($dcg:ident, $read:ident => $f:expr) => {
-        thunk!($dcg, ($read) => $f)
-    };
-    ($dcg:ident, ($($read:ident),*) => $f:expr) => {
-        thunk!($dcg, ($($read),*;) => $f)
-    };
-    ($dcg:ident, $f:expr) => {
-        thunk!($dcg, (;) => $f)
-    };
-}
+    }};
+    ($dcg:ident, $read:ident) => {
+        buffer!($dcg, ($read) => $read)
+    };
+    ($dcg:ident, $read:ident => $f:expr) => {
+        buffer!($dcg, ($read) => $f)
+    };
+    ($dcg:ident, ($($read:ident),*) => $f:expr) => {
+        buffer!($dcg, ($($read),*;) => $f)
+    };
+    ($dcg:ident, $f:expr) => {
+        buffer!($dcg, (;) => $f)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell,
+        panic::{self, AssertUnwindSafe},
+    };
+
+    use cell::Cell;
+
+    use super::*;
+    use crate::dot::{DotOptions, RankDir};
+
+    /// Defines a module of tests running `$body` under each [`VerificationStrategy`], bound to
+    /// `$strategy`.
+    macro_rules! both_strategies {
+        ($(#[$attr:meta])* fn $name:ident($strategy:ident) $body:block) => {
+            $(#[$attr])*
+            mod $name {
+                use super::*;
+
+                #[test]
+                fn dirty_flags() {
+                    let $strategy = VerificationStrategy::DirtyFlags;
+                    $body
+                }
+
+                #[test]
+                fn versions() {
+                    let $strategy = VerificationStrategy::Versions;
+                    $body
+                }
+            }
+        };
+    }
+
+    #[test]
+    fn create_var() {
+        let dcg = Dcg::default();
+
+        let a = dcg.var(1);
+
+        assert_eq!(dcg.inner.graph.borrow().node_count(), 1);
+        assert!(a.is_dirty());
+    }
+
+    #[test]
+    fn create_thunk() {
+        let dcg = Dcg::default();
+
+        let t = thunk!(dcg, 1);
+
+        assert_eq!(dcg.inner.graph.borrow().node_count(), 1);
+        assert!(t.is_dirty());
+    }
+
+    #[test]
+    fn create_memo() {
+        let dcg = Dcg::default();
+
+        let m = memo!(dcg, 1);
+
+        assert_eq!(dcg.inner.graph.borrow().node_count(), 1);
+        assert!(m.is_dirty());
+    }
+
+    #[test]
+    fn create_buffer() {
+        let dcg = Dcg::default();
+
+        let b = buffer!(dcg, 1);
+
+        assert_eq!(dcg.inner.graph.borrow().node_count(), 1);
+        assert!(b.is_dirty());
+    }
+
+    both_strategies! {
+        fn var_read(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+
+            assert_eq!(a.read(), 1);
+        }
+    }
+
+    both_strategies! {
+        fn thunk_read(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let t = thunk!(dcg, 1);
+
+            assert_eq!(t.read(), 1);
+        }
+    }
+
+    both_strategies! {
+        fn memo_read(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let m = memo!(dcg, 1);
+
+            assert_eq!(m.read(), 1);
+        }
+    }
+
+    both_strategies! {
+        fn buffer_read(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let b = buffer!(dcg, 1);
+
+            assert_eq!(b.read(), 1);
+        }
+    }
+
+    #[test]
+    fn var_write() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b1 = buffer!(dcg, a);
+        let b2 = buffer!(dcg, b1);
+        let b3 = buffer!(dcg, a);
+        let b4 = buffer!(dcg, b3);
+        b2.read();
+        b4.read();
+        // deliberately breaks invariants to test pruning
+        dcg.set_debug_checks(false);
+        dcg.inner.graph.borrow_mut()[b3.thunk.node.idx] = true;
+
+        //   b1 --> b2           (b1) --> (b2)
+        //  /               -->  /
+        // a --> (b3) --> b4   (a) --> (b3) --> b4
+        assert_eq!(a.write(2), 1);
+
+        assert!(a.is_dirty());
+        assert!(b1.is_dirty());
+        assert!(b2.is_dirty());
+        assert!(b3.is_dirty());
+        assert!(b4.is_clean());
+        assert_eq!(a.read(), 2);
+    }
+
+    #[test]
+    fn var_modify() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b1 = buffer!(dcg, a);
+        let b2 = buffer!(dcg, b1);
+        let b3 = buffer!(dcg, a);
+        let b4 = buffer!(dcg, b3);
+        b2.read();
+        b4.read();
+        // deliberately breaks invariants to test pruning
+        dcg.set_debug_checks(false);
+        dcg.inner.graph.borrow_mut()[b3.thunk.node.idx] = true;
+
+        //   b1 --> b2           (b1) --> (b2)
+        //  /               --> /
+        // a --> (b3) --> b4   (a) --> (b3) --> b4
+        assert_eq!(a.modify(|x| *x + 1), 1);
+
+        assert!(a.is_dirty());
+        assert!(b1.is_dirty());
+        assert!(b2.is_dirty());
+        assert!(b3.is_dirty());
+        assert!(b4.is_clean());
+        assert_eq!(a.read(), 2);
+    }
+
+    #[test]
+    fn thunk_read_cleans() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let t1 = thunk!(dcg, a);
+        let t2 = thunk!(dcg, b);
+        let t3 = thunk!(dcg, (t1, t2) => t1 + t2);
+        dcg.inner.graph.borrow_mut()[t1.node.idx] = false;
+
+        //        (a) --> t1            a --> t1
+        //                   \  -->             \
+        // (b) --> (t2) --> (t3)    b --> t2 --> t3
+        t3.read();
+
+        assert!(a.is_clean());
+        assert!(b.is_clean());
+        assert!(t1.is_clean());
+        assert!(t2.is_clean());
+        assert!(t3.is_clean());
+    }
+
+    #[test]
+    fn memo_read_cleans() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let m1 = memo!(dcg, a);
+        let m2 = memo!(dcg, b);
+        let m3 = memo!(dcg, (m1, m2) => m1 + m2);
+        dcg.inner.graph.borrow_mut()[m1.thunk.node.idx] = false;
+
+        //        (a) --> m1            a --> m1
+        //                   \  -->             \
+        // (b) --> (m2) --> (m3)    b --> m2 --> m3
+        m3.read();
+
+        assert!(a.is_clean());
+        assert!(b.is_clean());
+        assert!(m1.is_clean());
+        assert!(m2.is_clean());
+        assert!(m3.is_clean());
+    }
+
+    #[test]
+    fn buffer_read_cleans() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let b1 = buffer!(dcg, a);
+        let b2 = buffer!(dcg, b);
+        let b3 = buffer!(dcg, (b1, b2) => b1 + b2);
+        // we ensure b1 contains Some(value) to avoid unwrapping a None
+        b1.read();
+        a.write(2);
+        // deliberately breaks invariants to test cleaning
+        dcg.set_debug_checks(false);
+        dcg.inner.graph.borrow_mut()[b1.thunk.node.idx] = false;
+
+        //        (a) --> b1           (a) --> b1
+        //                   \  -->             \
+        // (b) --> (b2) --> (b3)   b --> b2 --> b3
+        b3.read();
+
+        assert!(a.is_dirty());
+        assert!(b.is_clean());
+        assert!(b1.is_clean());
+        assert!(b2.is_clean());
+        assert!(b3.is_clean());
+    }
+
+    both_strategies! {
+        fn conditional_execution(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let b = dcg.var(1);
+            let a_read = Rc::new(Cell::new(false));
+            let a_read_clone = a_read.clone();
+            let safe_div = buffer!(dcg, (b; a) => {
+                if b == 0 {
+                    None
+                } else {
+                    a_read_clone.set(true);
+                    Some(a.read() / b)
+                }
+            });
+
+            // lazy buffer created
+            assert!(!a_read.get());
+
+            a_read.set(false);
+
+            // computes and caches value
+            assert_eq!(safe_div.read(), Some(1));
+            assert!(a_read.get());
+
+            // affected by change
+            b.write(2);
+            assert_eq!(safe_div.read(), Some(0));
+            assert!(a_read.get());
+
+            a_read.set(false);
+
+            // not affected by change
+            b.write(0);
+            assert_eq!(safe_div.read(), None);
+            assert!(!a_read.get());
+        }
+    }
+
+    #[test]
+    fn read_with_budget_within_budget() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = buffer!(dcg, b => b + 1);
+
+        assert_eq!(dcg.read_with_budget(&c, 2), Ok(3));
+        // clean nodes cost nothing
+        assert_eq!(dcg.read_with_budget(&c, 0), Ok(3));
+    }
+
+    #[test]
+    fn read_with_budget_exceeded_on_chain() {
+        let dcg = Dcg::default();
+        let a = dcg.var(0);
+        let mut chain = vec![buffer!(dcg, a => a + 1)];
+        for _ in 1..10 {
+            let last = chain.last().unwrap().clone();
+            chain.push(buffer!(dcg, last => last + 1));
+        }
+        let end = chain.last().unwrap();
+
+        let exceeded = dcg.read_with_budget(end, 5).unwrap_err();
+
+        assert_eq!(exceeded.recomputed, 5);
+        assert_eq!(exceeded.stopped_at, chain[5].thunk.node.idx);
+        assert!(chain[..5].iter().all(|b| b.is_clean()));
+        assert!(chain[5..].iter().all(|b| b.is_dirty()));
+        assert!(dcg.inner.budget.get().is_none());
+
+        // the graph is resumable
+        assert_eq!(dcg.read_with_budget(end, 10), Ok(10));
+        assert!(chain.iter().all(|b| b.is_clean()));
+        a.write(1);
+        assert_eq!(end.read(), 11);
+    }
+
+    #[test]
+    fn read_with_budget_keeps_finished_results() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let x = buffer!(dcg, a => a * 10);
+        let probe = dcg.probe(&x);
+        let y1 = buffer!(dcg, a => a + 1);
+        let y2 = buffer!(dcg, y1 => y1 + 1);
+        let sum = buffer!(dcg, (x, y2) => x + y2);
+
+        // x, y1 and y2 run, then sum exceeds the budget
+        let exceeded = dcg.read_with_budget(&sum, 3).unwrap_err();
+
+        assert_eq!(exceeded.recomputed, 3);
+        assert_eq!(exceeded.stopped_at, sum.thunk.node.idx);
+        assert!(x.is_clean());
+        assert!(y1.is_clean());
+        assert!(y2.is_clean());
+        assert!(sum.is_dirty());
+        assert_eq!(sum.read(), 13);
+        assert_eq!(probe.recomputations(), 1);
+    }
+
+    #[test]
+    fn unused_dependencies_reports_unread() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        let a_clone = a.clone();
+        let t = dcg.thunk(&(a.clone(), b.clone()), move || a_clone.read());
+
+        // never evaluated
+        assert!(dcg.unused_dependencies(t.node()).is_empty());
+
+        t.read();
+
+        assert_eq!(dcg.unused_dependencies(t.node()), vec![b.node().index()]);
+        assert_eq!(dcg.audit(), vec![(b.node().index(), t.node().index())]);
+    }
+
+    #[test]
+    fn unused_dependencies_follow_latest_evaluation() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let safe_div = buffer!(dcg, (b; a) => {
+            if b == 0 {
+                None
+            } else {
+                Some(a.read() / b)
+            }
+        });
+
+        safe_div.read();
+        assert!(dcg.unused_dependencies(safe_div.node()).is_empty());
+
+        b.write(0);
+        safe_div.read();
+        assert_eq!(
+            dcg.unused_dependencies(safe_div.node()),
+            vec![a.node().index()]
+        );
+    }
+
+    #[test]
+    fn unused_dependencies_of_memo_include_params() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        let m = memo!(dcg, (a; b) => a);
+
+        m.read();
+
+        // `b` is read to build the cache key
+        assert!(dcg.unused_dependencies(m.node()).is_empty());
+        assert!(dcg.audit().is_empty());
+    }
+
+    #[test]
+    fn check_invariants_consistent() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let safe_div = buffer!(dcg, (b; a) => if b == 0 { None } else { Some(a.read() / b) });
+
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        safe_div.read();
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        b.write(0);
+        safe_div.read();
+        a.write(2);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn check_invariants_reports_clean_node_over_dirty_dependency() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b1 = buffer!(dcg, a);
+        let b2 = buffer!(dcg, b1);
+        b2.read();
+
+        dcg.inner.graph.borrow_mut()[b1.thunk.node.idx] = true;
+
+        assert_eq!(
+            dcg.check_invariants(),
+            Err(vec![format!(
+                "node {} is clean but its dependency {} is dirty",
+                b2.thunk.node.idx.index(),
+                b1.thunk.node.idx.index()
+            )])
+        );
+    }
+
+    #[test]
+    fn check_invariants_reports_cycles_and_unknown_nodes() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a);
+        b.read();
+
+        dcg.inner
+            .graph
+            .borrow_mut()
+            .add_edge(b.thunk.node.idx, a.node.idx, ());
+        dcg.inner
+            .last_reads
+            .borrow_mut()
+            .insert(NodeIndex::new(7), HashSet::new());
+
+        assert_eq!(
+            dcg.check_invariants(),
+            Err(vec![
+                format!(
+                    "node {} is part of a dependency cycle",
+                    b.thunk.node.idx.index()
+                ),
+                "reads recorded for unknown node 7".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn check_invariants_reports_clean_memo_without_cache() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let m = memo!(dcg, a => a * 10);
+        let b = buffer!(dcg, a);
+        let c = buffer!(dcg, a => a + 1);
+        m.read();
+        b.read();
+        c.read();
+
+        dcg.set_cache_capacity(2);
+        assert!(!m.node().is_cached() && m.is_clean());
+        assert_eq!(dcg.check_invariants(), Ok(()));
+
+        b.buffered.take();
+        dcg.inner.cached.borrow_mut().remove(&b.thunk.node.idx);
+        assert_eq!(
+            dcg.check_invariants(),
+            Err(vec![format!(
+                "node {} is clean but has no cached value",
+                b.thunk.node.idx.index()
+            )])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dcg invariants violated")]
+    fn write_checks_invariants() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b1 = buffer!(dcg, a);
+        let b2 = buffer!(dcg, b1);
+        b2.read();
+        dcg.inner.graph.borrow_mut()[b1.thunk.node.idx] = true;
+
+        a.write(2);
+    }
+
+    #[test]
+    fn set_debug_checks_disables_checks() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b1 = buffer!(dcg, a);
+        let b2 = buffer!(dcg, b1);
+        b2.read();
+        dcg.inner.graph.borrow_mut()[b1.thunk.node.idx] = true;
+
+        dcg.set_debug_checks(false);
+        a.write(2);
+        b2.read();
+    }
+
+    #[test]
+    fn write_during_thunk_evaluation() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let t = thunk!(dcg, (a; b) => b.try_write(a));
+
+        assert_eq!(
+            t.read(),
+            Err(DcgError::MutationDuringEvaluation {
+                evaluating: t.node.idx,
+                target: b.node.idx,
+            })
+        );
+        assert_eq!(b.read(), 1);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn modify_during_memo_evaluation() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let m = memo!(dcg, (a; b) => b.try_modify(|x| *x + a));
+
+        assert_eq!(
+            m.read(),
+            Err(DcgError::MutationDuringEvaluation {
+                evaluating: m.thunk.node.idx,
+                target: b.node.idx,
+            })
+        );
+        assert_eq!(b.read(), 1);
+    }
+
+    #[test]
+    fn mutation_error_names_innermost_evaluation() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let inner = thunk!(dcg, (; a) => a.try_write(2));
+        let outer = thunk!(dcg, inner);
+
+        assert_eq!(
+            outer.read(),
+            Err(DcgError::MutationDuringEvaluation {
+                evaluating: inner.node.idx,
+                target: a.node.idx,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot mutate node 0 while node 1 is being evaluated")]
+    fn write_during_evaluation_panics() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let t = thunk!(dcg, (; a) => a.write(2));
+
+        t.read();
+    }
+
+    both_strategies! {
+        fn no_dependencies(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let unit = dcg.thunk(&(), || 1);
+            let array = dcg.memo([] as [Var<i32>; 0], || 2);
+            let vec = dcg.buffer(&Vec::<Var<i32>>::new(), || 3);
+
+            assert_eq!(dcg.inner.graph.borrow().edge_count(), 0);
+            assert_eq!((unit.read(), array.read(), vec.read()), (1, 2, 3));
+        }
+    }
+
+    both_strategies! {
+        fn array_dependencies(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let c = dcg.var(3);
+            let pair = [a.clone(), b.clone()];
+            let triple = [a.clone(), b.clone(), c.clone()];
+            let pair_sum = dcg.buffer(&pair.clone(), move || {
+                pair.iter().map(|x| x.read()).sum::<i32>()
+            });
+            let triple_max = dcg.memo(triple.clone(), move || {
+                triple.iter().map(|x| x.read()).max()
+            });
+
+            assert_eq!(pair_sum.read(), 3);
+            assert_eq!(triple_max.read(), Some(3));
+            assert_eq!(dcg.inner.graph.borrow().edge_count(), 5);
+
+            c.write(0);
+            assert!(pair_sum.is_clean());
+            assert!(triple_max.is_dirty());
+            assert_eq!(triple_max.read(), Some(2));
+        }
+    }
+
+    both_strategies! {
+        fn vec_dependencies(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let vars = Rc::new((0..4).map(|x| dcg.var(x)).collect::<Vec<_>>());
+            let sum = buffer!(dcg, vars => vars.iter().sum::<i32>());
+
+            assert_eq!(sum.read(), 6);
+            vars[3].write(10);
+            assert!(sum.is_dirty());
+            assert_eq!(sum.read(), 13);
+        }
+    }
+
+    both_strategies! {
+        fn observers_called_on_change(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let first = Rc::new(RefCell::new(Vec::new()));
+            let second = Rc::new(RefCell::new(Vec::new()));
+            let first_clone = first.clone();
+            let second_clone = second.clone();
+            let first_id = a.observe(move |old, new| first_clone.borrow_mut().push((*old, *new)));
+            a.observe(move |old, new| second_clone.borrow_mut().push((*old, *new)));
+
+            a.write(2);
+            assert_eq!(*first.borrow(), vec![(1, 2)]);
+            assert_eq!(*second.borrow(), vec![(1, 2)]);
+
+            assert!(a.unobserve(first_id));
+            assert!(!a.unobserve(first_id));
+            a.modify(|x| *x * 5);
+            assert_eq!(*first.borrow(), vec![(1, 2)]);
+            assert_eq!(*second.borrow(), vec![(1, 2), (2, 10)]);
+        }
+    }
+
+    both_strategies! {
+        fn observers_not_called_without_change(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let calls = Rc::new(Cell::new(0));
+            let calls_clone = calls.clone();
+            a.observe(move |_, _| calls_clone.set(calls_clone.get() + 1));
+
+            a.write(1);
+            a.modify(|x| *x);
+
+            assert_eq!(calls.get(), 0);
+        }
+    }
+
+    both_strategies! {
+        fn watchers_receive_changed_values(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let parity = buffer!(dcg, a => a % 2);
+            parity.read();
+            let rx = parity.watch();
+            let dropped = parity.watch();
+            drop(dropped);
+
+            for value in [2, 4, 5] {
+                a.write(value);
+                parity.read();
+            }
+            assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![0, 1]);
+            assert_eq!(parity.watchers.borrow().len(), 1);
+
+            let var_rx = a.watch();
+            drop(rx);
+            a.write(6);
+            a.write(6);
+            parity.read();
+            assert_eq!(var_rx.try_iter().collect::<Vec<_>>(), vec![6]);
+            assert!(parity.watchers.borrow().is_empty());
+        }
+    }
+
+    both_strategies! {
+        fn versions_bump_once_per_change(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let m = memo!(dcg, a => a / 10);
+            let b = buffer!(dcg, a => a / 10);
+            m.compare_values();
+            b.compare_values();
+            m.read();
+            b.read();
+            let versions = |dcg: &Dcg| [a.node(), m.node(), b.node()].map(|node| dcg.version(node));
+            assert_eq!(versions(&dcg), [0, 1, 1]);
+
+            a.write(1);
+            a.modify(|x| *x);
+            m.read();
+            b.read();
+            assert_eq!(versions(&dcg), [0, 1, 1]);
+
+            a.write(2);
+            m.read();
+            b.read();
+            assert_eq!(versions(&dcg), [1, 1, 1]);
+
+            a.write(20);
+            m.read();
+            m.read();
+            b.read();
+            assert_eq!(versions(&dcg), [2, 2, 2]);
+
+            let seen = dcg.version(m.node());
+            a.write(2); // cache hit producing a different value
+            m.read();
+            assert!(dcg.changed_since(m.node(), seen));
+            assert!(!dcg.changed_since(m.node(), seen + 1));
+            assert_eq!(versions(&dcg), [3, 3, 2]);
+        }
+    }
+
+    both_strategies! {
+        fn provenance_updates_on_recomputation(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let price = dcg.var(10);
+            let qty = dcg.var(2);
+            let scaled = thunk!(dcg, qty => qty * 100);
+            let total = memo!(dcg, (price, scaled) => price * scaled);
+            let inputs = |dcg: &Dcg| dcg.provenance(total.node());
+            assert!(inputs(&dcg).is_empty());
+
+            total.read();
+            let first = vec![(price.node().index(), 0), (qty.node().index(), 0)];
+            assert_eq!(inputs(&dcg), first);
+            qty.write(2);
+            total.read();
+            assert_eq!(inputs(&dcg), first);
+
+            price.write(12);
+            qty.write(3);
+            qty.write(4);
+            assert_eq!(inputs(&dcg), first);
+            assert_eq!(total.read(), 4800);
+            assert_eq!(
+                inputs(&dcg),
+                vec![(price.node().index(), 1), (qty.node().index(), 2)]
+            );
+            assert!(dcg.provenance(price.node()).is_empty());
+        }
+    }
+
+    #[test]
+    fn undo_redo_scripted_edits() {
+        let dcg = Dcg::default();
+        let a = dcg.var(0);
+        let b = dcg.var(0);
+        let sum = buffer!(dcg, (a, b) => a + b);
+        a.undoable();
+        b.undoable();
+        dcg.enable_history(8);
+        sum.read();
+
+        a.write(1);
+        a.write(2);
+        assert_eq!(sum.read(), 2);
+
+        assert_eq!(dcg.undo(), Some(vec![a.node().index()]));
+        assert!(a.is_dirty() && sum.is_dirty());
+        assert_eq!(sum.read(), 1);
+
+        dcg.group_edits(|| {
+            a.write(5);
+            b.write(5);
+        });
+        assert_eq!(dcg.redo(), None);
+        assert!(sum.is_dirty());
+        assert_eq!(sum.read(), 10);
+
+        assert_eq!(dcg.undo(), Some(vec![b.node().index(), a.node().index()]));
+        assert!(sum.is_dirty());
+        assert_eq!((a.read(), b.read(), sum.read()), (1, 0, 1));
+
+        assert_eq!(dcg.undo(), Some(vec![a.node().index()]));
+        assert_eq!((a.read(), b.read(), sum.read()), (0, 0, 0));
+        assert_eq!(dcg.undo(), None);
+        assert!(sum.is_clean());
+
+        dcg.redo();
+        assert_eq!(sum.read(), 1);
+        dcg.enable_history(0);
+        a.write(3);
+        assert_eq!(dcg.undo(), None);
+    }
+
+    #[test]
+    fn restore_cells_reuses_cached_values() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        let c = dcg.var(3);
+        let d = dcg.var(4);
+        for var in [&a, &b, &c, &d] {
+            var.undoable();
+        }
+        let double = memo!(dcg, a => a * 2);
+        let sum = memo!(dcg, (b, c) => b + c);
+        assert_eq!((double.read(), sum.read()), (2, 5));
+
+        let snapshot = dcg.save_cells();
+        a.write(10);
+        b.write(20);
+        c.write(30);
+        assert_eq!((double.read(), sum.read()), (20, 50));
+        let dropped = d.node().index();
+        drop(d);
+        let e = dcg.var(5);
+        e.undoable();
+        e.write(6);
+
+        let missing = dcg.restore_cells(&snapshot);
+        assert_eq!(missing, Ok(vec![dropped]));
+        assert_eq!(e.read(), 6);
+        dcg.reset_stats();
+        assert_eq!((double.read(), sum.read()), (2, 5));
+        assert_eq!(dcg.cache_stats_all(), CacheStats { hits: 2, misses: 0 });
+        assert_eq!(snapshot.get::<i32>(a.node().index()), Some(&1));
+    }
+
+    both_strategies! {
+        fn dynamic_buffer_follows_reads(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let flag = dcg.var(true);
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let choice = dcg.dynamic_buffer({
+                let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+                move || if flag.read() { a.read() } else { b.read() }
+            });
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let events_clone = events.clone();
+            dcg.subscribe(move |event| match event {
+                Event::EdgeAdded { .. } | Event::EdgeRemoved { .. } => {
+                    events_clone.borrow_mut().push(event)
+                }
+                _ => {}
+            });
+            let edges = |dcg: &Dcg| {
+                [flag.node(), a.node(), b.node()].map(|var| dcg.contains_edge(var, choice.node()))
+            };
+            assert_eq!(edges(&dcg), [false; 3]);
+
+            assert_eq!(choice.read(), 1);
+            assert_eq!(edges(&dcg), [true, true, false]);
+            b.write(3);
+            assert!(choice.is_clean());
+
+            flag.write(false);
+            assert!(choice.is_dirty());
+            assert_eq!(choice.read(), 3);
+            assert_eq!(edges(&dcg), [true, false, true]);
+            a.write(4);
+            assert!(choice.is_clean());
+            b.write(5);
+            assert_eq!(choice.read(), 5);
+            assert_eq!(dcg.edge_count(), 2);
+
+            let (flag, a, b, choice) = (
+                flag.node().index(),
+                a.node().index(),
+                b.node().index(),
+                choice.node().index(),
+            );
+            assert_eq!(
+                *events.borrow(),
+                vec![
+                    Event::EdgeAdded {
+                        from: flag,
+                        to: choice
+                    },
+                    Event::EdgeAdded {
+                        from: a,
+                        to: choice
+                    },
+                    Event::EdgeRemoved {
+                        from: a,
+                        to: choice
+                    },
+                    Event::EdgeAdded {
+                        from: b,
+                        to: choice
+                    },
+                ]
+            );
+        }
+    }
+
+    both_strategies! {
+        fn staged_writes_commit_together(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let x = dcg.var(1);
+            let y = dcg.var(2);
+            let z = dcg.var(3);
+            let left = buffer!(dcg, (x, y) => x + y);
+            let right = buffer!(dcg, (y, z) => y * z);
+            let last = buffer!(dcg, z => z);
+            let total = buffer!(dcg, (left, right) => left + right);
+            assert_eq!((total.read(), last.read()), (9, 3));
+
+            // Each cell becomes the sum of its neighbours, read as they're staged.
+            dcg.stage(&x, y.read());
+            dcg.stage(&y, left.read() - y.read() + z.read());
+            dcg.stage(&z, y.read());
+            dcg.stage(&z, 3);
+            assert_eq!((x.read(), y.read(), z.read()), (1, 2, 3));
+            assert_eq!(total.read(), 9);
+
+            let observed = Rc::new(RefCell::new(Vec::new()));
+            let observed_clone = observed.clone();
+            let (left_clone, right_clone, last_clone, total_clone) =
+                (left.clone(), right.clone(), last.clone(), total.clone());
+            x.observe(move |_, _| {
+                let dirty = [&left_clone, &right_clone, &last_clone].map(|node| node.is_dirty());
+                observed_clone
+                    .borrow_mut()
+                    .push((dirty, total_clone.read()));
+            });
+            assert_eq!(dcg.commit_staged(), 2);
+            assert_eq!(*observed.borrow(), vec![([true, true, false], 18)]);
+            assert_eq!((x.read(), y.read(), z.read()), (2, 4, 3));
+
+            dcg.stage(&x, 5);
+            dcg.discard_staged();
+            assert_eq!(dcg.commit_staged(), 0);
+            assert_eq!(x.read(), 2);
+        }
+    }
+
+    both_strategies! {
+        fn batched_writes_are_glitch_free(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(0);
+            let b = dcg.var(0);
+            let sum = buffer!(dcg, (a, b) => a + b);
+            let seen = Rc::new(RefCell::new(Vec::new()));
+            for (var, name) in [(&b, "b"), (&a, "a")] {
+                let (seen, sum) = (seen.clone(), sum.clone());
+                var.observe(move |_, _| seen.borrow_mut().push((name, sum.read())));
+            }
+            let sums = sum.watch();
+            let (tx_a, rx_a) = mpsc::channel();
+            let (tx_b, rx_b) = mpsc::channel();
+            dcg.feed(&b, rx_b);
+            dcg.feed(&a, rx_a);
+
+            for i in 1..=3 {
+                tx_a.send(i).unwrap();
+                tx_b.send(i * 10).unwrap();
+                assert_eq!(dcg.pump(), 2);
+                dcg.stage(&b, i * 100);
+                dcg.stage(&a, i * 1000);
+                assert_eq!(dcg.commit_staged(), 2);
+            }
+            let expected: Vec<_> = (1..=3)
+                .flat_map(|i| [i * 11, i * 11, i * 1100, i * 1100])
+                .collect();
+            let seen = seen.borrow();
+            assert!(seen.iter().map(|(_, sum)| *sum).eq(expected));
+            assert!(seen
+                .chunks(2)
+                .all(|pair| pair[0].0 == "a" && pair[1].0 == "b"));
+            assert_eq!(
+                sums.try_iter().collect::<Vec<_>>(),
+                [11, 1100, 22, 2200, 33, 3300]
+            );
+        }
+    }
+
+    both_strategies! {
+        fn lens_writes_through_to_source(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let celsius = dcg.var(0);
+            let fahrenheit = dcg.lens(&celsius, |c| c * 9 / 5 + 32, |f| (f - 32) * 5 / 9);
+            let kelvin = buffer!(dcg, celsius => celsius + 273);
+            let label = fahrenheit.lens(
+                |f| format!("{f}F"),
+                |s| s.trim_end_matches('F').parse().unwrap(),
+            );
+            assert_eq!(
+                (fahrenheit.read(), kelvin.read(), label.read()),
+                (32, 273, "32F".to_owned())
+            );
+
+            assert_eq!(fahrenheit.write(212), 0);
+            assert!(kelvin.is_dirty() && label.is_dirty());
+            assert_eq!(
+                (celsius.read(), fahrenheit.read(), kelvin.read()),
+                (100, 212, 373)
+            );
+
+            label.write("-40F".to_owned());
+            assert_eq!(
+                (celsius.read(), fahrenheit.read(), label.read()),
+                (-40, -40, "-40F".to_owned())
+            );
+            assert!(Rc::ptr_eq(label.source(), &celsius));
+
+            fahrenheit.read();
+            fahrenheit.write(-40);
+            assert!(fahrenheit.is_clean());
+            assert_eq!(
+                dcg.cache_stats(fahrenheit.node()),
+                CacheStats { hits: 1, misses: 3 }
+            );
+        }
+    }
+
+    #[test]
+    fn buffer_or_keeps_last_good_value() {
+        let dcg = Dcg::default();
+        let input = dcg.var(-1);
+        let checked = dcg.buffer_or(
+            &input,
+            {
+                let input = input.clone();
+                move || match input.read() {
+                    n if n < 0 => Err(format!("{n} is negative")),
+                    n => Ok(n * 2),
+                }
+            },
+            0,
+        );
+        let incremented = buffer!(dcg, checked => checked + 1);
+        let error = |dcg: &Dcg| dcg.last_error::<String>(checked.node());
+
+        assert_eq!(incremented.read(), 1);
+        assert_eq!(error(&dcg), Some("-1 is negative".to_owned()));
+
+        input.write(5);
+        assert_eq!(incremented.read(), 11);
+        assert_eq!(error(&dcg), None);
+
+        input.write(-2);
+        assert_eq!(incremented.read(), 11);
+        assert_eq!(error(&dcg), Some("-2 is negative".to_owned()));
+        assert_eq!(dcg.last_error::<i32>(checked.node()), None);
+
+        input.write(6);
+        assert_eq!((checked.read(), error(&dcg)), (12, None));
+    }
+
+    #[test]
+    fn probes_record_produced_values() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let t = thunk!(dcg, a => a + 1);
+        let m = memo!(dcg, a => a * 10);
+        let cell = dcg.probe(&a);
+        let thunk = dcg.probe(&t);
+        let memo = dcg.probe(&m);
+
+        for value in [1, 2, 2, 1] {
+            a.write(value);
+            t.read();
+            m.read();
+        }
+        assert_eq!(cell.values(), vec![2, 1]);
+        assert_eq!(thunk.values(), vec![2, 3, 3, 2]);
+        assert_eq!(memo.values(), vec![10, 20]);
+        assert_eq!(memo.clone().last_value(), Some(20));
+        assert_eq!(
+            [&cell, &thunk, &memo].map(|probe| probe.recomputations()),
+            [2, 4, 2]
+        );
+        assert_eq!(dcg.probe(&m).last_value(), None);
+    }
+
+    #[test]
+    fn partial_cleans_leave_the_frontier_dirty() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = memo!(dcg, b => b * 2);
+        let d = buffer!(dcg, a => a * 3);
+        let e = buffer!(dcg, (c, d) => c + d);
+        let probes = [&b, &d, &e].map(|buffer| dcg.probe(buffer));
+        let memo = dcg.probe(&c);
+
+        assert_eq!(dcg.clean_until(&[c.node().index()]), 1);
+        assert_eq!(
+            probes.each_ref().map(|probe| probe.recomputations()),
+            [1, 0, 0]
+        );
+        assert!(c.is_dirty() && d.is_dirty() && e.is_dirty());
+
+        assert_eq!(dcg.clean_depth(1), 1);
+        assert_eq!(
+            probes.each_ref().map(|probe| probe.recomputations()),
+            [1, 1, 0]
+        );
+        assert_eq!(memo.recomputations(), 0);
+        assert!(c.is_dirty() && e.is_dirty());
+
+        a.write(2);
+        assert_eq!(dcg.clean_depth(2), 3);
+        assert_eq!(memo.values(), vec![6]);
+        assert!(e.is_dirty());
+        assert_eq!(e.read(), 12);
+        assert_eq!(probes.map(|probe| probe.recomputations()), [2, 2, 1]);
+        assert_eq!(dcg.clean_until(&[]), 0);
+    }
+
+    #[test]
+    fn prefetch_stays_within_budget() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = memo!(dcg, a => a * 2);
+        let d = buffer!(dcg, (b, c) => b + c);
+        let unrelated = buffer!(dcg, a => a - 1);
+        let probes = [&b, &d, &unrelated].map(|buffer| dcg.probe(buffer));
+        let memo = dcg.probe(&c);
+
+        assert_eq!(dcg.prefetch(&[d.node().index()], 1), 1);
+        assert_eq!(dcg.prefetch(&[d.node().index()], 0), 0);
+        assert_eq!(dcg.prefetch(&[d.node().index()], 5), 2);
+        assert_eq!(dcg.prefetch(&[d.node().index()], 5), 0);
+        assert_eq!(d.read(), 4);
+        assert_eq!(
+            probes.each_ref().map(|probe| probe.recomputations()),
+            [1, 1, 0]
+        );
+        assert_eq!(memo.recomputations(), 1);
+
+        a.write(2);
+        assert_eq!(dcg.prefetch(&[b.node().index()], 5), 1);
+        assert_eq!(d.read(), 7);
+        assert_eq!(probes.map(|probe| probe.recomputations()), [2, 2, 0]);
+        assert_eq!(memo.values(), vec![2, 4]);
+    }
+
+    #[test]
+    fn fold_constants_detaches_frozen_subgraphs() {
+        let dcg = Dcg::default();
+        let width = dcg.var(2);
+        let height = dcg.var(3);
+        let input = dcg.var(1);
+        let area = thunk!(dcg, (width, height) => width * height);
+        let doubled = memo!(dcg, area => area * 2);
+        let scaled = buffer!(dcg, (doubled, input) => doubled * input);
+        let unfrozen = buffer!(dcg, (width, input) => width + input);
+        let probes = [&scaled, &unfrozen].map(|buffer| dcg.probe(buffer));
+        let memo = dcg.probe(&doubled);
+
+        width.freeze();
+        assert_eq!(dcg.fold_constants(), 0);
+        height.freeze();
+        assert_eq!(
+            width.try_write(4),
+            Err(DcgError::Frozen {
+                target: width.node().index()
+            })
+        );
+        assert_eq!(dcg.fold_constants(), 1);
+        assert_eq!(dcg.fold_constants(), 0);
+        assert!(doubled.node().is_frozen() && !scaled.node().is_frozen());
+        assert!(!dcg.contains_edge(area.node(), doubled.node()));
+        assert_eq!(memo.values(), vec![12]);
+
+        assert_eq!(scaled.read(), 12);
+        assert_eq!(unfrozen.read(), 3);
+        input.write(2);
+        assert!(doubled.is_clean() && scaled.is_dirty() && unfrozen.is_dirty());
+        assert_eq!(scaled.read(), 24);
+        assert_eq!(unfrozen.read(), 4);
+        assert_eq!(probes.map(|probe| probe.recomputations()), [2, 2]);
+        assert_eq!(memo.recomputations(), 1);
+    }
+
+    #[test]
+    fn components_clean_independently() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = memo!(dcg, b => b * 2);
+        let x = dcg.var(10);
+        let y = buffer!(dcg, x => x - 1);
+        let first = dcg.probe(&b);
+        let second = dcg.probe(&y);
+
+        let pipelines = vec![
+            vec![a.node().index(), b.node().index(), c.node().index()],
+            vec![x.node().index(), y.node().index()],
+        ];
+        assert_eq!(dcg.components(), pipelines);
+        assert_eq!(
+            [a.node(), c.node(), x.node(), y.node()].map(|node| dcg.component_of(node)),
+            [0, 0, 1, 1]
+        );
+
+        assert_eq!(dcg.clean_component(0), 2);
+        assert!(c.is_clean() && y.is_dirty());
+        assert_eq!((first.recomputations(), second.recomputations()), (1, 0));
+        x.write(20);
+        assert_eq!(dcg.clean_component(1), 1);
+        assert_eq!(dcg.clean_component(0), 0);
+        assert_eq!(dcg.clean_component(2), 0);
+        assert_eq!(second.values(), vec![19]);
+
+        let joined = buffer!(dcg, (c, y) => c + y);
+        assert_eq!(dcg.components().len(), 1);
+        assert_eq!(dcg.component_of(joined.node()), dcg.component_of(x.node()));
+    }
+
+    #[test]
+    fn plans_reuse_their_order_until_the_structure_changes() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a * 2);
+        let flag = dcg.var(true);
+        let other = dcg.var(10);
+        let choice = dcg.dynamic_buffer({
+            let (flag, b, other) = (flag.clone(), b.clone(), other.clone());
+            move || if flag.read() { b.read() } else { other.read() }
+        });
+        choice.read();
+        let total = buffer!(dcg, (b, choice) => b + choice);
+        let probe = dcg.probe(&b);
+        let plan = dcg.plan(&[b.clone(), total.clone()]);
+
+        assert_eq!(plan.execute(&dcg), vec![2, 4]);
+        a.write(2);
+        assert_eq!(plan.execute(&dcg), vec![4, 8]);
+        assert_eq!(plan.rebuilds(), 0);
+        assert_eq!(probe.recomputations(), 1);
+        assert!(!plan.nodes().contains(&other.node().index()));
+
+        flag.write(false);
+        assert_eq!(plan.execute(&dcg), vec![4, 14]);
+        assert_eq!(plan.rebuilds(), 0);
+        other.write(11);
+        assert_eq!(plan.execute(&dcg), vec![4, 15]);
+        assert_eq!(plan.rebuilds(), 1);
+        assert!(plan.nodes().contains(&other.node().index()));
+        assert_eq!(probe.recomputations(), 1);
+    }
+
+    both_strategies! {
+        fn memos_restore_reverted_configurations(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let b = dcg.var(0);
+            let expensive = memo!(dcg, (a, b) => a * 100 + b);
+            expensive.remember(2);
+            expensive.compare_values();
+            let probe = dcg.probe(&expensive);
+
+            for (x, y) in [(1, 0), (2, 0), (1, 0)] {
+                a.write(x);
+                b.write(y);
+                expensive.read();
+            }
+            assert_eq!(probe.values(), vec![100, 200]);
+            assert_eq!(dcg.version(expensive.node()), 3);
+
+            b.write(5);
+            assert_eq!(expensive.read(), 105);
+            a.write(2);
+            b.write(0);
+            assert_eq!(expensive.read(), 200);
+            assert_eq!(probe.values(), vec![100, 200, 105, 200]);
+            a.write(1);
+            assert_eq!(expensive.read(), 100);
+            assert_eq!(probe.recomputations(), 5);
+        }
+    }
+
+    #[test]
+    fn invalidating_a_tag_recomputes_only_its_nodes() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let physics = memo!(dcg, a => a * 2);
+        let shared = buffer!(dcg, a => a * 3);
+        let ui = buffer!(dcg, a => a * 4);
+        let label = buffer!(dcg, physics => physics + 1);
+        for node in [physics.node(), shared.node()] {
+            dcg.tag(node, "physics");
+        }
+        for node in [shared.node(), ui.node()] {
+            dcg.tag(node, "ui");
+        }
+        let probes = [&shared, &ui, &label].map(|buffer| dcg.probe(buffer));
+        let memo = dcg.probe(&physics);
+
+        assert_eq!(dcg.clean_tag("physics"), 2);
+        assert!(ui.is_dirty() && label.is_dirty());
+        assert_eq!((ui.read(), label.read()), (4, 3));
+
+        dcg.invalidate_tag("physics");
+        assert!(physics.is_dirty() && shared.is_dirty() && label.is_dirty());
+        assert!(ui.is_clean());
+        assert_eq!(
+            (physics.read(), shared.read(), ui.read(), label.read()),
+            (2, 3, 4, 3)
+        );
+        assert_eq!(memo.recomputations(), 2);
+        assert_eq!(probes.map(|probe| probe.recomputations()), [2, 1, 2]);
+        assert_eq!(dcg.stats_for_tag("ui"), CacheStats { hits: 1, misses: 3 });
+
+        assert!(dcg.untag(shared.node(), "ui"));
+        assert!(!dcg.untag(shared.node(), "ui"));
+        assert_eq!(dcg.nodes_with_tag("ui"), vec![ui.node().index()]);
+    }
+
+    #[test]
+    fn as_fn_reads_through_caches() {
+        fn sample<T>(f: impl Fn() -> T, n: usize) -> Vec<T> {
+            (0..n).map(|_| f()).collect()
+        }
+
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let t = thunk!(dcg, a => a + 1);
+        let m = memo!(dcg, t => t * 10);
+        let b = buffer!(dcg, (a, m) => a + m);
+        let probe = dcg.probe(&b);
+
+        assert_eq!(sample(dcg.as_fn(&a), 2), vec![1, 1]);
+        assert_eq!(sample(dcg.as_fn(&t), 2), vec![2, 2]);
+        assert_eq!(sample(dcg.as_fn(&m), 2), vec![20, 20]);
+        let total = dcg.as_fn(&b);
+        assert_eq!(sample(&total, 3), vec![21, 21, 21]);
+        a.write(2);
+        assert_eq!(total(), 32);
+        assert_eq!(probe.recomputations(), 2);
+    }
+
+    both_strategies! {
+        #[cfg(feature = "arbitrary")]
+        fn random_writes_and_reads_match_brute_force(strategy) {
+            use crate::testing::arbitrary::{Config, Rng, Shape};
+
+            for seed in 0..64 {
+                let mut rng = Rng::new(seed);
+                let config = Config {
+                    nodes: 2 + rng.below(20) as usize,
+                    edge_density: 0.1 + rng.below(5) as f64 / 10.0,
+                    ..Config::default()
+                };
+                let shape = Shape::generate(&config, &mut rng);
+                let dcg = Dcg::with_strategy(strategy);
+                let built = shape.build(&dcg);
+                let vars = shape.vars();
+
+                for _ in 0..64 {
+                    if rng.chance(0.4) {
+                        let var = vars[rng.below(vars.len() as u64) as usize];
+                        built.write(var, rng.below(4) as i64 - 2);
+                    } else {
+                        let node = rng.below(shape.len() as u64) as usize;
+                        let expected = shape.evaluate(&built.var_values())[node];
+                        assert_eq!(built.read(node), expected, "seed {seed}, shape {shape:?}");
+                    }
+                }
+                assert_eq!(dcg.check_invariants(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn handles_outlive_the_function_building_them() {
+        fn build() -> (Var<i32>, Buffer<i32>) {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let sum = dcg.buffer(&(a.clone(), b.clone()), {
+                let (a, b) = (a.clone(), b.clone());
+                move || a.read() + b.read()
+            });
+            (a, sum)
+        }
+
+        let (a, sum) = build();
+        assert_eq!(sum.read(), 3);
+        a.write(5);
+        assert!(sum.is_dirty());
+        assert_eq!(sum.read(), 7);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_cells_set_all_together() {
+        #[derive(Debug, Clone, PartialEq, DcgCells)]
+        struct Inputs {
+            price: f64,
+            qty: u32,
+            fee: f64,
+        }
+
+        let dcg = Dcg::default();
+        let cells = Inputs {
+            price: 2.5,
+            qty: 4,
+            fee: 1.0,
+        }
+        .into_cells(&dcg);
+        let (price, qty) = (cells.price.clone(), cells.qty.clone());
+        let total = buffer!(dcg, (price, qty) => price * qty as f64);
+        let with_fee = {
+            let (total, fee) = (total.clone(), cells.fee.clone());
+            dcg.buffer(&(total.clone(), fee.clone()), move || {
+                total.read() + fee.read()
+            })
+        };
+        let totals = Rc::new(RefCell::new(Vec::new()));
+        let totals_clone = totals.clone();
+        let with_fee_clone = with_fee.clone();
+        cells
+            .price
+            .observe(move |_, _| totals_clone.borrow_mut().push(with_fee_clone.read()));
+
+        assert_eq!((price.read(), qty.read(), cells.fee.read()), (2.5, 4, 1.0));
+        assert_eq!(with_fee.read(), 11.0);
+
+        let updated = Inputs {
+            price: 3.0,
+            qty: 2,
+            fee: 1.0,
+        };
+        assert_eq!(cells.set_all(&dcg, updated.clone()), 2);
+        assert_eq!(cells.values(), updated);
+        assert_eq!(*totals.borrow(), vec![7.0]);
+        assert_eq!(cells.set_all(&dcg, updated), 0);
+    }
+
+    #[test]
+    fn nodes_of_different_types_share_a_dcg() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Order {
+            quantity: i64,
+        }
+
+        let dcg = Dcg::default();
+        let name = dcg.var("widget".to_owned());
+        let order = dcg.var(Order { quantity: 3 });
+        let length = memo!(dcg, name => name.len() as i64);
+        let label = buffer!(dcg, (name, order) => format!("{} x{}", name, order.quantity));
+        let weight = buffer!(dcg, (length, order) => length * order.quantity);
+
+        assert_eq!(label.read(), "widget x3");
+        assert_eq!(weight.read(), 18);
+        name.write("gear".to_owned());
+        order.modify(|order| Order {
+            quantity: order.quantity + 1,
+        });
+        assert_eq!(label.read(), "gear x4");
+        assert_eq!(weight.read(), 16);
+    }
+
+    #[test]
+    fn vec_cells_dirty_only_overlapping_ranges() {
+        let dcg = Dcg::default();
+        let cell = dcg.vec_cell_with_chunk_size((0..50).collect::<Vec<i64>>(), 10);
+        let ranges = [0..10, 15..25, 20..30, 45..55];
+        let sums = ranges
+            .map(|range| dcg.memo_over_range(&cell, range, |range| range.iter().sum::<i64>()));
+        assert_eq!(sums.each_ref().map(|sum| sum.read()), [45, 195, 245, 235]);
+        let probes = sums.each_ref().map(|sum| dcg.probe(sum));
+
+        cell.set_elem(22, 0);
+        assert_eq!(sums.each_ref().map(|sum| sum.read()), [45, 173, 223, 235]);
+        assert_eq!(
+            probes.each_ref().map(|probe| probe.recomputations()),
+            [0, 1, 1, 0]
+        );
+
+        cell.push(50);
+        assert_eq!(cell.len(), 51);
+        assert_eq!(sums.each_ref().map(|sum| sum.read()), [45, 173, 223, 285]);
+        assert_eq!(
+            probes.each_ref().map(|probe| probe.recomputations()),
+            [0, 1, 1, 1]
+        );
+
+        assert_eq!(cell.remove(35), 35);
+        assert_eq!(cell.get(35), Some(36));
+        assert_eq!(sums.each_ref().map(|sum| sum.read()), [45, 173, 223, 240]);
+        assert_eq!(
+            probes.each_ref().map(|probe| probe.recomputations()),
+            [0, 1, 1, 2]
+        );
+    }
+
+    #[test]
+    fn map_cells_dirty_only_dependents_of_changed_keys() {
+        let dcg = Dcg::default();
+        let map = dcg.map_cell(HashMap::from([("price", 10), ("quantity", 2)]));
+        let price = dcg.memo_on_key(&map, "price", |price| price.copied());
+        let tax = dcg.memo_on_key(&map, "tax", |tax| tax.copied());
+        let keys = dcg.memo_on_keys(&map, |keys| keys.len());
+        let total = dcg.memo_on_map(&map, |map| map.values().sum::<i32>());
+        assert_eq!(
+            (price.read(), tax.read(), keys.read(), total.read()),
+            (Some(10), None, 2, 12)
+        );
+        let probes = [&price, &tax].map(|buffer| dcg.probe(buffer));
+        let (counted, summed) = (dcg.probe(&keys), dcg.probe(&total));
+
+        assert_eq!(map.set_key("quantity", 3), 2);
+        assert!(price.is_clean() && tax.is_clean() && keys.is_clean());
+        assert_eq!(total.read(), 13);
+
+        assert_eq!(map.insert("tax", 1), None);
+        assert!(price.is_clean());
+        assert_eq!((tax.read(), keys.read(), total.read()), (Some(1), 3, 14));
+        map.set_key("tax", 2);
+        assert_eq!(total.read(), 15);
+
+        assert_eq!(map.remove(&"price"), Some(10));
+        assert_eq!(
+            (price.read(), tax.read(), keys.read(), total.read()),
+            (None, Some(2), 2, 5)
+        );
+        assert_eq!(probes.map(|probe| probe.recomputations()), [1, 2]);
+        assert_eq!((counted.recomputations(), summed.recomputations()), (2, 4));
+    }
+
+    #[test]
+    fn derived_collections_patch_changed_elements() {
+        let dcg = Dcg::default();
+        let cell = dcg.vec_cell_with_chunk_size((0..20).collect::<Vec<i64>>(), 4);
+        let (mapped, tested) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let squares = dcg.map_collection(&cell, {
+            let mapped = mapped.clone();
+            move |x| {
+                mapped.set(mapped.get() + 1);
+                x * x
+            }
+        });
+        let odd = dcg.filter_collection(&cell, {
+            let tested = tested.clone();
+            move |x| {
+                tested.set(tested.get() + 1);
+                x % 2 == 1
+            }
+        });
+        assert_eq!(squares.read()[..4], [0, 1, 4, 9]);
+        assert_eq!(odd.read().len(), 10);
+        assert_eq!((mapped.get(), tested.get()), (20, 20));
+
+        cell.set_elem(6, 7);
+        assert_eq!(squares.read()[6], 49);
+        assert_eq!(odd.read()[..4], [1, 3, 5, 7]);
+        assert_eq!((mapped.get(), tested.get()), (21, 21));
+
+        cell.push(21);
+        assert_eq!(squares.read().len(), 21);
+        assert_eq!(odd.read().last(), Some(&21));
+        assert_eq!((mapped.get(), tested.get()), (22, 22));
+    }
+
+    #[test]
+    fn buffers_expire_after_their_ttl() {
+        use std::time::Duration;
+
+        use crate::testing::MockClock;
+
+        let dcg = Dcg::default();
+        let clock = Rc::new(MockClock::default());
+        dcg.set_clock(clock.clone());
+        let rate = Rc::new(Cell::new(2));
+        let amount = dcg.var(10);
+        let fetched = dcg.memo_with_ttl(
+            &(),
+            {
+                let rate = rate.clone();
+                move || rate.get()
+            },
+            Duration::from_secs(30),
+        );
+        let converted = buffer!(dcg, (fetched, amount) => fetched * amount);
+        let probe = dcg.probe(&fetched);
+
+        assert_eq!(converted.read(), 20);
+        rate.set(3);
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(converted.read(), 20);
+        assert_eq!(fetched.read(), 2);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(fetched.read(), 3);
+        assert_eq!(converted.read(), 30);
+        assert_eq!(probe.recomputations(), 2);
+
+        rate.set(4);
+        assert!(dcg.expire_now(fetched.node()));
+        assert!(converted.is_dirty());
+        assert_eq!(converted.read(), 40);
+        assert!(!dcg.expire_now(amount.node()));
+        assert_eq!(probe.recomputations(), 3);
+    }
+
+    #[test]
+    fn cache_capacity_evicts_least_recently_used() {
+        let dcg = Dcg::default();
+        let x = dcg.var(1);
+        let a = buffer!(dcg, x => x + 1);
+        let b = buffer!(dcg, x => x + 2);
+        let c = memo!(dcg, x => x + 3);
+        let total = thunk!(dcg, (a, c) => a + c);
+        let probes = [&a, &b].map(|buffer| dcg.probe(buffer));
+        let memo = dcg.probe(&c);
+        dcg.set_cache_capacity(2);
+
+        assert_eq!((a.read(), b.read(), c.read()), (2, 3, 4));
+        assert!(!a.node().is_cached());
+        assert!(b.node().is_cached() && c.node().is_cached());
+        assert!(a.is_clean());
+
+        assert_eq!(b.read(), 3);
+        assert_eq!(a.read(), 2);
+        assert!(!c.node().is_cached());
+        assert_eq!(total.read(), 6);
+        assert!(!b.node().is_cached());
+        assert_eq!(probes.map(|probe| probe.recomputations()), [2, 1]);
+        assert_eq!(memo.recomputations(), 2);
+
+        dcg.set_cache_capacity(usize::MAX);
+        assert_eq!((total.read(), b.read()), (6, 3));
+        assert!([a.node(), b.node(), c.node()]
+            .iter()
+            .all(|node| node.is_cached()));
+    }
+
+    #[test]
+    fn scheduled_writes_apply_at_their_ticks() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(1);
+        let sum = buffer!(dcg, (a, b) => a + b);
+        let probe = dcg.probe(&sum);
+        dcg.schedule(&a, 5, 2).unwrap();
+        dcg.schedule(&b, 5, 3).unwrap();
+        dcg.schedule(&b, 8, 7).unwrap();
+        dcg.schedule(&b, 8, 1).unwrap();
+        dcg.schedule(&a, 10, 4).unwrap();
+
+        assert_eq!(sum.read(), 2);
+        assert_eq!(dcg.advance_to(4), 0);
+        assert_eq!(sum.read(), 2);
+        assert_eq!(dcg.advance_to(7), 2);
+        assert_eq!(dcg.current_tick(), 7);
+        assert_eq!(sum.read(), 5);
+        assert_eq!(
+            dcg.schedule(&a, 6, 0),
+            Err(DcgError::ScheduledInPast {
+                target: a.node().index(),
+                at: 6,
+                current: 7,
+            })
+        );
+        assert_eq!(dcg.advance_to(9), 1);
+        assert_eq!(sum.read(), 3);
+        assert_eq!(dcg.advance_to(12), 1);
+        assert_eq!(sum.read(), 5);
+        assert_eq!(probe.values(), vec![2, 5, 3, 5]);
+    }
+
+    #[test]
+    fn named_dcgs_build_pipelines_by_key() {
+        use crate::named::{NamedDcg, NamedError};
+
+        let named = NamedDcg::default();
+        named.cell("price", 10.0).unwrap();
+        named.cell("quantity", 2.0).unwrap();
+        named.cell("tax_rate", 0.5).unwrap();
+        named
+            .memo("subtotal", |x| x[0] * x[1], &["price", "quantity"])
+            .unwrap();
+        named
+            .memo("total", |x| x[0] * (1.0 + x[1]), &["subtotal", "tax_rate"])
+            .unwrap();
+
+        assert_eq!(named.get("total"), Ok(30.0));
+        assert_eq!(named.set("quantity", 4.0), Ok(2.0));
+        assert_eq!(named.get("subtotal"), Ok(40.0));
+        assert_eq!(named.get("total"), Ok(60.0));
+
+        assert_eq!(
+            named.cell("price", 1.0),
+            Err(NamedError::DuplicateKey {
+                key: "price".to_owned()
+            })
+        );
+        assert_eq!(
+            named.memo("discount", |x| x[0], &["price", "coupon"]),
+            Err(NamedError::UnknownKey {
+                key: "coupon".to_owned()
+            })
+        );
+        assert!(!named.contains("discount"));
+        assert_eq!(
+            named.set("total", 0.0),
+            Err(NamedError::NotACell {
+                key: "total".to_owned()
+            })
+        );
+        assert_eq!(named.dcg().node_count(), 5);
+        assert_eq!(named.get("price"), Ok(10.0));
+    }
+
+    #[test]
+    fn memos_look_thunk_outputs_up_in_their_cache() {
+        let dcg = Dcg::default();
+        let a = dcg.var(20);
+        let capped = thunk!(dcg, a => a.min(10));
+        let memo = memo!(dcg, capped => capped * 2);
+        let buffer = buffer!(dcg, capped => capped * 2);
+        memo.compare_values();
+        let thunk_probe = dcg.probe(&capped);
+        let probes = (dcg.probe(&memo), dcg.probe(&buffer));
+
+        assert_eq!((memo.read(), buffer.read()), (20, 20));
+        a.write(30);
+        assert!(memo.is_dirty() && buffer.is_dirty());
+        assert_eq!((memo.read(), buffer.read()), (20, 20));
+        assert_eq!(thunk_probe.recomputations(), 5);
+        assert_eq!(
+            (probes.0.recomputations(), probes.1.recomputations()),
+            (1, 2)
+        );
+        assert_eq!(dcg.version(memo.node()), 1);
+        assert_eq!(dcg.version(buffer.node()), 2);
+
+        a.write(5);
+        assert_eq!((memo.read(), buffer.read()), (10, 10));
+        assert_eq!(
+            (probes.0.recomputations(), probes.1.recomputations()),
+            (2, 3)
+        );
+    }
+
+    #[test]
+    fn stale_memos_lists_dirty_caches_without_recomputing() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(10);
+        let from_a = buffer!(dcg, a => a * 2);
+        let from_b = buffer!(dcg, b => b * 2);
+        let memo = memo!(dcg, a => a + 1);
+        let never = buffer!(dcg, from_a => from_a + 1);
+        from_a.show_value();
+        memo.show_value();
+        memo.node().set_name("memo");
+        let probe = dcg.probe(&from_a);
+
+        assert_eq!((from_a.read(), from_b.read(), memo.read()), (2, 20, 2));
+        a.write(2);
+        b.write(20);
+        from_b.read();
+        let stale = dcg.stale_memos();
+        assert_eq!(
+            stale,
+            vec![
+                StaleMemo {
+                    idx: from_a.node().index(),
+                    kind: NodeKind::Buffer,
+                    name: None,
+                    cached: true,
+                    value: Some("2".to_owned()),
+                },
+                StaleMemo {
+                    idx: memo.node().index(),
+                    kind: NodeKind::Memo,
+                    name: Some("memo".to_owned()),
+                    cached: true,
+                    value: Some("1 cached".to_owned()),
+                },
+                StaleMemo {
+                    idx: never.node().index(),
+                    kind: NodeKind::Buffer,
+                    name: None,
+                    cached: false,
+                    value: None,
+                },
+            ]
+        );
+        assert_eq!(from_a.last_computed(), Some(2));
+        assert_eq!(never.last_computed(), None);
+        assert_eq!(probe.recomputations(), 1);
+
+        never.read();
+        assert!(dcg
+            .stale_memos()
+            .iter()
+            .all(|stale| stale.idx == memo.node().index()));
+    }
+
+    #[test]
+    fn records_dirty_only_changed_components() {
+        let dcg = Dcg::default();
+        let record = dcg.record(vec![1, 2, 3]);
+        let (x, y) = (record.component(0).clone(), record.component(1).clone());
+        let from_y = memo!(dcg, y => y * 10);
+        let from_x = buffer!(dcg, x => x * 10);
+        let probe = dcg.probe(&from_y);
+
+        assert_eq!((from_x.read(), from_y.read()), (10, 20));
+        assert_eq!(record.set_all(vec![5, 2, 3]), Ok(1));
+        assert!(from_y.is_clean() && from_x.is_dirty());
+        assert_eq!((from_x.read(), from_y.read()), (50, 20));
+        assert_eq!(probe.recomputations(), 1);
+
+        assert_eq!(
+            record.set_all(vec![1, 2]),
+            Err(DcgError::LengthMismatch {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(record.get_all(), vec![5, 2, 3]);
+        assert_eq!(record.set_all(vec![6, 7, 8]), Ok(3));
+        assert_eq!((from_x.read(), from_y.read()), (60, 70));
+        assert_eq!(record.len(), 3);
+    }
+
+    #[test]
+    fn trace_diamond() {
+        use crate::trace::TraceEvent::{CacheHit, Cleaned, Evaluated};
+
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = memo!(dcg, a => a * 2);
+        let d = buffer!(dcg, (b, c) => b + c);
+        let [a_idx, b_idx, c_idx, d_idx] =
+            [a.node(), b.node(), c.node(), d.node()].map(|node| node.index());
+
+        assert_eq!(
+            dcg.trace(|| d.read()),
+            (
+                4,
+                vec![
+                    Cleaned(a_idx),
+                    Evaluated(b_idx),
+                    Cleaned(b_idx),
+                    Evaluated(c_idx),
+                    Cleaned(c_idx),
+                    Evaluated(d_idx),
+                    Cleaned(d_idx),
+                ]
+            )
+        );
+        a.write(2);
+        let (value, events) = dcg.trace(|| {
+            let (b, inner) = dcg.trace(|| b.read());
+            assert_eq!(
+                inner,
+                vec![Cleaned(a_idx), Evaluated(b_idx), Cleaned(b_idx)]
+            );
+            b + d.read()
+        });
+        assert_eq!(value, 10);
+        assert_eq!(
+            events,
+            vec![
+                CacheHit(b_idx),
+                Evaluated(c_idx),
+                Cleaned(c_idx),
+                Evaluated(d_idx),
+                Cleaned(d_idx),
+            ]
+        );
+        a.write(1);
+        assert_eq!(
+            dcg.trace(|| c.read()),
+            (2, vec![Cleaned(a_idx), CacheHit(c_idx), Cleaned(c_idx)])
+        );
+    }
+
+    #[test]
+    fn trace_chain() {
+        use crate::trace::TraceEvent::{CacheHit, Cleaned, Evaluated};
+
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = thunk!(dcg, b => b * 2);
+        let d = buffer!(dcg, c => c + 1);
+        let [a_idx, b_idx, c_idx, d_idx] =
+            [a.node(), b.node(), c.node(), d.node()].map(|node| node.index());
+
+        d.read();
+        assert_eq!(dcg.trace(|| d.read()), (5, vec![CacheHit(d_idx)]));
+        a.write(2);
+        assert_eq!(
+            dcg.trace(|| d.read()),
+            (
+                7,
+                vec![
+                    Cleaned(a_idx),
+                    Evaluated(b_idx),
+                    Cleaned(b_idx),
+                    Evaluated(c_idx),
+                    Cleaned(c_idx),
+                    Evaluated(d_idx),
+                    Cleaned(d_idx),
+                ]
+            )
+        );
+        assert_eq!(
+            dcg.trace(|| c.read()),
+            (6, vec![CacheHit(b_idx), Evaluated(c_idx)])
+        );
+    }
+
+    both_strategies! {
+        fn eager_nodes_recompute_during_writes(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let eager = memo!(dcg, a => a * 2);
+            let downstream = buffer!(dcg, eager => eager + 1);
+            let lazy = buffer!(dcg, a => a * 3);
+            dcg.set_policy(eager.node(), RecomputePolicy::Eager);
+            dcg.set_policy(downstream.node(), RecomputePolicy::Eager);
+            let fired = Rc::new(RefCell::new(Vec::new()));
+            eager.on_recompute({
+                let fired = fired.clone();
+                move |value| fired.borrow_mut().push(*value)
+            });
+            let probes = [&downstream, &lazy].map(|buffer| dcg.probe(buffer));
+            let memo = dcg.probe(&eager);
+
+            a.write(2);
+            assert_eq!(*fired.borrow(), vec![4]);
+            assert_eq!(memo.recomputations(), 1);
+            assert_eq!(
+                probes.each_ref().map(|probe| probe.recomputations()),
+                [1, 0]
+            );
+            assert!(downstream.is_clean() && lazy.is_dirty());
+
+            dcg.stage(&a, 3);
+            dcg.commit_staged();
+            assert_eq!(downstream.last_computed(), Some(7));
+            assert_eq!(lazy.read(), 9);
+            assert_eq!(
+                probes.each_ref().map(|probe| probe.recomputations()),
+                [2, 1]
+            );
+
+            dcg.set_policy(downstream.node(), RecomputePolicy::OnDemand);
+            assert_eq!(dcg.policy(downstream.node()), RecomputePolicy::OnDemand);
+            a.write(4);
+            assert_eq!(*fired.borrow(), vec![4, 6, 8]);
+            assert!(downstream.is_dirty());
+        }
+    }
+
+    #[test]
+    fn sync_links_pulls_values_from_other_dcgs() {
+        let a = Dcg::default();
+        let x = a.var(2);
+        let y = a.var(1);
+        let sum = buffer!(a, (x, y) => x + y);
+
+        let b = Dcg::default();
+        let linked = b.link(&sum);
+        let scaled = buffer!(b, linked => linked * 10);
+        let probe = b.probe(&scaled);
+        assert_eq!(scaled.read(), 30);
+
+        x.write(5);
+        assert!(sum.is_dirty() && linked.is_clean() && scaled.is_clean());
+        assert_eq!(scaled.read(), 30);
+        assert_eq!(b.sync_links(), 1);
+        assert!(scaled.is_dirty());
+        assert_eq!(scaled.read(), 60);
+
+        x.write(4);
+        y.write(2);
+        assert_eq!(b.sync_links(), 0);
+        assert!(scaled.is_clean());
+        assert_eq!(scaled.read(), 60);
+        assert_eq!(probe.recomputations(), 2);
+
+        drop((linked, scaled));
+        x.write(0);
+        assert_eq!(b.sync_links(), 0);
+        assert!(b.inner.links.borrow().is_empty());
+    }
+
+    #[test]
+    fn clean_for_spends_its_budget_and_converges() {
+        use std::time::Duration;
+
+        use crate::testing::MockClock;
+
+        let dcg = Dcg::default();
+        let clock = Rc::new(MockClock::default());
+        dcg.set_clock(clock.clone());
+        let a = dcg.var(0);
+        let mut chain = vec![dcg.buffer(&a, {
+            let (a, clock) = (a.clone(), clock.clone());
+            move || {
+                clock.advance(Duration::from_millis(2));
+                a.read() + 1
+            }
+        })];
+        for _ in 0..5 {
+            let prev = chain.last().unwrap().clone();
+            chain.push(dcg.buffer(&prev.clone(), {
+                let clock = clock.clone();
+                move || {
+                    clock.advance(Duration::from_millis(2));
+                    prev.read() + 1
+                }
+            }));
+        }
+        let budget = Duration::from_millis(5);
+        let progress = |cleaned, remaining| CleanProgress { cleaned, remaining };
+
+        assert_eq!(dcg.clean_for(budget), progress(3, 3));
+        assert!(chain[2].is_clean() && chain[3].is_dirty());
+        a.write(10);
+        assert_eq!(dcg.clean_for(budget), progress(3, 3));
+        assert_eq!(dcg.clean_for(budget), progress(3, 0));
+        assert_eq!(dcg.clean_for(budget), progress(0, 0));
+        assert_eq!(chain[5].read(), 16);
+
+        a.write(20);
+        let mut calls = 0;
+        while dcg.clean_for(Duration::from_millis(1)).remaining > 0 {
+            calls += 1;
+        }
+        assert_eq!(calls, 5);
+        assert_eq!(dcg.clean_for(Duration::ZERO), progress(0, 0));
+        assert_eq!(chain[5].read(), 26);
+    }
+
+    #[test]
+    fn accessors_peek_without_evaluating() {
+        let dcg = Dcg::default();
+        let a = dcg.var(2);
+        let t = dcg.thunk(&a, {
+            let a = a.clone();
+            move || a.read() + 1
+        });
+        let m = memo!(dcg, a => a * 10);
+        let b = buffer!(dcg, a => a + 100);
+        let nodes = [a.node(), t.node(), m.node(), b.node()];
+        assert_eq!(
+            nodes.map(Node::kind),
+            [
+                NodeKind::Var,
+                NodeKind::Thunk,
+                NodeKind::Memo,
+                NodeKind::Buffer
+            ]
+        );
+        assert_eq!(
+            nodes.map(|node| dcg.kind(node.index())),
+            nodes.map(|node| Some(node.kind()))
+        );
+        assert_eq!(dcg.kind(NodeIndex::new(4)), None);
+
+        assert_eq!(a.value(), 2);
+        assert!(a.is_dirty());
+        assert_eq!((m.cached(&(2,)), b.last_computed()), (None, None));
+        assert_eq!((t.read(), m.read(), b.read()), (3, 20, 102));
+        a.write(5);
+        assert_eq!(a.value(), 5);
+        assert_eq!((m.cached(&(2,)), m.cached(&(5,))), (Some(20), None));
+        assert_eq!(b.last_computed(), Some(102));
+        assert!(m.is_dirty() && b.is_dirty());
+        assert_eq!(dcg.cache_stats(m.node()).hits, 0);
+    }
+
+    both_strategies! {
+        fn weak_dependencies_are_read_without_dirtying(strategy) {
+            let dcg = Dcg::with_strategy(strategy);
+            let a = dcg.var(1);
+            let scale = dcg.var(10);
+            let m = dcg.memo_with_weak(a.clone(), &scale, {
+                let (a, scale) = (a.clone(), scale.clone());
+                move || a.read() * scale.read()
+            });
+            let probe = dcg.probe(&m);
+            assert_eq!(m.read(), 10);
+            scale.write(100);
+            assert!(m.is_clean());
+            assert_eq!(m.read(), 10);
+            a.write(2);
+            assert_eq!(m.read(), 200);
+            a.write(1);
+            assert_eq!(m.read(), 10);
+            assert_eq!(probe.recomputations(), 2);
+            assert_eq!(dcg.weak_dependencies(m.node()), vec![scale.node().index()]);
+            assert!(!dcg.contains_edge(scale.node(), m.node()));
+            assert_eq!(dcg.check_invariants(), Ok(()));
+
+            let d = dcg.dynamic_buffer({
+                let (a, scale) = (a.clone(), scale.clone());
+                move || a.read() + scale.read()
+            });
+            dcg.add_weak_dependency(d.node(), scale.node());
+            assert_eq!(d.read(), 101);
+            assert!(dcg.contains_edge(a.node(), d.node()));
+            assert!(!dcg.contains_edge(scale.node(), d.node()));
+            scale.write(5);
+            assert!(d.is_clean());
+            assert_eq!(dcg.unused_dependencies(d.node()), vec![]);
+
+            assert!(dcg
+                .to_dot()
+                .contains("    1 -> 2 [style=dashed, arrowhead=empty];\n"));
+            assert!(dcg.to_dot().contains("    0 -> 2;\n"));
+            assert!(dcg.to_mermaid().contains("    n1 -.-> n2\n"));
+            assert_eq!(
+                dcg.edges_to_csv(),
+                "from,to,dirty,weak,label\n0,2,false,false,\n0,3,false,false,\n1,2,false,true,\n1,3,false,true,\n"
+            );
+        }
+    }
+
+    #[test]
+    fn versions_strategy_writes_without_traversing() {
+        let run = |strategy| {
+            let dcg = Dcg::with_strategy(strategy);
+            let dirtied = Rc::new(Cell::new(0));
+            dcg.subscribe({
+                let dirtied = dirtied.clone();
+                move |event| {
+                    if let Event::NodeDirtied { .. } = event {
+                        dirtied.set(dirtied.get() + 1);
+                    }
+                }
+            });
+            let a = dcg.var(0);
+            let parity = buffer!(dcg, a => a % 2);
+            parity.compare_values();
+            let mut chain = vec![buffer!(dcg, parity => parity + 1)];
+            for _ in 0..20 {
+                let last = chain.last().unwrap().clone();
+                chain.push(buffer!(dcg, last => last + 1));
+            }
+            let end = chain.last().unwrap();
+            let probe = dcg.probe(end);
+
+            let mut values = vec![end.read()];
+            dirtied.set(0);
+            for value in 1..=4 {
+                a.write(value);
+                values.push(end.read());
+            }
+            a.write(6);
+            values.push(end.read());
+            (values, dirtied.get(), probe.recomputations())
+        };
+
+        let (flags_values, flags_dirtied, flags_recomputations) =
+            run(VerificationStrategy::DirtyFlags);
+        let (versions_values, versions_dirtied, versions_recomputations) =
+            run(VerificationStrategy::Versions);
+        assert_eq!(flags_values, vec![21, 22, 21, 22, 21, 21]);
+        assert_eq!(versions_values, flags_values);
+        assert_eq!(flags_dirtied, 5 * 23);
+        assert_eq!(versions_dirtied, 5);
+        assert_eq!(flags_recomputations, 6);
+        // Writing 6 after 4 leaves `parity` equal, so `end` isn't recomputed.
+        assert_eq!(versions_recomputations, 5);
+    }
+
+    #[test]
+    fn readers_read_and_inspect() {
+        let dcg = Dcg::default();
+        let a = dcg.var(2);
+        let b = buffer!(dcg, a => a * 10);
+        let c = buffer!(dcg, (a, b) => a + b);
+        c.node().set_name("sum");
+        let reader = dcg.reader();
+        let (a_idx, b_idx, c_idx) = (a.node().index(), b.node().index(), c.node().index());
+
+        assert_eq!(reader.node_count(), 3);
+        assert_eq!(reader.kind(a_idx), Some(NodeKind::Var));
+        assert_eq!(reader.name(c_idx).as_deref(), Some("sum"));
+        assert_eq!(reader.dependencies(c_idx), vec![a_idx, b_idx]);
+        assert_eq!(reader.dependents(a_idx), vec![b_idx, c_idx]);
+        assert_eq!(reader.last_computed(&c), None);
+        assert!(reader.is_dirty(c_idx));
+
+        assert_eq!(reader.read(&c), 22);
+        assert!(!reader.is_dirty(c_idx));
+        assert_eq!(reader.last_computed(&c), Some(22));
+        assert_eq!(reader.read(&c), 22);
+        assert_eq!(reader.cache_stats(c_idx).hits, 1);
+
+        a.write(3);
+        assert!(reader.is_dirty(c_idx));
+        assert_eq!(reader.last_computed(&c), Some(22));
+        assert!(reader.read_with_budget(&c, 1).is_err());
+        assert_eq!(reader.read_with_budget(&c, 2), Ok(33));
+    }
+
+    #[test]
+    fn change_reports_count_the_dirtied_diamond() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = buffer!(dcg, a => a * 2);
+        let d = buffer!(dcg, (b, c) => b + c);
+        let diamond = vec![b.node().index(), c.node().index(), d.node().index()];
+        d.read();
+
+        assert_eq!(
+            a.write_report(1),
+            ChangeReport {
+                previous: 1,
+                changed: false,
+                edges_dirtied: 0,
+                memos_invalidated: vec![],
+            }
+        );
+        assert!(d.is_clean());
+        assert_eq!(
+            a.write_report(2),
+            ChangeReport {
+                previous: 1,
+                changed: true,
+                edges_dirtied: 4,
+                memos_invalidated: diamond.clone(),
+            }
+        );
+        let again = a.write_report(3);
+        assert!(again.changed);
+        assert_eq!((again.edges_dirtied, again.memos_invalidated), (0, vec![]));
+        assert_eq!(d.read(), 10);
+
+        let record = dcg.record(vec![1, 2]);
+        let (x, y) = (record.component(0).clone(), record.component(1).clone());
+        let sum = buffer!(dcg, (x, y) => x + y);
+        sum.read();
+        assert_eq!(
+            record.set_all_report(vec![1, 2]),
+            Ok(BatchReport::default())
+        );
+        assert_eq!(
+            record.set_all_report(vec![5, 6]),
+            Ok(BatchReport {
+                changed: 2,
+                edges_dirtied: 2,
+                memos_invalidated: vec![sum.node().index()],
+            })
+        );
+
+        dcg.stage(&a, 3);
+        assert_eq!(dcg.commit_staged_report(), BatchReport::default());
+        dcg.stage(&a, 4);
+        assert_eq!(
+            dcg.commit_staged_report(),
+            BatchReport {
+                changed: 1,
+                edges_dirtied: 4,
+                memos_invalidated: diamond,
+            }
+        );
+    }
+
+    #[test]
+    fn fingerprints_track_structure_only() {
+        let build = || {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let c = buffer!(dcg, a => a * 2);
+            c.node().set_name("c");
+            (dcg, a, b, c)
+        };
+        let (first, ..) = build();
+        let (second, a, b, c) = build();
+        let fingerprint = first.fingerprint();
+        assert_eq!(second.fingerprint(), fingerprint);
+
+        a.write(5);
+        c.read();
+        assert_eq!(second.fingerprint(), fingerprint);
+
+        c.node().add_dependencies(&b);
+        assert_ne!(second.fingerprint(), fingerprint);
+        let (third, _, b, c) = build();
+        third.add_weak_dependency(c.node(), b.node());
+        assert_ne!(third.fingerprint(), fingerprint);
+        assert_ne!(third.fingerprint(), second.fingerprint());
+    }
+
+    #[test]
+    fn retire_detaches_and_prunes_upstream() {
+        let dcg = Dcg::default();
+        let source = dcg.var(1);
+        let kept = dcg.var(10);
+        let a = buffer!(dcg, source => source + 1);
+        let b = memo!(dcg, a => a * 2);
+        let c = buffer!(dcg, (b, kept) => b + kept);
+        let d = buffer!(dcg, c => c * 3);
+        let probe = dcg.probe(&d);
+        drop((a, b));
+
+        assert_eq!(dcg.retire(&c, true), Ok(2));
+        assert!(c.node().is_frozen() && c.is_clean());
+        assert_eq!(c.last_computed(), Some(14));
+        assert_eq!(dcg.edge_count(), 1);
+        assert!(dcg.contains_edge(c.node(), d.node()));
+        assert_eq!(d.read(), 42);
+
+        source.write(5);
+        kept.write(20);
+        assert!(c.is_clean() && d.is_clean());
+        assert_eq!(d.read(), 42);
+        assert_eq!(probe.recomputations(), 1);
+        assert_eq!(
+            dcg.retire(&c, false),
+            Err(DcgError::Frozen {
+                target: c.node().index()
+            })
+        );
+    }
+
+    #[test]
+    fn for_each_value_visits_in_topological_order() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let d = buffer!(dcg, a => a + 1);
+        let c = memo!(dcg, d => d * 2);
+        let b = buffer!(dcg, (c, d) => c + d);
+        let t = thunk!(dcg, b => b + 100);
+        let e = buffer!(dcg, t => t.to_string());
+        drop(buffer!(dcg, a => a * 1000));
+
+        let mut visited = Vec::new();
+        dcg.for_each_value(|idx, kind, value: &i32| {
+            let reader = dcg.reader();
+            assert!(!reader.is_dirty(idx));
+            assert!(reader
+                .dependencies(idx)
+                .iter()
+                .all(|&dep| !reader.is_dirty(dep)));
+            visited.push((idx, kind, *value));
+        });
+        let index = |node: &Node| node.index();
+        assert_eq!(
+            visited,
+            vec![
+                (index(d.node()), NodeKind::Buffer, 2),
+                (index(c.node()), NodeKind::Memo, 4),
+                (index(b.node()), NodeKind::Buffer, 6),
+            ]
+        );
+        assert_eq!((c.cached(&(2,)), c.cached(&(3,))), (Some(4), None));
+        assert_eq!(e.last_computed().as_deref(), Some("106"));
+        assert!(t.is_clean());
+        assert_eq!(
+            dcg.values::<String>(),
+            vec![(index(e.node()), "106".to_string())]
+        );
+
+        a.write(2);
+        assert_eq!(dcg.values::<i32>().len(), 3);
+        assert_eq!(c.cached(&(3,)), Some(6));
+
+        a.write(3);
+        let write = panic::catch_unwind(AssertUnwindSafe(|| {
+            dcg.for_each_value(|_, _, _: &i32| {
+                a.write(4);
+            });
+        }));
+        assert!(write.is_err());
+        assert!(dcg.inner.evaluating.borrow().is_empty());
+        assert_eq!(a.read(), 3);
+    }
+
+    thread_local! {
+        static CLONES_BEFORE_PANIC: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    /// Panics when cloned once [`CLONES_BEFORE_PANIC`] clones have succeeded.
+    #[derive(Debug, PartialEq)]
+    struct Fragile(i32);
+
+    impl Clone for Fragile {
+        fn clone(&self) -> Self {
+            CLONES_BEFORE_PANIC.with(|left| match left.get() {
+                Some(0) => panic!("Fragile clone failed"),
+                Some(n) => left.set(Some(n - 1)),
+                None => {}
+            });
+            Fragile(self.0)
+        }
+    }
+
+    fn fail_clones_after(clones: Option<usize>) {
+        CLONES_BEFORE_PANIC.with(|left| left.set(clones));
+    }
+
+    #[test]
+    fn panicking_clones_leave_writes_unapplied() {
+        let dcg = Dcg::default();
+        dcg.enable_history(8);
+        let a = dcg.var(Fragile(1));
+        let b = dcg.var(Fragile(10));
+        a.undoable();
+        b.undoable();
+        a.record_history(4);
+        let sum = buffer!(dcg, (a, b) => Fragile(a.0 + b.0));
+        sum.read();
+        let versions = (dcg.version(a.node()), dcg.version(b.node()));
+
+        let unchanged = || {
+            fail_clones_after(None);
+            assert_eq!((a.value(), b.value()), (Fragile(1), Fragile(10)));
+            assert_eq!(a.history(), vec![]);
+            assert_eq!((dcg.version(a.node()), dcg.version(b.node())), versions);
+            assert!(sum.is_clean());
+            assert_eq!(dcg.check_invariants(), Ok(()));
+        };
+        // A write clones the old value for undoing, then the new one for the history and redoing.
+        for clones in 0..3 {
+            fail_clones_after(Some(clones));
+            assert!(panic::catch_unwind(AssertUnwindSafe(|| a.write(Fragile(2)))).is_err());
+            unchanged();
+            fail_clones_after(Some(clones));
+            let modify = panic::catch_unwind(AssertUnwindSafe(|| a.modify(|a| Fragile(a.0 + 1))));
+            assert!(modify.is_err());
+            unchanged();
+        }
+        for clones in 3..5 {
+            dcg.stage(&a, Fragile(3));
+            dcg.stage(&b, Fragile(20));
+            fail_clones_after(Some(clones));
+            assert!(panic::catch_unwind(AssertUnwindSafe(|| dcg.commit_staged())).is_err());
+            unchanged();
+        }
+        assert_eq!(dcg.undo(), None);
+
+        a.write(Fragile(2));
+        assert_eq!(dcg.undo(), Some(vec![a.node().index()]));
+        a.write(Fragile(5));
+        for clones in 0.. {
+            fail_clones_after(Some(clones));
+            let read = panic::catch_unwind(AssertUnwindSafe(|| sum.read()));
+            fail_clones_after(None);
+            assert_eq!(dcg.check_invariants(), Ok(()));
+            match read {
+                Ok(read) => {
+                    assert_eq!(read, Fragile(15));
+                    break;
+                }
+                Err(_) => assert!(sum.is_dirty()),
+            }
+        }
+        assert!(sum.is_clean());
+        assert_eq!(sum.read(), Fragile(15));
+    }
+
+    #[test]
+    fn deferred_buffers_read_once_finalized() {
+        let dcg = Dcg::default();
+        let total = dcg.deferred_buffer({
+            let parts = dcg.var(vec![1, 2]);
+            move || parts.read().iter().sum::<i32>()
+        });
+        let doubled = buffer!(dcg, total => total * 2);
+        let a = dcg.var(3);
+        let b = buffer!(dcg, a => a + 1);
+        let late = dcg.deferred_buffer({
+            let (a, b) = (a.clone(), b.clone());
+            move || a.read() * b.read()
+        });
+        let late_doubled = buffer!(dcg, late => late * 2);
+
+        assert_eq!(
+            total.try_read(),
+            Err(DcgError::Unfinalized {
+                target: total.node().index()
+            })
+        );
+        let premature = panic::catch_unwind(panic::AssertUnwindSafe(|| doubled.read()));
+        assert!(premature.is_err());
+
+        assert_eq!(
+            dcg.finalize_dependencies(late.node(), &late_doubled),
+            Err(DcgError::Cycle {
+                from: late_doubled.node().index(),
+                to: late.node().index()
+            })
+        );
+        assert_eq!(
+            dcg.finalize_dependencies(late.node(), &(a.clone(), b)),
+            Ok(())
+        );
+        assert_eq!(late_doubled.read(), 24);
+        a.write(4);
+        assert_eq!(late_doubled.read(), 40);
+        assert_eq!(
+            dcg.finalize_dependencies(late.node(), &a),
+            Err(DcgError::AlreadyFinalized {
+                target: late.node().index()
+            })
+        );
+        assert_eq!(
+            dcg.finalize_dependencies(doubled.node(), &a),
+            Err(DcgError::AlreadyFinalized {
+                target: doubled.node().index()
+            })
+        );
+    }
+
+    #[test]
+    fn validators_reject_writes_atomically() {
+        let in_range = |value: &i32| {
+            if (0..=100).contains(value) {
+                Ok(())
+            } else {
+                Err(format!("{} is out of range", value))
+            }
+        };
+        let dcg = Dcg::default();
+        let record = dcg.record(vec![10, 20]);
+        let (x, y) = (record.component(0).clone(), record.component(1).clone());
+        x.add_validator(in_range);
+        y.add_validator(in_range);
+        y.add_validator(|value| {
+            if value % 2 == 0 {
+                Ok(())
+            } else {
+                Err("odd".to_string())
+            }
+        });
+        let sum = buffer!(dcg, (x, y) => x + y);
+        assert_eq!(sum.read(), 30);
+
+        let rejected = |target: &Var<i32>, message: &str| DcgError::ValidationFailed {
+            target: target.node().index(),
+            message: message.to_string(),
+        };
+        assert_eq!(x.try_write(150), Err(rejected(&x, "150 is out of range")));
+        assert_eq!(y.try_modify(|y| *y + 1), Err(rejected(&y, "odd")));
+        assert_eq!((x.value(), y.value()), (10, 20));
+        assert!(x.is_clean() && y.is_clean() && sum.is_clean());
+
+        assert_eq!(record.set_all(vec![50, 31]), Err(rejected(&y, "odd")));
+        assert_eq!(record.get_all(), vec![10, 20]);
+        assert!(sum.is_clean());
+
+        let snapshot = {
+            x.undoable();
+            y.undoable();
+            dcg.save_cells()
+        };
+        assert_eq!(record.set_all(vec![50, 40]), Ok(2));
+        assert_eq!(y.try_write(60), Ok(40));
+        assert_eq!(sum.read(), 110);
+        assert_eq!(dcg.restore_cells(&snapshot), Ok(Vec::new()));
+        assert_eq!(sum.read(), 30);
+    }
+
+    #[test]
+    fn build_bulk_validates_specs_first() {
+        use crate::bulk::{BulkError, NodeSpec};
+
+        let sum = |values: &[i32]| values.iter().sum();
+        let dcg = Dcg::default();
+        let _existing = dcg.var(0);
+        assert_eq!(
+            dcg.build_bulk(vec![NodeSpec::Var(1), NodeSpec::buffer(vec![0, 2], sum)])
+                .err(),
+            Some(BulkError::UnknownDependency {
+                spec: 1,
+                dependency: 2
+            })
+        );
+        let cycle = dcg.build_bulk(vec![
+            NodeSpec::Var(1),
+            NodeSpec::buffer(vec![0, 3], sum),
+            NodeSpec::buffer(vec![1], sum),
+            NodeSpec::buffer(vec![2], sum),
+        ]);
+        assert!(matches!(cycle, Err(BulkError::Cycle { spec: 1..=3 })));
+        assert_eq!(
+            dcg.build_bulk(vec![NodeSpec::buffer(vec![0], sum)]).err(),
+            Some(BulkError::Cycle { spec: 0 })
+        );
+        assert_eq!((dcg.node_count(), dcg.edge_count()), (1, 0));
+
+        let nodes = dcg
+            .build_bulk(vec![
+                NodeSpec::buffer(vec![1, 2], sum),
+                NodeSpec::buffer(vec![2, 2], sum),
+                NodeSpec::Var(5),
+            ])
+            .unwrap();
+        assert_eq!((dcg.node_count(), dcg.edge_count()), (4, 4));
+        let indices: Vec<_> = nodes
+            .iter()
+            .map(|node| node.node().index().index())
+            .collect();
+        assert_eq!(indices, vec![1, 2, 3]);
+        assert!(dcg.contains_edge(nodes[1].node(), nodes[0].node()));
+        assert_eq!(nodes[0].read(), 15);
+        nodes[2].as_var().unwrap().write(1);
+        assert!(nodes[0].as_buffer().unwrap().is_dirty());
+        assert_eq!(nodes[0].read(), 3);
+    }
+
+    #[test]
+    fn pending_vars_block_their_dependents() {
+        let dcg = Dcg::default();
+        let rate = dcg.pending_var::<i32>();
+        let amount = dcg.var(100);
+        let converted = memo!(dcg, (rate, amount) => rate * amount);
+        let label = buffer!(dcg, converted => format!("{} units", converted));
+        let direct = buffer!(dcg, amount => amount + 1);
+        let pending = DcgError::Pending {
+            target: rate.node().index(),
+        };
+
+        assert!(!dcg.is_ready(rate.node()) && !dcg.is_ready(converted.node()));
+        assert!(dcg.is_ready(amount.node()) && dcg.is_ready(direct.node()));
+        assert_eq!(rate.try_read(), Err(pending.clone()));
+        assert_eq!(label.try_read(), Err(pending.clone()));
+        assert_eq!(rate.try_write(2), Err(pending));
+        assert_eq!(direct.try_read(), Ok(101));
+        assert!(converted.is_dirty() && label.is_dirty());
+
+        assert_eq!(dcg.fulfill(&rate, 2), Ok(()));
+        assert!(dcg.is_ready(converted.node()) && dcg.is_ready(label.node()));
+        assert_eq!(converted.read(), 200);
+        assert_eq!(label.try_read().as_deref(), Ok("200 units"));
+        assert_eq!(
+            dcg.fulfill(&rate, 3),
+            Err(DcgError::NotPending {
+                target: rate.node().index()
+            })
+        );
+        rate.write(3);
+        assert_eq!(label.read(), "300 units");
+    }
+
+    #[test]
+    fn interceptors_transform_values_before_caching() {
+        let dcg = Dcg::default();
+        let a = dcg.var(7);
+        let calls = Rc::new(Cell::new(0));
+        let m = memo!(dcg, a => a * 3);
+        let b = buffer!(dcg, m => m + 1);
+        let clamp = m.intercept({
+            let calls = calls.clone();
+            move |value| {
+                calls.set(calls.get() + 1);
+                value.min(20)
+            }
+        });
+        m.intercept(|value| value * 10);
+        let computed = Rc::new(RefCell::new(Vec::new()));
+        m.on_recompute({
+            let computed = computed.clone();
+            move |value| computed.borrow_mut().push(*value)
+        });
+
+        assert_eq!(dcg.clean_depth(usize::MAX), 2);
+        assert_eq!((m.read(), b.read()), (200, 201));
+        assert!(dcg.is_intercepted(m.node()) && !dcg.is_intercepted(b.node()));
+        a.write(2);
+        assert_eq!(b.read(), 61);
+        a.write(7);
+        assert_eq!((m.read(), b.read()), (200, 201));
+        assert_eq!(calls.get(), 2);
+        assert_eq!(*computed.borrow(), vec![200, 60]);
+        assert_eq!(m.cached(&(7,)), Some(200));
+
+        assert!(m.remove_interceptor(clamp));
+        assert!(!m.remove_interceptor(clamp));
+        assert_eq!(m.read(), 200);
+        a.write(8);
+        assert_eq!(b.read(), 241);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn stability_checks_restore_dirtiness() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let c = memo!(dcg, b => b * 2);
+        let calls = Rc::new(Cell::new(0));
+        let d = buffer!(dcg, b => {
+            calls.set(calls.get() + 1);
+            b + calls.get()
+        });
+        assert_eq!(c.read(), 4);
+        dcg.track_stability(&c);
+        dcg.track_stability(&d);
+        a.write(2);
+        let stats = dcg.cache_stats_all();
+
+        dcg.assert_stable(&c, 3);
+        assert_eq!(dcg.check_stable(&d, 3), Err(vec![4, 5, 6]));
+        let unstable = panic::catch_unwind(AssertUnwindSafe(|| dcg.assert_stable(&d, 2)));
+        assert!(unstable.is_err());
+        assert_eq!(dcg.audit_stability(2), vec![d.node().index()]);
+        assert!(b.is_dirty() && c.is_dirty() && d.is_dirty());
+        assert_eq!(c.cached(&(2,)), Some(4));
+        assert_eq!(d.last_computed(), None);
+        assert_eq!(dcg.cache_stats_all(), stats);
+
+        assert_eq!((c.read(), d.read()), (6, 11));
+        drop(d);
+        assert!(dcg.audit_stability(2).is_empty());
+        assert!(c.is_clean());
+    }
+
+    #[test]
+    fn change_classes_only_dirty_sensitive_buffers() {
+        const HARD: u8 = 0b01;
+        const SOFT: u8 = 0b10;
+        let dcg = Dcg::default();
+        let events = dcg.var(vec![1, 2]);
+        let recent = buffer!(dcg, events => events.last().copied());
+        let calls = Rc::new(Cell::new(0));
+        let analytics = dcg.buffer_sensitive_to(&recent, HARD, {
+            let (recent, calls) = (recent.clone(), calls.clone());
+            move || {
+                calls.set(calls.get() + 1);
+                recent.read()
+            }
+        });
+        let report = buffer!(dcg, analytics => analytics.map(|n| n * 10));
+        assert_eq!((recent.read(), report.read()), (Some(2), Some(20)));
+
+        events.write_classed(vec![1, 2, 3], SOFT);
+        assert!(recent.is_dirty());
+        assert!(analytics.is_clean() && report.is_clean());
+        assert_eq!(report.read(), Some(20));
+        assert_eq!(calls.get(), 1);
+
+        events.write_classed(vec![4], HARD | SOFT);
+        assert!(analytics.is_dirty() && report.is_dirty());
+        assert_eq!(report.read(), Some(40));
+        events.write(vec![5]);
+        assert_eq!(report.read(), Some(50));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn extended_vars_are_counted_in_order() {
+        let dcg = Dcg::new();
+        assert!(dcg.is_empty());
+        let a = dcg.var(0);
+        let vars = dcg.extend_vars(vec![1, 2, 3]);
+        assert_eq!((dcg.len(), dcg.node_count()), (4, 4));
+        assert!(!dcg.is_empty());
+        let indices: Vec<_> = vars.iter().map(|var| var.node().index()).collect();
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(a.node().index() < indices[0]);
+        assert_eq!(
+            vars.iter().map(|var| var.read()).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(dcg.extend_vars(Vec::<i32>::new()).is_empty());
+        assert_eq!(dcg.len(), 4);
+
+        let record: collections::Record<_> = vars.iter().map(|var| var.read() * 10).collect();
+        assert_eq!(record.len(), 3);
+        assert_eq!(record.get_all(), vec![10, 20, 30]);
+        let doubled = buffer!(dcg, a => a * 2);
+        record.component(1).write(25);
+        assert_eq!(record.component(1).read(), 25);
+        assert_eq!((dcg.len(), doubled.read()), (5, 0));
+    }
+
+    #[test]
+    fn skipping_thunk_sinks_dirties_only_observed_nodes() {
+        let mut results = Vec::new();
+        for skip in [false, true] {
+            let dcg = Dcg::default();
+            dcg.set_skip_thunk_sinks(skip);
+            let logged = dcg.var(1);
+            let log = thunk!(dcg, logged => logged * 10);
+            let echo = thunk!(dcg, log => log + 1);
+            let a = dcg.var(2);
+            let t = thunk!(dcg, a => a + 1);
+            let b = buffer!(dcg, t => t * 2);
+            let side = thunk!(dcg, a => a * 3);
+            let hooked = thunk!(dcg, a => a - 1);
+            dcg.on_dirtied(hooked.node(), |_| {});
+            assert_eq!((echo.read(), b.read()), (11, 6));
+            assert_eq!((side.read(), hooked.read()), (6, 1));
+
+            let report = logged.write_report(2);
+            let logged_edges = report.edges_dirtied;
+            let reads = (echo.read(), log.read());
+            let report = a.write_report(3);
+            let expected = [t.node(), b.node(), side.node(), hooked.node()];
+            let dirtied: Vec<_> = expected.iter().map(|node| node.is_dirty()).collect();
+            results.push((reads, b.read(), side.read(), hooked.read()));
+            if skip {
+                assert_eq!(logged_edges, 0);
+                assert_eq!(report.edges_dirtied, 3);
+                assert_eq!(dirtied, [true, true, false, true]);
+            } else {
+                assert_eq!(logged_edges, 2);
+                assert_eq!(report.edges_dirtied, 4);
+                assert!(dirtied.iter().all(|&dirty| dirty));
+            }
+            assert_eq!(dcg.check_invariants(), Ok(()));
+
+            let late = buffer!(dcg, side => side + 1);
+            assert_eq!(late.read(), 10);
+            a.write(4);
+            assert!(side.is_dirty() && late.is_dirty());
+            assert_eq!(late.read(), 13);
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn toggling_thunk_skipping_catches_up_on_skipped_thunks() {
+        let dcg = Dcg::default();
+        dcg.set_skip_thunk_sinks(true);
+        let a = dcg.var(1);
+        let t = thunk!(dcg, a => a + 1);
+        let u = thunk!(dcg, t => t * 10);
+        assert_eq!(u.read(), 20);
+        assert_eq!(a.write_report(2).edges_dirtied, 0);
+        assert!(t.is_clean() && u.is_clean());
+        assert_eq!(dcg.check_invariants(), Ok(()));
+
+        dcg.set_skip_thunk_sinks(false);
+        assert!(t.is_dirty() && u.is_dirty());
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        dcg.var(0).write(1);
+        assert_eq!(u.read(), 30);
+
+        dcg.set_skip_thunk_sinks(true);
+        a.write(3);
+        assert!(t.is_clean());
+        let b = buffer!(dcg, u => u + 1);
+        assert_eq!(b.read(), 41);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        a.write(4);
+        assert!(b.is_dirty());
+        assert_eq!(b.read(), 51);
+        dcg.set_skip_thunk_sinks(false);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+    }
+
+    /// Returns every node's dirty flag, in index order.
+    fn dirty_flags(dcg: &Dcg) -> Vec<bool> {
+        let graph = dcg.inner.graph.borrow();
+        graph.node_indices().map(|idx| graph[idx]).collect()
+    }
+
+    #[test]
+    fn check_invariants_leaves_skipped_thunks_alone() {
+        let dcg = Dcg::default();
+        dcg.set_skip_thunk_sinks(true);
+        let a = dcg.var(1);
+        let t = thunk!(dcg, a => a + 1);
+        assert_eq!(t.read(), 2);
+        a.write(2);
+        let b = buffer!(dcg, t => t * 10);
+
+        let flags = dirty_flags(&dcg);
+        assert!(t.is_clean());
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        let after = dirty_flags(&dcg);
+        assert_eq!(after, flags);
+        assert!(dcg.inner.observed.borrow().is_none());
+
+        assert_eq!(b.read(), 30);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn removed_watch_indices_alias_later_nodes() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let removed = Rc::new(RefCell::new(Vec::new()));
+        dcg.subscribe({
+            let removed = removed.clone();
+            move |event| {
+                if let Event::NodeRemoved { idx } = event {
+                    removed.borrow_mut().push(idx);
+                }
+            }
+        });
+        let watch = dcg.watch_expr(&a, {
+            let a = a.clone();
+            move || a.read() + 1
+        });
+        let stale = watch.node().index();
+        assert_eq!(dcg.kind(stale), Some(NodeKind::Buffer));
+        drop(watch);
+        assert_eq!(*removed.borrow(), vec![stale]);
+
+        let b = dcg.var("b");
+        assert_eq!(b.node().index(), stale);
+        assert_eq!(dcg.kind(stale), Some(NodeKind::Var));
+        assert_eq!(dcg.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn dropped_watches_leave_the_graph_as_before() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a * 10);
+        let counts = (dcg.node_count(), dcg.edge_count());
+        let watch = dcg.watch_expr(&(a.clone(), b.clone()), {
+            let (a, b) = (a.clone(), b.clone());
+            move || a.read() + b.read()
+        });
+        watch.node().set_name("watch");
+        let idx = watch.node().index();
+        assert_eq!(watch.read(), 11);
+        a.write(2);
+        assert_eq!(watch.read(), 22);
+        assert_eq!(dcg.node_count(), counts.0 + 1);
+        drop(watch);
+
+        assert_eq!((dcg.node_count(), dcg.edge_count()), counts);
+        assert_eq!(dcg.kind(idx), None);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        a.write(3);
+        assert_eq!(b.read(), 30);
+        let c = buffer!(dcg, b => b + 1);
+        assert_eq!(c.node().index(), idx);
+        assert_eq!(c.node().name(), None);
+        assert_eq!(c.read(), 31);
+
+        let watch = dcg.watch_expr(&c, {
+            let c = c.clone();
+            move || c.read() * 2
+        });
+        assert_eq!(watch.read(), 62);
+        let d = dcg.var(0);
+        drop(watch);
+        assert_eq!((dcg.node_count(), dcg.edge_count()), (4, 2));
+        d.write(1);
+        a.write(4);
+        assert_eq!((c.read(), d.read()), (41, 1));
+    }
+
+    #[test]
+    fn dropped_watches_are_removed_after_later_nodes() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let watch = dcg.watch_expr(&a, {
+            let a = a.clone();
+            move || a.read() + 1
+        });
+        let idx = watch.node().index();
+        assert_eq!(watch.read(), 2);
+        let b = dcg.var(10);
+        let c = buffer!(dcg, (a, b) => a + b);
+        drop(watch);
+
+        assert_eq!((dcg.node_count(), dcg.edge_count()), (3, 2));
+        assert_eq!(dcg.kind(idx), None);
+        assert_eq!(b.node().kind(), NodeKind::Var);
+        assert_eq!(c.node().kind(), NodeKind::Buffer);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+        assert_eq!(c.read(), 11);
+        b.write(20);
+        assert_eq!(c.read(), 21);
+    }
+
+    #[test]
+    fn removed_watch_handles_are_stale() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let watch = dcg.watch_expr(&a, {
+            let a = a.clone();
+            move || a.read() + 1
+        });
+        let kept = Buffer::clone(&watch);
+        assert_eq!(kept.read(), 2);
+        drop(watch);
+        assert_eq!(dcg.node_count(), 1);
+
+        let b = dcg.var(10);
+        let idx = kept.node().index();
+        assert_eq!(b.node().index(), idx);
+        assert_eq!(kept.try_read(), Err(DcgError::StaleHandle { target: idx }));
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| kept.read())).is_err());
+        let dependent = panic::catch_unwind(AssertUnwindSafe(|| buffer!(dcg, kept => kept * 2)));
+        assert!(dependent.is_err());
+        assert_eq!(dcg.node_count(), 2);
+        assert_eq!(b.read(), 10);
+        assert_eq!(dcg.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn settle_runs_feedback_until_quiescent() {
+        let dcg = Dcg::default();
+        let level = dcg.var(0);
+        let target = dcg.var(40);
+        let next = buffer!(dcg, (level, target) => level + (target - level) / 2);
+        dcg.set_policy(next.node(), RecomputePolicy::Eager);
+        let (tx, rx) = mpsc::channel();
+        dcg.feed(&level, rx);
+        next.on_recompute(move |next| tx.send(*next).unwrap());
+
+        let report = dcg.settle(20).unwrap();
+        assert_eq!((level.read(), next.read()), (39, 39));
+        assert_eq!(report.recomputed, vec![next.node().index()]);
+        assert_eq!(report.rounds, 8);
+        let report = dcg.settle(1).unwrap();
+        assert_eq!((report.rounds, report.recomputed), (1, Vec::new()));
+
+        let flip = dcg.var(false);
+        let flipped = buffer!(dcg, flip => !flip);
+        dcg.set_policy(flipped.node(), RecomputePolicy::Eager);
+        let (tx, rx) = mpsc::channel();
+        dcg.feed(&flip, rx);
+        flipped.on_recompute(move |flipped| tx.send(*flipped).unwrap());
+        let changing = vec![flip.node().index(), flipped.node().index()];
+        assert_eq!(dcg.settle(10), Err(DcgError::Unsettled { changing }));
+        assert!(level.is_clean() && next.is_clean());
+    }
+
+    #[test]
+    fn settle_returns_rejected_values() {
+        let dcg = Dcg::default();
+        let capped = dcg.var(0);
+        capped.add_validator(|value| {
+            if *value < 10 {
+                Ok(())
+            } else {
+                Err("too big".to_string())
+            }
+        });
+        let (tx, rx) = mpsc::channel();
+        dcg.feed(&capped, rx);
+
+        tx.send(50).unwrap();
+        assert_eq!(
+            dcg.settle(10),
+            Err(DcgError::ValidationFailed {
+                target: capped.node.idx,
+                message: "too big".to_string(),
+            })
+        );
+        assert_eq!(capped.read(), 0);
+        tx.send(5).unwrap();
+        assert_eq!(dcg.settle(10).map(|report| report.rounds), Ok(2));
+        assert_eq!(capped.read(), 5);
+    }
+
+    #[test]
+    fn versions_check_each_node_once_per_revision() {
+        let dcg = Dcg::with_strategy(VerificationStrategy::Versions);
+        let a = dcg.var(0);
+        let mut chain = vec![buffer!(dcg, a => a + 1)];
+        for _ in 1..1000 {
+            let last = chain.last().unwrap().clone();
+            chain.push(buffer!(dcg, last => last + 1));
+        }
+        let end = chain.last().unwrap();
+        let checks = || dcg.inner.version_checks.replace(0);
+
+        assert_eq!(end.read(), 1000);
+        assert!(checks() <= 4 * 1001);
+        assert!(end.is_clean());
+        assert_eq!(checks(), 1);
+        a.write(1);
+        assert_eq!(end.read(), 1001);
+        assert!(checks() <= 4 * 1001);
+        assert_eq!(end.read(), 1001);
+        assert_eq!(checks(), 1);
+    }
+
+    #[test]
+    fn rejected_batches_stay_pending() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        for var in [&a, &b] {
+            var.add_validator(|value| {
+                if *value < 100 {
+                    Ok(())
+                } else {
+                    Err("too big".to_string())
+                }
+            });
+        }
+        let rejected = |target: &Var<i32>| {
+            Err(DcgError::ValidationFailed {
+                target: target.node.idx,
+                message: "too big".to_string(),
+            })
+        };
+
+        dcg.stage(&a, 10);
+        dcg.stage(&b, 200);
+        assert_eq!(dcg.try_commit_staged(), rejected(&b));
+        assert_eq!(dcg.try_commit_staged(), rejected(&b));
+        assert_eq!((a.read(), b.read()), (1, 2));
+        dcg.stage(&b, 20);
+        assert_eq!(dcg.try_commit_staged(), Ok(2));
+        assert_eq!((a.read(), b.read()), (10, 20));
+
+        dcg.schedule(&a, 1, 11).unwrap();
+        dcg.schedule(&a, 2, 300).unwrap();
+        dcg.schedule(&b, 2, 22).unwrap();
+        assert_eq!(dcg.try_advance_to(3), rejected(&a));
+        assert_eq!((dcg.current_tick(), a.read(), b.read()), (1, 11, 20));
+        dcg.schedule(&a, 2, 33).unwrap();
+        assert_eq!(dcg.try_advance_to(3), Ok(2));
+        assert_eq!((dcg.current_tick(), a.read(), b.read()), (3, 33, 22));
+
+        let source = dcg.var(5);
+        let proxy = dcg.link(&source);
+        proxy.add_validator(|value| {
+            if *value < 100 {
+                Ok(())
+            } else {
+                Err("too big".to_string())
+            }
+        });
+        source.write(500);
+        assert_eq!(dcg.try_sync_links(), rejected(&proxy));
+        source.write(50);
+        assert_eq!(dcg.try_sync_links(), Ok(1));
+        assert_eq!(proxy.read(), 50);
+    }
+
+    #[test]
+    fn rejected_modifications_leave_the_value_untouched() {
+        let dcg = Dcg::default();
+        let a = dcg.var(10);
+        a.add_validator(|value: &i32| {
+            if *value >= 0 {
+                Ok(())
+            } else {
+                Err("negative".to_string())
+            }
+        });
+        let doubled = buffer!(dcg, a => a * 2);
+        let changes = Rc::new(Cell::new(0));
+        a.observe({
+            let changes = changes.clone();
+            move |_, _| changes.set(changes.get() + 1)
+        });
+        assert_eq!(doubled.read(), 20);
+
+        let rejected = a.try_modify(|value| {
+            *value = -5;
+            *value
+        });
+        assert_eq!(
+            rejected,
+            Err(DcgError::ValidationFailed {
+                target: a.node().index(),
+                message: "negative".to_string(),
+            })
+        );
+        assert_eq!(a.value(), 10);
+        assert!(doubled.is_clean());
+        assert_eq!((doubled.read(), changes.get()), (20, 0));
+    }
+
+    #[test]
+    fn modify_closures_run_before_any_mutation() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a * 10);
+        assert_eq!(b.read(), 10);
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+            a.modify(|value| {
+                *value = 5;
+                panic!("halfway")
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(a.value(), 1);
+        assert!(b.is_clean());
+        assert_eq!(b.read(), 10);
+
+        let previous = a.modify(|value| {
+            *value += 1;
+            *value
+        });
+        assert_eq!((previous, a.value()), (1, 2));
+        assert!(b.is_dirty());
+        assert_eq!(b.read(), 20);
+    }
+
+    #[test]
+    fn pump_writes_latest_fed_values() {
+        let dcg = Dcg::default();
+        let a = dcg.var(0);
+        let b = dcg.var(0);
+        let (tx_a, rx_a) = mpsc::channel();
+        let (tx_b, rx_b) = mpsc::channel();
+        dcg.feed(&a, rx_a);
+        dcg.feed(&b, rx_b);
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let changes_clone = changes.clone();
+        a.observe(move |old, new| changes_clone.borrow_mut().push((*old, *new)));
+
+        assert_eq!(dcg.pump(), 0);
+        for value in 1..=3 {
+            tx_a.send(value).unwrap();
+        }
+        tx_b.send(0).unwrap();
+        assert_eq!(dcg.pump(), 1);
+        assert_eq!((a.read(), b.read()), (3, 0));
+        assert_eq!(*changes.borrow(), vec![(0, 3)]);
+
+        drop(tx_a);
+        tx_b.send(1).unwrap();
+        assert_eq!(dcg.pump(), 1);
+        assert_eq!(dcg.inner.feeds.borrow().len(), 1);
+        drop(b);
+        tx_b.send(2).unwrap();
+        assert_eq!(dcg.pump(), 0);
+        assert!(dcg.inner.feeds.borrow().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    mod futures {
+        use super::*;
+        use std::{
+            future::Future,
+            pin::{pin, Pin},
+            task::{Context, Poll, Waker},
+        };
+
+        fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+            future.poll(&mut Context::from_waker(Waker::noop()))
+        }
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let mut future = pin!(future);
+            loop {
+                if let Poll::Ready(value) = poll(future.as_mut()) {
+                    return value;
+                }
+            }
+        }
+
+        /// A future that is pending the first time it is polled.
+        struct YieldOnce(bool);
+
+        impl Future for YieldOnce {
+            type Output = ();
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                if self.0 {
+                    return Poll::Ready(());
+                }
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+
+        #[test]
+        fn async_buffer_caches_and_coalesces() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let computed = Rc::new(Cell::new(0));
+            let b = dcg.async_buffer(&a, {
+                let (a, computed) = (a.clone(), computed.clone());
+                move || {
+                    let a = a.read();
+                    let computed = computed.clone();
+                    Box::pin(async move {
+                        YieldOnce(false).await;
+                        computed.set(computed.get() + 1);
+                        a * 10
+                    })
+                }
+            });
+
+            assert_eq!(block_on(b.read_async()), 10);
+            assert_eq!(block_on(b.read_async()), 10);
+            assert_eq!(computed.get(), 1);
+            assert!(!b.is_dirty());
+
+            a.write(2);
+            assert!(b.is_dirty());
+            let mut first = pin!(b.read_async());
+            let mut second = pin!(b.read_async());
+            assert_eq!(poll(first.as_mut()), Poll::Pending);
+            assert_eq!(poll(second.as_mut()), Poll::Ready(20));
+            assert_eq!(poll(first.as_mut()), Poll::Ready(20));
+            assert_eq!(computed.get(), 2);
+            assert!(!b.is_dirty());
+
+            let unrelated = dcg.var(0);
+            a.write(3);
+            let mut pending = pin!(b.read_async());
+            assert_eq!(poll(pending.as_mut()), Poll::Pending);
+            unrelated.write(1);
+            assert_eq!(poll(pending.as_mut()), Poll::Ready(30));
+            assert!(!b.is_dirty());
+            assert_eq!(block_on(b.read_async()), 30);
+
+            a.write(33);
+            let mut racing = pin!(b.read_async());
+            assert_eq!(poll(racing.as_mut()), Poll::Pending);
+            a.write(4);
+            assert_eq!(poll(racing.as_mut()), Poll::Ready(330));
+            assert!(b.is_dirty());
+            assert_eq!(block_on(b.read_async()), 40);
+            assert_eq!(dcg.cache_stats(b.node()), CacheStats { hits: 2, misses: 5 });
+        }
+
+        #[test]
+        fn clean_all_yielding_tolerates_writes_between_yields() {
+            use crate::future::CleanSummary;
+
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = buffer!(dcg, a => a + 1);
+            let c = buffer!(dcg, b => b * 2);
+            let d = buffer!(dcg, (b, c) => b + c);
+            let e = memo!(dcg, a => a * 10);
+            let mut clean = pin!(dcg.clean_all_yielding(2));
+
+            assert_eq!(poll(clean.as_mut()), Poll::Pending);
+            assert_eq!(dcg.dirty_refreshable().len(), 2);
+            a.write(5);
+            assert_eq!(dcg.dirty_refreshable().len(), 4);
+            assert_eq!(poll(clean.as_mut()), Poll::Pending);
+            a.write(6);
+            assert_eq!(
+                block_on(clean),
+                CleanSummary {
+                    cleaned: 8,
+                    yields: 3
+                }
+            );
+
+            for node in [b.node(), c.node(), d.node(), e.node()] {
+                assert!(!node.is_dirty());
+            }
+            let values = (b.last_computed(), c.last_computed(), d.last_computed());
+            assert_eq!(values, (Some(7), Some(14), Some(21)));
+            assert_eq!(e.cached(&(6,)), Some(60));
+            assert_eq!(block_on(dcg.clean_all_yielding(1)).cleaned, 0);
+        }
+    }
+
+    #[test]
+    fn observers_run_after_dirtying_without_borrows() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(0);
+        let doubled = buffer!(dcg, a => a * 2);
+        doubled.read();
+        let a_clone = a.clone();
+        let b_clone = b.clone();
+        let doubled_clone = doubled.clone();
+        a.observe(move |_, new| {
+            assert!(doubled_clone.is_dirty());
+            assert_eq!(a_clone.read(), *new);
+            b_clone.write(doubled_clone.read());
+        });
+
+        a.write(5);
+
+        assert_eq!(b.read(), 10);
+    }
+
+    #[test]
+    fn memo_on_recompute_only_on_misses() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let invocations = Rc::new(Cell::new(0));
+        let invocations_clone = invocations.clone();
+        let m = memo!(dcg, a => {
+            invocations_clone.set(invocations_clone.get() + 1);
+            a * 2
+        });
+        let hooked = Rc::new(RefCell::new(Vec::new()));
+        let hooked_clone = hooked.clone();
+        m.on_recompute(move |value| hooked_clone.borrow_mut().push(*value));
+
+        for value in [1, 2, 2, 1, 3] {
+            a.write(value);
+            m.read();
+            assert_eq!(hooked.borrow().len(), invocations.get());
+        }
+        assert_eq!(*hooked.borrow(), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn buffer_on_recompute_only_when_dirty() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let hooked = Rc::new(RefCell::new(Vec::new()));
+        let hooked_clone = hooked.clone();
+        b.on_recompute(move |value| hooked_clone.borrow_mut().push(*value));
+
+        b.read();
+        b.read();
+        a.write(5);
+        b.read();
+
+        assert_eq!(*hooked.borrow(), vec![2, 6]);
+    }
+
+    #[test]
+    fn on_any_recompute_reports_every_closure_execution() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let t = thunk!(dcg, a => a + 1);
+        let m = memo!(dcg, t => t * 2);
+        let b = buffer!(dcg, m => m + 1);
+        let recomputed = Rc::new(RefCell::new(Vec::new()));
+        let recomputed_clone = recomputed.clone();
+        dcg.on_any_recompute(move |node, value| {
+            recomputed_clone
+                .borrow_mut()
+                .push((node, *value.downcast_ref::<i32>().unwrap()))
+        });
+
+        b.read();
+        b.read();
+
+        // `t` is read for `m`'s cache key, then again by `m`'s closure
+        assert_eq!(
+            *recomputed.borrow(),
+            vec![
+                (t.node.idx, 2),
+                (t.node.idx, 2),
+                (m.thunk.node.idx, 4),
+                (b.thunk.node.idx, 5)
+            ]
+        );
+        a.write(2);
+        b.read();
+        assert_eq!(
+            recomputed.borrow()[4..],
+            [
+                (t.node.idx, 3),
+                (t.node.idx, 3),
+                (m.thunk.node.idx, 6),
+                (b.thunk.node.idx, 7)
+            ]
+        );
+    }
+
+    fn record_events(dcg: &Dcg) -> (SubscriptionId, Rc<RefCell<Vec<Event>>>) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_clone = events.clone();
+        let id = dcg.subscribe(move |event| events_clone.borrow_mut().push(event));
+        (id, events)
+    }
+
+    #[test]
+    fn event_trace() {
+        let dcg = Dcg::default();
+        let (id, events) = record_events(&dcg);
+
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        b.read();
+        b.read();
+        a.write(2);
+        a.write(2);
+        assert!(dcg.unsubscribe(id));
+        a.write(3);
+        b.read();
+
+        let (a, b) = (a.node.idx, b.thunk.node.idx);
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                Event::NodeAdded {
+                    idx: a,
+                    kind: NodeKind::Var
+                },
+                Event::NodeAdded {
+                    idx: b,
+                    kind: NodeKind::Buffer
+                },
+                Event::EdgeAdded { from: a, to: b },
+                Event::NodeCleaned { idx: a },
+                Event::Recomputed { idx: b },
+                Event::NodeCleaned { idx: b },
+                Event::VarWritten { idx: a },
+                Event::NodeDirtied { idx: a },
+                Event::NodeDirtied { idx: b },
+            ]
+        );
+    }
+
+    #[test]
+    fn event_kinds() {
+        let dcg = Dcg::default();
+        let (_, events) = record_events(&dcg);
+
+        dcg.var(1);
+        thunk!(dcg, 1);
+        memo!(dcg, 1);
+        buffer!(dcg, 1);
+
+        let kinds: Vec<_> = events
+            .borrow()
+            .iter()
+            .map(|event| match event {
+                Event::NodeAdded { kind, .. } => *kind,
+                event => panic!("unexpected {:?}", event),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                NodeKind::Var,
+                NodeKind::Thunk,
+                NodeKind::Memo,
+                NodeKind::Buffer
+            ]
+        );
+    }
+
+    #[test]
+    fn multiple_subscribers() {
+        let dcg = Dcg::default();
+        let (first, first_events) = record_events(&dcg);
+        let (_, second_events) = record_events(&dcg);
+
+        dcg.var(1);
+        dcg.unsubscribe(first);
+        dcg.var(2);
+
+        assert_eq!(first_events.borrow().len(), 1);
+        assert_eq!(second_events.borrow().len(), 2);
+        assert!(!dcg.unsubscribe(first));
+    }
+
+    #[test]
+    fn buffer_cache_stats() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+
+        b.read();
+        b.read();
+        a.write(2);
+        b.read();
+
+        assert_eq!(dcg.cache_stats(b.node()), CacheStats { hits: 1, misses: 2 });
+        assert_eq!(dcg.cache_stats(a.node()), CacheStats::default());
+    }
+
+    #[test]
+    fn memo_cache_stats() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let m = memo!(dcg, a => a * 2);
+
+        m.read();
+        a.write(2);
+        m.read();
+        a.write(1);
+        m.read();
+        m.read();
+
+        let stats = dcg.cache_stats(m.node());
+        assert_eq!(stats, CacheStats { hits: 2, misses: 2 });
+        assert_eq!(stats.hit_ratio(), Some(0.5));
+    }
+
+    #[test]
+    fn cache_stats_all_and_reset() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let m = memo!(dcg, b => b * 2);
+
+        // The memo reads `b` once for its key and once in its closure.
+        m.read();
+        m.read();
+        assert_eq!(dcg.cache_stats_all(), CacheStats { hits: 3, misses: 2 });
+
+        dcg.reset_stats();
+        assert_eq!(dcg.cache_stats_all(), CacheStats::default());
+        assert_eq!(dcg.cache_stats(m.node()).hit_ratio(), None);
+        m.read();
+        assert_eq!(dcg.cache_stats(m.node()), CacheStats { hits: 1, misses: 0 });
+    }
+
+    #[test]
+    fn explain_attributes_recomputation_to_written_var() {
+        let dcg = Dcg::default();
+        dcg.enable_audit(true);
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        let c = buffer!(dcg, a => a + 1);
+        let d = buffer!(dcg, (c, b) => c + b);
+        d.read();
+
+        a.write(2);
+        let causes = dcg.explain(d.node());
+        assert_eq!(causes.len(), 1);
+        assert_eq!(causes[0].origin, a.node().index());
+        assert_eq!(
+            causes[0].edges,
+            vec![
+                (a.node().index(), c.node().index()),
+                (c.node().index(), d.node().index())
+            ]
+        );
+        d.read();
+        assert_eq!(dcg.explain(d.node()), causes);
+
+        b.write(3);
+        d.read();
+        let causes = dcg.explain(d.node());
+        assert_eq!(causes.len(), 1);
+        assert_eq!(causes[0].origin, b.node().index());
+        assert_eq!(causes[0].edges, vec![(b.node().index(), d.node().index())]);
+        assert_eq!(dcg.explain(c.node())[0].origin, a.node().index());
+    }
+
+    #[test]
+    fn audit_log_is_bounded() {
+        let dcg = Dcg::default();
+        dcg.enable_audit(true);
+        dcg.set_audit_capacity(2);
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+
+        for i in 2..6 {
+            b.read();
+            a.write(i);
+        }
+        let causes = dcg.explain(b.node());
+        assert_eq!(causes.len(), 1);
+        assert_eq!(causes[0].seq, 3);
+        assert_eq!(dcg.inner.audit.borrow().entries.len(), 2);
 
-/// Ergonomic [`Memo`] creation.
-///
-/// The first argument is the [`Dcg`] in which the [`Memo`] will be created.
-///
-/// The second argument specifies how the [`Memo`] will generate values.
-/// It can be:
-///
-/// - An [`Incremental`]'s `ident`; the [`Incremental`] is simply (`read`)[`Incremental::read`].
-/// - Of the form `params => expr` where
-///     - `params` can be
-///         - `(reads; unreads)` where `read` and `unread` are `,`-separated lists of [`Incremental`] `ident`s.
-///         - `read` - an [`Incremental`]'s `ident`.
-///         - `(reads)` where `reads` is a `,`-separated list of [`Incremental`] `ident`s.
-///     - `expr` is an expression that treats:
-///         - `read` params as if they were (`read`)[`Incremental::read`].
-///         - `unread` params as normal.
-/// - An `expr` (ideally not referencing an [`Incremental`]).
-///
-/// # Examples
-///
-/// ```
-/// use cachegrab::{Dcg, incremental::Incremental, memo};
-///
-/// let dcg = Dcg::default();
-/// let numerator = dcg.var(1);
-/// let denominator = dcg.var(1);
-/// let safe_div = memo!(dcg, (denominator; numerator) => {
-///     if denominator == 0 {
-///         None
-///     } else {
-///         Some(numerator.read() / denominator)
-///     }
-/// });
-///
-/// assert_eq!(safe_div.read(), Some(1));
-/// denominator.write(0);
-/// // numerator doesn't have to be- and isn't- executed!
-/// assert_eq!(safe_div.read(), None);
-/// ```
-#[macro_export]
-macro_rules! memo {
-    ($dcg:ident, ($($read:ident),*; $($unread:ident),*) => $f:expr) => {{
-        $crate::paste! {
-            $(let [<$read _clone>] = std::rc::Rc::clone(&$read);)*
-            $(let $unread = std::rc::Rc::clone(&$unread);)*
-            $dcg.memo(($(std::rc::Rc::clone(&$read),)* $(std::rc::Rc::clone(&$unread)),*), move || {
-                $(let $read = $crate::incremental::Incremental::read(&*[<$read _clone>]);)*
-                $f
-            })
+        dcg.enable_audit(false);
+        assert!(dcg.explain(b.node()).is_empty());
+    }
+
+    #[test]
+    fn var_history_keeps_last_values() {
+        let dcg = Dcg::default();
+        let a = dcg.var(0);
+        assert!(a.history().is_empty());
+        a.write(1);
+        a.record_history(3);
+
+        for i in 2..=6 {
+            a.write(i);
         }
-    }};
-    ($dcg:ident, $read:ident) => {
-        memo!($dcg, ($read) => $read)
-    };
-    ($dcg:ident, $read:ident => $f:expr) => {
-        memo!($dcg, ($read) => $f)
-    };
-    ($dcg:ident, ($($read:ident),*) => $f:expr) => {
-        memo!($dcg, ($($read),*;) => $f)
-    };
-    ($dcg:ident, $f:expr) => {
-        memo!($dcg, (;) => $f)
-    };
-}
+        a.modify(|a| *a + 1);
+        assert_eq!(a.history(), vec![5, 6, 7]);
 
-/// Ergonomic [`Buffer`] creation.
-///
-/// The first argument is the [`Dcg`] in which the [`Buffer`] will be created.
-///
-/// The second argument specifies how the [`Buffer`] will generate values.
-/// It can be:
-///
-/// - An [`Incremental`]'s `ident`; the [`Incremental`] is simply (`read`)[`Incremental::read`].
-/// - Of the form `params => expr` where
-///     - `params` can be
-///         - `(reads; unreads)` where `read` and `unread` are `,`-separated lists of [`Incremental`] `ident`s.
-///         - `read` - an [`Incremental`]'s `ident`.
-///         - `(reads)` where `reads` is a `,`-separated list of [`Incremental`] `ident`s.
-///     - `expr` is an expression that treats:
-///         - `read` params as if they were (`read`)[`Incremental::read`].
-///         - `unread` params as normal.
-/// - An `expr` (ideally not referencing an [`Incremental`]).
-///
-/// # Examples
-///
-/// ```
-/// use cachegrab::{Dcg, incremental::Incremental, buffer};
-///
-/// let dcg = Dcg::default();
-/// let numerator = dcg.var(1);
-/// let denominator = dcg.var(1);
-/// let safe_div = buffer!(dcg, (denominator; numerator) => {
-///     if denominator == 0 {
-///         None
-///     } else {
-///         Some(numerator.read() / denominator)
-///     }
-/// });
-///
-/// assert_eq!(safe_div.read(), Some(1));
-/// denominator.write(0);
-/// // numerator doesn't have to be- and isn't- executed!
-/// assert_eq!(safe_div.read(), None);
-/// ```
-#[macro_export]
-macro_rules! buffer {
-    ($dcg:ident, ($($read:ident),*; $($unread:ident),*) => $f:expr) => {{
-        $crate::paste! {
-            $(let [<$read _clone>] = std::rc::Rc::clone(&$read);)*
-            $(let $unread = std::rc::Rc::clone(&$unread);)*
-            $dcg.buffer(&($(std::rc::Rc::clone(&$read),)* $(std::rc::Rc::clone(&$unread)),*), move || {
-                $(let $read = $crate::incremental::Incremental::read(&*[<$read _clone>]);)*
-                $f
-            })
+        a.record_history(2);
+        assert_eq!(a.history(), vec![6, 7]);
+        a.record_history(0);
+        a.write(8);
+        assert!(a.history().is_empty());
+    }
+
+    #[test]
+    fn computed_history_records_evaluations() {
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let t = thunk!(dcg, a => a + 1);
+        let m = memo!(dcg, a => a * 2);
+        let b = buffer!(dcg, a => a * 3);
+        t.record_history(4);
+        m.record_history(4);
+        b.record_history(4);
+
+        for value in [1, 2, 1] {
+            a.write(value);
+            t.read();
+            m.read();
+            b.read();
+            b.read();
+        }
+        assert_eq!(t.history(), vec![2, 3, 2]);
+        assert_eq!(m.history(), vec![2, 4]);
+        assert_eq!(b.history(), vec![3, 6, 3]);
+    }
+
+    #[test]
+    fn sync_dcg_readers_race_writer() {
+        use crate::sync::SyncDcg;
+        use std::sync::Barrier;
+
+        let dcg = SyncDcg::new();
+        let a = dcg.var(0u64);
+        let doubled = dcg.buffer(&[a.node()], {
+            let a = a.clone();
+            move || a.read() * 2
+        });
+        let plus_one = dcg.thunk(&[doubled.node()], {
+            let doubled = doubled.clone();
+            move || doubled.read() + 1
+        });
+        let barrier = Barrier::new(5);
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    barrier.wait();
+                    let mut last = 0;
+                    while last < 2000 {
+                        let value = doubled.read();
+                        assert_eq!(value % 2, 0);
+                        assert!(value >= last, "{} read after {}", value, last);
+                        assert_eq!(plus_one.read() % 2, 1);
+                        last = value;
+                    }
+                });
+            }
+            barrier.wait();
+            for i in 1..=1000 {
+                a.write(i);
+            }
+        });
+
+        assert_eq!(doubled.read(), 2000);
+        assert!(!doubled.node().is_dirty());
+        assert_eq!(a.modify(|a| a + 1), 1000);
+        assert!(doubled.node().is_dirty() && plus_one.node().is_dirty());
+        assert_eq!(plus_one.read(), 2003);
+    }
+
+    #[test]
+    fn sync_dcg_disjoint_writes_keep_caches() {
+        use crate::sync::SyncDcg;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let dcg = SyncDcg::new();
+        let shared = dcg.var(1);
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                let dcg = &dcg;
+                let shared = &shared;
+                scope.spawn(move || {
+                    let own = dcg.var(0);
+                    let computed = Arc::new(AtomicUsize::new(0));
+                    let sum = dcg.buffer(&[own.node(), shared.node()], {
+                        let (own, shared, computed) =
+                            (own.clone(), (*shared).clone(), computed.clone());
+                        move || {
+                            computed.fetch_add(1, Ordering::SeqCst);
+                            own.read() + shared.read()
+                        }
+                    });
+                    for i in 1..=500 {
+                        own.write(i);
+                        assert_eq!(sum.read(), i + 1);
+                        assert_eq!(sum.read(), i + 1);
+                    }
+                    // Other threads' writes never invalidate this thread's buffer.
+                    assert_eq!(computed.load(Ordering::SeqCst), 500);
+                });
+            }
+        });
+        assert_eq!(dcg.node_count(), 9);
+    }
+
+    #[test]
+    fn sync_dcg_front_frames_are_consistent() {
+        use crate::sync::SyncDcg;
+
+        let dcg = SyncDcg::new();
+        let tick = dcg.var(0u64);
+        let doubled = dcg.buffer(&[tick.node()], {
+            let tick = tick.clone();
+            move || tick.read() * 2
+        });
+        let label = dcg.thunk(&[doubled.node()], {
+            let doubled = doubled.clone();
+            move || format!("frame {}", doubled.read() / 2)
+        });
+        assert_eq!(dcg.front().number(), 0);
+        assert_eq!(dcg.front().get::<u64>(tick.node()), None);
+
+        thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| {
+                    let mut last = 0;
+                    while last < 500 {
+                        let frame = dcg.front();
+                        if frame.number() == 0 {
+                            continue;
+                        }
+                        let tick = frame.get::<u64>(tick.node()).unwrap();
+                        assert_eq!(frame.number(), tick);
+                        assert_eq!(frame.get::<u64>(doubled.node()), Some(tick * 2));
+                        assert_eq!(frame.get(label.node()), Some(format!("frame {}", tick)));
+                        assert_eq!(frame.get::<i32>(doubled.node()), None);
+                        assert!(tick >= last);
+                        last = tick;
+                    }
+                });
+            }
+            for i in 1..=500 {
+                tick.write(i);
+                assert_eq!(dcg.publish(&[&*tick, &*doubled, &*label]), i);
+            }
+        });
+    }
+
+    #[test]
+    fn sync_publish_excludes_commits() {
+        use crate::sync::SyncDcg;
+
+        let dcg = SyncDcg::new();
+        let a = dcg.var(0u64);
+        let b = dcg.var(0u64);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 1..=500 {
+                    dcg.commit(&[(&a, i), (&b, i)]);
+                }
+            });
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    let mut last = (0, 0);
+                    for _ in 0..500 {
+                        dcg.publish(&[&*a, &*b]);
+                        let frame = dcg.front();
+                        let value = frame.get::<u64>(a.node()).unwrap();
+                        assert_eq!(frame.get::<u64>(b.node()), Some(value));
+                        assert!(frame.number() > last.0 && value >= last.1);
+                        last = (frame.number(), value);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn sync_refresh_all_resumes_after_cancellation() {
+        use crate::sync::{CancellationToken, Cancelled, SyncBuffer, SyncDcg};
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let dcg = SyncDcg::new();
+        let a = dcg.var(0);
+        let token = CancellationToken::new();
+        let computed = Arc::new(AtomicUsize::new(0));
+        let mut chain: Vec<SyncBuffer<i32>> = vec![];
+        for i in 0..5 {
+            let dependency = chain.last().map_or(a.node(), |previous| previous.node());
+            let previous = chain.last().cloned();
+            let (a, token, computed) = (a.clone(), token.clone(), computed.clone());
+            let buffer = dcg.buffer(&[dependency], move || {
+                computed.fetch_add(1, Ordering::SeqCst);
+                if i == 2 {
+                    token.cancel();
+                }
+                previous
+                    .as_ref()
+                    .map_or_else(|| a.read(), |previous| previous.read())
+                    + 1
+            });
+            chain.push(buffer);
         }
-    }};
-    ($dcg:ident, $read:ident) => {
-        buffer!($dcg, ($read) => $read)
-    };
-    ($dcg:ident, $read:ident => $f:expr) => {
-        buffer!($dcg, ($read) => $f)
-    };
-    ($dcg:ident, ($($read:ident),*) => $f:expr) => {
-        buffer!($dcg, ($($read),*;) => $f)
-    };
-    ($dcg:ident, $f:expr) => {
-        buffer!($dcg, (;) => $f)
-    };
-}
 
-#[cfg(test)]
-mod tests {
-    use std::cell;
+        assert_eq!(
+            dcg.refresh_all_with(&token),
+            Err(Cancelled { remaining: 2 })
+        );
+        assert_eq!(computed.load(Ordering::SeqCst), 3);
+        let dirty: Vec<_> = chain.iter().map(|b| b.node().is_dirty()).collect();
+        assert_eq!(dirty, [false, false, false, true, true]);
 
-    use cell::Cell;
+        assert_eq!(dcg.refresh_all_with(&CancellationToken::new()), Ok(()));
+        assert_eq!(computed.load(Ordering::SeqCst), 5);
+        assert_eq!(chain[4].last_computed(), Some(5));
 
-    use super::*;
+        a.write(10);
+        dcg.refresh_all();
+        assert_eq!(computed.load(Ordering::SeqCst), 10);
+        assert_eq!(chain[4].read(), 15);
+    }
 
     #[test]
-    fn create_var() {
-        let dcg = Dcg::default();
+    fn sync_refresh_all_runs_shallow_nodes_first() {
+        use crate::sync::{CancellationToken, SyncDcg};
+        use std::sync::{Arc, Mutex};
 
+        let dcg = SyncDcg::new();
         let a = dcg.var(1);
+        let token = CancellationToken::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let logged = |name: &'static str, value: i32| {
+            let (log, token) = (log.clone(), token.clone());
+            move || {
+                log.lock().unwrap().push(name);
+                if name == "deadline" {
+                    token.cancel();
+                }
+                value
+            }
+        };
+        // Created before the shallow nodes, so creation order alone would run it early.
+        let mid = dcg.buffer(&[a.node()], logged("mid", 1));
+        let expensive = dcg.buffer(&[mid.node()], logged("expensive", 2));
+        let cheap = dcg.buffer(&[a.node()], logged("cheap", 3));
+        let deadline = dcg.buffer(&[a.node()], logged("deadline", 4));
+        dcg.set_priority(cheap.node(), 1);
+        assert_eq!(
+            [&mid, &expensive, &cheap, &deadline].map(|b| dcg.height(b.node())),
+            [1, 2, 1, 1]
+        );
 
-        assert_eq!(dcg.graph.borrow().node_count(), 1);
-        assert!(a.is_dirty());
+        assert!(dcg.refresh_all_with(&token).is_err());
+        assert_eq!(*log.lock().unwrap(), ["cheap", "mid", "deadline"]);
+        assert!(expensive.node().is_dirty());
+
+        dcg.refresh_all();
+        assert_eq!(log.lock().unwrap().last(), Some(&"expensive"));
     }
 
     #[test]
-    fn create_thunk() {
-        let dcg = Dcg::default();
+    fn sync_commit_is_never_torn() {
+        use crate::sync::SyncDcg;
 
-        let t = thunk!(dcg, 1);
+        let dcg = SyncDcg::new();
+        let (a, b) = (dcg.var(0), dcg.var(0));
+        let difference = dcg.buffer(&[a.node(), b.node()], {
+            let (a, b) = (a.clone(), b.clone());
+            move || a.read() - b.read()
+        });
 
-        assert_eq!(dcg.graph.borrow().node_count(), 1);
-        assert!(t.is_dirty());
+        thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| {
+                    let mut last = 0;
+                    while last < 1000 {
+                        let (x, y, d) =
+                            dcg.read_consistent(|| (a.read(), b.read(), difference.read()));
+                        assert_eq!((x, d), (y, 0));
+                        last = x;
+                    }
+                });
+            }
+            for i in 1..=1000 {
+                assert_eq!(dcg.commit(&[(&a, i), (&b, i)]), 2);
+            }
+        });
+        assert_eq!(dcg.commit(&[(&a, 1000), (&b, 0)]), 1);
+        assert_eq!(difference.read(), 1000);
     }
 
     #[test]
-    fn create_memo() {
-        let dcg = Dcg::default();
+    fn sync_worker_refreshes_in_background() {
+        use crate::sync::SyncDcg;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
 
-        let m = memo!(dcg, 1);
+        let dcg = SyncDcg::new();
+        let a = dcg.var(1);
+        let computed = Arc::new(AtomicUsize::new(0));
+        let tens = dcg.buffer(&[a.node()], {
+            let (a, computed) = (a.clone(), computed.clone());
+            move || {
+                computed.fetch_add(1, Ordering::SeqCst);
+                a.read() * 10
+            }
+        });
+        let hundreds = dcg.buffer(&[tens.node()], {
+            let tens = tens.clone();
+            move || tens.read() * 10
+        });
+        assert_eq!(hundreds.read(), 100);
 
-        assert_eq!(dcg.graph.borrow().node_count(), 1);
-        assert!(m.is_dirty());
+        let worker = dcg.spawn_worker();
+        worker.flush();
+        a.write(2);
+        assert!(matches!(tens.last_computed(), Some(10 | 20)));
+        worker.flush();
+        assert_eq!(tens.last_computed(), Some(20));
+        assert_eq!(hundreds.last_computed(), Some(200));
+        assert!(!hundreds.node().is_dirty());
+        assert_eq!(computed.load(Ordering::SeqCst), 2);
+
+        drop(worker);
+        a.write(3);
+        assert_eq!(hundreds.last_computed(), Some(200));
+        assert_eq!(hundreds.read(), 300);
     }
 
     #[test]
-    fn create_buffer() {
+    fn graph_accessors() {
         let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        let c = buffer!(dcg, (a, b) => a + b);
+        assert_eq!((dcg.node_count(), dcg.edge_count()), (3, 2));
+        assert!(dcg.contains_edge(b.node(), c.node()));
+        assert!(!dcg.contains_edge(a.node(), b.node()));
+        assert!(dcg.with_graph(|graph| graph[c.node().index()]));
+        assert_eq!(
+            dcg.with_graph(|graph| (graph.node_count(), graph.edge_count())),
+            (dcg.node_count(), dcg.edge_count())
+        );
+        let raw_edges = dcg.with_graph(|graph| {
+            graph
+                .edge_indices()
+                .map(|edge| graph.edge_endpoints(edge).unwrap())
+                .collect::<Vec<_>>()
+        });
+        let nodes = [a.node(), b.node(), c.node()];
+        for (from, to) in raw_edges {
+            assert!(dcg.contains_edge(nodes[from.index()], nodes[to.index()]));
+        }
+        // Nothing is borrowed across calls, so writes and reads afterwards don't panic.
+        a.write(3);
+        assert_eq!(c.read(), 5);
 
-        let b = buffer!(dcg, 1);
+        c.read();
+        dcg.set_debug_checks(false);
+        dcg.with_graph_mut(|graph| graph[a.node().index()] = true);
+        assert_eq!(
+            dcg.check_invariants(),
+            Err(vec![
+                "node 2 is clean but its dependency 0 is dirty".to_owned()
+            ])
+        );
+    }
 
-        assert_eq!(dcg.graph.borrow().node_count(), 1);
-        assert!(b.is_dirty());
+    #[test]
+    fn graph_ref_runs_petgraph_algorithms() {
+        use petgraph::algo::has_path_connecting;
+
+        let dcg = Dcg::default();
+        let a = dcg.var(1);
+        let b = dcg.var(2);
+        let c = buffer!(dcg, a => a + 1);
+        let d = buffer!(dcg, (c, a) => c + a);
+        let _e = dcg.var(3);
+        let f = buffer!(dcg, b => b * 2);
+        d.read();
+        f.read();
+
+        let graph = dcg.with_graph(|graph| graph.clone());
+        let graph_ref = dcg.as_graph_ref();
+        assert_eq!(
+            toposort(&graph_ref, None).unwrap(),
+            toposort(&graph, None).unwrap()
+        );
+        let (a, b, d) = (a.node().index(), b.node().index(), d.node().index());
+        assert!(has_path_connecting(&graph_ref, a, d, None));
+        assert!(!has_path_connecting(&graph_ref, b, d, None));
+        assert_eq!(
+            has_path_connecting(&graph_ref, b, f.node().index(), None),
+            has_path_connecting(&graph, b, f.node().index(), None)
+        );
     }
 
     #[test]
-    fn var_read() {
+    fn on_dirtied_fires_on_clean_to_dirty_transitions() {
         let dcg = Dcg::default();
         let a = dcg.var(1);
+        let b = dcg.var(2);
+        let c = buffer!(dcg, (a, b) => a + b);
+        let sources = Rc::new(RefCell::new(Vec::new()));
+        let sources_clone = sources.clone();
+        dcg.on_dirtied(c.node(), move |source| {
+            sources_clone.borrow_mut().push(source)
+        });
+
+        c.read();
+        b.write(3);
+        a.write(2);
+        assert_eq!(*sources.borrow(), vec![b.node().index()]);
 
-        assert_eq!(a.read(), 1);
+        c.read();
+        a.write(3);
+        assert_eq!(*sources.borrow(), vec![b.node().index(), a.node().index()]);
     }
 
     #[test]
-    fn thunk_read() {
+    fn read_with_budget_does_not_unwind_through_closures() {
         let dcg = Dcg::default();
-        let t = thunk!(dcg, 1);
+        let a = dcg.var(1);
+        let b = buffer!(dcg, a => a + 1);
+        let caught = Rc::new(Cell::new(false));
+        let c = {
+            let (b, caught) = (b.clone(), caught.clone());
+            dcg.buffer(&b.clone(), move || {
+                match panic::catch_unwind(AssertUnwindSafe(|| b.read())) {
+                    Ok(b) => b * 2,
+                    Err(_) => {
+                        caught.set(true);
+                        0
+                    }
+                }
+            })
+        };
 
-        assert_eq!(t.read(), 1);
+        let exceeded = dcg.read_with_budget(&c, 1).unwrap_err();
+
+        assert_eq!(exceeded.stopped_at, c.node().index());
+        assert!(!caught.get());
+        assert!(c.is_dirty());
+        assert_eq!(dcg.read_with_budget(&c, 1), Ok(4));
     }
 
     #[test]
-    fn memo_read() {
+    #[should_panic(expected = "not a budget")]
+    fn read_with_budget_propagates_other_panics() {
         let dcg = Dcg::default();
-        let m = memo!(dcg, 1);
+        let t = thunk!(dcg, panic!("not a budget"));
 
-        assert_eq!(m.read(), 1);
+        let _ = dcg.read_with_budget(&t, 10);
     }
 
     #[test]
-    fn buffer_read() {
+    fn costs_sorted_most_expensive_first() {
         let dcg = Dcg::default();
-        let b = buffer!(dcg, 1);
+        let a = dcg.var(2);
+        let t = thunk!(dcg, a => a * 10);
+        let m = memo!(dcg, a => a);
+        let b = buffer!(dcg, a => a + 1);
+        t.set_cost(|value| *value as u64);
+        m.set_cost(|value| *value as u64);
 
-        assert_eq!(b.read(), 1);
+        t.read();
+        m.read();
+        b.read();
+        a.write(3);
+        t.read();
+        m.read();
+        a.write(2);
+        m.read();
+        assert_eq!(
+            dcg.costs(),
+            vec![(t.node().index(), 50, 2), (m.node().index(), 5, 2)]
+        );
+
+        b.set_cost(|_| 100);
+        b.read();
+        assert_eq!(dcg.costs()[0], (b.node().index(), 100, 1));
     }
 
     #[test]
-    fn var_write() {
+    fn to_dot_diamond() {
         let dcg = Dcg::default();
-        let a = dcg.var(1);
-        let b1 = buffer!(dcg, a);
-        let b2 = buffer!(dcg, b1);
-        let b3 = buffer!(dcg, a);
-        let b4 = buffer!(dcg, b3);
-        b2.read();
-        b4.read();
-        dcg.graph.borrow_mut()[b3.thunk.node.idx] = true;
+        let a = dcg.var("a".to_owned());
+        let b = buffer!(dcg, a => a.len());
+        let c = thunk!(dcg, a => a.to_uppercase());
+        let d = memo!(dcg, (b, c) => c.repeat(b));
+        a.show_value();
+        b.show_value();
+        d.show_value();
 
-        //   b1 --> b2           (b1) --> (b2)
-        //  /               -->  /
-        // a --> (b3) --> b4   (a) --> (b3) --> b4
-        assert_eq!(a.write(2), 1);
+        d.read();
+        assert_eq!(
+            dcg.to_dot(),
+            r#"digraph {
+    rankdir=TB;
+    0 [label="0\n\"a\"", shape=box];
+    1 [label="1\n1", shape=ellipse, peripheries=2];
+    2 [label="2", shape=ellipse];
+    3 [label="3\n1 cached", shape=ellipse, peripheries=2];
+    0 -> 1;
+    0 -> 2;
+    1 -> 3;
+    2 -> 3;
+}
+"#
+        );
 
-        assert!(a.is_dirty());
-        assert!(b1.is_dirty());
-        assert!(b2.is_dirty());
-        assert!(b3.is_dirty());
-        assert!(b4.is_clean());
-        assert_eq!(a.read(), 2);
+        a.write("abcdefgh".to_owned());
+        let options = DotOptions {
+            max_value_len: 4,
+            rankdir: RankDir::LeftToRight,
+            ..DotOptions::default()
+        };
+        assert_eq!(
+            dcg.to_dot_with(options),
+            r#"digraph {
+    rankdir=LR;
+    0 [label="0\n\"abc…", shape=box];
+    1 [label="1\n1", shape=ellipse, peripheries=2];
+    2 [label="2", shape=ellipse];
+    3 [label="3\n1 ca…", shape=ellipse, peripheries=2];
+    0 -> 1 [color=red, style=dashed];
+    0 -> 2 [color=red, style=dashed];
+    1 -> 3 [color=red, style=dashed];
+    2 -> 3 [color=red, style=dashed];
+}
+"#
+        );
+
+        let options = DotOptions {
+            values: false,
+            ..DotOptions::default()
+        };
+        assert!(dcg.to_dot_with(options).contains("1 [label=\"1\", "));
     }
 
     #[test]
-    fn var_modify() {
+    fn to_csv_escapes_fields() {
+        /// Splits a CSV line into its fields, unquoting them.
+        fn split(line: &str) -> Vec<String> {
+            let mut fields = vec![String::new()];
+            let mut quoted = false;
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if quoted && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        fields.last_mut().unwrap().push('"');
+                    }
+                    '"' => quoted = !quoted,
+                    ',' if !quoted => fields.push(String::new()),
+                    c => fields.last_mut().unwrap().push(c),
+                }
+            }
+            fields
+        }
+
         let dcg = Dcg::default();
-        let a = dcg.var(1);
-        let b1 = buffer!(dcg, a);
-        let b2 = buffer!(dcg, b1);
-        let b3 = buffer!(dcg, a);
-        let b4 = buffer!(dcg, b3);
-        b2.read();
-        b4.read();
-        dcg.graph.borrow_mut()[b3.thunk.node.idx] = true;
+        let a = dcg.var("x, \"y\"".to_owned());
+        let b = buffer!(dcg, a => a.len());
+        let c = thunk!(dcg, a => a.to_uppercase());
+        let d = memo!(dcg, b => b * 2);
+        a.node().set_name("a,1");
+        a.show_value();
+        b.show_value();
+        a.write("z".to_owned());
+        b.read();
 
-        //   b1 --> b2           (b1) --> (b2)
-        //  /               --> /
-        // a --> (b3) --> b4   (a) --> (b3) --> b4
-        assert_eq!(a.modify(|x| *x + 1), 1);
+        let csv = dcg.to_csv();
+        let rows: Vec<_> = csv.lines().map(split).collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(
+            rows[0],
+            ["index", "kind", "name", "dirty", "cached", "value"]
+        );
+        assert_eq!(rows[1], ["0", "Var", "a,1", "false", "", "\"z\""]);
+        assert_eq!(rows[2], ["1", "Buffer", "", "false", "true", "1"]);
+        assert_eq!(rows[3][..5], ["2", "Thunk", "", "true", ""]);
+        assert_eq!(rows[4][..5], ["3", "Memo", "", "true", "false"]);
 
-        assert!(a.is_dirty());
-        assert!(b1.is_dirty());
-        assert!(b2.is_dirty());
-        assert!(b3.is_dirty());
-        assert!(b4.is_clean());
-        assert_eq!(a.read(), 2);
+        a.write("x, \"y\"".to_owned());
+        let mut written = Vec::new();
+        dcg.write_csv(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert_eq!(
+            written.lines().nth(1).unwrap(),
+            r#"0,Var,"a,1",true,,"""x, \""y\""""""#
+        );
+        drop((c, d));
     }
 
     #[test]
-    fn thunk_read_cleans() {
+    fn labelled_edges_render_and_propagate() {
         let dcg = Dcg::default();
-        let a = dcg.var(1);
-        let b = dcg.var(1);
-        let t1 = thunk!(dcg, a);
-        let t2 = thunk!(dcg, b);
-        let t3 = thunk!(dcg, (t1, t2) => t1 + t2);
-        dcg.graph.borrow_mut()[t1.node.idx] = false;
+        let a = dcg.var(5);
+        let b = dcg.var(3);
+        let difference = buffer!(dcg, (a, b) => a - b);
+        let flag = dcg.var(true);
+        let choice = dcg.dynamic_buffer({
+            let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+            move || if flag.read() { a.read() } else { b.read() }
+        });
+        assert!(dcg.label_edge(a.node(), difference.node(), "minuend"));
+        assert!(dcg.label_edge(b.node(), difference.node(), "subtrahend \"b\""));
+        assert!(!dcg.label_edge(difference.node(), a.node(), "backwards"));
 
-        //        (a) --> t1            a --> t1
-        //                   \  -->             \
-        // (b) --> (t2) --> (t3)    b --> t2 --> t3
-        t3.read();
+        assert_eq!((difference.read(), choice.read()), (2, 5));
+        assert!(dcg.label_edge(a.node(), choice.node(), "chosen"));
+        b.write(1);
+        assert!(difference.is_dirty() && choice.is_clean());
+        assert_eq!(
+            dcg.to_dot(),
+            r#"digraph {
+    rankdir=TB;
+    0 [label="0", shape=box];
+    1 [label="1", shape=box];
+    2 [label="2", shape=ellipse, peripheries=2];
+    3 [label="3", shape=box];
+    4 [label="4", shape=ellipse, peripheries=2];
+    0 -> 2 [label="minuend", color=red, style=dashed];
+    1 -> 2 [label="subtrahend \"b\"", color=red, style=dashed];
+    0 -> 4 [label="chosen"];
+    3 -> 4;
+}
+"#
+        );
+        assert_eq!(difference.read(), 4);
 
-        assert!(a.is_clean());
-        assert!(b.is_clean());
-        assert!(t1.is_clean());
-        assert!(t2.is_clean());
-        assert!(t3.is_clean());
+        flag.write(false);
+        assert_eq!(choice.read(), 1);
+        assert_eq!(dcg.edge_label(a.node(), choice.node()), None);
+        assert!(dcg
+            .to_mermaid()
+            .contains("n0 -->|\"minuend\"| n2\n    n1 -->|\"subtrahend #quot;b#quot;\"| n2\n"));
+        assert_eq!(
+            dcg.edges_to_csv(),
+            "from,to,dirty,weak,label\n0,2,false,false,minuend\n1,2,false,false,\"subtrahend \"\"b\"\"\"\n1,4,false,false,\n3,4,false,false,\n"
+        );
     }
 
     #[test]
-    fn memo_read_cleans() {
+    fn to_dot_around_trims_beyond_hop_limits() {
         let dcg = Dcg::default();
         let a = dcg.var(1);
-        let b = dcg.var(1);
-        let m1 = memo!(dcg, a);
-        let m2 = memo!(dcg, b);
-        let m3 = memo!(dcg, (m1, m2) => m1 + m2);
-        dcg.graph.borrow_mut()[m1.thunk.node.idx] = false;
+        let b = buffer!(dcg, a => a + 1);
+        let c = buffer!(dcg, b => b + 1);
+        let d = buffer!(dcg, c => c + 1);
+        let e = buffer!(dcg, d => d + 1);
+        let side = buffer!(dcg, c => c * 2);
+        assert_eq!((e.read(), side.read()), (5, 6));
 
-        //        (a) --> m1            a --> m1
-        //                   \  -->             \
-        // (b) --> (m2) --> (m3)    b --> m2 --> m3
-        m3.read();
+        let included = |dot: &str| {
+            let mut nodes: Vec<usize> = dot
+                .lines()
+                .filter(|line| line.contains("[label=") && !line.contains("trimmed_"))
+                .map(|line| line.trim().split(' ').next().unwrap().parse().unwrap())
+                .collect();
+            nodes.sort();
+            nodes
+        };
+        let dot = dcg.to_dot_around(c.node(), 1, 1);
+        assert_eq!(included(&dot), vec![1, 2, 3, 5]);
+        assert!(dot.contains("    1 [label=\"1\", shape=ellipse, peripheries=2, style=dotted];"));
+        assert!(dot.contains("    trimmed_above_1 [label=\"…\", shape=plaintext];"));
+        assert!(dot.contains("    trimmed_above_1 -> 1 [style=dotted];"));
+        assert!(dot.contains("    3 -> trimmed_below_3 [style=dotted];"));
+        assert!(dot.contains("    2 [label=\"2\", shape=ellipse, peripheries=2];"));
+        assert!(dot.contains("    5 [label=\"5\", shape=ellipse, peripheries=2];"));
+        assert!(!dot.contains("trimmed_above_2") && !dot.contains("trimmed_below_5"));
+        assert!(dot.contains("    2 -> 3;") && !dot.contains("3 -> 4;"));
 
-        assert!(a.is_clean());
-        assert!(b.is_clean());
-        assert!(m1.is_clean());
-        assert!(m2.is_clean());
-        assert!(m3.is_clean());
+        assert_eq!(included(&dcg.to_dot_around(c.node(), 0, 0)), vec![2]);
+        assert_eq!(
+            included(&dcg.to_dot_around(c.node(), 9, 9)),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert!(!dcg.to_dot_around(c.node(), 9, 9).contains("trimmed_"));
+
+        let dot = dcg.to_dot_around_with(c.node(), 9, 9, DotOptions::default(), |idx| {
+            idx != d.node().index()
+        });
+        assert_eq!(included(&dot), vec![0, 1, 2, 5]);
+        assert!(dot.contains("    2 -> trimmed_below_2 [style=dotted];"));
+        assert!(!dot.contains("trimmed_above"));
     }
 
     #[test]
-    fn buffer_read_cleans() {
+    fn to_mermaid_diamond() {
         let dcg = Dcg::default();
         let a = dcg.var(1);
-        let b = dcg.var(1);
-        let b1 = buffer!(dcg, a);
-        let b2 = buffer!(dcg, b);
-        let b3 = buffer!(dcg, (b1, b2) => b1 + b2);
-        // we ensure b1 contains Some(value) to avoid unwrapping a None
-        b1.read();
+        let b = buffer!(dcg, a => a + 1);
+        let c = thunk!(dcg, a => a * 2);
+        let d = memo!(dcg, (b, c) => b + c);
+        a.show_value();
+        d.show_value();
+
+        d.read();
         a.write(2);
-        dcg.graph.borrow_mut()[b1.thunk.node.idx] = false;
+        d.read();
+        c.read();
+        a.write(3);
+        assert_eq!(
+            dcg.to_mermaid(),
+            r#"graph TD
+    n0["0<br/>3"]
+    n1((("1")))
+    n2(["2"])
+    n3((("3<br/>2 cached")))
+    n0 --> n1
+    n0 --> n2
+    n1 --> n3
+    n2 --> n3
+    linkStyle 0,1,2,3 stroke:red,stroke-dasharray:5 5
+"#
+        );
+    }
 
-        //        (a) --> b1           (a) --> b1
-        //                   \  -->             \
-        // (b) --> (b2) --> (b3)   b --> b2 --> b3
-        b3.read();
+    #[test]
+    fn to_mermaid_escapes_values() {
+        let dcg = Dcg::default();
+        let a = dcg.var("say \"<hi>\" #1".to_owned());
+        a.show_value();
 
-        assert!(a.is_dirty());
-        assert!(b.is_clean());
-        assert!(b1.is_clean());
-        assert!(b2.is_clean());
-        assert!(b3.is_clean());
+        assert_eq!(
+            dcg.to_mermaid(),
+            r#"graph TD
+    n0["0<br/>#quot;say \#quot;#lt;hi#gt;\#quot; #35;1#quot;"]
+"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    mod persistence {
+        use super::*;
+        use crate::persist::{ClosureRegistry, HydrateError, SavedDcg, SnapshotPolicy};
+
+        fn registry() -> ClosureRegistry {
+            let mut registry = ClosureRegistry::new();
+            registry.register("a", |h| {
+                let a = h.dcg().var(0);
+                a.persist();
+                Ok(a)
+            });
+            registry.register("b", |h| {
+                let b = h.dcg().var(h.value::<i32>()?);
+                b.persist();
+                Ok(b)
+            });
+            registry.register("total", |h| {
+                let dcg = h.dcg();
+                let a: Var<i32> = h.dependency("a")?;
+                let b: Var<i32> = h.dependency("b")?;
+                let total = buffer!(dcg, (a, b) => a + b);
+                total.persist();
+                Ok(total)
+            });
+            registry.register("tens", |h| {
+                let dcg = h.dcg();
+                let a: Var<i32> = h.dependency("a")?;
+                let tens = memo!(dcg, a => a * 10);
+                tens.persist();
+                Ok(tens)
+            });
+            registry
+        }
+
+        fn saved() -> SavedDcg {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let total = buffer!(dcg, (a, b) => a + b);
+            let tens = memo!(dcg, a => a * 10);
+            for (node, name) in [
+                (a.node(), "a"),
+                (b.node(), "b"),
+                (total.node(), "total"),
+                (tens.node(), "tens"),
+            ] {
+                node.set_name(name);
+            }
+            a.persist();
+            b.persist();
+            total.persist();
+            tens.persist();
+
+            tens.read();
+            a.write(2);
+            tens.read();
+            total.read();
+            a.write(3);
+            dcg.to_saved()
+        }
+
+        #[test]
+        fn hydrate_round_trips() {
+            let saved = saved();
+            let json = serde_json::to_string(&saved).unwrap();
+            let saved: SavedDcg = serde_json::from_str(&json).unwrap();
+
+            let (dcg, handles) = Dcg::hydrate(saved.clone(), &registry()).unwrap();
+            assert_eq!(dcg.to_saved(), saved);
+            assert_eq!(handles.index("tens"), Some(NodeIndex::new(3)));
+
+            let a: Var<i32> = handles.get("a").unwrap();
+            let total: Buffer<i32> = handles.get("total").unwrap();
+            let tens: Memo<(i32,), i32> = handles.get("tens").unwrap();
+            assert!(total.is_dirty() && tens.is_dirty());
+            assert_eq!(*total.buffered.borrow(), Some(4));
+            assert_eq!(total.read(), 5);
+            a.write(2);
+            assert_eq!(tens.read(), 20);
+            assert_eq!(dcg.cache_stats(tens.node()).hits, 1);
+        }
+
+        #[test]
+        fn export_closure_hydrates_ancestry() {
+            let dcg = Dcg::default();
+            let x = dcg.var(5);
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let total = buffer!(dcg, (a, b) => a + b);
+            let tens = memo!(dcg, a => a * 10);
+            let doubled = buffer!(dcg, total => total * 2);
+            let shifted = thunk!(dcg, x => x + 1);
+            for (node, name) in [
+                (x.node(), "x"),
+                (a.node(), "a"),
+                (b.node(), "b"),
+                (total.node(), "total"),
+                (tens.node(), "tens"),
+            ] {
+                node.set_name(name);
+            }
+            a.persist();
+            b.persist();
+            total.persist();
+            assert_eq!(total.read(), 3);
+            a.write(4);
+
+            let saved = dcg.export_closure(total.node());
+            assert_eq!(saved.target, Some(2));
+            assert_eq!(saved.edges, vec![(0, 2), (1, 2)]);
+            let names: Vec<_> = saved.nodes.iter().map(|n| n.name.as_deref()).collect();
+            assert_eq!(names, [Some("a"), Some("b"), Some("total")]);
+            assert_eq!(saved.nodes[0].value, Some(4.into()));
+
+            let mut registry = ClosureRegistry::new();
+            registry.register_all(["a", "b"], |h| {
+                let var = h.dcg().var(h.value::<i32>()?);
+                var.persist();
+                Ok(var)
+            });
+            registry.register("total", |h| {
+                let dcg = h.dcg();
+                let a: Var<i32> = h.dependency("a")?;
+                let b: Var<i32> = h.dependency("b")?;
+                let total = buffer!(dcg, (a, b) => a + b);
+                total.persist();
+                Ok(total)
+            });
+            let (hydrated, handles) = Dcg::hydrate(saved.clone(), &registry).unwrap();
+            assert_eq!(hydrated.to_saved().nodes, saved.nodes);
+            assert_eq!(hydrated.to_saved().edges, saved.edges);
+            let total: Buffer<i32> = handles.get("total").unwrap();
+            assert_eq!(total.read(), 6);
+            drop((tens, doubled, shifted));
+        }
+
+        #[test]
+        fn hydrate_reports_missing_closure() {
+            let mut registry = registry();
+            registry.register("tens", |h| Ok(h.dcg().var(0)));
+            assert_eq!(
+                Dcg::hydrate(saved(), &registry).err(),
+                Some(HydrateError::Mismatch {
+                    name: "tens".to_owned()
+                })
+            );
+
+            let mut saved = saved();
+            saved.nodes[2].name = Some("sum".to_owned());
+            assert_eq!(
+                Dcg::hydrate(saved, &registry).err(),
+                Some(HydrateError::MissingClosure {
+                    name: "sum".to_owned()
+                })
+            );
+        }
+
+        #[test]
+        fn registry_reports_unknown_and_unused_names() {
+            let mut registry = registry();
+            registry.register_all(["x", "y"], |h| Ok(h.dcg().var(h.name().len())));
+            let mut saved = saved();
+            saved.nodes[3].name = Some("hundreds".to_owned());
+
+            assert_eq!(registry.unknown(&saved), vec!["hundreds".to_owned()]);
+            assert_eq!(
+                registry.unused(&saved),
+                vec!["tens".to_owned(), "x".to_owned(), "y".to_owned()]
+            );
+        }
+
+        #[test]
+        fn values_json_policies() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let doubled = memo!(dcg, a => a * 2);
+            let tripled = memo!(dcg, a => a * 3);
+            a.node().set_name("a");
+            doubled.node().set_name("doubled");
+            a.persist();
+            doubled.persist();
+            tripled.persist();
+
+            assert_eq!(
+                dcg.values_json(SnapshotPolicy::CachedOnly),
+                r#"{"2":null,"a":1,"doubled":null}"#
+            );
+            doubled.read();
+            tripled.read();
+            a.write(2);
+            doubled.read();
+            assert_eq!(
+                dcg.values_json(SnapshotPolicy::CachedOnly),
+                r#"{"2":null,"a":2,"doubled":4}"#
+            );
+            assert_eq!(
+                dcg.values_json(SnapshotPolicy::Evaluate),
+                r#"{"2":6,"a":2,"doubled":4}"#
+            );
+            assert!(tripled.is_clean());
+        }
+
+        #[test]
+        fn evaluate_to_graph_keeps_topology() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let sum = buffer!(dcg, (a, b) => a + b);
+            let labels = memo!(dcg, (a, sum) => format!("{}/{}", a, sum));
+            let probe = dcg.probe(&labels);
+            a.persist();
+            sum.persist();
+            labels.persist();
+
+            labels.read();
+            a.write(5);
+            let values = dcg.evaluate_to_graph();
+            assert_eq!(values.node_count(), 4);
+            assert_eq!(
+                values
+                    .node_indices()
+                    .map(|idx| values[idx].clone())
+                    .collect::<Vec<_>>(),
+                vec![Some(5.into()), None, Some(7.into()), Some("5/7".into())]
+            );
+            fn edges<N>(graph: &StableDiGraph<N, ()>) -> Vec<(NodeIndex, NodeIndex)> {
+                graph
+                    .edge_indices()
+                    .filter_map(|edge| graph.edge_endpoints(edge))
+                    .collect()
+            }
+            assert_eq!(edges(&values), dcg.with_graph(edges));
+            assert_eq!(probe.recomputations(), 2);
+            assert!(labels.is_clean() && sum.is_clean());
+        }
+
+        #[test]
+        fn register_all_shares_factory() {
+            let dcg = Dcg::default();
+            let x = dcg.var(1);
+            let long_name = dcg.var(2);
+            x.node().set_name("x");
+            long_name.node().set_name("long_name");
+
+            let mut registry = ClosureRegistry::new();
+            registry.register_all(["x", "long_name"], |h| Ok(h.dcg().var(h.name().len())));
+            let (_, handles) = Dcg::hydrate(dcg.to_saved(), &registry).unwrap();
+            let x: Var<usize> = handles.get("x").unwrap();
+            let long_name: Var<usize> = handles.get("long_name").unwrap();
+            assert_eq!((x.read(), long_name.read()), (1, 9));
+        }
+
+        #[test]
+        fn hydrate_partial_poisons_unknown_nodes() {
+            let saved = saved();
+            let mut registry = ClosureRegistry::new();
+            registry.register_all(["a", "b"], |h| Ok(h.dcg().var(h.value::<i32>()?)));
+            assert_eq!(
+                Dcg::hydrate(saved.clone(), &registry).err(),
+                Some(HydrateError::MissingClosure {
+                    name: "total".to_owned()
+                })
+            );
+
+            let (dcg, handles) = Dcg::hydrate_partial(saved.clone(), &registry).unwrap();
+            assert_eq!(handles.poisoned(), &[NodeIndex::new(2), NodeIndex::new(3)]);
+            let hydrated = dcg.to_saved();
+            assert_eq!(hydrated.edges, saved.edges);
+            for (hydrated, saved) in hydrated.nodes.iter().zip(&saved.nodes) {
+                assert_eq!(
+                    (hydrated.kind, &hydrated.name, hydrated.dirty),
+                    (saved.kind, &saved.name, saved.dirty)
+                );
+            }
+
+            let a: Var<i32> = handles.get("a").unwrap();
+            assert_eq!(a.read(), 3);
+            let total: Thunk<()> = handles.get("total").unwrap();
+            let read = panic::catch_unwind(AssertUnwindSafe(|| total.read()));
+            assert_eq!(
+                read.unwrap_err().downcast_ref::<String>().unwrap(),
+                "node `total` has no registered closure"
+            );
+        }
+
+        #[test]
+        fn hydrate_rejects_invalid_edges() {
+            let registry = ClosureRegistry::new();
+            for edge in [(1, 0), (2, 2), (0, 4)] {
+                let mut saved = saved();
+                saved.edges.push(edge);
+                assert_eq!(
+                    Dcg::hydrate_partial(saved, &registry).err(),
+                    Some(HydrateError::InvalidEdge {
+                        from: edge.0,
+                        to: edge.1
+                    })
+                );
+            }
+        }
+
+        #[test]
+        fn to_saved_renumbers_around_removed_watches() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let watch = dcg.watch_expr(&a, {
+                let a = a.clone();
+                move || a.read() + 1
+            });
+            let b = buffer!(dcg, a => a * 10);
+            drop(watch);
+
+            let saved = dcg.to_saved();
+            assert_eq!(saved.nodes.len(), 2);
+            assert_eq!(saved.edges, vec![(0, 1)]);
+            assert_eq!(dcg.export_closure(b.node()).target, Some(1));
+        }
+
+        #[test]
+        fn forks_are_independent_copies() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = dcg.var(2);
+            let total = buffer!(dcg, (a, b) => a + b);
+            let tens = memo!(dcg, a => a * 10);
+            for (node, name) in [
+                (a.node(), "a"),
+                (b.node(), "b"),
+                (total.node(), "total"),
+                (tens.node(), "tens"),
+            ] {
+                node.set_name(name);
+            }
+            a.persist();
+            b.persist();
+            total.persist();
+            tens.persist();
+            total.read();
+            tens.read();
+            let probes = (dcg.probe(&total), dcg.probe(&tens));
+
+            let (fork, handles) = dcg.fork(&registry()).unwrap();
+            assert_eq!(fork.to_saved(), dcg.to_saved());
+            let fork_b: Var<i32> = handles.get("b").unwrap();
+            let fork_total: Buffer<i32> = handles.get("total").unwrap();
+            let fork_tens: Memo<(i32,), i32> = handles.get("tens").unwrap();
+            fork_b.write(5);
+            assert!(fork_total.is_dirty() && total.is_clean());
+            assert_eq!(fork_total.read(), 6);
+            assert_eq!(fork_tens.cached(&(1,)), Some(10));
+
+            assert_eq!((total.read(), tens.read()), (3, 10));
+            assert_eq!(
+                (probes.0.recomputations(), probes.1.recomputations()),
+                (0, 0)
+            );
+            a.write(4);
+            assert_eq!(fork_total.read(), 6);
+            assert_eq!(total.read(), 6);
+        }
+
+        #[test]
+        fn diff_reports_edges_and_values() {
+            use crate::diff::{diff, GraphDiff, ValueChange};
+
+            let build = |b_start: i32, uses_b: bool| {
+                let dcg = Dcg::default();
+                let a = dcg.var(1);
+                let b = dcg.var(b_start);
+                let total = if uses_b {
+                    buffer!(dcg, (a, b) => a + b)
+                } else {
+                    buffer!(dcg, a => a * 2)
+                };
+                for (node, name) in [(a.node(), "a"), (b.node(), "b"), (total.node(), "total")] {
+                    node.set_name(name);
+                }
+                a.persist();
+                b.persist();
+                total.persist();
+                (dcg, b, total)
+            };
+
+            let (two, five) = (build(2, true), build(5, false));
+            let same = diff(&two.0, &build(2, true).0);
+            assert!(same.is_empty());
+            assert_eq!(same.to_string(), "no differences\n");
+
+            let changed = diff(&two.0, &five.0);
+            assert_eq!(
+                changed,
+                GraphDiff {
+                    removed_edges: vec![("b".to_string(), "total".to_string())],
+                    changed_values: vec![ValueChange {
+                        node: "b".to_string(),
+                        before: Some(2.into()),
+                        after: Some(5.into()),
+                    }],
+                    ..GraphDiff::default()
+                }
+            );
+            assert_eq!(
+                changed.to_string(),
+                "- edge b -> total\n~ value b: 2 -> 5\n"
+            );
+
+            let dcg = &five.0;
+            let extra = dcg.var(0);
+            let copy = buffer!(dcg, extra => extra);
+            copy.read();
+            let grown = diff(&two.0, &five.0);
+            assert_eq!(grown.added_nodes, vec!["#3", "#4"]);
+            assert_eq!(
+                grown.added_edges,
+                vec![("#3".to_string(), "#4".to_string())]
+            );
+        }
     }
 
+    #[cfg(feature = "timings")]
     #[test]
-    fn conditional_execution() {
+    fn timings_sorted_slowest_first() {
+        use std::time::Duration;
+
         let dcg = Dcg::default();
         let a = dcg.var(1);
-        let b = dcg.var(1);
-        let a_read = Rc::new(Cell::new(false));
-        let a_read_clone = a_read.clone();
-        let safe_div = buffer!(dcg, (b; a) => {
-            if b == 0 {
-                None
-            } else {
-                a_read_clone.set(true);
-                Some(a.read() / b)
-            }
+        let slow = buffer!(dcg, a => {
+            thread::sleep(Duration::from_millis(20));
+            a
         });
+        let fast = buffer!(dcg, a => a);
+
+        slow.read();
+        fast.read();
+        a.write(2);
+        slow.read();
+        let timings = dcg.timings();
+        assert_eq!(timings.len(), 2);
+        assert_eq!((timings[0].0, timings[0].2), (slow.node().index(), 2));
+        assert!(timings[0].1 >= Duration::from_millis(40));
+        assert_eq!((timings[1].0, timings[1].2), (fast.node().index(), 1));
+    }
 
-        // lazy buffer created
-        assert!(!a_read.get());
+    #[cfg(feature = "tracing")]
+    mod instrumentation {
+        use std::{
+            fmt::Debug,
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc, Mutex,
+            },
+        };
 
-        a_read.set(false);
+        use tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id, Record},
+            Metadata, Subscriber,
+        };
 
-        // computes and caches value
-        assert_eq!(safe_div.read(), Some(1));
-        assert!(a_read.get());
+        use super::*;
 
-        // affected by change
-        b.write(2);
-        assert_eq!(safe_div.read(), Some(0));
-        assert!(a_read.get());
+        /// Records spans, span field updates and events as strings.
+        #[derive(Default)]
+        struct Recorder {
+            records: Arc<Mutex<Vec<String>>>,
+            next_id: AtomicU64,
+        }
 
-        a_read.set(false);
+        #[derive(Default)]
+        struct Fields(Vec<String>);
 
-        // not affected by change
-        b.write(0);
-        assert_eq!(safe_div.read(), None);
-        assert!(!a_read.get());
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes) -> Id {
+                let mut fields = Fields::default();
+                span.record(&mut fields);
+                self.records.lock().unwrap().push(format!(
+                    "span {} {}",
+                    span.metadata().name(),
+                    fields.0.join(" ")
+                ));
+                Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+            }
+
+            fn record(&self, _: &Id, values: &Record) {
+                let mut fields = Fields::default();
+                values.record(&mut fields);
+                self.records
+                    .lock()
+                    .unwrap()
+                    .push(format!("record {}", fields.0.join(" ")));
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &tracing::Event) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.records
+                    .lock()
+                    .unwrap()
+                    .push(format!("event {}", fields.0.join(" ")));
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        fn record<F: FnOnce()>(f: F) -> Vec<String> {
+            let recorder = Recorder::default();
+            let records = recorder.records.clone();
+            tracing::subscriber::with_default(recorder, f);
+            let records = records.lock().unwrap().clone();
+            records
+        }
+
+        #[test]
+        fn read_spans_and_recompute_events() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = buffer!(dcg, a => a + 1);
+
+            let records = record(|| {
+                b.read();
+            });
+
+            assert_eq!(records.len(), 3);
+            assert_eq!(records[0], "span read node=1");
+            assert_eq!(records[1], "span read node=0");
+            assert!(records[2].starts_with("event message=recomputed node=1 elapsed="));
+        }
+
+        #[test]
+        fn write_spans_record_dirtied_count() {
+            let dcg = Dcg::default();
+            let a = dcg.var(1);
+            let b = buffer!(dcg, a => a + 1);
+            let _c = buffer!(dcg, b => b + 1);
+            b.read();
+
+            let records = record(|| {
+                a.write(2);
+            });
+
+            assert_eq!(records, vec!["span write node=0", "record dirtied=2"]);
+        }
     }
 }
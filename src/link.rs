@@ -0,0 +1,96 @@
+//! Depending on nodes of another [`Dcg`] through proxy [`Var`]s created by [`Dcg::link`].
+
+use std::rc::Rc;
+
+use petgraph::graph::NodeIndex;
+
+use crate::{incremental::Incremental, stage_write, Dcg, DcgError, Staged, Var};
+
+/// Stages a write of a linked node's latest value into its proxy, or returns [`None`] once the
+/// proxy is gone.
+pub(crate) type Link = Box<dyn Fn() -> Option<(NodeIndex, Staged)>>;
+
+impl Dcg {
+    /// Creates a [`Var`] in this [`Dcg`] holding the value of `source`, typically a node of
+    /// another [`Dcg`], updated from it whenever [`sync_links`](Dcg::sync_links) is called.
+    ///
+    /// Between syncs the proxy keeps the value it last received, so its dependents stay as they
+    /// are however `source` changes. The [`Dcg`] keeps `source` alive, but not the proxy: the
+    /// link is removed once the proxy is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let physics = Dcg::default();
+    /// let speed = physics.var(2);
+    /// let doubled = buffer!(physics, speed => speed * 2);
+    ///
+    /// let audio = Dcg::default();
+    /// let linked = audio.link(&doubled);
+    /// let pitch = buffer!(audio, linked => linked + 100);
+    /// assert_eq!(pitch.read(), 104);
+    ///
+    /// speed.write(3);
+    /// assert_eq!(pitch.read(), 104);
+    /// assert_eq!(audio.sync_links(), 1);
+    /// assert_eq!(pitch.read(), 106);
+    /// ```
+    pub fn link<S>(&self, source: &S) -> Var<S::Output>
+    where
+        S: Incremental + Clone + 'static,
+        S::Output: PartialEq + Clone + 'static,
+    {
+        let proxy = self.var(source.read());
+        let idx = proxy.node.idx;
+        let weak = Rc::downgrade(&proxy);
+        let source = source.clone();
+        self.inner.links.borrow_mut().push(Box::new(move || {
+            if weak.strong_count() == 0 {
+                return None;
+            }
+            Some((idx, stage_write(weak.clone(), source.read())))
+        }));
+        proxy
+    }
+
+    /// Reads the current value of every [linked](Dcg::link) node, evaluating it if need be, and
+    /// writes it into its proxy, returning how many proxies changed.
+    ///
+    /// Proxies receiving an equal value are left untouched, so their dependents stay clean. The
+    /// values are written together as by [`commit_staged`](Dcg::commit_staged).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated and a link is registered, or if a value
+    /// is rejected by a [validator](crate::RawVar::add_validator). See
+    /// [`try_sync_links`](Dcg::try_sync_links).
+    pub fn sync_links(&self) -> usize {
+        self.try_sync_links()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`sync_links`](Dcg::sync_links), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) or
+    /// [`ValidationFailed`](DcgError::ValidationFailed) instead of panicking, having written no
+    /// proxy. The linked nodes are read again by the next sync.
+    pub fn try_sync_links(&self) -> Result<usize, DcgError> {
+        let mut links = self.inner.links.take();
+        let mut writes = Vec::new();
+        links.retain(|link| match link() {
+            Some(write) => {
+                writes.push(write);
+                true
+            }
+            None => false,
+        });
+        {
+            let mut registered = self.inner.links.borrow_mut();
+            links.append(&mut registered);
+            *registered = links;
+        }
+        self.try_apply_report(&mut writes)
+            .map(|report| report.changed)
+    }
+}
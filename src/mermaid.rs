@@ -0,0 +1,109 @@
+//! Rendering a [`Dcg`] as a [Mermaid](https://mermaid.js.org/syntax/flowchart.html) flowchart.
+
+use std::fmt::Write;
+
+use crate::{Dcg, NodeKind};
+
+/// Replaces characters that would end or corrupt a quoted Mermaid label with entity codes.
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '\n' => escaped.push_str("<br/>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Dcg {
+    /// Renders the [`Dcg`] as a Mermaid flowchart, e.g. for embedding in Markdown.
+    ///
+    /// Renders the same content as [`to_dot`](Dcg::to_dot): [`Var`](crate::Var)s are drawn as
+    /// rectangles, [`Thunk`](crate::Thunk)s as stadiums and [`Memo`](crate::Memo)s and
+    /// [`Buffer`](crate::Buffer)s as double circles, edges are labelled with their
+    /// [label](Dcg::label_edge), if any, edges into dirty nodes are red and dashed, and
+    /// [weak dependencies](Dcg::add_weak_dependency) are dotted. Node `n` has the identifier
+    /// `n{n}`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// a.show_value();
+    ///
+    /// assert_eq!(
+    ///     dcg.to_mermaid(),
+    ///     r#"graph TD
+    ///     n0["0<br/>1"]
+    ///     n1((("1")))
+    ///     n0 --> n1
+    ///     linkStyle 0 stroke:red,stroke-dasharray:5 5
+    /// "#
+    /// );
+    /// ```
+    pub fn to_mermaid(&self) -> String {
+        let (nodes, edges) = self.describe(true, 32);
+        let mut mermaid = String::new();
+        writeln!(mermaid, "graph TD").unwrap();
+        for node in nodes {
+            let (open, close) = match node.kind {
+                NodeKind::Var => ("[", "]"),
+                NodeKind::Thunk => ("([", "])"),
+                NodeKind::Memo | NodeKind::Buffer => ("(((", ")))"),
+            };
+            let mut label = node.idx.index().to_string();
+            if let Some(name) = node.name {
+                write!(label, "<br/>{}", escape(&name)).unwrap();
+            }
+            if let Some(value) = node.value {
+                write!(label, "<br/>{}", escape(&value)).unwrap();
+            }
+            writeln!(
+                mermaid,
+                "    n{}{}\"{}\"{}",
+                node.idx.index(),
+                open,
+                label,
+                close
+            )
+            .unwrap();
+        }
+        let mut dirty = Vec::new();
+        for (i, edge) in edges.iter().enumerate() {
+            let label = match &edge.label {
+                Some(label) => format!("|\"{}\"|", escape(label)),
+                None => String::new(),
+            };
+            writeln!(
+                mermaid,
+                "    n{} {}{} n{}",
+                edge.from.index(),
+                if edge.weak { "-.->" } else { "-->" },
+                label,
+                edge.to.index()
+            )
+            .unwrap();
+            if edge.dirty {
+                dirty.push(i.to_string());
+            }
+        }
+        if !dirty.is_empty() {
+            writeln!(
+                mermaid,
+                "    linkStyle {} stroke:red,stroke-dasharray:5 5",
+                dirty.join(",")
+            )
+            .unwrap();
+        }
+        mermaid
+    }
+}
@@ -0,0 +1,228 @@
+//! [`NamedDcg`]: a [`Dcg`] whose nodes are created, read and written by string keys.
+
+use std::{cell::RefCell, collections::HashMap, error, fmt};
+
+use crate::{incremental::Incremental, Buffer, Dcg, DcgError, Node, Var};
+
+/// A [`Dcg`] of values of one type, whose nodes are identified by string keys rather than
+/// handles.
+///
+/// Each key names a cell, holding a [`Var`], or a memo, holding a [`Buffer`] whose dependencies
+/// are given by key. Nodes are [named](Node::set_name) after their keys, so they are labelled in
+/// exports of the [underlying `Dcg`](NamedDcg::dcg).
+///
+/// # Examples
+///
+/// ```
+/// use cachegrab::named::NamedDcg;
+///
+/// let named = NamedDcg::default();
+/// named.cell("price", 10.0).unwrap();
+/// named.cell("quantity", 3.0).unwrap();
+/// named.memo("total", |inputs| inputs[0] * inputs[1], &["price", "quantity"]).unwrap();
+///
+/// assert_eq!(named.get("total"), Ok(30.0));
+/// named.set("quantity", 4.0).unwrap();
+/// assert_eq!(named.get("total"), Ok(40.0));
+/// ```
+pub struct NamedDcg<T> {
+    dcg: Dcg,
+    entries: RefCell<HashMap<String, Entry<T>>>,
+}
+
+/// A node of a [`NamedDcg`].
+enum Entry<T> {
+    Cell(Var<T>),
+    Memo(Buffer<T>),
+}
+
+impl<T> Clone for Entry<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Entry::Cell(var) => Entry::Cell(var.clone()),
+            Entry::Memo(buffer) => Entry::Memo(buffer.clone()),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Incremental for Entry<T> {
+    type Output = T;
+
+    fn read(&self) -> T {
+        match self {
+            Entry::Cell(var) => var.read(),
+            Entry::Memo(buffer) => buffer.read(),
+        }
+    }
+
+    fn latest(&self) -> T {
+        match self {
+            Entry::Cell(var) => var.latest(),
+            Entry::Memo(buffer) => buffer.latest(),
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        match self {
+            Entry::Cell(var) => var.is_dirty(),
+            Entry::Memo(buffer) => buffer.is_dirty(),
+        }
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        match self {
+            Entry::Cell(var) => var.nodes(),
+            Entry::Memo(buffer) => buffer.nodes(),
+        }
+    }
+}
+
+/// Errors reported by [`NamedDcg`] operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamedError {
+    /// A node was created with a key already in use.
+    DuplicateKey {
+        /// The key.
+        key: String,
+    },
+    /// A key was used without a node having been created for it.
+    UnknownKey {
+        /// The key.
+        key: String,
+    },
+    /// A memo's key was [set](NamedDcg::set), though only cells can be.
+    NotACell {
+        /// The key.
+        key: String,
+    },
+    /// The write to a cell was rejected by the [`Dcg`].
+    Dcg(DcgError),
+}
+
+impl fmt::Display for NamedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NamedError::DuplicateKey { key } => write!(f, "key `{}` is already in use", key),
+            NamedError::UnknownKey { key } => write!(f, "no node has key `{}`", key),
+            NamedError::NotACell { key } => write!(f, "`{}` is a memo, not a cell", key),
+            NamedError::Dcg(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for NamedError {}
+
+impl From<DcgError> for NamedError {
+    fn from(err: DcgError) -> Self {
+        NamedError::Dcg(err)
+    }
+}
+
+impl<T> Default for NamedDcg<T> {
+    fn default() -> Self {
+        Self {
+            dcg: Dcg::default(),
+            entries: RefCell::default(),
+        }
+    }
+}
+
+impl<T> NamedDcg<T> {
+    /// Returns the underlying [`Dcg`], for example to export it.
+    pub fn dcg(&self) -> &Dcg {
+        &self.dcg
+    }
+
+    /// Returns every key in use, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<_> = self.entries.borrow().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns `true` if a node has key `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.borrow().contains_key(key)
+    }
+
+    /// Returns [`DuplicateKey`](NamedError::DuplicateKey) if `key` is in use.
+    fn check_unused(&self, key: &str) -> Result<(), NamedError> {
+        if self.contains(key) {
+            Err(NamedError::DuplicateKey {
+                key: key.to_owned(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the node with key `key`, or [`UnknownKey`](NamedError::UnknownKey).
+    fn entry(&self, key: &str) -> Result<Entry<T>, NamedError> {
+        self.entries
+            .borrow()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| NamedError::UnknownKey {
+                key: key.to_owned(),
+            })
+    }
+}
+
+impl<T: PartialEq + Clone + 'static> NamedDcg<T> {
+    /// Creates a cell with key `key`, holding `value`.
+    ///
+    /// Returns [`DuplicateKey`](NamedError::DuplicateKey), creating nothing, if `key` is in use.
+    pub fn cell(&self, key: &str, value: T) -> Result<(), NamedError> {
+        self.check_unused(key)?;
+        let var = self.dcg.var(value);
+        var.node().set_name(key);
+        self.entries
+            .borrow_mut()
+            .insert(key.to_owned(), Entry::Cell(var));
+        Ok(())
+    }
+
+    /// Creates a memo with key `key`, of `f` applied to the values of `dependencies`, in order.
+    ///
+    /// Returns [`DuplicateKey`](NamedError::DuplicateKey) if `key` is in use, or
+    /// [`UnknownKey`](NamedError::UnknownKey) for the first of `dependencies` not in use,
+    /// creating nothing.
+    pub fn memo<F>(&self, key: &str, f: F, dependencies: &[&str]) -> Result<(), NamedError>
+    where
+        F: Fn(&[T]) -> T + 'static,
+    {
+        self.check_unused(key)?;
+        let params = dependencies
+            .iter()
+            .map(|dependency| self.entry(dependency))
+            .collect::<Result<Vec<_>, _>>()?;
+        let buffer = self.dcg.buffer(&params.clone(), move || f(&params.read()));
+        buffer.node().set_name(key);
+        self.entries
+            .borrow_mut()
+            .insert(key.to_owned(), Entry::Memo(buffer));
+        Ok(())
+    }
+
+    /// [Read](Incremental::read)s the value of the node with key `key`.
+    ///
+    /// Returns [`UnknownKey`](NamedError::UnknownKey) if `key` is not in use.
+    pub fn get(&self, key: &str) -> Result<T, NamedError> {
+        Ok(self.entry(key)?.read())
+    }
+
+    /// Writes `value` into the cell with key `key`, returning its previous value.
+    ///
+    /// Returns [`UnknownKey`](NamedError::UnknownKey) if `key` is not in use,
+    /// [`NotACell`](NamedError::NotACell) if it is a memo's, or
+    /// [`Dcg`](NamedError::Dcg) if the write is rejected, as by
+    /// [`try_write`](crate::RawVar::try_write).
+    pub fn set(&self, key: &str, value: T) -> Result<T, NamedError> {
+        match self.entry(key)? {
+            Entry::Cell(var) => Ok(var.try_write(value)?),
+            Entry::Memo(_) => Err(NamedError::NotACell {
+                key: key.to_owned(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Partial evaluation: cleaning the part of a [`Dcg`] above a frontier, or up to a height, with
+//! [`Dcg::clean_until`], [`Dcg::clean_depth`] and [`Dcg::clean_component`], for a time with
+//! [`Dcg::clean_for`], or warming caches with [`Dcg::prefetch`].
+//!
+//! Only [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s are evaluated, as they are the
+//! nodes holding values. The [`Var`](crate::Var)s and [`Thunk`](crate::Thunk)s they read are
+//! cleaned by being read; everything else is left dirty.
+
+use std::{any::Any, collections::HashSet, hash::Hash, rc::Weak, time::Duration};
+
+use petgraph::{
+    algo::toposort,
+    graph::NodeIndex,
+    Direction::{self, Incoming, Outgoing},
+};
+
+use crate::{incremental::Incremental, CleanProgress, Dcg, Graph, RawBuffer, RawMemo};
+
+/// Brings a node up to date, or discards its cached values, without keeping it alive.
+pub(crate) trait Refresh {
+    /// Reads the node, returning `false` if it has been dropped.
+    fn refresh(&self) -> bool;
+
+    /// Discards the node's cached values, so its next read executes its closure.
+    fn forget(&self);
+
+    /// Returns `true` if the node's handle has been dropped.
+    fn is_dropped(&self) -> bool {
+        false
+    }
+
+    /// Reads the node and passes its value to `f`, unless it has been dropped.
+    fn visit(&self, _f: &mut dyn FnMut(&dyn Any)) {}
+}
+
+impl<T: Clone + 'static> Refresh for Weak<RawBuffer<T>> {
+    fn refresh(&self) -> bool {
+        self.upgrade().map(|buffer| buffer.read()).is_some()
+    }
+
+    fn forget(&self) {
+        if let Some(buffer) = self.upgrade() {
+            buffer.buffered.take();
+            buffer.node().forget_cached();
+        }
+    }
+
+    fn is_dropped(&self) -> bool {
+        self.strong_count() == 0
+    }
+
+    fn visit(&self, f: &mut dyn FnMut(&dyn Any)) {
+        if let Some(node) = self.upgrade() {
+            f(&node.read());
+        }
+    }
+}
+
+impl<A: Eq + Hash, T: Clone + 'static> Refresh for Weak<RawMemo<A, T>> {
+    fn refresh(&self) -> bool {
+        self.upgrade().map(|memo| memo.read()).is_some()
+    }
+
+    fn forget(&self) {
+        if let Some(memo) = self.upgrade() {
+            memo.cache.borrow_mut().clear();
+            memo.node().forget_cached();
+        }
+    }
+
+    fn is_dropped(&self) -> bool {
+        self.strong_count() == 0
+    }
+
+    fn visit(&self, f: &mut dyn FnMut(&dyn Any)) {
+        if let Some(node) = self.upgrade() {
+            f(&node.read());
+        }
+    }
+}
+
+/// Returns the nodes reachable from `from` by following edges in `direction`, excluding `from`
+/// unless one reaches another.
+pub(crate) fn reach(graph: &Graph, from: &[NodeIndex], direction: Direction) -> HashSet<NodeIndex> {
+    let mut reached = HashSet::new();
+    let mut stack: Vec<_> = from
+        .iter()
+        .flat_map(|&idx| graph.neighbors_directed(idx, direction))
+        .collect();
+    while let Some(next) = stack.pop() {
+        if reached.insert(next) {
+            stack.extend(graph.neighbors_directed(next, direction));
+        }
+    }
+    reached
+}
+
+impl Dcg {
+    /// Evaluates the dirty [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s the `frontier`
+    /// nodes depend on, without evaluating the `frontier` nodes themselves or anything depending
+    /// on them, returning how many nodes were evaluated.
+    ///
+    /// The `frontier` and everything below it stay dirty, to be brought up to date by a later
+    /// read, which then only evaluates what changed beneath the frontier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 2);
+    ///
+    /// assert_eq!(dcg.clean_until(&[c.node().index()]), 1);
+    /// assert!(b.is_clean());
+    /// assert!(c.is_dirty());
+    /// assert_eq!(c.read(), 4);
+    /// ```
+    pub fn clean_until(&self, frontier: &[NodeIndex]) -> usize {
+        let (above, below) = {
+            let graph = self.inner.graph.borrow();
+            (
+                reach(&graph, frontier, Incoming),
+                reach(&graph, frontier, Outgoing),
+            )
+        };
+        self.refresh_where(|idx| {
+            above.contains(&idx) && !below.contains(&idx) && !frontier.contains(&idx)
+        })
+    }
+
+    /// Evaluates the dirty [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s whose height is
+    /// at most `max_height`, returning how many nodes were evaluated.
+    ///
+    /// A node's height is 0 if it has no dependencies, and otherwise one more than the greatest
+    /// height among its dependencies. Higher nodes stay dirty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 2);
+    ///
+    /// assert_eq!(dcg.clean_depth(1), 1);
+    /// assert!(b.is_clean());
+    /// assert!(c.is_dirty());
+    /// ```
+    pub fn clean_depth(&self, max_height: usize) -> usize {
+        let heights = self.inner.heights();
+        self.refresh_where(|idx| heights[&idx] <= max_height)
+    }
+
+    /// Evaluates the dirty [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s in component
+    /// `id` of [`components`](Dcg::components), returning how many nodes were evaluated.
+    ///
+    /// No other component's closures run, as no node in component `id` depends on them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let c = buffer!(dcg, a => a + 1);
+    /// let d = buffer!(dcg, b => b + 1);
+    ///
+    /// assert_eq!(dcg.clean_component(dcg.component_of(c.node())), 1);
+    /// assert!(c.is_clean());
+    /// assert!(d.is_dirty());
+    /// ```
+    pub fn clean_component(&self, id: usize) -> usize {
+        let component: HashSet<_> = self.component(id).into_iter().collect();
+        self.refresh_where(|idx| component.contains(&idx))
+    }
+
+    /// Evaluates up to `budget` of the dirty [`Memo`](crate::Memo)s and
+    /// [`Buffer`](crate::Buffer)s among `nodes` and their dependencies, returning how many were
+    /// evaluated.
+    ///
+    /// Shallower nodes are evaluated first, so each node's dependencies are up to date before it
+    /// is, then those with the lowest average [cost](Dcg::costs). Evaluated nodes stay cached and
+    /// clean, so prefetching can be stopped at any point and resumed by another call, and later
+    /// reads only evaluate what is left. Prefetching never changes the values read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 2);
+    ///
+    /// assert_eq!(dcg.prefetch(&[c.node().index()], 1), 1);
+    /// assert!(b.is_clean());
+    /// assert_eq!(dcg.prefetch(&[c.node().index()], 5), 1);
+    /// assert!(c.is_clean());
+    /// assert_eq!(c.read(), 4);
+    /// ```
+    pub fn prefetch(&self, nodes: &[NodeIndex], budget: usize) -> usize {
+        let mut candidates: Vec<_> = {
+            let graph = self.inner.graph.borrow();
+            let refreshers = self.inner.refreshers.borrow();
+            let mut candidates = reach(&graph, nodes, Incoming);
+            candidates.extend(nodes);
+            candidates
+                .into_iter()
+                .filter(|idx| self.inner.is_dirty(*idx) && refreshers.contains_key(idx))
+                .collect()
+        };
+        self.prioritize(&mut candidates);
+
+        let mut refreshed = 0;
+        for idx in candidates {
+            if refreshed == budget {
+                break;
+            }
+            if self.refresh(idx) {
+                refreshed += 1;
+            }
+        }
+        refreshed
+    }
+
+    /// Evaluates dirty [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s, in the order of
+    /// [`prefetch`](Dcg::prefetch), until `budget` has elapsed on the [`Dcg`]'s
+    /// [clock](Dcg::set_clock).
+    ///
+    /// The time is checked before each evaluation, which is never interrupted, so the call may
+    /// overrun by up to one node's evaluation. At least one node is evaluated if any is dirty, so
+    /// repeated calls clean every node once the [`Var`](crate::Var)s stop changing, even if a
+    /// single evaluation takes longer than `budget`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use cachegrab::{CleanProgress, Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 2);
+    ///
+    /// assert_eq!(
+    ///     dcg.clean_for(Duration::from_secs(1)),
+    ///     CleanProgress { cleaned: 2, remaining: 0 }
+    /// );
+    /// assert!(c.is_clean());
+    /// ```
+    pub fn clean_for(&self, budget: Duration) -> CleanProgress {
+        let start = self.now();
+        let mut candidates = self.dirty_refreshable();
+        self.prioritize(&mut candidates);
+
+        let mut cleaned = 0;
+        for idx in candidates {
+            if cleaned > 0 && self.now().saturating_duration_since(start) >= budget {
+                break;
+            }
+            if self.refresh(idx) {
+                cleaned += 1;
+            }
+        }
+        CleanProgress {
+            cleaned,
+            remaining: self.dirty_refreshable().len(),
+        }
+    }
+
+    /// Returns the dirty [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s.
+    pub(crate) fn dirty_refreshable(&self) -> Vec<NodeIndex> {
+        self.inner
+            .refreshers
+            .borrow()
+            .keys()
+            .copied()
+            .filter(|&idx| self.inner.is_dirty(idx))
+            .collect()
+    }
+
+    /// Sorts `candidates` by height, so each node's dependencies come before it, then by average
+    /// [cost](Dcg::costs).
+    pub(crate) fn prioritize(&self, candidates: &mut [NodeIndex]) {
+        let heights = self.inner.heights();
+        let costs = self.inner.costs.borrow().clone();
+        let average_cost = |idx| match costs.get(&idx) {
+            Some(&(total, count)) if count > 0 => total / count,
+            _ => 0,
+        };
+        candidates.sort_by_key(|&idx| (heights[&idx], average_cost(idx), idx));
+    }
+
+    /// Reads every dirty registered node for which `included` holds, in topological order, so
+    /// each node's dependencies are up to date before it is read.
+    pub(crate) fn refresh_where(&self, included: impl Fn(NodeIndex) -> bool) -> usize {
+        let order = {
+            let graph = self.inner.graph.borrow();
+            toposort(&*graph, None).expect("a Dcg's dependencies are acyclic")
+        };
+        order
+            .into_iter()
+            .filter(|&idx| included(idx) && self.refresh(idx))
+            .count()
+    }
+
+    /// Reads the node at `idx` if it is a dirty [`Memo`](crate::Memo) or
+    /// [`Buffer`](crate::Buffer), returning whether it was read.
+    pub(crate) fn refresh(&self, idx: NodeIndex) -> bool {
+        if !self.inner.is_dirty(idx) {
+            return false;
+        }
+        let refresher = self.inner.refreshers.borrow().get(&idx).cloned();
+        refresher.is_some_and(|refresher| refresher.refresh())
+    }
+}
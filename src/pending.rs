@@ -0,0 +1,134 @@
+//! [`Var`]s whose values aren't available yet, created with [`Dcg::pending_var`] and given one
+//! with [`Dcg::fulfill`].
+
+use std::rc::Rc;
+
+use petgraph::{graph::NodeIndex, Direction::Incoming};
+
+use crate::{incremental::Incremental, partial::reach, Dcg, DcgError, Node, RawVar, Var};
+
+impl Dcg {
+    /// Creates a [`Var`] whose value is yet to arrive, to be given by [`fulfill`](Dcg::fulfill).
+    ///
+    /// Until then, the [`Var`] can't be read or written: [`read`](Incremental::read)
+    /// and [`write`](RawVar::write) panic, and [`try_read`](RawVar::try_read) and
+    /// [`try_write`](RawVar::try_write) return [`Pending`](DcgError::Pending). Nodes depending on
+    /// it can be created as usual, but [`RawBuffer::try_read`](crate::RawBuffer::try_read) on one
+    /// returns [`Pending`](DcgError::Pending) instead of evaluating it. The [`Var`] holds
+    /// `T::default()` in the meantime, which no read ever sees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, DcgError, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let price = dcg.pending_var::<i32>();
+    /// let qty = dcg.var(3);
+    /// let total = buffer!(dcg, (price, qty) => price * qty);
+    ///
+    /// assert!(!dcg.is_ready(total.node()));
+    /// assert_eq!(
+    ///     total.try_read(),
+    ///     Err(DcgError::Pending { target: price.node().index() })
+    /// );
+    ///
+    /// dcg.fulfill(&price, 10).unwrap();
+    /// assert!(dcg.is_ready(total.node()));
+    /// assert_eq!(total.read(), 30);
+    /// ```
+    pub fn pending_var<T: Default>(&self) -> Var<T> {
+        let var = self.var(T::default());
+        self.inner.pending.borrow_mut().insert(var.node.idx);
+        var
+    }
+
+    /// Writes `value` into `var`, created by [`pending_var`](Dcg::pending_var), allowing it and
+    /// the nodes depending on it to be read.
+    ///
+    /// Nothing can have read the [`Var`] before, so no dependent holds a value computed from it,
+    /// and dependents are only dirtied as by any other [`write`](RawVar::write).
+    ///
+    /// Returns [`NotPending`](DcgError::NotPending) if `var` wasn't created by
+    /// [`pending_var`](Dcg::pending_var) or has already been fulfilled. Otherwise returns the same
+    /// errors as [`try_write`](RawVar::try_write), leaving `var` pending.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `var` belongs to another [`Dcg`].
+    pub fn fulfill<T>(&self, var: &Var<T>, value: T) -> Result<(), DcgError>
+    where
+        T: PartialEq + Clone,
+    {
+        assert!(
+            Rc::ptr_eq(&var.node.inner, &self.inner),
+            "var must belong to the Dcg"
+        );
+        if !self.inner.pending.borrow_mut().remove(&var.node.idx) {
+            return Err(DcgError::NotPending {
+                target: var.node.idx,
+            });
+        }
+        var.try_write(value).map(drop).inspect_err(|_| {
+            self.inner.pending.borrow_mut().insert(var.node.idx);
+        })
+    }
+
+    /// Returns `true` if `node` can be read without waiting on anything: neither it nor anything
+    /// it depends on, transitively, is a [pending](Dcg::pending_var) [`Var`] or an
+    /// [unfinalized](Dcg::finalize_dependencies) [deferred](Dcg::deferred_buffer)
+    /// [`Buffer`](crate::Buffer).
+    pub fn is_ready(&self, node: &Node) -> bool {
+        node.check_ready().is_ok()
+    }
+}
+
+impl Node {
+    /// Returns [`Pending`](DcgError::Pending) if the node is a [pending](Dcg::pending_var)
+    /// [`Var`].
+    pub(crate) fn check_fulfilled(&self) -> Result<(), DcgError> {
+        let pending = self.inner.pending.borrow();
+        if !pending.is_empty() && pending.contains(&self.idx) {
+            Err(DcgError::Pending { target: self.idx })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns [`Pending`](DcgError::Pending) or [`Unfinalized`](DcgError::Unfinalized) for the
+    /// earliest created node, among this one and those it depends on, that can't be read yet.
+    pub(crate) fn check_ready(&self) -> Result<(), DcgError> {
+        let blocked: Vec<NodeIndex> = {
+            let pending = self.inner.pending.borrow();
+            let unfinalized = self.inner.unfinalized.borrow();
+            if pending.is_empty() && unfinalized.is_empty() {
+                return Ok(());
+            }
+            let mut ancestry = reach(&self.inner.graph.borrow(), &[self.idx], Incoming);
+            ancestry.insert(self.idx);
+            let mut blocked: Vec<_> = ancestry
+                .into_iter()
+                .filter(|idx| pending.contains(idx) || unfinalized.contains(idx))
+                .collect();
+            blocked.sort_unstable();
+            blocked
+        };
+        match blocked.first() {
+            Some(&target) if self.inner.pending.borrow().contains(&target) => {
+                Err(DcgError::Pending { target })
+            }
+            Some(&target) => Err(DcgError::Unfinalized { target }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Clone> RawVar<T> {
+    /// Like [`read`](Incremental::read), but returns
+    /// [`Pending`](DcgError::Pending) instead of panicking if the [`Var`] was created by
+    /// [`pending_var`](Dcg::pending_var) and hasn't been fulfilled.
+    pub fn try_read(&self) -> Result<T, DcgError> {
+        self.node.check_fulfilled()?;
+        Ok(self.read())
+    }
+}
@@ -0,0 +1,866 @@
+//! Saving a [`Dcg`]'s topology and values with [`Dcg::to_saved`], and rebuilding it with
+//! [`Dcg::hydrate`].
+//!
+//! Closures can't be serialized, so hydration rebuilds each node by calling the factory registered
+//! under the node's [name](crate::Node::set_name) in a [`ClosureRegistry`]. Nodes' values can
+//! have different types, so they are saved as JSON [`Value`]s, and only for nodes registered with
+//! `persist`.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    error, fmt,
+    hash::Hash,
+    rc::{Rc, Weak},
+};
+
+use petgraph::{
+    algo::toposort,
+    graph::NodeIndex,
+    stable_graph::StableDiGraph,
+    visit::{Dfs, EdgeRef, IntoEdgeReferences, Reversed, Walker},
+    Direction::Incoming,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    incremental::Incremental, Dcg, Graph, Node, NodeKind, RawBuffer, RawMemo, RawThunk, RawVar,
+    Thunk,
+};
+
+/// A [`Dcg`]'s topology and persisted values, as returned by [`Dcg::to_saved`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedDcg {
+    /// Every node, in index order.
+    pub nodes: Vec<SavedNode>,
+    /// Every `(dependency, dependent)` pair of node indices.
+    pub edges: Vec<(usize, usize)>,
+    /// The index of the node whose ancestry was saved by [`Dcg::export_closure`].
+    #[serde(default)]
+    pub target: Option<usize>,
+}
+
+/// A node in a [`SavedDcg`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedNode {
+    /// The node's kind.
+    pub kind: NodeKind,
+    /// The node's [name](crate::Node::set_name), used to find its factory when hydrating.
+    pub name: Option<String>,
+    /// Whether the node was dirty.
+    pub dirty: bool,
+    /// The node's state if it was registered with `persist`: a [`Var`](crate::Var)'s value, a
+    /// [`Buffer`](crate::Buffer)'s buffered value or `null`, or a [`Memo`](crate::Memo)'s cache as
+    /// `[params, value]` pairs.
+    pub value: Option<Value>,
+}
+
+/// Whether [`Dcg::values_json`] recomputes out of date values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotPolicy {
+    /// Out of date [`Buffer`](crate::Buffer)s and [`Memo`](crate::Memo)s appear as `null`.
+    CachedOnly,
+    /// Out of date [`Buffer`](crate::Buffer)s and [`Memo`](crate::Memo)s are
+    /// [read](Incremental::read) first.
+    Evaluate,
+}
+
+/// Saves and restores a node's state, without keeping the node alive.
+pub(crate) trait Persist {
+    fn save(&self) -> Option<Value>;
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error>;
+
+    /// Returns the node's current value, reading it first if `evaluate` is set, or `null` if it
+    /// is out of date.
+    fn current(&self, evaluate: bool) -> Option<Value>;
+}
+
+impl<T: Serialize + DeserializeOwned> Persist for Weak<RawVar<T>> {
+    fn save(&self) -> Option<Value> {
+        let var = self.upgrade()?;
+        let value = serde_json::to_value(&*var.value.borrow()).ok();
+        value
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        if let Some(var) = self.upgrade() {
+            var.value.replace(serde_json::from_value(value)?);
+        }
+        Ok(())
+    }
+
+    fn current(&self, _evaluate: bool) -> Option<Value> {
+        self.save()
+    }
+}
+
+impl<T: Clone + Serialize + DeserializeOwned + 'static> Persist for Weak<RawBuffer<T>> {
+    fn save(&self) -> Option<Value> {
+        let buffer = self.upgrade()?;
+        let value = serde_json::to_value(&*buffer.buffered.borrow()).ok();
+        value
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        if let Some(buffer) = self.upgrade() {
+            let buffered: Option<T> = serde_json::from_value(value)?;
+            if buffered.is_some() {
+                buffer.thunk.node.mark_cached();
+            }
+            buffer.buffered.replace(buffered);
+        }
+        Ok(())
+    }
+
+    fn current(&self, evaluate: bool) -> Option<Value> {
+        let buffer = self.upgrade()?;
+        if evaluate {
+            buffer.read();
+        } else if buffer.is_dirty() {
+            return Some(Value::Null);
+        }
+        self.save()
+    }
+}
+
+impl<A, T> Persist for Weak<RawMemo<A, T>>
+where
+    A: Eq + Hash + Serialize + DeserializeOwned,
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    fn save(&self) -> Option<Value> {
+        let memo = self.upgrade()?;
+        let cache = memo.cache.borrow();
+        let mut entries = cache
+            .iter()
+            .map(|(args, (value, _))| serde_json::to_value((args, value)))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        // Sorted so that saving the same cache always produces the same value.
+        entries.sort_by_cached_key(|entry| entry.to_string());
+        Some(Value::Array(entries))
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        if let Some(memo) = self.upgrade() {
+            let entries: Vec<(A, T)> = serde_json::from_value(value)?;
+            if !entries.is_empty() {
+                memo.thunk.node.mark_cached();
+            }
+            memo.cache
+                .borrow_mut()
+                .extend(entries.into_iter().map(|(args, value)| (args, (value, 0))));
+            memo.evict();
+        }
+        Ok(())
+    }
+
+    fn current(&self, evaluate: bool) -> Option<Value> {
+        let memo = self.upgrade()?;
+        let value = if evaluate {
+            memo.read()
+        } else if memo.is_dirty() {
+            return Some(Value::Null);
+        } else {
+            memo.latest()
+        };
+        serde_json::to_value(value).ok()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + 'static> RawVar<T> {
+    /// Includes the [`Var`](crate::Var)'s value in [`Dcg::to_saved`], to be restored by
+    /// [`Dcg::hydrate`].
+    pub fn persist(self: &Rc<Self>) {
+        self.node
+            .inner
+            .persisted
+            .borrow_mut()
+            .insert(self.node.idx, Rc::new(Rc::downgrade(self)));
+    }
+}
+
+impl<T: Clone + Serialize + DeserializeOwned + 'static> RawBuffer<T> {
+    /// Includes the [`Buffer`](crate::Buffer)'s buffered value in [`Dcg::to_saved`], to be
+    /// restored by [`Dcg::hydrate`].
+    pub fn persist(self: &Rc<Self>) {
+        self.thunk
+            .node
+            .inner
+            .persisted
+            .borrow_mut()
+            .insert(self.thunk.node.idx, Rc::new(Rc::downgrade(self)));
+    }
+}
+
+impl<A, T> RawMemo<A, T>
+where
+    A: Eq + Hash + Serialize + DeserializeOwned + 'static,
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    /// Includes the [`Memo`](crate::Memo)'s cache in [`Dcg::to_saved`], to be restored by
+    /// [`Dcg::hydrate`].
+    pub fn persist(self: &Rc<Self>) {
+        self.thunk
+            .node
+            .inner
+            .persisted
+            .borrow_mut()
+            .insert(self.thunk.node.idx, Rc::new(Rc::downgrade(self)));
+    }
+}
+
+type Factory = Rc<dyn Fn(&Hydrator) -> Result<(NodeIndex, Box<dyn Any>), HydrateError>>;
+
+/// Factories rebuilding named nodes during [`Dcg::hydrate`].
+#[derive(Default)]
+pub struct ClosureRegistry {
+    factories: HashMap<String, Factory>,
+}
+
+impl ClosureRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `factory` to rebuild the node named `name`, replacing any factory previously
+    /// registered under `name`.
+    ///
+    /// The factory must create exactly one node in [`Hydrator::dcg`], with the same kind and
+    /// dependencies as the saved node, and return its handle. The handle is then available to
+    /// later factories through [`Hydrator::dependency`].
+    pub fn register<H, F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        H: Incremental + Clone + 'static,
+        F: Fn(&Hydrator) -> Result<H, HydrateError> + 'static,
+    {
+        self.register_all([name], factory);
+    }
+
+    /// Registers `factory` to rebuild every node named in `names`. See
+    /// [`register`](ClosureRegistry::register).
+    ///
+    /// The factory can tell the nodes apart with [`Hydrator::name`].
+    pub fn register_all<H, F>(
+        &mut self,
+        names: impl IntoIterator<Item = impl Into<String>>,
+        factory: F,
+    ) where
+        H: Incremental + Clone + 'static,
+        F: Fn(&Hydrator) -> Result<H, HydrateError> + 'static,
+    {
+        let factory: Factory = Rc::new(move |hydrator| {
+            let handle = factory(hydrator)?;
+            let idx = match handle.nodes().as_slice() {
+                [node] => node.index(),
+                _ => {
+                    return Err(HydrateError::Mismatch {
+                        name: hydrator.name.to_owned(),
+                    })
+                }
+            };
+            Ok((idx, Box::new(handle)))
+        });
+        for name in names {
+            self.factories.insert(name.into(), factory.clone());
+        }
+    }
+
+    /// Returns the names of nodes in `saved` with no registered factory, sorted.
+    pub fn unknown(&self, saved: &SavedDcg) -> Vec<String> {
+        let mut unknown: Vec<_> = saved
+            .nodes
+            .iter()
+            .filter_map(|node| node.name.clone())
+            .filter(|name| !self.factories.contains_key(name))
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        unknown
+    }
+
+    /// Returns the names with a registered factory that no node in `saved` has, sorted.
+    pub fn unused(&self, saved: &SavedDcg) -> Vec<String> {
+        let mut unused: Vec<_> = self
+            .factories
+            .keys()
+            .filter(|name| {
+                !saved
+                    .nodes
+                    .iter()
+                    .any(|node| node.name.as_ref() == Some(*name))
+            })
+            .cloned()
+            .collect();
+        unused.sort();
+        unused
+    }
+}
+
+/// Gives a [`ClosureRegistry`] factory access to the [`Dcg`] being hydrated.
+pub struct Hydrator<'a> {
+    dcg: &'a Dcg,
+    name: &'a str,
+    node: &'a SavedNode,
+    handles: &'a Handles,
+}
+
+impl Hydrator<'_> {
+    /// Returns the name of the node to create.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Returns the [`Dcg`] in which to create the node.
+    pub fn dcg(&self) -> &Dcg {
+        self.dcg
+    }
+
+    /// Returns the node's saved value. See [`SavedNode::value`].
+    pub fn value<T: DeserializeOwned>(&self) -> Result<T, HydrateError> {
+        let value = self.node.value.clone().ok_or_else(|| HydrateError::Value {
+            name: self.name.to_owned(),
+            message: "no value was saved".to_owned(),
+        })?;
+        serde_json::from_value(value).map_err(|err| HydrateError::Value {
+            name: self.name.to_owned(),
+            message: err.to_string(),
+        })
+    }
+
+    /// Returns the handle of the already hydrated node named `name`.
+    pub fn dependency<H: Clone + 'static>(&self, name: &str) -> Result<H, HydrateError> {
+        self.handles
+            .get(name)
+            .ok_or_else(|| HydrateError::MissingDependency {
+                name: name.to_owned(),
+            })
+    }
+}
+
+/// The handles of the nodes rebuilt by [`Dcg::hydrate`], by name.
+#[derive(Default)]
+pub struct Handles {
+    handles: HashMap<String, (NodeIndex, Box<dyn Any>)>,
+    poisoned: Vec<NodeIndex>,
+}
+
+impl Handles {
+    /// Returns the handle of the node named `name`, if it was rebuilt with type `H`.
+    pub fn get<H: Clone + 'static>(&self, name: &str) -> Option<H> {
+        let (_, handle) = self.handles.get(name)?;
+        handle.downcast_ref::<H>().cloned()
+    }
+
+    /// Returns the index of the node named `name`.
+    pub fn index(&self, name: &str) -> Option<NodeIndex> {
+        self.handles.get(name).map(|(idx, _)| *idx)
+    }
+
+    /// Returns the indices of the nodes [poisoned](Dcg::hydrate_partial) for lack of a factory.
+    pub fn poisoned(&self) -> &[NodeIndex] {
+        &self.poisoned
+    }
+
+    /// Returns the index of every rebuilt node, by name.
+    pub fn indices(&self) -> HashMap<String, NodeIndex> {
+        self.handles
+            .iter()
+            .map(|(name, (idx, _))| (name.clone(), *idx))
+            .collect()
+    }
+}
+
+/// Errors reported by [`Dcg::hydrate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HydrateError {
+    /// A saved node has no name, so no factory can be found for it.
+    Unnamed {
+        /// The node's saved index.
+        index: usize,
+    },
+    /// No factory is registered for a saved node's name.
+    MissingClosure {
+        /// The node's name.
+        name: String,
+    },
+    /// A factory asked for a dependency that has not been hydrated, or has a different type.
+    MissingDependency {
+        /// The dependency's name.
+        name: String,
+    },
+    /// A saved value is missing or could not be deserialized.
+    Value {
+        /// The node's name.
+        name: String,
+        /// Why the value could not be used.
+        message: String,
+    },
+    /// A factory created a node with a different index, kind or dependencies than the saved node.
+    Mismatch {
+        /// The node's name.
+        name: String,
+    },
+    /// A saved edge refers to a node past the end of the list, or doesn't point from an earlier
+    /// node to a later one, as every dependency is created before its dependents.
+    InvalidEdge {
+        /// The dependency's saved index.
+        from: usize,
+        /// The dependent's saved index.
+        to: usize,
+    },
+}
+
+impl fmt::Display for HydrateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HydrateError::Unnamed { index } => {
+                write!(f, "saved node {} has no name to hydrate it by", index)
+            }
+            HydrateError::MissingClosure { name } => {
+                write!(f, "no closure is registered for node `{}`", name)
+            }
+            HydrateError::MissingDependency { name } => {
+                write!(f, "dependency `{}` has not been hydrated", name)
+            }
+            HydrateError::Value { name, message } => {
+                write!(
+                    f,
+                    "cannot restore the value of node `{}`: {}",
+                    name, message
+                )
+            }
+            HydrateError::Mismatch { name } => {
+                write!(f, "node `{}` was not rebuilt as it was saved", name)
+            }
+            HydrateError::InvalidEdge { from, to } => {
+                write!(f, "saved edge from node {} to node {} is invalid", from, to)
+            }
+        }
+    }
+}
+
+impl error::Error for HydrateError {}
+
+/// Returns each node's position in index order, its index in a [`SavedDcg`].
+fn dense_positions(graph: &Graph) -> HashMap<NodeIndex, usize> {
+    graph
+        .node_indices()
+        .enumerate()
+        .map(|(position, idx)| (idx, position))
+        .collect()
+}
+
+impl Dcg {
+    /// Returns the [`Dcg`]'s topology and the values of nodes registered with `persist`.
+    ///
+    /// Nodes are saved in index order. Their indices in the [`SavedDcg`] are renumbered densely,
+    /// so they differ from the [`Dcg`]'s once a [watch](Dcg::watch_expr) has been removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, NodeKind};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// a.node().set_name("a");
+    /// a.persist();
+    ///
+    /// let saved = dcg.to_saved();
+    /// assert_eq!(saved.nodes[0].kind, NodeKind::Var);
+    /// assert_eq!(saved.nodes[0].value, Some(1.into()));
+    /// ```
+    pub fn to_saved(&self) -> SavedDcg {
+        let persisted: Vec<_> = self
+            .inner
+            .persisted
+            .borrow()
+            .iter()
+            .map(|(&idx, persist)| (idx, persist.clone()))
+            .collect();
+        let mut values: HashMap<_, _> = persisted
+            .into_iter()
+            .filter_map(|(idx, persist)| Some((idx, persist.save()?)))
+            .collect();
+
+        let graph = self.inner.graph.borrow();
+        let kinds = self.inner.kinds.borrow();
+        let names = self.inner.names.borrow();
+        let positions = dense_positions(&graph);
+        let nodes = graph
+            .node_indices()
+            .map(|idx| SavedNode {
+                kind: kinds[&idx],
+                name: names.get(&idx).cloned(),
+                dirty: self.inner.is_dirty(idx),
+                value: values.remove(&idx),
+            })
+            .collect();
+        let edges = graph
+            .edge_references()
+            .map(|edge| (positions[&edge.source()], positions[&edge.target()]))
+            .collect();
+        SavedDcg {
+            nodes,
+            edges,
+            target: None,
+        }
+    }
+
+    /// Returns `node` and its transitive dependencies like [`to_saved`](Dcg::to_saved), with
+    /// indices renumbered densely in the same order and `node` marked as the
+    /// [`target`](SavedDcg::target).
+    ///
+    /// This is useful for reproducing how a single node was computed without the rest of the
+    /// [`Dcg`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, NodeKind, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = dcg.var(2);
+    /// let c = buffer!(dcg, b => b + 1);
+    ///
+    /// let saved = dcg.export_closure(c.node());
+    /// assert_eq!(saved.nodes.len(), 2);
+    /// assert_eq!(saved.edges, vec![(0, 1)]);
+    /// assert_eq!(saved.target, Some(1));
+    /// ```
+    pub fn export_closure(&self, node: &Node) -> SavedDcg {
+        let (mut ancestry, positions) = {
+            let graph = self.inner.graph.borrow();
+            let ancestry: Vec<_> = Dfs::new(Reversed(&*graph), node.idx)
+                .iter(Reversed(&*graph))
+                .collect();
+            (ancestry, dense_positions(&graph))
+        };
+        ancestry.sort_unstable();
+        let remapped: HashMap<_, _> = ancestry
+            .iter()
+            .enumerate()
+            .map(|(new, old)| (positions[old], new))
+            .collect();
+
+        let saved = self.to_saved();
+        let nodes = saved
+            .nodes
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| remapped.contains_key(index))
+            .map(|(_, node)| node)
+            .collect();
+        let edges = saved
+            .edges
+            .into_iter()
+            .filter_map(|(from, to)| Some((*remapped.get(&from)?, *remapped.get(&to)?)))
+            .collect();
+        SavedDcg {
+            nodes,
+            edges,
+            target: Some(remapped[&positions[&node.idx]]),
+        }
+    }
+
+    /// Returns a JSON object mapping the name, or else the index, of every node registered with
+    /// `persist` to its current value.
+    ///
+    /// Unlike [`to_saved`](Dcg::to_saved), this includes neither topology nor caches. Reading a
+    /// clean [`Memo`](crate::Memo)'s value under [`SnapshotPolicy::CachedOnly`] still computes
+    /// its params.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, persist::SnapshotPolicy};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// a.node().set_name("a");
+    /// a.persist();
+    /// b.persist();
+    ///
+    /// assert_eq!(dcg.values_json(SnapshotPolicy::CachedOnly), r#"{"1":null,"a":1}"#);
+    /// assert_eq!(dcg.values_json(SnapshotPolicy::Evaluate), r#"{"1":2,"a":1}"#);
+    /// ```
+    pub fn values_json(&self, policy: SnapshotPolicy) -> String {
+        let mut persisted: Vec<_> = self
+            .inner
+            .persisted
+            .borrow()
+            .iter()
+            .map(|(&idx, persist)| (idx, persist.clone()))
+            .collect();
+        persisted.sort_by_key(|(idx, _)| *idx);
+        let mut values = serde_json::Map::new();
+        for (idx, persist) in persisted {
+            if let Some(value) = persist.current(policy == SnapshotPolicy::Evaluate) {
+                let key = self.inner.names.borrow().get(&idx).cloned();
+                values.insert(key.unwrap_or_else(|| idx.index().to_string()), value);
+            }
+        }
+        Value::Object(values).to_string()
+    }
+
+    /// Returns the [`Dcg`]'s topology with each node replaced by its current value, or [`None`] if
+    /// it isn't registered with `persist`.
+    ///
+    /// Nodes keep their indices. Persisted nodes are [read](Incremental::read) in topological
+    /// order first, so dirty [`Buffer`](crate::Buffer)s and [`Memo`](crate::Memo)s are
+    /// recomputed from up-to-date dependencies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, b => b * 10);
+    /// a.persist();
+    /// c.persist();
+    ///
+    /// let values = dcg.evaluate_to_graph();
+    /// assert_eq!(values[a.node().index()], Some(1.into()));
+    /// assert_eq!(values[b.node().index()], None);
+    /// assert_eq!(values[c.node().index()], Some(20.into()));
+    /// ```
+    pub fn evaluate_to_graph(&self) -> StableDiGraph<Option<Value>, ()> {
+        let order =
+            toposort(&*self.inner.graph.borrow(), None).expect("a Dcg's dependencies are acyclic");
+        let mut values = HashMap::new();
+        for idx in order {
+            let persist = self.inner.persisted.borrow().get(&idx).cloned();
+            if let Some(value) = persist.and_then(|persist| persist.current(true)) {
+                values.insert(idx, value);
+            }
+        }
+        self.inner
+            .graph
+            .borrow()
+            .map(|idx, _| values.remove(&idx), |_, _| ())
+    }
+
+    /// Rebuilds a [`Dcg`] from `saved`, calling the factory registered in `registry` under each
+    /// node's name in index order, then restoring persisted values and every node's dirtiness.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{
+    ///     Dcg, Var, Buffer, buffer, incremental::Incremental, persist::ClosureRegistry,
+    /// };
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// a.node().set_name("a");
+    /// b.node().set_name("b");
+    /// a.persist();
+    /// a.write(2);
+    ///
+    /// let mut registry = ClosureRegistry::new();
+    /// registry.register("a", |h| Ok(h.dcg().var(h.value::<i32>()?)));
+    /// registry.register("b", |h| {
+    ///     let dcg = h.dcg();
+    ///     let a: Var<i32> = h.dependency("a")?;
+    ///     Ok(buffer!(dcg, a => a + 1))
+    /// });
+    ///
+    /// let (_hydrated, handles) = Dcg::hydrate(dcg.to_saved(), &registry).unwrap();
+    /// let b: Buffer<i32> = handles.get("b").unwrap();
+    /// assert_eq!(b.read(), 3);
+    /// ```
+    pub fn hydrate(
+        saved: SavedDcg,
+        registry: &ClosureRegistry,
+    ) -> Result<(Dcg, Handles), HydrateError> {
+        Dcg::hydrate_with(saved, registry, false)
+    }
+
+    /// Copies the [`Dcg`] by [saving](Dcg::to_saved) it and [hydrating](Dcg::hydrate) the
+    /// result, so the copy can be written without affecting the original, or the original the
+    /// copy.
+    ///
+    /// Closures capture the handles of the nodes they read, so they can't be shared with the
+    /// copy: every node is rebuilt by its factory in `registry`, as when hydrating. The copy has
+    /// the original's topology, names and dirtiness, and the values of nodes registered with
+    /// `persist`; other nodes start with empty caches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{
+    ///     Dcg, Var, Buffer, buffer, incremental::Incremental, persist::ClosureRegistry,
+    /// };
+    ///
+    /// let mut registry = ClosureRegistry::new();
+    /// registry.register("a", |h| {
+    ///     let a = h.dcg().var(h.value::<i32>()?);
+    ///     a.persist();
+    ///     Ok(a)
+    /// });
+    /// registry.register("b", |h| {
+    ///     let dcg = h.dcg();
+    ///     let a: Var<i32> = h.dependency("a")?;
+    ///     Ok(buffer!(dcg, a => a + 1))
+    /// });
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// a.node().set_name("a");
+    /// b.node().set_name("b");
+    /// a.persist();
+    ///
+    /// let (_fork, handles) = dcg.fork(&registry).unwrap();
+    /// let (fork_a, fork_b): (Var<i32>, Buffer<i32>) =
+    ///     (handles.get("a").unwrap(), handles.get("b").unwrap());
+    /// fork_a.write(10);
+    /// assert_eq!(fork_b.read(), 11);
+    /// assert_eq!(b.read(), 2);
+    /// ```
+    pub fn fork(&self, registry: &ClosureRegistry) -> Result<(Dcg, Handles), HydrateError> {
+        Dcg::hydrate(self.to_saved(), registry)
+    }
+
+    /// Rebuilds a [`Dcg`] from `saved` like [`hydrate`](Dcg::hydrate), but poisons nodes that
+    /// have no name or no registered factory instead of failing.
+    ///
+    /// A poisoned node keeps its saved kind and dependencies, but panics when read. Poisoned nodes
+    /// are listed by [`Handles::poisoned`], and named ones are available as [`Thunk<()>`]s.
+    pub fn hydrate_partial(
+        saved: SavedDcg,
+        registry: &ClosureRegistry,
+    ) -> Result<(Dcg, Handles), HydrateError> {
+        Dcg::hydrate_with(saved, registry, true)
+    }
+
+    fn hydrate_with(
+        saved: SavedDcg,
+        registry: &ClosureRegistry,
+        partial: bool,
+    ) -> Result<(Dcg, Handles), HydrateError> {
+        if let Some(&(from, to)) = saved
+            .edges
+            .iter()
+            .find(|(from, to)| from >= to || *to >= saved.nodes.len())
+        {
+            return Err(HydrateError::InvalidEdge { from, to });
+        }
+        let dcg = Dcg::new();
+        let mut handles = Handles::default();
+        for (index, node) in saved.nodes.iter().enumerate() {
+            let factory = match &node.name {
+                Some(name) => {
+                    registry
+                        .factories
+                        .get(name)
+                        .ok_or_else(|| HydrateError::MissingClosure {
+                            name: name.to_owned(),
+                        })
+                }
+                None => Err(HydrateError::Unnamed { index }),
+            };
+            let factory = match factory {
+                Ok(factory) => factory,
+                Err(_) if partial => {
+                    let poisoned = dcg.poison(&saved, index);
+                    let idx = poisoned.node.idx;
+                    handles.poisoned.push(idx);
+                    if let Some(name) = &node.name {
+                        handles
+                            .handles
+                            .insert(name.clone(), (idx, Box::new(poisoned)));
+                    }
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let name = node.name.as_deref().unwrap_or_default();
+            let (idx, handle) = factory(&Hydrator {
+                dcg: &dcg,
+                name,
+                node,
+                handles: &handles,
+            })?;
+
+            let mut dependencies: Vec<_> = dcg
+                .inner
+                .graph
+                .borrow()
+                .neighbors_directed(idx, Incoming)
+                .map(|dependency| dependency.index())
+                .collect();
+            dependencies.sort_unstable();
+            let mut saved_dependencies: Vec<_> = saved
+                .edges
+                .iter()
+                .filter(|(_, to)| *to == index)
+                .map(|(from, _)| *from)
+                .collect();
+            saved_dependencies.sort_unstable();
+            let kind = dcg.inner.kinds.borrow()[&idx];
+            if idx.index() != index || kind != node.kind || dependencies != saved_dependencies {
+                return Err(HydrateError::Mismatch {
+                    name: name.to_owned(),
+                });
+            }
+
+            dcg.inner.names.borrow_mut().insert(idx, name.to_owned());
+            handles.handles.insert(name.to_owned(), (idx, handle));
+        }
+
+        for (index, node) in saved.nodes.into_iter().enumerate() {
+            let idx = NodeIndex::new(index);
+            let persist = dcg.inner.persisted.borrow().get(&idx).cloned();
+            if let (Some(value), Some(persist)) = (node.value, persist) {
+                persist.restore(value).map_err(|err| HydrateError::Value {
+                    name: node.name.clone().unwrap_or_default(),
+                    message: err.to_string(),
+                })?;
+            }
+            dcg.inner.graph.borrow_mut()[idx] = node.dirty;
+        }
+        dcg.inner.new_revision();
+        Ok((dcg, handles))
+    }
+
+    /// Creates a node like `saved.nodes[index]` that panics when read.
+    fn poison(&self, saved: &SavedDcg, index: usize) -> Thunk<()> {
+        let saved_node = &saved.nodes[index];
+        let node = Node::new(self, saved_node.kind);
+        node.add_dependency_indices(
+            saved
+                .edges
+                .iter()
+                .filter(|(_, to)| *to == index)
+                .map(|(from, _)| NodeIndex::new(*from))
+                .collect(),
+        );
+        let name = saved_node.name.clone();
+        if let Some(name) = &name {
+            node.set_name(name.clone());
+        }
+        Rc::new(RawThunk {
+            f: RefCell::new(Box::new(move || match &name {
+                Some(name) => panic!("node `{}` has no registered closure", name),
+                None => panic!("node {} has no name to hydrate it by", index),
+            })),
+            node,
+            history: Default::default(),
+            cost: Default::default(),
+            hooks: Default::default(),
+            interceptors: Default::default(),
+        })
+    }
+}
@@ -0,0 +1,127 @@
+//! [`Plan`]s: reusable evaluation orders for reading several outputs of a [`Dcg`].
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    rc::Rc,
+};
+
+use petgraph::{algo::toposort, graph::NodeIndex, Direction::Incoming};
+
+use crate::{incremental::Incremental, partial::reach, Dcg, Inner};
+
+/// The nodes needed to read a [`Plan`]'s outputs, in topological order, as returned by
+/// [`Dcg::plan`].
+///
+/// The order is computed once and reused by every [`execute`](Plan::execute) until a node or
+/// dependency is added to or removed from the [`Dcg`], when it is recomputed.
+pub struct Plan<I> {
+    inner: Rc<Inner>,
+    outputs: Vec<I>,
+    /// The [`Dcg`]'s generation when `order` was computed.
+    generation: Cell<u64>,
+    order: RefCell<Vec<NodeIndex>>,
+    rebuilds: Cell<usize>,
+}
+
+impl Dcg {
+    /// Creates a [`Plan`] for reading `outputs`, ordering the nodes they depend on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = buffer!(dcg, (a, b) => a + b);
+    /// let plan = dcg.plan(&[b.clone(), c.clone()]);
+    ///
+    /// assert_eq!(plan.execute(&dcg), vec![2, 3]);
+    /// a.write(2);
+    /// assert_eq!(plan.execute(&dcg), vec![3, 5]);
+    /// assert_eq!(plan.rebuilds(), 0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if any output belongs to another [`Dcg`].
+    pub fn plan<I>(&self, outputs: &[I]) -> Plan<I>
+    where
+        I: Incremental + Clone,
+    {
+        for output in outputs {
+            for node in output.nodes() {
+                assert!(
+                    Rc::ptr_eq(&node.inner, &self.inner),
+                    "planned outputs must belong to the Dcg"
+                );
+            }
+        }
+        let plan = Plan {
+            inner: self.inner.clone(),
+            outputs: outputs.to_vec(),
+            generation: Cell::new(self.inner.generation.get()),
+            order: RefCell::default(),
+            rebuilds: Cell::new(0),
+        };
+        plan.order.replace(plan.order());
+        plan
+    }
+}
+
+impl<I: Incremental> Plan<I> {
+    /// Computes the topologically ordered nodes the outputs depend on, including the outputs.
+    fn order(&self) -> Vec<NodeIndex> {
+        let graph = self.inner.graph.borrow();
+        let outputs: Vec<_> = self
+            .outputs
+            .iter()
+            .flat_map(|output| output.nodes())
+            .map(|node| node.idx)
+            .collect();
+        let mut needed: HashSet<_> = reach(&graph, &outputs, Incoming);
+        needed.extend(outputs);
+        toposort(&*graph, None)
+            .expect("a Dcg's dependencies are acyclic")
+            .into_iter()
+            .filter(|idx| needed.contains(idx))
+            .collect()
+    }
+
+    /// Brings every dirty [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer) in the plan up to
+    /// date in order, skipping clean ones, then [`read`](Incremental::read)s and returns each
+    /// output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dcg` is not the [`Dcg`] the plan was made for.
+    pub fn execute(&self, dcg: &Dcg) -> Vec<I::Output> {
+        assert!(
+            Rc::ptr_eq(&dcg.inner, &self.inner),
+            "plans must be executed on their own Dcg"
+        );
+        let generation = self.inner.generation.get();
+        if self.generation.get() != generation {
+            self.order.replace(self.order());
+            self.generation.set(generation);
+            self.rebuilds.set(self.rebuilds.get() + 1);
+        }
+        for &idx in self.order.borrow().iter() {
+            dcg.refresh(idx);
+        }
+        self.outputs.iter().map(|output| output.read()).collect()
+    }
+
+    /// Returns how many times the plan's order has been recomputed since it was created, due to
+    /// the [`Dcg`]'s structure changing.
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds.get()
+    }
+
+    /// Returns the nodes the plan evaluates, in order.
+    pub fn nodes(&self) -> Vec<NodeIndex> {
+        self.order.borrow().clone()
+    }
+}
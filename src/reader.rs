@@ -0,0 +1,142 @@
+//! [`DcgReader`]: a read-only view of a [`Dcg`], created with [`Dcg::reader`].
+
+use petgraph::{
+    graph::NodeIndex,
+    Direction::{self, Incoming, Outgoing},
+};
+
+use crate::{incremental::Incremental, BudgetExceeded, Buffer, CacheStats, Dcg, NodeKind};
+
+/// A read-only view of a [`Dcg`], for handing to code that may read and inspect its nodes but
+/// not write [`Var`](crate::Var)s or add nodes.
+///
+/// Reading a dirty node through the view recomputes it as usual, populating its cache and
+/// cleaning it, as that doesn't change any value later reads see. The view only restricts what
+/// is reachable through it: handles passed alongside it keep their whole API.
+///
+/// # Examples
+///
+/// ```
+/// use cachegrab::{Dcg, buffer, incremental::Incremental};
+///
+/// let dcg = Dcg::default();
+/// let a = dcg.var(1);
+/// let b = buffer!(dcg, a => a + 1);
+///
+/// let reader = dcg.reader();
+/// assert!(reader.is_dirty(b.node().index()));
+/// assert_eq!(reader.read(&b), 2);
+/// assert_eq!(reader.dependencies(b.node().index()), vec![a.node().index()]);
+/// ```
+///
+/// The view has none of the [`Dcg`]'s constructors:
+///
+/// ```compile_fail
+/// use cachegrab::Dcg;
+///
+/// let dcg = Dcg::default();
+/// let reader = dcg.reader();
+/// reader.var(1);
+/// ```
+///
+/// Nor can [`Var`](crate::Var)s be written through it:
+///
+/// ```compile_fail
+/// use cachegrab::Dcg;
+///
+/// let dcg = Dcg::default();
+/// let a = dcg.var(1);
+/// let reader = dcg.reader();
+/// reader.write(&a, 2);
+/// ```
+#[derive(Clone, Copy)]
+pub struct DcgReader<'a> {
+    dcg: &'a Dcg,
+}
+
+impl Dcg {
+    /// Returns a read-only view of the [`Dcg`].
+    pub fn reader(&self) -> DcgReader<'_> {
+        DcgReader { dcg: self }
+    }
+}
+
+impl DcgReader<'_> {
+    /// [Read](Incremental::read)s `node`, recomputing it if it is dirty.
+    pub fn read<I: Incremental>(&self, node: &I) -> I::Output {
+        node.read()
+    }
+
+    /// Reads `node` with at most `max_recomputes` recomputations, as by
+    /// [`Dcg::read_with_budget`].
+    pub fn read_with_budget<I: Incremental>(
+        &self,
+        node: &I,
+        max_recomputes: usize,
+    ) -> Result<I::Output, BudgetExceeded> {
+        self.dcg.read_with_budget(node, max_recomputes)
+    }
+
+    /// Returns `buffer`'s buffered value without recomputing it, even if it is out of date.
+    pub fn last_computed<T: Clone>(&self, buffer: &Buffer<T>) -> Option<T> {
+        buffer.last_computed()
+    }
+
+    /// Returns `true` if the node at `idx` is dirty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`Dcg`] has no node at `idx`.
+    pub fn is_dirty(&self, idx: NodeIndex) -> bool {
+        self.dcg.inner.is_dirty(idx)
+    }
+
+    /// Returns the kind of the node at `idx`, or [`None`] if the [`Dcg`] has no such node.
+    pub fn kind(&self, idx: NodeIndex) -> Option<NodeKind> {
+        self.dcg.kind(idx)
+    }
+
+    /// Returns the [name](crate::Node::set_name) of the node at `idx`, if any.
+    pub fn name(&self, idx: NodeIndex) -> Option<String> {
+        self.dcg.inner.names.borrow().get(&idx).cloned()
+    }
+
+    /// Returns the nodes the node at `idx` depends on, sorted.
+    pub fn dependencies(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        self.neighbors(idx, Incoming)
+    }
+
+    /// Returns the nodes depending on the node at `idx`, sorted.
+    pub fn dependents(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        self.neighbors(idx, Outgoing)
+    }
+
+    fn neighbors(&self, idx: NodeIndex, direction: Direction) -> Vec<NodeIndex> {
+        let mut neighbors: Vec<_> = self
+            .dcg
+            .inner
+            .graph
+            .borrow()
+            .neighbors_directed(idx, direction)
+            .collect();
+        neighbors.sort();
+        neighbors.dedup();
+        neighbors
+    }
+
+    /// Returns the [`CacheStats`] of the node at `idx`, as by [`Dcg::cache_stats`].
+    pub fn cache_stats(&self, idx: NodeIndex) -> CacheStats {
+        self.dcg
+            .inner
+            .cache_stats
+            .borrow()
+            .get(&idx)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of nodes in the [`Dcg`].
+    pub fn node_count(&self) -> usize {
+        self.dcg.node_count()
+    }
+}
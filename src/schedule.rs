@@ -0,0 +1,131 @@
+//! Logical time: [`Var`] writes [scheduled](Dcg::schedule) for future ticks, applied by
+//! [`Dcg::advance_to`].
+
+use std::rc::Rc;
+
+use crate::{stage_write, Dcg, DcgError, Var};
+
+impl Dcg {
+    /// Returns the current tick: the latest passed to [`advance_to`](Dcg::advance_to), or 0.
+    pub fn current_tick(&self) -> u64 {
+        self.inner.tick.get()
+    }
+
+    /// Schedules a write of `value` into `var` at tick `at`, to be applied by
+    /// [`advance_to`](Dcg::advance_to). Replaces any write already scheduled for `var` at `at`.
+    ///
+    /// Returns [`ScheduledInPast`](DcgError::ScheduledInPast), scheduling nothing, if `at` is
+    /// before the [current tick](Dcg::current_tick).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let speed = dcg.var(1);
+    /// let doubled = buffer!(dcg, speed => speed * 2);
+    ///
+    /// dcg.schedule(&speed, 10, 5).unwrap();
+    /// assert_eq!(dcg.advance_to(9), 0);
+    /// assert_eq!(doubled.read(), 2);
+    /// assert_eq!(dcg.advance_to(10), 1);
+    /// assert_eq!(doubled.read(), 10);
+    /// assert!(dcg.schedule(&speed, 3, 0).is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `var` belongs to another [`Dcg`].
+    pub fn schedule<T>(&self, var: &Var<T>, at: u64, value: T) -> Result<(), DcgError>
+    where
+        T: PartialEq + Clone + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&var.node.inner, &self.inner),
+            "scheduled vars must belong to the Dcg"
+        );
+        let current = self.current_tick();
+        if at < current {
+            return Err(DcgError::ScheduledInPast {
+                target: var.node.idx,
+                at,
+                current,
+            });
+        }
+        let idx = var.node.idx;
+        let write = stage_write(Rc::downgrade(var), value);
+        let mut scheduled = self.inner.scheduled.borrow_mut();
+        let writes = scheduled.entry(at).or_default();
+        writes.retain(|(scheduled, _)| *scheduled != idx);
+        writes.push((idx, write));
+        Ok(())
+    }
+
+    /// Advances the [current tick](Dcg::current_tick) to `tick`, applying the writes
+    /// [scheduled](Dcg::schedule) up to and including it, and returns how many [`Var`]s changed.
+    ///
+    /// The writes scheduled for each tick are applied together, like
+    /// [`commit_staged`](Dcg::commit_staged), in order of their ticks. Observers and watchers see
+    /// the tick of the writes they are notified of as the current tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tick` is before the current tick, if called while a node is being evaluated
+    /// and a write is due, or if a due value is rejected by a
+    /// [validator](crate::RawVar::add_validator). See [`try_advance_to`](Dcg::try_advance_to).
+    pub fn advance_to(&self, tick: u64) -> usize {
+        self.try_advance_to(tick)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`advance_to`](Dcg::advance_to), but returns
+    /// [`MutationDuringEvaluation`](DcgError::MutationDuringEvaluation) or
+    /// [`ValidationFailed`](DcgError::ValidationFailed) instead of panicking.
+    ///
+    /// On error the writes scheduled for earlier ticks have been applied, and the current tick
+    /// is left at the last of them. None of the writes for the failing tick are applied, and
+    /// they stay scheduled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tick` is before the current tick.
+    pub fn try_advance_to(&self, tick: u64) -> Result<usize, DcgError> {
+        let current = self.current_tick();
+        assert!(
+            tick >= current,
+            "cannot advance from tick {} back to {}",
+            current,
+            tick
+        );
+        let mut changed = 0;
+        loop {
+            let due = {
+                let mut scheduled = self.inner.scheduled.borrow_mut();
+                match scheduled.first_key_value() {
+                    Some((&at, _)) if at <= tick => scheduled.pop_first(),
+                    _ => None,
+                }
+            };
+            let Some((at, mut writes)) = due else {
+                break;
+            };
+            let before = self.inner.tick.replace(at.max(current));
+            match self.try_apply_report(&mut writes) {
+                Ok(report) => changed += report.changed,
+                Err(err) => {
+                    self.inner.tick.set(before);
+                    let mut scheduled = self.inner.scheduled.borrow_mut();
+                    let rescheduled = scheduled.entry(at).or_default();
+                    // Writes scheduled since, e.g. by a validator, replace the kept ones.
+                    writes.retain(|(idx, _)| rescheduled.iter().all(|(since, _)| since != idx));
+                    writes.append(rescheduled);
+                    *rescheduled = writes;
+                    return Err(err);
+                }
+            }
+        }
+        self.inner.tick.set(tick);
+        Ok(changed)
+    }
+}
@@ -0,0 +1,100 @@
+//! Running a [`Dcg`]'s deferred work until it stops changing, with [`Dcg::settle`].
+
+use std::collections::HashMap;
+
+use petgraph::graph::NodeIndex;
+
+use crate::{trace::TraceEvent, Dcg, DcgError};
+
+/// The work done by [`Dcg::settle`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SettleReport {
+    /// The number of rounds run, including the final one in which nothing changed.
+    pub rounds: usize,
+    /// The nodes that executed their closures, in index order.
+    pub recomputed: Vec<NodeIndex>,
+}
+
+impl Dcg {
+    /// Runs the [`Dcg`]'s deferred work in rounds until a round changes no node's
+    /// [version](Dcg::version).
+    ///
+    /// Each round [syncs links](Dcg::sync_links), [pumps](Dcg::pump) feeds,
+    /// [commits](Dcg::commit_staged) staged writes and recomputes the dirty
+    /// [`Eager`](crate::RecomputePolicy::Eager) nodes. Observers and watchers reacting to those
+    /// changes may stage or send more writes, which the next round applies, so feedback loops
+    /// run until they reach a fixed point.
+    ///
+    /// Returns [`Unsettled`](DcgError::Unsettled), with the nodes that changed in the last
+    /// round, if the [`Dcg`] is still changing after `max_rounds` rounds. Errors applying the
+    /// work, such as [`ValidationFailed`](DcgError::ValidationFailed) for a rejected value, are
+    /// returned as by [`try_sync_links`](Dcg::try_sync_links), [`try_pump`](Dcg::try_pump) and
+    /// [`try_commit_staged`](Dcg::try_commit_staged), ending the settle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    /// use std::sync::mpsc;
+    ///
+    /// let dcg = Dcg::default();
+    /// let estimate = dcg.var(0);
+    /// let (tx, rx) = mpsc::channel();
+    /// dcg.feed(&estimate, rx);
+    /// estimate.observe(move |_, new| tx.send((new + 16) / 2).unwrap());
+    ///
+    /// estimate.write(2);
+    /// let report = dcg.settle(10).unwrap();
+    /// assert_eq!(estimate.read(), 15);
+    /// assert_eq!(report.rounds, 5);
+    /// ```
+    pub fn settle(&self, max_rounds: usize) -> Result<SettleReport, DcgError> {
+        let mut report = SettleReport::default();
+        let mut changing = Vec::new();
+        while report.rounds < max_rounds {
+            report.rounds += 1;
+            let before = self.inner.versions.borrow().clone();
+            let (result, events) = self.trace(|| self.settle_round());
+            result?;
+            report
+                .recomputed
+                .extend(events.into_iter().filter_map(|event| match event {
+                    TraceEvent::Evaluated(idx) => Some(idx),
+                    _ => None,
+                }));
+            changing = changed_since(&before, &self.inner.versions.borrow());
+            if changing.is_empty() {
+                report.recomputed.sort();
+                report.recomputed.dedup();
+                return Ok(report);
+            }
+        }
+        Err(DcgError::Unsettled { changing })
+    }
+
+    /// Applies one round of [`settle`](Dcg::settle)'s deferred work.
+    fn settle_round(&self) -> Result<(), DcgError> {
+        self.try_sync_links()?;
+        self.try_pump()?;
+        self.try_commit_staged()?;
+        let eager: Vec<_> = self.inner.eager.borrow().iter().copied().collect();
+        if !eager.is_empty() {
+            self.refresh_where(|idx| eager.contains(&idx));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the nodes whose versions differ from `before`, in index order.
+fn changed_since(
+    before: &HashMap<NodeIndex, u64>,
+    after: &HashMap<NodeIndex, u64>,
+) -> Vec<NodeIndex> {
+    let mut changed: Vec<_> = after
+        .iter()
+        .filter(|&(idx, version)| before.get(idx) != Some(version))
+        .map(|(&idx, _)| idx)
+        .collect();
+    changed.sort();
+    changed
+}
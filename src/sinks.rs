@@ -0,0 +1,137 @@
+//! Skipping [`Thunk`](crate::Thunk)s nothing observes when dirtying, enabled with
+//! [`Dcg::set_skip_thunk_sinks`].
+
+use std::{collections::HashSet, rc::Rc};
+
+use petgraph::{
+    graph::NodeIndex,
+    Direction::{Incoming, Outgoing},
+};
+
+use crate::{partial::reach, Dcg, Inner, NodeKind};
+
+impl Dcg {
+    /// Enables or disables skipping, when a write dirties its dependents, every
+    /// [`Thunk`](crate::Thunk) whose dirtiness nothing can observe.
+    ///
+    /// A [`Thunk`](crate::Thunk) re-executes whenever it's read, dirty or not, so its dirty flag
+    /// only matters to the [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s depending on it,
+    /// and to its [`on_dirtied`](Dcg::on_dirtied) hooks. With skipping enabled, writes only
+    /// traverse nodes from which such a node can be reached, so a [`Var`](crate::Var) feeding
+    /// only plain [`Thunk`](crate::Thunk)s dirties nothing. Reads return the same values either
+    /// way, but skipped [`Thunk`](crate::Thunk)s aren't reported dirty, emit no
+    /// [`NodeDirtied`](crate::Event::NodeDirtied) events and don't count towards
+    /// [`ChangeReport`](crate::ChangeReport)s.
+    ///
+    /// The traversed nodes are cached until a node, dependency or hook is added or removed.
+    /// When they're recomputed, or skipping is disabled, the skipped [`Thunk`](crate::Thunk)s
+    /// whose dependencies are still dirty, and their dependents, are dirtied as the writes would
+    /// have, without emitting events or calling hooks. Skipping is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, thunk, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// dcg.set_skip_thunk_sinks(true);
+    /// let a = dcg.var(1);
+    /// let log = thunk!(dcg, a => format!("a = {}", a));
+    ///
+    /// assert_eq!(log.read(), "a = 1");
+    /// assert_eq!(a.write_report(2).edges_dirtied, 0);
+    /// assert_eq!(log.read(), "a = 2");
+    /// ```
+    pub fn set_skip_thunk_sinks(&self, enabled: bool) {
+        if self.inner.skip_thunk_sinks.replace(enabled) && !enabled {
+            self.inner.observed.take();
+            self.inner.dirty_skipped();
+        }
+    }
+}
+
+impl Inner {
+    /// Returns the nodes writes dirty while [skipping](Dcg::set_skip_thunk_sinks) is enabled:
+    /// those from which a [`Memo`](crate::Memo), [`Buffer`](crate::Buffer) or node with
+    /// [`on_dirtied`](Dcg::on_dirtied) hooks can be reached, or [`None`] if skipping is disabled.
+    pub(crate) fn observed(&self) -> Option<Rc<HashSet<NodeIndex>>> {
+        if !self.skip_thunk_sinks.get() {
+            return None;
+        }
+        if self.observed.borrow().is_none() {
+            self.dirty_skipped();
+        }
+        let mut observed = self.observed.borrow_mut();
+        Some(
+            observed
+                .get_or_insert_with(|| Rc::new(self.find_observed()))
+                .clone(),
+        )
+    }
+
+    /// Like [`observed`](Inner::observed), but without caching the nodes or dirtying skipped
+    /// [`Thunk`](crate::Thunk)s, for [`check_invariants`](Dcg::check_invariants).
+    ///
+    /// Until the nodes are recomputed, skipped [`Thunk`](crate::Thunk)s and their dependents may
+    /// be clean over dirty dependencies, so they're left out.
+    pub(crate) fn peek_observed(&self) -> Option<Rc<HashSet<NodeIndex>>> {
+        if !self.skip_thunk_sinks.get() {
+            return None;
+        }
+        if let Some(observed) = self.observed.borrow().clone() {
+            return Some(observed);
+        }
+        let mut observed = self.find_observed();
+        let skipped: Vec<_> = self.skipped.borrow().iter().copied().collect();
+        for idx in reach(&self.graph.borrow(), &skipped, Outgoing) {
+            observed.remove(&idx);
+        }
+        for idx in skipped {
+            observed.remove(&idx);
+        }
+        Some(Rc::new(observed))
+    }
+
+    /// Finds the nodes from which a node other than a plain [`Thunk`](crate::Thunk) can be
+    /// reached.
+    fn find_observed(&self) -> HashSet<NodeIndex> {
+        let kinds = self.kinds.borrow();
+        let hooks = self.dirtied_hooks.borrow();
+        let graph = self.graph.borrow();
+        let sinks: Vec<_> = graph
+            .node_indices()
+            .filter(|idx| {
+                !matches!(kinds.get(idx), Some(NodeKind::Thunk)) || hooks.contains_key(idx)
+            })
+            .collect();
+        let mut observed = reach(&graph, &sinks, Incoming);
+        observed.extend(sinks);
+        observed
+    }
+
+    /// Dirties the [skipped](Dcg::set_skip_thunk_sinks) [`Thunk`]s still depending on a dirty
+    /// node, and their clean dependents, as the writes that skipped them would have.
+    fn dirty_skipped(&self) {
+        let skipped = self.skipped.take();
+        if skipped.is_empty() {
+            return;
+        }
+        let mut graph = self.graph.borrow_mut();
+        let mut stack: Vec<_> = skipped
+            .into_iter()
+            .filter(|&idx| {
+                !graph[idx]
+                    && graph
+                        .neighbors_directed(idx, Incoming)
+                        .any(|dependency| graph[dependency])
+            })
+            .collect();
+        while let Some(idx) = stack.pop() {
+            if graph[idx] {
+                continue;
+            }
+            graph[idx] = true;
+            stack.extend(graph.neighbors_directed(idx, Outgoing));
+        }
+    }
+}
@@ -0,0 +1,861 @@
+//! A thread-safe [`SyncDcg`], whose nodes can be read from many threads while others write.
+//!
+//! [`SyncDcg`] mirrors [`Dcg`](crate::Dcg)'s core surface with [`RwLock`]s in place of
+//! [`RefCell`](std::cell::RefCell)s. Dependencies are declared explicitly when a node is created,
+//! and closures read their dependencies' handles directly.
+//!
+//! Each node keeps its own value behind its own lock, and its own dirty flag and version as
+//! atomics, so nodes in disjoint parts of the graph can be written, recomputed and read without
+//! contending. The topology has a separate lock, only written when nodes are created. Closures run
+//! without any lock held, and a recomputation racing a write to one of the node's dependencies is
+//! discarded rather than cached.
+
+use std::{
+    any::Any,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    error, fmt, iter,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering::SeqCst},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        Weak,
+    },
+    thread::{self, JoinHandle},
+};
+
+use petgraph::{
+    algo::toposort,
+    graph::{DiGraph, NodeIndex},
+    visit::{depth_first_search, DfsEvent},
+    Direction::Incoming,
+};
+
+/// A thread-safe [`Dcg`](crate::Dcg). See the [module documentation](self).
+#[derive(Default, Clone)]
+pub struct SyncDcg {
+    inner: Arc<Inner>,
+}
+
+/// State shared between a [`SyncDcg`] and its [`SyncNode`]s.
+#[derive(Default)]
+struct Inner {
+    topology: RwLock<Topology>,
+    signal: Signal,
+    /// The latest [`Frame`] passed to [`SyncDcg::publish`].
+    front: RwLock<Arc<Frame>>,
+    /// Written by [`SyncDcg::commit`] and read by [`SyncDcg::read_consistent`] and
+    /// [`SyncDcg::publish`].
+    commit: RwLock<()>,
+    /// Held by [`SyncDcg::publish`], so frames are numbered in the order their values are read.
+    publish: Mutex<()>,
+}
+
+#[derive(Default)]
+struct Topology {
+    graph: DiGraph<Arc<NodeState>, ()>,
+    /// Every [`SyncBuffer`], for [`Worker`]s to refresh.
+    buffers: HashMap<NodeIndex, Weak<dyn Refresh>>,
+    /// Set by [`SyncDcg::set_priority`].
+    priorities: HashMap<NodeIndex, i32>,
+}
+
+impl Topology {
+    /// Returns every node's height: 0 for nodes without dependencies, or else one more than the
+    /// greatest height among its dependencies.
+    fn heights(&self) -> HashMap<NodeIndex, usize> {
+        let mut heights = HashMap::new();
+        for idx in toposort(&self.graph, None).expect("a SyncDcg's dependencies are acyclic") {
+            let height = self
+                .graph
+                .neighbors_directed(idx, Incoming)
+                .map(|dependency| heights[&dependency] + 1)
+                .max()
+                .unwrap_or(0);
+            heights.insert(idx, height);
+        }
+        heights
+    }
+}
+
+/// A node's dirtiness, shared between the node and the [`Topology`] so writes can dirty it.
+struct NodeState {
+    dirty: AtomicBool,
+    /// Incremented whenever the node is dirtied.
+    version: AtomicU64,
+}
+
+/// Wakes [`Worker`]s when [`SyncVar`]s change, and [`Worker::flush`] when they are done.
+#[derive(Default)]
+struct Signal {
+    status: Mutex<Status>,
+    changed: Condvar,
+    /// The number of running [`Worker`]s, so writes only lock `status` if one is listening.
+    workers: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Status {
+    /// Whether a [`SyncVar`] changed since a [`Worker`] last started refreshing.
+    pending: bool,
+    /// The number of [`Worker`]s refreshing.
+    busy: usize,
+}
+
+impl Signal {
+    fn status(&self) -> MutexGuard<'_, Status> {
+        self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, status: MutexGuard<'a, Status>) -> MutexGuard<'a, Status> {
+        self.changed
+            .wait(status)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn notify(&self) {
+        if self.workers.load(SeqCst) == 0 {
+            return;
+        }
+        self.status().pending = true;
+        self.changed.notify_all();
+    }
+}
+
+/// A [`SyncBuffer`] that a [`Worker`] can recompute without knowing its type.
+trait Refresh: Send + Sync {
+    fn refresh(&self);
+}
+
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A [`SyncDcg`] node.
+pub struct SyncNode {
+    inner: Arc<Inner>,
+    idx: NodeIndex,
+    state: Arc<NodeState>,
+}
+
+impl SyncNode {
+    fn new(dcg: &SyncDcg, dependencies: &[&SyncNode]) -> Self {
+        let state = Arc::new(NodeState {
+            dirty: AtomicBool::new(true),
+            version: AtomicU64::new(0),
+        });
+        let mut topology = write(&dcg.inner.topology);
+        let idx = topology.graph.add_node(state.clone());
+        for dependency in dependencies {
+            assert!(
+                Arc::ptr_eq(&dependency.inner, &dcg.inner),
+                "dependencies must belong to the same SyncDcg"
+            );
+            topology.graph.add_edge(dependency.idx, idx, ());
+        }
+        Self {
+            inner: dcg.inner.clone(),
+            idx,
+            state,
+        }
+    }
+
+    /// Returns the node's index in its [`SyncDcg`].
+    pub fn index(&self) -> NodeIndex {
+        self.idx
+    }
+
+    /// Returns whether the node is dirty.
+    pub fn is_dirty(&self) -> bool {
+        self.state.dirty.load(SeqCst)
+    }
+
+    fn version(&self) -> u64 {
+        self.state.version.load(SeqCst)
+    }
+
+    /// Cleans the node unless it has been dirtied since `version`, returning whether it did.
+    ///
+    /// Writers bump the version before dirtying, so rechecking it after cleaning catches a write
+    /// racing the clean.
+    fn clean(&self, version: u64) -> bool {
+        if self.version() != version {
+            return false;
+        }
+        self.state.dirty.store(false, SeqCst);
+        if self.version() != version {
+            self.state.dirty.store(true, SeqCst);
+            return false;
+        }
+        true
+    }
+}
+
+/// A thread-safe [`Var`](crate::Var).
+pub type SyncVar<T> = Arc<RawSyncVar<T>>;
+
+/// A thread-safe [`Thunk`](crate::Thunk).
+pub type SyncThunk<T> = Arc<RawSyncThunk<T>>;
+
+/// A thread-safe [`Buffer`](crate::Buffer).
+pub type SyncBuffer<T> = Arc<RawSyncBuffer<T>>;
+
+type Closure<T> = Box<dyn Fn() -> T + Send + Sync>;
+
+impl SyncDcg {
+    /// Creates an empty [`SyncDcg`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a [`SyncVar`] holding `value`.
+    pub fn var<T>(&self, value: T) -> SyncVar<T>
+    where
+        T: Send + Sync + Clone,
+    {
+        Arc::new(RawSyncVar {
+            value: RwLock::new(value),
+            node: SyncNode::new(self, &[]),
+        })
+    }
+
+    /// Creates a [`SyncThunk`] computing `f`, which reads `dependencies`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::SyncDcg;
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let a = dcg.var(1);
+    /// let b = dcg.thunk(&[a.node()], {
+    ///     let a = a.clone();
+    ///     move || a.read() + 1
+    /// });
+    ///
+    /// a.write(2);
+    /// assert_eq!(b.read(), 3);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dependency belongs to another [`SyncDcg`].
+    pub fn thunk<T, F>(&self, dependencies: &[&SyncNode], f: F) -> SyncThunk<T>
+    where
+        T: Send + Sync + Clone,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        Arc::new(RawSyncThunk {
+            f: Box::new(f),
+            node: SyncNode::new(self, dependencies),
+        })
+    }
+
+    /// Creates a [`SyncBuffer`] buffering the latest result of `f`, which reads `dependencies`.
+    ///
+    /// Out of date [`SyncBuffer`]s are recomputed when read, or in the background by a
+    /// [`Worker`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::SyncDcg;
+    /// use std::thread;
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let a = dcg.var(1);
+    /// let b = dcg.buffer(&[a.node()], {
+    ///     let a = a.clone();
+    ///     move || a.read() * 10
+    /// });
+    ///
+    /// thread::spawn(move || a.write(2)).join().unwrap();
+    /// assert_eq!(b.read(), 20);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dependency belongs to another [`SyncDcg`].
+    pub fn buffer<T, F>(&self, dependencies: &[&SyncNode], f: F) -> SyncBuffer<T>
+    where
+        T: Send + Sync + Clone + 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let buffer = Arc::new(RawSyncBuffer {
+            thunk: RawSyncThunk {
+                f: Box::new(f),
+                node: SyncNode::new(self, dependencies),
+            },
+            buffered: RwLock::new(None),
+        });
+        let weak: Weak<dyn Refresh> = Arc::downgrade(&buffer) as _;
+        write(&self.inner.topology)
+            .buffers
+            .insert(buffer.thunk.node.idx, weak);
+        buffer
+    }
+
+    /// Spawns a thread recomputing out of date [`SyncBuffer`]s, in topological order, whenever
+    /// a [`SyncVar`] changes.
+    ///
+    /// Writes return without waiting for the [`Worker`]; use [`Worker::flush`] to wait for it,
+    /// and [`RawSyncBuffer::last_computed`] to read values without recomputing them. Dropping the
+    /// [`Worker`] stops its thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::SyncDcg;
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let a = dcg.var(1);
+    /// let b = dcg.buffer(&[a.node()], {
+    ///     let a = a.clone();
+    ///     move || a.read() * 10
+    /// });
+    /// let worker = dcg.spawn_worker();
+    ///
+    /// a.write(2);
+    /// worker.flush();
+    /// assert_eq!(b.last_computed(), Some(20));
+    /// assert!(!b.node().is_dirty());
+    /// ```
+    pub fn spawn_worker(&self) -> Worker {
+        self.inner.signal.workers.fetch_add(1, SeqCst);
+        self.inner.signal.notify();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let pass = Arc::new(Mutex::new(CancellationToken::new()));
+        let inner = self.inner.clone();
+        let (thread_stopped, thread_pass) = (stopped.clone(), pass.clone());
+        let thread = thread::spawn(move || {
+            let (signal, stopped) = (&inner.signal, thread_stopped);
+            loop {
+                {
+                    let mut status = signal.status();
+                    while !status.pending && !stopped.load(SeqCst) {
+                        status = signal.wait(status);
+                    }
+                    if stopped.load(SeqCst) {
+                        return;
+                    }
+                    status.pending = false;
+                    status.busy += 1;
+                }
+                let token = CancellationToken::new();
+                *thread_pass.lock().unwrap_or_else(PoisonError::into_inner) = token.clone();
+                let refreshed = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let _ = refresh_all(&inner, &token);
+                }));
+                if refreshed.is_err() {
+                    stopped.store(true, SeqCst);
+                }
+                signal.status().busy -= 1;
+                signal.changed.notify_all();
+            }
+        });
+        Worker {
+            inner: self.inner.clone(),
+            stopped,
+            pass,
+            thread: Some(thread),
+        }
+    }
+
+    /// Recomputes every out of date [`SyncBuffer`], lowest [height](SyncDcg::height) first.
+    ///
+    /// [`SyncBuffer`]s of equal height are recomputed highest
+    /// [priority](SyncDcg::set_priority) first, then in creation order. [`Worker`]s use the same
+    /// order.
+    pub fn refresh_all(&self) {
+        let _ = refresh_all(&self.inner, &CancellationToken::new());
+    }
+
+    /// Like [`refresh_all`](SyncDcg::refresh_all), but stops before the next [`SyncBuffer`] once
+    /// `token` is cancelled.
+    ///
+    /// [`SyncBuffer`]s recomputed before the cancellation stay clean, and the rest stay dirty, so
+    /// a later pass resumes where this one stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::{CancellationToken, Cancelled, SyncDcg};
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let a = dcg.var(1);
+    /// let b = dcg.buffer(&[a.node()], {
+    ///     let a = a.clone();
+    ///     move || a.read() + 1
+    /// });
+    /// let token = CancellationToken::new();
+    ///
+    /// token.cancel();
+    /// assert_eq!(dcg.refresh_all_with(&token), Err(Cancelled { remaining: 1 }));
+    /// assert!(b.node().is_dirty());
+    /// assert_eq!(dcg.refresh_all_with(&CancellationToken::new()), Ok(()));
+    /// assert_eq!(b.last_computed(), Some(2));
+    /// ```
+    pub fn refresh_all_with(&self, token: &CancellationToken) -> Result<(), Cancelled> {
+        refresh_all(&self.inner, token)
+    }
+
+    /// Writes each of `updates`' values into its [`SyncVar`] as one atomic change, returning how
+    /// many [`SyncVar`]s changed.
+    ///
+    /// Code running in [`read_consistent`](SyncDcg::read_consistent) sees either all of the
+    /// writes or none of them. [`Worker`]s are only notified once every write has been applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::SyncDcg;
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let (a, b) = (dcg.var(1), dcg.var(1));
+    ///
+    /// assert_eq!(dcg.commit(&[(&a, 2), (&b, 1)]), 1);
+    /// assert_eq!(dcg.read_consistent(|| (a.read(), b.read())), (2, 1));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a [`SyncVar`] belongs to another [`SyncDcg`].
+    ///
+    /// Deadlocks if called within [`read_consistent`](SyncDcg::read_consistent).
+    pub fn commit<T>(&self, updates: &[(&SyncVar<T>, T)]) -> usize
+    where
+        T: PartialEq + Clone,
+    {
+        let changed = {
+            let _commit = write(&self.inner.commit);
+            updates
+                .iter()
+                .filter(|(var, value)| {
+                    assert!(
+                        Arc::ptr_eq(&var.node.inner, &self.inner),
+                        "committed vars must belong to the SyncDcg"
+                    );
+                    var.replace(|_| value.clone()).1
+                })
+                .count()
+        };
+        if changed > 0 {
+            self.inner.signal.notify();
+        }
+        changed
+    }
+
+    /// Runs `f` while no [`commit`](SyncDcg::commit) is in progress, so every [`SyncVar`] it
+    /// reads reflects the same set of commits.
+    ///
+    /// Plain [`write`](RawSyncVar::write)s are not excluded.
+    pub fn read_consistent<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _commit = read(&self.inner.commit);
+        f()
+    }
+
+    /// Returns the number of nodes in the [`SyncDcg`].
+    pub fn node_count(&self) -> usize {
+        read(&self.inner.topology).graph.node_count()
+    }
+
+    /// Returns the length of the longest chain of dependencies leading to `node`: 0 for
+    /// [`SyncVar`]s, and one more than the greatest height among its dependencies otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::SyncDcg;
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let a = dcg.var(1);
+    /// let b = dcg.buffer(&[a.node()], || 1);
+    /// let c = dcg.buffer(&[a.node(), b.node()], || 2);
+    ///
+    /// assert_eq!(dcg.height(c.node()), 2);
+    /// ```
+    pub fn height(&self, node: &SyncNode) -> usize {
+        read(&self.inner.topology).heights()[&node.idx]
+    }
+
+    /// Sets the priority of `node` among nodes of the same [height](SyncDcg::height) when
+    /// [refreshing](SyncDcg::refresh_all): higher priorities are recomputed first. Priorities
+    /// default to 0.
+    pub fn set_priority(&self, node: &SyncNode, priority: i32) {
+        write(&self.inner.topology)
+            .priorities
+            .insert(node.idx, priority);
+    }
+}
+
+impl SyncDcg {
+    /// Reads each of `outputs` and publishes their values together as the next [`Frame`],
+    /// returning its number.
+    ///
+    /// Readers of [`front`](SyncDcg::front) see either all of the [`Frame`]'s values or none of
+    /// them. The values are read while no [`commit`](SyncDcg::commit) is in progress, so a
+    /// [`Frame`] never holds part of one, and concurrent publishes are numbered in the order they
+    /// read their values. Frames are numbered from 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::sync::SyncDcg;
+    ///
+    /// let dcg = SyncDcg::new();
+    /// let a = dcg.var(1);
+    /// let b = dcg.buffer(&[a.node()], {
+    ///     let a = a.clone();
+    ///     move || a.read() * 10
+    /// });
+    ///
+    /// assert_eq!(dcg.publish(&[&*a, &*b]), 1);
+    /// a.write(2);
+    /// let frame = dcg.front();
+    /// assert_eq!(frame.get::<i32>(b.node()), Some(10));
+    /// assert_eq!(frame.number(), 1);
+    /// ```
+    ///
+    /// Deadlocks if called within [`read_consistent`](SyncDcg::read_consistent).
+    pub fn publish(&self, outputs: &[&dyn Publish]) -> u64 {
+        let _publish = self
+            .inner
+            .publish
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _commit = read(&self.inner.commit);
+        let values = outputs
+            .iter()
+            .map(|output| (output.node().idx, output.value()))
+            .collect();
+        let mut front = write(&self.inner.front);
+        let number = front.number + 1;
+        *front = Arc::new(Frame { number, values });
+        number
+    }
+
+    /// Returns the latest published [`Frame`], without locking the [`SyncDcg`]'s nodes.
+    ///
+    /// Before the first [`publish`](SyncDcg::publish), this is an empty frame numbered 0.
+    pub fn front(&self) -> Arc<Frame> {
+        read(&self.inner.front).clone()
+    }
+}
+
+/// A set of values published together by [`SyncDcg::publish`].
+#[derive(Default)]
+pub struct Frame {
+    number: u64,
+    values: HashMap<NodeIndex, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Frame {
+    /// Returns the frame's number.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Returns `node`'s published value, or [`None`] if it was not published in this frame or
+    /// is not a `T`.
+    pub fn get<T: Clone + 'static>(&self, node: &SyncNode) -> Option<T> {
+        self.values.get(&node.idx)?.downcast_ref().cloned()
+    }
+}
+
+/// A [`SyncDcg`] node whose value can be [published](SyncDcg::publish).
+pub trait Publish {
+    /// Returns the node.
+    fn node(&self) -> &SyncNode;
+
+    /// Reads the node's value.
+    fn value(&self) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Clone + Send + Sync + 'static> Publish for RawSyncVar<T> {
+    fn node(&self) -> &SyncNode {
+        &self.node
+    }
+
+    fn value(&self) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(self.read())
+    }
+}
+
+impl<T: Send + Sync + 'static> Publish for RawSyncThunk<T> {
+    fn node(&self) -> &SyncNode {
+        &self.node
+    }
+
+    fn value(&self) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(self.read())
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Publish for RawSyncBuffer<T> {
+    fn node(&self) -> &SyncNode {
+        &self.thunk.node
+    }
+
+    fn value(&self) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(self.read())
+    }
+}
+
+/// The data behind a [`SyncVar`].
+pub struct RawSyncVar<T> {
+    value: RwLock<T>,
+    node: SyncNode,
+}
+
+impl<T: Clone> RawSyncVar<T> {
+    /// Returns the [`SyncVar`]'s [`SyncNode`].
+    pub fn node(&self) -> &SyncNode {
+        &self.node
+    }
+
+    /// Returns the [`SyncVar`]'s value, cleaning it.
+    pub fn read(&self) -> T {
+        self.node.state.dirty.store(false, SeqCst);
+        read(&self.value).clone()
+    }
+}
+
+impl<T: PartialEq + Clone> RawSyncVar<T> {
+    /// Writes a value into the [`SyncVar`] and dirties its dependents if necessary, returning the
+    /// old value. See [`RawVar::write`](crate::RawVar::write).
+    pub fn write(&self, new: T) -> T {
+        self.modify(|_| new)
+    }
+
+    /// Writes `f` applied to the [`SyncVar`]'s value into it, returning the old value. See
+    /// [`write`](RawSyncVar::write).
+    ///
+    /// The [`SyncVar`] is locked while `f` runs, so `f` must not access it.
+    pub fn modify<F>(&self, f: F) -> T
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let (old, changed) = self.replace(f);
+        if changed {
+            self.node.inner.signal.notify();
+        }
+        old
+    }
+
+    /// Writes `f` applied to the [`SyncVar`]'s value into it and dirties its dependents, without
+    /// notifying [`Worker`]s. Returns the old value and whether it changed.
+    fn replace<F>(&self, f: F) -> (T, bool)
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let mut value = write(&self.value);
+        let new = f(&value);
+        if *value == new {
+            return (new, false);
+        }
+        let old = std::mem::replace(&mut *value, new);
+        {
+            let topology = read(&self.node.inner.topology);
+            depth_first_search(&topology.graph, Some(self.node.idx), |event| {
+                if let DfsEvent::Discover(n, _) = event {
+                    let state = &topology.graph[n];
+                    state.version.fetch_add(1, SeqCst);
+                    state.dirty.store(true, SeqCst);
+                }
+            });
+        }
+        (old, true)
+    }
+}
+
+fn refresh_all(inner: &Inner, token: &CancellationToken) -> Result<(), Cancelled> {
+    let buffers = out_of_date(&read(&inner.topology));
+    for (refreshed, buffer) in buffers.iter().enumerate() {
+        if token.is_cancelled() {
+            return Err(Cancelled {
+                remaining: buffers.len() - refreshed,
+            });
+        }
+        buffer.refresh();
+    }
+    Ok(())
+}
+
+/// A flag requesting that a [`refresh_all_with`](SyncDcg::refresh_all_with) or a [`Worker`]'s
+/// pass stop early. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token and its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, SeqCst);
+    }
+
+    /// Returns whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(SeqCst)
+    }
+}
+
+/// Returned by [`refresh_all_with`](SyncDcg::refresh_all_with) when its token was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled {
+    /// The number of out of date [`SyncBuffer`]s left dirty.
+    pub remaining: usize,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "refresh cancelled with {} buffers remaining",
+            self.remaining
+        )
+    }
+}
+
+impl error::Error for Cancelled {}
+
+/// Returns the dirty [`SyncBuffer`]s that are still alive, lowest height first, then highest
+/// priority first.
+fn out_of_date(topology: &Topology) -> Vec<Arc<dyn Refresh>> {
+    let heights = topology.heights();
+    let mut queue: BinaryHeap<_> = topology
+        .buffers
+        .keys()
+        .filter(|idx| topology.graph[**idx].dirty.load(SeqCst))
+        .map(|&idx| {
+            let priority = topology.priorities.get(&idx).copied().unwrap_or_default();
+            (Reverse(heights[&idx]), priority, Reverse(idx))
+        })
+        .collect();
+    iter::from_fn(|| queue.pop())
+        .filter_map(|(_, _, Reverse(idx))| topology.buffers[&idx].upgrade())
+        .collect()
+}
+
+/// A thread recomputing a [`SyncDcg`]'s [`SyncBuffer`]s, returned by [`SyncDcg::spawn_worker`].
+pub struct Worker {
+    inner: Arc<Inner>,
+    /// Set when the [`Worker`] is dropped, or when a closure it ran panicked.
+    stopped: Arc<AtomicBool>,
+    /// Cancels the pass in progress.
+    pass: Arc<Mutex<CancellationToken>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Stops the [`Worker`]'s pass in progress before its next [`SyncBuffer`].
+    ///
+    /// [`SyncBuffer`]s the pass did not reach stay dirty until the next [`SyncVar`] change
+    /// starts another pass.
+    pub fn cancel_pass(&self) {
+        self.pass
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .cancel();
+    }
+
+    /// Blocks until the [`Worker`] has refreshed every [`SyncBuffer`] after the latest
+    /// [`SyncVar`] change, or has stopped because a closure panicked.
+    pub fn flush(&self) {
+        let signal = &self.inner.signal;
+        let mut status = signal.status();
+        while (status.pending || status.busy > 0) && !self.stopped.load(SeqCst) {
+            status = signal.wait(status);
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let signal = &self.inner.signal;
+        self.cancel_pass();
+        {
+            let _status = signal.status();
+            self.stopped.store(true, SeqCst);
+        }
+        signal.changed.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        signal.workers.fetch_sub(1, SeqCst);
+    }
+}
+
+/// The data behind a [`SyncThunk`].
+pub struct RawSyncThunk<T> {
+    f: Closure<T>,
+    node: SyncNode,
+}
+
+impl<T> RawSyncThunk<T> {
+    /// Returns the [`SyncThunk`]'s [`SyncNode`].
+    pub fn node(&self) -> &SyncNode {
+        &self.node
+    }
+
+    /// Computes the [`SyncThunk`]'s value, cleaning it unless a [`SyncVar`] changed meanwhile.
+    pub fn read(&self) -> T {
+        let version = self.node.version();
+        let value = (self.f)();
+        self.node.clean(version);
+        value
+    }
+}
+
+/// The data behind a [`SyncBuffer`].
+pub struct RawSyncBuffer<T> {
+    thunk: RawSyncThunk<T>,
+    buffered: RwLock<Option<T>>,
+}
+
+impl<T: Clone> RawSyncBuffer<T> {
+    /// Returns the [`SyncBuffer`]'s [`SyncNode`].
+    pub fn node(&self) -> &SyncNode {
+        &self.thunk.node
+    }
+
+    /// Returns the [`SyncBuffer`]'s buffered value if it is clean, or else recomputes it.
+    ///
+    /// The recomputed value is only buffered, and the [`SyncBuffer`] cleaned, if no
+    /// [`SyncVar`] changed while it was computed.
+    pub fn read(&self) -> T {
+        let node = &self.thunk.node;
+        let version = node.version();
+        if !node.is_dirty() {
+            if let Some(value) = &*read(&self.buffered) {
+                return value.clone();
+            }
+        }
+        let value = (self.thunk.f)();
+        let mut buffered = write(&self.buffered);
+        if node.version() == version {
+            *buffered = Some(value.clone());
+            node.clean(version);
+        }
+        value
+    }
+
+    /// Returns the [`SyncBuffer`]'s buffered value without recomputing it, even if it is out of
+    /// date.
+    pub fn last_computed(&self) -> Option<T> {
+        read(&self.buffered).clone()
+    }
+}
+
+impl<T: Clone + Send + Sync> Refresh for RawSyncBuffer<T> {
+    fn refresh(&self) {
+        self.read();
+    }
+}
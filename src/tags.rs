@@ -0,0 +1,144 @@
+//! Grouping nodes under tags with [`Dcg::tag`], and operating on every node with a tag.
+
+use std::{collections::HashSet, rc::Rc};
+
+use petgraph::graph::NodeIndex;
+
+use crate::{CacheStats, Dcg, DcgError, Node};
+
+impl Dcg {
+    /// Adds `tag` to `node`. Nodes can have any number of tags.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// dcg.tag(a.node(), "physics");
+    /// dcg.tag(b.node(), "physics");
+    /// dcg.tag(b.node(), "ui");
+    ///
+    /// assert_eq!(dcg.nodes_with_tag("physics"), vec![a.node().index(), b.node().index()]);
+    /// assert_eq!(dcg.nodes_with_tag("ui"), vec![b.node().index()]);
+    /// assert!(dcg.nodes_with_tag("audio").is_empty());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn tag(&self, node: &Node, tag: &str) {
+        assert!(
+            Rc::ptr_eq(&node.inner, &self.inner),
+            "tagged nodes must belong to the Dcg"
+        );
+        self.inner
+            .tags
+            .borrow_mut()
+            .entry(tag.to_owned())
+            .or_default()
+            .insert(node.idx);
+    }
+
+    /// Removes `tag` from `node`, returning whether it had the tag.
+    pub fn untag(&self, node: &Node, tag: &str) -> bool {
+        let mut tags = self.inner.tags.borrow_mut();
+        let Some(nodes) = tags.get_mut(tag) else {
+            return false;
+        };
+        let removed = Rc::ptr_eq(&node.inner, &self.inner) && nodes.remove(&node.idx);
+        if nodes.is_empty() {
+            tags.remove(tag);
+        }
+        removed
+    }
+
+    /// Returns the indices of the nodes with `tag`, in index order.
+    pub fn nodes_with_tag(&self, tag: &str) -> Vec<NodeIndex> {
+        let mut nodes: Vec<_> = self
+            .inner
+            .tags
+            .borrow()
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        nodes.sort();
+        nodes
+    }
+
+    /// Dirties the nodes with `tag` and their transitive dependents, and discards the cached
+    /// values of those that are [`Memo`](crate::Memo)s or [`Buffer`](crate::Buffer)s, so they
+    /// execute their closures when next read. [Frozen](Node::is_frozen) nodes are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental, memo};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = memo!(dcg, a => a + 1);
+    /// let probe = dcg.probe(&b);
+    /// dcg.tag(b.node(), "ui");
+    ///
+    /// b.read();
+    /// dcg.invalidate_tag("ui");
+    /// assert!(b.is_dirty());
+    /// b.read();
+    /// assert_eq!(probe.recomputations(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated.
+    pub fn invalidate_tag(&self, tag: &str) {
+        let nodes: Vec<_> = self
+            .nodes_with_tag(tag)
+            .into_iter()
+            .filter(|idx| !self.inner.frozen.borrow().contains(idx))
+            .collect();
+        if let (Some(evaluation), Some(&target)) =
+            (self.inner.evaluating.borrow().last(), nodes.first())
+        {
+            panic!(
+                "{}",
+                DcgError::MutationDuringEvaluation {
+                    evaluating: evaluation.node,
+                    target,
+                }
+            );
+        }
+        for idx in nodes {
+            let refresher = self.inner.refreshers.borrow().get(&idx).cloned();
+            if let Some(refresher) = refresher {
+                refresher.forget();
+            }
+            let node = Node::at(self.inner.clone(), idx);
+            node.dirty_dependents();
+            node.debug_check();
+        }
+    }
+
+    /// Evaluates the dirty [`Memo`](crate::Memo)s and [`Buffer`](crate::Buffer)s with `tag`, and
+    /// whatever they read, returning how many tagged nodes were evaluated.
+    pub fn clean_tag(&self, tag: &str) -> usize {
+        let nodes: HashSet<_> = self.nodes_with_tag(tag).into_iter().collect();
+        self.refresh_where(|idx| nodes.contains(&idx))
+    }
+
+    /// Returns the sum of the [`cache_stats`](Dcg::cache_stats) of the nodes with `tag`.
+    pub fn stats_for_tag(&self, tag: &str) -> CacheStats {
+        let stats = self.inner.cache_stats.borrow();
+        self.nodes_with_tag(tag)
+            .iter()
+            .filter_map(|idx| stats.get(idx))
+            .fold(CacheStats::default(), |total, stats| CacheStats {
+                hits: total.hits + stats.hits,
+                misses: total.misses + stats.misses,
+            })
+    }
+}
@@ -0,0 +1,450 @@
+//! Utilities for testing code built on a [`Dcg`].
+//!
+//! A [`Probe`] records every value a node produces, replacing hand-rolled counters in closures:
+//!
+//! ```
+//! use cachegrab::{Dcg, buffer, incremental::Incremental};
+//!
+//! let dcg = Dcg::default();
+//! let a = dcg.var(1);
+//! let b = buffer!(dcg, a => a * 2);
+//! let probe = dcg.probe(&b);
+//!
+//! b.read();
+//! b.read();
+//! a.write(2);
+//! b.read();
+//! assert_eq!(probe.recomputations(), 2);
+//! assert_eq!(probe.values(), vec![2, 4]);
+//! ```
+//!
+//! A [`MockClock`] stands in for the system time when testing [`Dcg::memo_with_ttl`].
+//!
+//! [`Dcg::assert_stable`] and [`Dcg::audit_stability`] find nodes whose closures don't always
+//! produce the same value from the same inputs, such as ones reading the time or a random number
+//! generator, which caching would otherwise hide.
+//!
+//! With the `arbitrary` feature, [`arbitrary`] generates random [`Dcg`]s for property testing.
+
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    hash::Hash,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use petgraph::graph::NodeIndex;
+
+use crate::{
+    clock::Clock, verify::PlaceholderGuard, Dcg, Node, RawBuffer, RawMemo, RawThunk, RawVar,
+};
+
+/// Records the values produced by a node, as returned by [`Dcg::probe`]. Clones share the
+/// record.
+pub struct Probe<T> {
+    values: Rc<RefCell<Vec<T>>>,
+}
+
+impl<T> Clone for Probe<T> {
+    fn clone(&self) -> Self {
+        Self {
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Probe<T> {
+    /// Returns the number of values produced since the [`Probe`] was created: how many times a
+    /// [`Thunk`](crate::Thunk), [`Memo`](crate::Memo) or [`Buffer`](crate::Buffer) executed its
+    /// closure, or how many times a [`Var`](crate::Var)'s value changed.
+    pub fn recomputations(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    /// Returns the most recently produced value.
+    pub fn last_value(&self) -> Option<T> {
+        self.values.borrow().last().cloned()
+    }
+
+    /// Returns every value produced since the [`Probe`] was created, oldest first.
+    pub fn values(&self) -> Vec<T> {
+        self.values.borrow().clone()
+    }
+}
+
+/// Nodes that can be [probed](Dcg::probe).
+pub trait Probed {
+    /// The type of the node's values.
+    type Value;
+
+    /// Returns the node's [`Node`].
+    fn probed_node(&self) -> &Node;
+
+    /// Registers `record` to be called with every value the node produces.
+    fn on_value<F>(&self, record: F)
+    where
+        F: FnMut(&Self::Value) + 'static;
+}
+
+impl<T> Probed for RawVar<T> {
+    type Value = T;
+
+    fn probed_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn on_value<F>(&self, mut record: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.observe(move |_, new| record(new));
+    }
+}
+
+impl<T> Probed for RawThunk<T> {
+    type Value = T;
+
+    fn probed_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn on_value<F>(&self, record: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.on_recompute(record);
+    }
+}
+
+impl<A: Eq + Hash, T> Probed for RawMemo<A, T> {
+    type Value = T;
+
+    fn probed_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn on_value<F>(&self, record: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.on_recompute(record);
+    }
+}
+
+impl<T> Probed for RawBuffer<T> {
+    type Value = T;
+
+    fn probed_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn on_value<F>(&self, record: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.on_recompute(record);
+    }
+}
+
+impl<P: Probed> Probed for Rc<P> {
+    type Value = P::Value;
+
+    fn probed_node(&self) -> &Node {
+        self.as_ref().probed_node()
+    }
+
+    fn on_value<F>(&self, record: F)
+    where
+        F: FnMut(&Self::Value) + 'static,
+    {
+        self.as_ref().on_value(record);
+    }
+}
+
+impl Dcg {
+    /// Returns a [`Probe`] recording every value `node` produces from now on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn probe<N>(&self, node: &N) -> Probe<N::Value>
+    where
+        N: Probed,
+        N::Value: Clone + 'static,
+    {
+        assert!(
+            Rc::ptr_eq(&node.probed_node().inner, &self.inner),
+            "probed nodes must belong to the Dcg"
+        );
+        let values = Rc::new(RefCell::new(Vec::new()));
+        let record = values.clone();
+        node.on_value(move |value: &N::Value| record.borrow_mut().push(value.clone()));
+        Probe { values }
+    }
+}
+
+/// Nodes whose closures can be [checked for stability](Dcg::assert_stable).
+pub trait Recomputable {
+    /// The type of the node's values.
+    type Value;
+
+    /// Returns the node's [`Node`].
+    fn recomputable_node(&self) -> &Node;
+
+    /// Executes the node's closure, without caching the value or cleaning the node.
+    fn recompute(&self) -> Self::Value;
+}
+
+impl<T> Recomputable for RawThunk<T> {
+    type Value = T;
+
+    fn recomputable_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn recompute(&self) -> T {
+        self.compute()
+    }
+}
+
+impl<A: Eq + Hash, T> Recomputable for RawMemo<A, T> {
+    type Value = T;
+
+    fn recomputable_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn recompute(&self) -> T {
+        self.thunk.compute()
+    }
+}
+
+impl<T> Recomputable for RawBuffer<T> {
+    type Value = T;
+
+    fn recomputable_node(&self) -> &Node {
+        self.node()
+    }
+
+    fn recompute(&self) -> T {
+        self.thunk.compute()
+    }
+}
+
+impl<R: Recomputable> Recomputable for Rc<R> {
+    type Value = R::Value;
+
+    fn recomputable_node(&self) -> &Node {
+        self.as_ref().recomputable_node()
+    }
+
+    fn recompute(&self) -> Self::Value {
+        self.as_ref().recompute()
+    }
+}
+
+/// Checks a node [tracked](Dcg::track_stability) for stability over a number of trials,
+/// returning [`None`] once it has been dropped.
+pub(crate) type StabilityCheck = Rc<dyn Fn(usize) -> Option<bool>>;
+
+impl Dcg {
+    /// Executes `node`'s closure `trials` times with the same inputs, returning every value
+    /// produced if they aren't all equal.
+    ///
+    /// The values are neither cached nor returned by reads. Dependencies evaluated along the way
+    /// keep the values they compute, but every node's dirtiness, and the record of what its
+    /// cache hits and misses, is restored afterwards, so nothing is considered up to date that
+    /// wasn't before. The closure must not write to the [`Dcg`], which panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    /// use std::cell::Cell;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let stable = buffer!(dcg, a => a + 1);
+    /// let calls = Cell::new(0);
+    /// let unstable = buffer!(dcg, a => {
+    ///     calls.set(calls.get() + 1);
+    ///     a + calls.get()
+    /// });
+    ///
+    /// assert_eq!(dcg.check_stable(&stable, 3), Ok(()));
+    /// assert_eq!(dcg.check_stable(&unstable, 3), Err(vec![2, 3, 4]));
+    /// assert!(stable.is_dirty() && unstable.is_dirty());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn check_stable<N>(&self, node: &N, trials: usize) -> Result<(), Vec<N::Value>>
+    where
+        N: Recomputable,
+        N::Value: PartialEq,
+    {
+        let idx = node.recomputable_node().idx;
+        assert!(
+            Rc::ptr_eq(&node.recomputable_node().inner, &self.inner),
+            "checked nodes must belong to the Dcg"
+        );
+        let values: Vec<_> = self.preserving_dirtiness(|| {
+            (0..trials)
+                .map(|_| {
+                    let _guard = PlaceholderGuard::push(&self.inner, idx);
+                    node.recompute()
+                })
+                .collect()
+        });
+        if values.windows(2).all(|pair| pair[0] == pair[1]) {
+            Ok(())
+        } else {
+            Err(values)
+        }
+    }
+
+    /// Like [`check_stable`](Dcg::check_stable), but panics, listing the values produced, if they
+    /// aren't all equal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` produces different values, or belongs to another [`Dcg`].
+    pub fn assert_stable<N>(&self, node: &N, trials: usize)
+    where
+        N: Recomputable,
+        N::Value: PartialEq + fmt::Debug,
+    {
+        if let Err(values) = self.check_stable(node, trials) {
+            panic!(
+                "node {} produced different values from the same inputs: {:?}",
+                node.recomputable_node().idx.index(),
+                values
+            );
+        }
+    }
+
+    /// Registers `node` to be checked by [`audit_stability`](Dcg::audit_stability), for as long
+    /// as it is alive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` belongs to another [`Dcg`].
+    pub fn track_stability<R>(&self, node: &Rc<R>)
+    where
+        R: Recomputable + 'static,
+        R::Value: PartialEq,
+    {
+        let idx = node.recomputable_node().idx;
+        assert!(
+            Rc::ptr_eq(&node.recomputable_node().inner, &self.inner),
+            "tracked nodes must belong to the Dcg"
+        );
+        let node = Rc::downgrade(node);
+        let dcg = Rc::downgrade(&self.inner);
+        let check: StabilityCheck = Rc::new(move |trials| {
+            let (node, inner) = (node.upgrade()?, dcg.upgrade()?);
+            Some(Dcg { inner }.check_stable(&node, trials).is_ok())
+        });
+        self.inner.stability.borrow_mut().insert(idx, check);
+    }
+
+    /// [Checks](Dcg::check_stable) every node registered with
+    /// [`track_stability`](Dcg::track_stability) over `trials` trials, returning those producing
+    /// different values, sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    /// use std::cell::Cell;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let stable = buffer!(dcg, a => a + 1);
+    /// let calls = Cell::new(0);
+    /// let unstable = buffer!(dcg, a => {
+    ///     calls.set(calls.get() + 1);
+    ///     a + calls.get()
+    /// });
+    /// dcg.track_stability(&stable);
+    /// dcg.track_stability(&unstable);
+    ///
+    /// assert_eq!(dcg.audit_stability(2), vec![unstable.node().index()]);
+    /// ```
+    pub fn audit_stability(&self, trials: usize) -> Vec<NodeIndex> {
+        let checks: Vec<_> = self
+            .inner
+            .stability
+            .borrow()
+            .iter()
+            .map(|(&idx, check)| (idx, check.clone()))
+            .collect();
+        let mut unstable = Vec::new();
+        for (idx, check) in checks {
+            match check(trials) {
+                Some(true) => {}
+                Some(false) => unstable.push(idx),
+                None => {
+                    self.inner.stability.borrow_mut().remove(&idx);
+                }
+            }
+        }
+        unstable.sort_unstable();
+        unstable
+    }
+
+    /// Runs `f`, then restores every node's dirtiness and cache statistics to what they were.
+    fn preserving_dirtiness<R>(&self, f: impl FnOnce() -> R) -> R {
+        let flags: Vec<_> = {
+            let graph = self.inner.graph.borrow();
+            graph.node_indices().map(|idx| (idx, graph[idx])).collect()
+        };
+        let verified = self.inner.verified.borrow().clone();
+        let cache_stats = self.inner.cache_stats.borrow().clone();
+        let result = f();
+        {
+            let mut graph = self.inner.graph.borrow_mut();
+            for (idx, dirty) in flags {
+                graph[idx] = dirty;
+            }
+        }
+        self.inner.verified.replace(verified);
+        self.inner.new_revision();
+        self.inner.cache_stats.replace(cache_stats);
+        result
+    }
+}
+
+/// A [`Clock`] that only moves when [advanced](MockClock::advance), for testing
+/// [`Dcg::memo_with_ttl`] without sleeping.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Cell<Instant>,
+}
+
+impl Default for MockClock {
+    /// Creates a [`MockClock`] reading the current system time until advanced.
+    fn default() -> Self {
+        Self {
+            now: Cell::new(Instant::now()),
+        }
+    }
+}
+
+impl MockClock {
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
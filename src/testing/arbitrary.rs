@@ -0,0 +1,324 @@
+//! Random [`Dcg`] shapes for property testing, with a brute-force evaluator to compare reads
+//! against.
+//!
+//! A [`Shape`] is generated from a seeded [`Rng`] and a [`Config`], [built](Shape::build) into a
+//! [`Dcg`] of `i64` nodes computing simple arithmetic on their inputs, and
+//! [evaluated](Shape::evaluate) from scratch given the [`Var`]s' values:
+//!
+//! ```
+//! use cachegrab::{Dcg, testing::arbitrary::{Config, Rng, Shape}};
+//!
+//! let mut rng = Rng::new(7);
+//! let shape = Shape::generate(&Config::default(), &mut rng);
+//! let dcg = Dcg::default();
+//! let built = shape.build(&dcg);
+//!
+//! for var in shape.vars() {
+//!     built.write(var, rng.below(10) as i64);
+//! }
+//! let expected = shape.evaluate(&built.var_values());
+//! for node in 0..shape.len() {
+//!     assert_eq!(built.read(node), expected[node]);
+//! }
+//! ```
+
+use crate::{incremental::Incremental, Buffer, Dcg, Memo, Node, NodeKind, Thunk, Var};
+
+/// A small, seeded pseudo-random number generator (xorshift64*), so failures reproduce from
+/// their seed.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Creates a generator from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    /// Returns the next pseudo-random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in `0..n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "the range must not be empty");
+        self.next_u64() % n
+    }
+
+    /// Returns `true` with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64) < p * (1u64 << 53) as f64
+    }
+}
+
+/// Parameters for [`Shape::generate`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The number of nodes, at least 1.
+    pub nodes: usize,
+    /// The probability of each node depending on each earlier node.
+    pub edge_density: f64,
+    /// The relative frequency of each node kind, by [`Var`], [`Thunk`], [`Memo`] and [`Buffer`].
+    pub weights: [u32; 4],
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            nodes: 12,
+            edge_density: 0.3,
+            weights: [1, 1, 1, 1],
+        }
+    }
+}
+
+/// The arithmetic a non-[`Var`] node applies to its inputs, in input order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// The sum of the inputs.
+    Sum,
+    /// The greatest input.
+    Max,
+    /// The least input.
+    Min,
+    /// The first input minus the rest.
+    Difference,
+    /// The inputs folded as `acc * 3 + input`.
+    Mix,
+}
+
+impl Op {
+    const ALL: [Op; 5] = [Op::Sum, Op::Max, Op::Min, Op::Difference, Op::Mix];
+
+    /// Applies the operation to `inputs`, wrapping on overflow. Empty inputs produce 0.
+    pub fn apply(self, inputs: &[i64]) -> i64 {
+        let mut rest = inputs.iter().copied();
+        let Some(first) = rest.next() else {
+            return 0;
+        };
+        match self {
+            Op::Sum => rest.fold(first, i64::wrapping_add),
+            Op::Max => rest.fold(first, i64::max),
+            Op::Min => rest.fold(first, i64::min),
+            Op::Difference => rest.fold(first, i64::wrapping_sub),
+            Op::Mix => rest.fold(first, |acc, input| acc.wrapping_mul(3).wrapping_add(input)),
+        }
+    }
+}
+
+/// A node of a [`Shape`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeNode {
+    /// The node's kind.
+    pub kind: NodeKind,
+    /// The positions of the node's inputs in the [`Shape`], all before the node's own. Empty for
+    /// [`Var`]s.
+    pub inputs: Vec<usize>,
+    /// What the node computes from its inputs. Ignored for [`Var`]s.
+    pub op: Op,
+}
+
+/// A random DAG of nodes, each depending only on earlier ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape {
+    /// The nodes, in topological order.
+    pub nodes: Vec<ShapeNode>,
+}
+
+impl Shape {
+    /// Generates a [`Shape`] as described by `config`. The first node is always a [`Var`], and
+    /// every other node depends on at least one earlier node.
+    pub fn generate(config: &Config, rng: &mut Rng) -> Self {
+        const KINDS: [NodeKind; 4] = [
+            NodeKind::Var,
+            NodeKind::Thunk,
+            NodeKind::Memo,
+            NodeKind::Buffer,
+        ];
+        let total: u64 = config.weights.iter().map(|&weight| u64::from(weight)).sum();
+        let mut nodes = Vec::with_capacity(config.nodes);
+        for position in 0..config.nodes.max(1) {
+            let kind = if position == 0 || total == 0 {
+                NodeKind::Var
+            } else {
+                let mut pick = rng.below(total);
+                let mut kinds = KINDS.iter().zip(config.weights);
+                loop {
+                    let (&kind, weight) = kinds.next().expect("pick is below the total weight");
+                    match pick.checked_sub(u64::from(weight)) {
+                        Some(rest) => pick = rest,
+                        None => break kind,
+                    }
+                }
+            };
+            let mut inputs = Vec::new();
+            if kind != NodeKind::Var {
+                inputs = (0..position)
+                    .filter(|_| rng.chance(config.edge_density))
+                    .collect();
+                if inputs.is_empty() {
+                    inputs.push(rng.below(position as u64) as usize);
+                }
+            }
+            let op = Op::ALL[rng.below(Op::ALL.len() as u64) as usize];
+            nodes.push(ShapeNode { kind, inputs, op });
+        }
+        Self { nodes }
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the [`Shape`] has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the positions of the [`Var`]s.
+    pub fn vars(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&position| self.nodes[position].kind == NodeKind::Var)
+            .collect()
+    }
+
+    /// Creates the [`Shape`]'s nodes in `dcg`, with every [`Var`] starting at 0.
+    pub fn build(&self, dcg: &Dcg) -> Built {
+        let mut handles: Vec<Handle> = Vec::with_capacity(self.len());
+        for node in &self.nodes {
+            let inputs: Vec<_> = node.inputs.iter().map(|&i| handles[i].clone()).collect();
+            let op = node.op;
+            let compute = {
+                let inputs = inputs.clone();
+                move || op.apply(&inputs.iter().map(Incremental::read).collect::<Vec<_>>())
+            };
+            handles.push(match node.kind {
+                NodeKind::Var => Handle::Var(dcg.var(0)),
+                NodeKind::Thunk => Handle::Thunk(dcg.thunk(&inputs, compute)),
+                NodeKind::Memo => Handle::Memo(dcg.memo(inputs, compute)),
+                NodeKind::Buffer => Handle::Buffer(dcg.buffer(&inputs, compute)),
+            });
+        }
+        Built { handles }
+    }
+
+    /// Computes every node's value from scratch, given the value of each [`Var`] in position
+    /// order, as returned by [`Built::var_values`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if fewer values are given than there are [`Var`]s.
+    pub fn evaluate(&self, var_values: &[i64]) -> Vec<i64> {
+        let mut var_values = var_values.iter();
+        let mut values: Vec<i64> = Vec::with_capacity(self.len());
+        for node in &self.nodes {
+            let value = match node.kind {
+                NodeKind::Var => *var_values.next().expect("a value for every Var"),
+                _ => {
+                    let inputs: Vec<_> = node.inputs.iter().map(|&i| values[i]).collect();
+                    node.op.apply(&inputs)
+                }
+            };
+            values.push(value);
+        }
+        values
+    }
+}
+
+/// A handle to a node of a built [`Shape`].
+#[derive(Clone)]
+enum Handle {
+    Var(Var<i64>),
+    Thunk(Thunk<i64>),
+    Memo(Memo<Vec<i64>, i64>),
+    Buffer(Buffer<i64>),
+}
+
+impl Incremental for Handle {
+    type Output = i64;
+
+    fn read(&self) -> i64 {
+        match self {
+            Handle::Var(var) => var.read(),
+            Handle::Thunk(thunk) => thunk.read(),
+            Handle::Memo(memo) => memo.read(),
+            Handle::Buffer(buffer) => buffer.read(),
+        }
+    }
+
+    fn latest(&self) -> i64 {
+        match self {
+            Handle::Var(var) => var.latest(),
+            Handle::Thunk(thunk) => thunk.latest(),
+            Handle::Memo(memo) => memo.latest(),
+            Handle::Buffer(buffer) => buffer.latest(),
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        match self {
+            Handle::Var(var) => var.is_dirty(),
+            Handle::Thunk(thunk) => thunk.is_dirty(),
+            Handle::Memo(memo) => memo.is_dirty(),
+            Handle::Buffer(buffer) => buffer.is_dirty(),
+        }
+    }
+
+    fn nodes(&self) -> Vec<&Node> {
+        match self {
+            Handle::Var(var) => var.nodes(),
+            Handle::Thunk(thunk) => thunk.nodes(),
+            Handle::Memo(memo) => memo.nodes(),
+            Handle::Buffer(buffer) => buffer.nodes(),
+        }
+    }
+}
+
+/// The nodes created by [`Shape::build`], addressed by their position in the [`Shape`].
+pub struct Built {
+    handles: Vec<Handle>,
+}
+
+impl Built {
+    /// [`read`](Incremental::read)s the node at `position`.
+    pub fn read(&self, position: usize) -> i64 {
+        self.handles[position].read()
+    }
+
+    /// Writes `value` into the [`Var`] at `position`, returning its previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node at `position` is not a [`Var`].
+    pub fn write(&self, position: usize, value: i64) -> i64 {
+        match &self.handles[position] {
+            Handle::Var(var) => var.write(value),
+            _ => panic!("node {} is not a Var", position),
+        }
+    }
+
+    /// Returns the node at `position`.
+    pub fn node(&self, position: usize) -> &Node {
+        self.handles[position].nodes()[0]
+    }
+
+    /// Returns the value of every [`Var`], in position order, without reading them.
+    pub fn var_values(&self) -> Vec<i64> {
+        self.handles
+            .iter()
+            .filter_map(|handle| match handle {
+                Handle::Var(var) => Some(var.latest()),
+                _ => None,
+            })
+            .collect()
+    }
+}
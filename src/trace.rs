@@ -0,0 +1,79 @@
+//! Capturing the evaluations performed by a piece of code with [`Dcg::trace`].
+
+use petgraph::graph::NodeIndex;
+
+use crate::{Dcg, Inner};
+
+/// Something that happened to a node during a [`Dcg::trace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceEvent {
+    /// The node executed its closure.
+    Evaluated(NodeIndex),
+    /// A [`Memo`](crate::Memo) or [`Buffer`](crate::Buffer) served its value from its cache.
+    CacheHit(NodeIndex),
+    /// The dirty node was cleaned.
+    Cleaned(NodeIndex),
+}
+
+/// Ends a [`Dcg::trace`], even if the traced closure unwinds.
+struct TraceGuard<'a>(&'a Inner);
+
+impl Drop for TraceGuard<'_> {
+    fn drop(&mut self) {
+        self.0.traces.borrow_mut().pop();
+    }
+}
+
+impl Inner {
+    /// Records `event` in the innermost [`Dcg::trace`], if any.
+    pub(crate) fn trace(&self, event: TraceEvent) {
+        if let Some(trace) = self.traces.borrow_mut().last_mut() {
+            trace.push(event);
+        }
+    }
+}
+
+impl Dcg {
+    /// Calls `f`, returning its result along with every [`TraceEvent`] in the [`Dcg`] during the
+    /// call, in order.
+    ///
+    /// Traces nest: events during an inner `trace` are only returned by it, not by outer ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental, trace::TraceEvent};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let (a_idx, b_idx) = (a.node().index(), b.node().index());
+    ///
+    /// assert_eq!(
+    ///     dcg.trace(|| b.read()),
+    ///     (
+    ///         2,
+    ///         vec![
+    ///             TraceEvent::Cleaned(a_idx),
+    ///             TraceEvent::Evaluated(b_idx),
+    ///             TraceEvent::Cleaned(b_idx),
+    ///         ]
+    ///     )
+    /// );
+    /// assert_eq!(dcg.trace(|| b.read()), (2, vec![TraceEvent::CacheHit(b_idx)]));
+    /// ```
+    pub fn trace<R>(&self, f: impl FnOnce() -> R) -> (R, Vec<TraceEvent>) {
+        self.inner.traces.borrow_mut().push(Vec::new());
+        let guard = TraceGuard(&self.inner);
+        let result = f();
+        let events = self
+            .inner
+            .traces
+            .borrow_mut()
+            .last_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        drop(guard);
+        (result, events)
+    }
+}
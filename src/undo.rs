@@ -0,0 +1,362 @@
+//! Undoing and redoing [`Var`](crate::Var) writes with [`Dcg::undo`] and [`Dcg::redo`], and
+//! restoring [`CellSnapshot`]s of their values.
+//!
+//! [`Var`](crate::Var)s can have different types, so their values are recorded type-erased, and
+//! only for [`Var`](crate::Var)s registered with `undoable`. Undoing or restoring writes the
+//! recorded values back, dirtying dependents as any other write.
+
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use petgraph::graph::NodeIndex;
+
+use crate::{Dcg, DcgError, Node, RawVar, ALL_CHANGES};
+
+/// Copies and restores a [`Var`](crate::Var)'s value, without keeping the
+/// [`Var`](crate::Var) alive.
+pub(crate) trait Undo {
+    fn value(&self) -> Option<Box<dyn Any>>;
+
+    /// Writes `value` into the [`Var`](crate::Var), returning whether it changed, or [`None`] if
+    /// the [`Var`](crate::Var) has been dropped.
+    fn restore(&self, value: &dyn Any) -> Option<bool>;
+
+    /// Returns [`ValidationFailed`](DcgError::ValidationFailed) if one of the
+    /// [`Var`](crate::Var)'s validators rejects `value`.
+    fn validate(&self, value: &dyn Any) -> Result<(), DcgError>;
+}
+
+impl<T: PartialEq + Clone + 'static> Undo for Weak<RawVar<T>> {
+    fn value(&self) -> Option<Box<dyn Any>> {
+        let var = self.upgrade()?;
+        let value = var.value.borrow().clone();
+        Some(Box::new(value))
+    }
+
+    fn restore(&self, value: &dyn Any) -> Option<bool> {
+        let var = self.upgrade()?;
+        let value = value.downcast_ref::<T>()?;
+        var.node
+            .check_mutable()
+            .unwrap_or_else(|err| panic!("{}", err));
+        Some(var.write_unvalidated(value.clone(), ALL_CHANGES).changed)
+    }
+
+    fn validate(&self, value: &dyn Any) -> Result<(), DcgError> {
+        match (self.upgrade(), value.downcast_ref::<T>()) {
+            (Some(var), Some(value)) => var.validate(value),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A [`Var`](crate::Var)'s value before and after a write.
+pub(crate) struct Edit {
+    idx: NodeIndex,
+    before: Box<dyn Any>,
+    after: Box<dyn Any>,
+}
+
+/// The undo and redo stacks of a [`Dcg`], each entry holding the [`Edit`]s undone in one step.
+#[derive(Default)]
+pub(crate) struct UndoLog {
+    capacity: usize,
+    undo: VecDeque<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    /// The step being built by [`Dcg::group_edits`], and how many calls are nested.
+    group: Option<(Vec<Edit>, usize)>,
+    /// Set while undoing or redoing, so the writes aren't recorded.
+    replaying: bool,
+    vars: HashMap<NodeIndex, Rc<dyn Undo>>,
+}
+
+impl UndoLog {
+    /// Stops recording writes to the removed node at `idx`.
+    pub(crate) fn forget(&mut self, idx: NodeIndex) {
+        self.vars.remove(&idx);
+    }
+
+    fn push(&mut self, step: Vec<Edit>) {
+        if step.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(step);
+    }
+}
+
+impl Node {
+    /// Returns a copy of the node's value if its writes are being recorded, to be passed to
+    /// [`after_edit`](Node::after_edit) once written.
+    pub(crate) fn before_edit(&self) -> Option<Box<dyn Any>> {
+        let var = {
+            let log = self.inner.undo.borrow();
+            if log.capacity == 0 || log.replaying {
+                return None;
+            }
+            log.vars.get(&self.idx)?.clone()
+        };
+        var.value()
+    }
+
+    /// Returns the write of the node, given its value from [`before_edit`](Node::before_edit), to
+    /// be passed to [`record_edit`](Node::record_edit) once the write can no longer fail.
+    pub(crate) fn after_edit(&self, before: Option<Box<dyn Any>>) -> Option<Edit> {
+        let before = before?;
+        let var = self.inner.undo.borrow().vars.get(&self.idx).cloned();
+        let after = var.and_then(|var| var.value())?;
+        Some(Edit {
+            idx: self.idx,
+            before,
+            after,
+        })
+    }
+
+    /// Records a write of the node from [`after_edit`](Node::after_edit).
+    pub(crate) fn record_edit(&self, edit: Option<Edit>) {
+        let Some(edit) = edit else {
+            return;
+        };
+        let mut log = self.inner.undo.borrow_mut();
+        match &mut log.group {
+            Some((step, _)) => step.push(edit),
+            None => log.push(vec![edit]),
+        }
+    }
+}
+
+/// Ends a [`Dcg::group_edits`], even if it unwinds.
+struct GroupGuard<'a>(&'a Dcg);
+
+impl Drop for GroupGuard<'_> {
+    fn drop(&mut self) {
+        let mut log = self.0.inner.undo.borrow_mut();
+        if let Some((step, depth)) = log.group.take() {
+            if depth > 1 {
+                log.group = Some((step, depth - 1));
+            } else {
+                log.push(step);
+            }
+        }
+    }
+}
+
+impl Dcg {
+    /// Starts recording writes to [`Var`](crate::Var)s registered with
+    /// [`undoable`](RawVar::undoable), keeping the last `capacity` steps to be undone by
+    /// [`undo`](Dcg::undo).
+    ///
+    /// Recording is disabled by default, and disabled again by a `capacity` of 0, which also
+    /// discards the recorded steps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// a.undoable();
+    /// dcg.enable_history(16);
+    ///
+    /// a.write(2);
+    /// a.write(3);
+    /// assert_eq!(dcg.undo(), Some(vec![a.node().index()]));
+    /// assert_eq!(a.read(), 2);
+    /// assert_eq!(dcg.redo(), Some(vec![a.node().index()]));
+    /// assert_eq!(a.read(), 3);
+    /// assert_eq!(dcg.redo(), None);
+    /// ```
+    pub fn enable_history(&self, capacity: usize) {
+        let mut log = self.inner.undo.borrow_mut();
+        log.capacity = capacity;
+        if capacity == 0 {
+            log.undo.clear();
+            log.redo.clear();
+        }
+        while log.undo.len() > capacity {
+            log.undo.pop_front();
+        }
+    }
+
+    /// Calls `f`, recording every write it makes as a single step, undone and redone together.
+    ///
+    /// Nested calls are part of the outermost call's step.
+    pub fn group_edits<R>(&self, f: impl FnOnce() -> R) -> R {
+        {
+            let mut log = self.inner.undo.borrow_mut();
+            let depth = log.group.as_ref().map_or(0, |(_, depth)| *depth);
+            let step = log.group.take().map(|(step, _)| step).unwrap_or_default();
+            log.group = Some((step, depth + 1));
+        }
+        let _guard = GroupGuard(self);
+        f()
+    }
+
+    /// Writes back the values from before the most recent recorded step, returning the indices
+    /// of the [`Var`](crate::Var)s that changed, or [`None`] if there is nothing to undo.
+    ///
+    /// New writes after an undo discard the steps that could be [redone](Dcg::redo).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated, as [`write`](RawVar::write) does.
+    pub fn undo(&self) -> Option<Vec<NodeIndex>> {
+        let step = self.inner.undo.borrow_mut().undo.pop_back()?;
+        let changed = self.replay(step.iter().rev().map(|edit| (edit.idx, &*edit.before)));
+        self.inner.undo.borrow_mut().redo.push(step);
+        Some(changed)
+    }
+
+    /// Writes back the values undone by the most recent [`undo`](Dcg::undo), returning the
+    /// indices of the [`Var`](crate::Var)s that changed, or [`None`] if there is nothing to redo.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated, as [`write`](RawVar::write) does.
+    pub fn redo(&self) -> Option<Vec<NodeIndex>> {
+        let step = self.inner.undo.borrow_mut().redo.pop()?;
+        let changed = self.replay(step.iter().map(|edit| (edit.idx, &*edit.after)));
+        self.inner.undo.borrow_mut().undo.push_back(step);
+        Some(changed)
+    }
+
+    /// Restores each value without recording the writes, returning the indices of the
+    /// [`Var`](crate::Var)s that changed.
+    fn replay<'a>(&self, values: impl Iterator<Item = (NodeIndex, &'a dyn Any)>) -> Vec<NodeIndex> {
+        self.inner.undo.borrow_mut().replaying = true;
+        let mut changed = Vec::new();
+        for (idx, value) in values {
+            let var = self.inner.undo.borrow().vars.get(&idx).cloned();
+            if var.and_then(|var| var.restore(value)) == Some(true) && !changed.contains(&idx) {
+                changed.push(idx);
+            }
+        }
+        self.inner.undo.borrow_mut().replaying = false;
+        changed
+    }
+}
+
+impl<T: PartialEq + Clone + 'static> RawVar<T> {
+    /// Records the [`Var`](crate::Var)'s writes while the [`Dcg`]'s history is
+    /// [enabled](Dcg::enable_history), so they can be [undone](Dcg::undo), and includes its value
+    /// in [`Dcg::save_cells`].
+    pub fn undoable(self: &Rc<Self>) {
+        self.node
+            .inner
+            .undo
+            .borrow_mut()
+            .vars
+            .insert(self.node.idx, Rc::new(Rc::downgrade(self)));
+    }
+}
+
+/// The values of a [`Dcg`]'s undoable [`Var`](crate::Var)s, as returned by [`Dcg::save_cells`].
+pub struct CellSnapshot {
+    values: HashMap<NodeIndex, Box<dyn Any>>,
+}
+
+impl CellSnapshot {
+    /// Returns the indices of the saved [`Var`](crate::Var)s, in index order.
+    pub fn cells(&self) -> Vec<NodeIndex> {
+        let mut cells: Vec<_> = self.values.keys().copied().collect();
+        cells.sort();
+        cells
+    }
+
+    /// Returns the value saved for the [`Var`](crate::Var) at `idx`, or [`None`] if it wasn't
+    /// saved or isn't a `T`.
+    pub fn get<T: 'static>(&self, idx: NodeIndex) -> Option<&T> {
+        self.values.get(&idx)?.downcast_ref()
+    }
+}
+
+impl fmt::Debug for CellSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CellSnapshot")
+            .field("cells", &self.cells())
+            .finish()
+    }
+}
+
+impl Dcg {
+    /// Saves the current value of every [`Var`](crate::Var) registered with
+    /// [`undoable`](RawVar::undoable), to be written back by [`restore_cells`](Dcg::restore_cells).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// a.undoable();
+    ///
+    /// let snapshot = dcg.save_cells();
+    /// a.write(2);
+    /// let b = dcg.var(1);
+    /// b.undoable();
+    /// assert_eq!(dcg.restore_cells(&snapshot), Ok(Vec::new()));
+    /// assert_eq!(a.read(), 1);
+    /// assert_eq!(snapshot.cells(), vec![a.node().index()]);
+    /// ```
+    pub fn save_cells(&self) -> CellSnapshot {
+        let vars: Vec<_> = self
+            .inner
+            .undo
+            .borrow()
+            .vars
+            .iter()
+            .map(|(&idx, var)| (idx, var.clone()))
+            .collect();
+        let values = vars
+            .into_iter()
+            .filter_map(|(idx, var)| Some((idx, var.value()?)))
+            .collect();
+        CellSnapshot { values }
+    }
+
+    /// Writes the values in `snapshot` back into their [`Var`](crate::Var)s, returning the
+    /// indices of those that have since been dropped.
+    ///
+    /// As with [`write`](RawVar::write), [`Var`](crate::Var)s whose value is unchanged and their
+    /// dependents stay clean. [`Var`](crate::Var)s created after the snapshot are left untouched.
+    /// The writes are recorded as a single [undoable](Dcg::undo) step.
+    ///
+    /// Every value is checked by its [`Var`](crate::Var)'s [validators](RawVar::add_validator)
+    /// before any is written, so if one is rejected, nothing is restored and
+    /// [`ValidationFailed`](DcgError::ValidationFailed) is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while a node is being evaluated, as [`write`](RawVar::write) does.
+    pub fn restore_cells(&self, snapshot: &CellSnapshot) -> Result<Vec<NodeIndex>, DcgError> {
+        let vars: Vec<_> = snapshot
+            .cells()
+            .into_iter()
+            .map(|idx| (idx, self.inner.undo.borrow().vars.get(&idx).cloned()))
+            .collect();
+        for (idx, var) in &vars {
+            if let Some(var) = var {
+                var.validate(&*snapshot.values[idx])?;
+            }
+        }
+        Ok(self.group_edits(|| {
+            vars.into_iter()
+                .filter(|(idx, var)| {
+                    var.as_ref()
+                        .and_then(|var| var.restore(&*snapshot.values[idx]))
+                        .is_none()
+                })
+                .map(|(idx, _)| idx)
+                .collect()
+        }))
+    }
+}
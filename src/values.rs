@@ -0,0 +1,79 @@
+//! Visiting the values of every [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer) of a type with
+//! [`Dcg::for_each_value`] and [`Dcg::values`].
+
+use petgraph::{algo::toposort, graph::NodeIndex};
+
+use crate::{verify::PlaceholderGuard, Dcg, NodeKind};
+
+impl Dcg {
+    /// Reads every [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer) whose value is a `T`, in
+    /// topological order, passing each one's index, kind and value to `f`.
+    ///
+    /// Dependencies are visited before their dependents, so each node is evaluated with its
+    /// dependencies already up to date, and a dirty node's cache is populated as it is visited.
+    /// A [`Memo`](crate::Memo) is read with its current arguments. Nodes of other types are
+    /// evaluated all the same, so every [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer) is
+    /// clean afterwards. [`Var`](crate::Var)s and [`Thunk`](crate::Thunk)s hold no cached value,
+    /// so they aren't visited.
+    ///
+    /// No borrows of the [`Dcg`] are held while `f` runs, so it may inspect the [`Dcg`], for
+    /// example through a [`reader`](Dcg::reader), but it must not mutate it: writes from `f`
+    /// panic as they would during an evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, memo};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(2);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let c = memo!(dcg, b => b * 10);
+    /// let label = buffer!(dcg, c => format!("{} units", c));
+    ///
+    /// let mut seen = Vec::new();
+    /// dcg.for_each_value(|idx, _, value: &i32| seen.push((idx, *value)));
+    /// assert_eq!(seen, vec![(b.node().index(), 3), (c.node().index(), 30)]);
+    /// assert_eq!(label.last_computed().as_deref(), Some("30 units"));
+    /// ```
+    pub fn for_each_value<T: 'static>(&self, mut f: impl FnMut(NodeIndex, NodeKind, &T)) {
+        let order = {
+            let graph = self.inner.graph.borrow();
+            toposort(&*graph, None).expect("a Dcg's dependencies are acyclic")
+        };
+        for idx in order {
+            let Some(refresher) = self.inner.refreshers.borrow().get(&idx).cloned() else {
+                continue;
+            };
+            let kind = self.inner.kinds.borrow()[&idx];
+            refresher.visit(&mut |value| {
+                if let Some(value) = value.downcast_ref::<T>() {
+                    let _guard = PlaceholderGuard::push(&self.inner, idx);
+                    f(idx, kind, value);
+                }
+            });
+        }
+    }
+
+    /// Returns the index and value of every [`Memo`](crate::Memo) and [`Buffer`](crate::Buffer)
+    /// whose value is a `T`, reading them in topological order as
+    /// [`for_each_value`](Dcg::for_each_value) does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(2);
+    /// let b = buffer!(dcg, a => a * 2);
+    ///
+    /// assert_eq!(dcg.values::<i32>(), vec![(b.node().index(), 4)]);
+    /// assert!(dcg.values::<String>().is_empty());
+    /// ```
+    pub fn values<T: Clone + 'static>(&self) -> Vec<(NodeIndex, T)> {
+        let mut values = Vec::new();
+        self.for_each_value(|idx, _, value: &T| values.push((idx, value.clone())));
+        values
+    }
+}
@@ -0,0 +1,295 @@
+//! Deciding whether nodes are dirty under each [`VerificationStrategy`].
+//!
+//! Under [`DirtyFlags`](VerificationStrategy::DirtyFlags), a node is dirty if its flag, the
+//! weight of its node in the graph, is set. Writes set the flags of every dependent.
+//!
+//! Under [`Versions`](VerificationStrategy::Versions), writes only set the written node's flag
+//! and bump its version. Cleaning a node records the [versions](crate::Dcg::version) of its
+//! inputs: its dependencies, with [`Thunk`](crate::Thunk)s, which have no versions as they
+//! recompute whenever read, replaced by their own inputs. A node is dirty if its flag is set, or
+//! if any input's version differs from the recorded one or the input is itself dirty. Before
+//! recomputing, a [`Buffer`](crate::Buffer) brings its dirty [`Buffer`](crate::Buffer)
+//! dependencies up to date, so a dependency recomputing an equal value, as detected by
+//! [`compare_values`](crate::RawBuffer::compare_values), doesn't dirty it.
+//! Each node's answer is kept until the next write or change to the graph, or until one of its
+//! inputs is cleaned or recomputed, so reading a node checks each of its ancestors once.
+//!
+//! The recorded versions are kept under either strategy, as the node's
+//! [provenance](crate::Dcg::provenance).
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::{
+    graph::NodeIndex,
+    Direction::{Incoming, Outgoing},
+};
+
+use crate::{Dcg, Evaluation, Inner, Node, NodeKind, VerificationStrategy};
+
+/// A placeholder evaluation of a node, pushed while [`Node::verify_dependencies`] refreshes its
+/// dependencies or [`Dcg::for_each_value`] runs its callback, so writes are rejected as during an
+/// evaluation. Popped on drop, even if unwinding.
+pub(crate) struct PlaceholderGuard<'a>(&'a Inner);
+
+impl<'a> PlaceholderGuard<'a> {
+    pub(crate) fn push(inner: &'a Inner, node: NodeIndex) -> Self {
+        inner.evaluating.borrow_mut().push(Evaluation {
+            node,
+            reads: Default::default(),
+        });
+        PlaceholderGuard(inner)
+    }
+}
+
+impl Drop for PlaceholderGuard<'_> {
+    fn drop(&mut self) {
+        self.0.evaluating.borrow_mut().pop();
+    }
+}
+
+impl Dcg {
+    /// Creates an empty [`Dcg`] deciding which nodes are dirty with `strategy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, VerificationStrategy, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::with_strategy(VerificationStrategy::Versions);
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a % 2);
+    /// let c = buffer!(dcg, b => b + 1);
+    /// b.compare_values();
+    ///
+    /// assert_eq!(c.read(), 2);
+    /// a.write(3);
+    /// assert!(c.is_dirty());
+    /// assert_eq!(b.read(), 1);
+    /// // `b` recomputed an equal value, so `c` is up to date.
+    /// assert!(c.is_clean());
+    /// ```
+    pub fn with_strategy(strategy: VerificationStrategy) -> Self {
+        let dcg = Self::default();
+        dcg.inner.strategy.set(strategy);
+        dcg
+    }
+
+    /// Returns the [`VerificationStrategy`] the [`Dcg`] was created with.
+    pub fn strategy(&self) -> VerificationStrategy {
+        self.inner.strategy.get()
+    }
+
+    /// Returns the inputs `node` was last computed or verified from, with the
+    /// [version](Dcg::version) of each at the time, sorted by index.
+    ///
+    /// A node's inputs are its dependencies, with each [`Thunk`](crate::Thunk) replaced by its
+    /// own inputs. The pairs are recorded whenever the node is cleaned, so they stay fixed across
+    /// cache hits. Nodes that have never been evaluated, [`Var`](crate::Var)s and
+    /// [`Thunk`](crate::Thunk)s have none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let price = dcg.var(10);
+    /// let qty = dcg.var(3);
+    /// let total = buffer!(dcg, (price, qty) => price * qty);
+    /// assert!(dcg.provenance(total.node()).is_empty());
+    ///
+    /// price.write(12);
+    /// total.read();
+    /// assert_eq!(
+    ///     dcg.provenance(total.node()),
+    ///     vec![(price.node().index(), 1), (qty.node().index(), 0)]
+    /// );
+    /// ```
+    pub fn provenance(&self, node: &Node) -> Vec<(NodeIndex, u64)> {
+        let mut provenance: Vec<_> = self
+            .inner
+            .verified
+            .borrow()
+            .get(&node.idx)
+            .map(|verified| {
+                verified
+                    .iter()
+                    .map(|(&idx, &version)| (idx, version))
+                    .collect()
+            })
+            .unwrap_or_default();
+        provenance.sort_unstable();
+        provenance
+    }
+}
+
+impl Inner {
+    /// Returns `true` if the node at `idx` is dirty under the [`Dcg`]'s strategy.
+    pub(crate) fn is_dirty(&self, idx: NodeIndex) -> bool {
+        match self.strategy.get() {
+            VerificationStrategy::DirtyFlags => self.graph.borrow()[idx],
+            VerificationStrategy::Versions => self.is_outdated(idx),
+        }
+    }
+
+    /// Returns `true` if the node at `idx` is dirty under
+    /// [`Versions`](VerificationStrategy::Versions).
+    ///
+    /// Each node is checked at most once per revision: its inputs are checked first, walking
+    /// iteratively through those not yet checked, so only its direct inputs' versions and answers
+    /// need comparing.
+    fn is_outdated(&self, idx: NodeIndex) -> bool {
+        if let Some(outdated) = self.checked_outdated(idx) {
+            return outdated;
+        }
+        let mut stack = vec![(idx, None)];
+        while let Some((node, inputs)) = stack.pop() {
+            let Some(inputs) = inputs else {
+                if self.checked_outdated(node).is_some() {
+                    continue;
+                }
+                let inputs: Vec<_> = self.inputs(node).into_iter().collect();
+                let unchecked: Vec<_> = inputs
+                    .iter()
+                    .copied()
+                    .filter(|&input| self.checked_outdated(input).is_none())
+                    .collect();
+                stack.push((node, Some(inputs)));
+                stack.extend(unchecked.into_iter().map(|input| (input, None)));
+                continue;
+            };
+            #[cfg(test)]
+            self.version_checks.set(self.version_checks.get() + 1);
+            let outdated = {
+                let verified = self.verified.borrow();
+                let verified = verified.get(&node);
+                // Every input is checked, even past an outdated one, so a node is only ever
+                // checked after all its inputs are.
+                let mut outdated = self.graph.borrow()[node];
+                for input in inputs {
+                    let recorded = verified.and_then(|verified| verified.get(&input)).copied();
+                    outdated |= recorded != Some(self.version_of(input))
+                        || self.checked_outdated(input) == Some(true);
+                }
+                outdated
+            };
+            self.checked
+                .borrow_mut()
+                .insert(node, (self.revision.get(), outdated));
+        }
+        self.checked_outdated(idx)
+            .expect("the node is checked after its inputs")
+    }
+
+    /// Returns whether the node at `idx` was outdated, if it has been checked this revision.
+    fn checked_outdated(&self, idx: NodeIndex) -> Option<bool> {
+        match self.checked.borrow().get(&idx) {
+            Some(&(revision, outdated)) if revision == self.revision.get() => Some(outdated),
+            _ => None,
+        }
+    }
+
+    /// Discards every node's [`is_outdated`](Inner::is_outdated) answer, as a write or change to
+    /// the graph may have outdated any of them.
+    pub(crate) fn new_revision(&self) {
+        self.revision.set(self.revision.get() + 1);
+    }
+
+    /// Discards the [`is_outdated`](Inner::is_outdated) answers of the node at `idx` and those
+    /// whose inputs include it, transitively, as it was just cleaned or its version bumped.
+    ///
+    /// As nodes are only checked after their inputs, the walk stops at unchecked nodes other
+    /// than [`Thunk`](crate::Thunk)s, which aren't inputs themselves.
+    pub(crate) fn forget_checked(&self, idx: NodeIndex) {
+        if self.strategy.get() != VerificationStrategy::Versions {
+            return;
+        }
+        let graph = self.graph.borrow();
+        let kinds = self.kinds.borrow();
+        let mut checked = self.checked.borrow_mut();
+        checked.remove(&idx);
+        let mut thunks = HashSet::new();
+        let mut stack: Vec<_> = graph.neighbors_directed(idx, Outgoing).collect();
+        while let Some(dependent) = stack.pop() {
+            if checked.remove(&dependent).is_some()
+                || (kinds[&dependent] == NodeKind::Thunk && thunks.insert(dependent))
+            {
+                stack.extend(graph.neighbors_directed(dependent, Outgoing));
+            }
+        }
+    }
+
+    /// Returns the dependencies of the node at `idx`, with each [`Thunk`](crate::Thunk)
+    /// replaced by its own inputs.
+    fn inputs(&self, idx: NodeIndex) -> HashSet<NodeIndex> {
+        let graph = self.graph.borrow();
+        let kinds = self.kinds.borrow();
+        let mut inputs = HashSet::new();
+        let mut thunks = HashSet::new();
+        let mut stack: Vec<_> = graph.neighbors_directed(idx, Incoming).collect();
+        while let Some(dependency) = stack.pop() {
+            if kinds[&dependency] != NodeKind::Thunk {
+                inputs.insert(dependency);
+            } else if thunks.insert(dependency) {
+                stack.extend(graph.neighbors_directed(dependency, Incoming));
+            }
+        }
+        inputs
+    }
+
+    /// Returns the [version](Dcg::version) of the node at `idx`.
+    fn version_of(&self, idx: NodeIndex) -> u64 {
+        self.versions
+            .borrow()
+            .get(&idx)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Node {
+    /// Records the versions of the node's inputs as it is cleaned, to decide whether it is dirty
+    /// under [`Versions`](VerificationStrategy::Versions), and for [`Dcg::provenance`].
+    pub(crate) fn record_verified(&self) {
+        let dependencies: HashMap<_, _> = self
+            .inner
+            .inputs(self.idx)
+            .into_iter()
+            .map(|input| (input, self.inner.version_of(input)))
+            .collect();
+        self.inner
+            .verified
+            .borrow_mut()
+            .insert(self.idx, dependencies);
+        self.inner.forget_checked(self.idx);
+    }
+
+    /// Brings the node's dirty [`Buffer`](crate::Buffer) dependencies up to date under
+    /// [`Versions`](VerificationStrategy::Versions), so whether the node is dirty depends on
+    /// their new versions.
+    ///
+    /// [`Memo`](crate::Memo)s are left alone, as reading one evaluates its params even if it is
+    /// clean. The dependencies' reads aren't recorded against any node being evaluated.
+    pub(crate) fn verify_dependencies(&self) {
+        if self.inner.strategy.get() != VerificationStrategy::Versions || !self.is_dirty() {
+            return;
+        }
+        let dependencies: Vec<_> = self
+            .inner
+            .graph
+            .borrow()
+            .neighbors_directed(self.idx, Incoming)
+            .filter(|dependency| self.inner.kinds.borrow()[dependency] == NodeKind::Buffer)
+            .collect();
+        if dependencies.is_empty() {
+            return;
+        }
+        let _guard = PlaceholderGuard::push(&self.inner, self.idx);
+        let dcg = Dcg {
+            inner: self.inner.clone(),
+        };
+        for dependency in dependencies {
+            dcg.refresh(dependency);
+        }
+    }
+}
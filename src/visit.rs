@@ -0,0 +1,123 @@
+//! Running [`petgraph`] algorithms on a [`Dcg`] through [`DcgRef`].
+
+use std::cell::Ref;
+
+use petgraph::{
+    stable_graph::{EdgeIndex, EdgeReference, EdgeReferences, Neighbors, NodeIndex, NodeIndices},
+    visit::{
+        Data, GraphBase, IntoEdgeReferences, IntoNeighbors, IntoNeighborsDirected,
+        IntoNodeIdentifiers, NodeCount, NodeIndexable, Visitable,
+    },
+    Direction,
+};
+
+use crate::{Dcg, Graph};
+
+/// A read-only view of a [`Dcg`]'s topology implementing [`petgraph::visit`]'s traits, returned
+/// by [`Dcg::as_graph_ref`].
+///
+/// Algorithms take `&DcgRef`. Node weights are the nodes' dirty flags.
+///
+/// The view borrows the [`Dcg`]'s graph: creating, writing or reading nodes in the [`Dcg`] while
+/// it is held panics.
+pub struct DcgRef<'a> {
+    graph: Ref<'a, Graph>,
+}
+
+impl Dcg {
+    /// Returns a view of the [`Dcg`]'s topology for use with [`petgraph`]'s algorithms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer};
+    /// use petgraph::algo::toposort;
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    ///
+    /// let order = toposort(&dcg.as_graph_ref(), None).unwrap();
+    /// assert_eq!(order, vec![a.node().index(), b.node().index()]);
+    /// ```
+    pub fn as_graph_ref(&self) -> DcgRef<'_> {
+        DcgRef {
+            graph: self.inner.graph.borrow(),
+        }
+    }
+}
+
+impl GraphBase for DcgRef<'_> {
+    type EdgeId = EdgeIndex;
+    type NodeId = NodeIndex;
+}
+
+impl Data for DcgRef<'_> {
+    type NodeWeight = bool;
+    type EdgeWeight = ();
+}
+
+impl<'b> IntoNeighbors for &'b DcgRef<'_> {
+    type Neighbors = Neighbors<'b, ()>;
+
+    fn neighbors(self, a: NodeIndex) -> Self::Neighbors {
+        self.graph.neighbors(a)
+    }
+}
+
+impl<'b> IntoNeighborsDirected for &'b DcgRef<'_> {
+    type NeighborsDirected = Neighbors<'b, ()>;
+
+    fn neighbors_directed(self, n: NodeIndex, d: Direction) -> Self::NeighborsDirected {
+        self.graph.neighbors_directed(n, d)
+    }
+}
+
+impl<'b> IntoNodeIdentifiers for &'b DcgRef<'_> {
+    type NodeIdentifiers = NodeIndices<'b, bool>;
+
+    fn node_identifiers(self) -> Self::NodeIdentifiers {
+        self.graph.node_indices()
+    }
+}
+
+impl<'b> IntoEdgeReferences for &'b DcgRef<'_> {
+    type EdgeRef = EdgeReference<'b, ()>;
+    type EdgeReferences = EdgeReferences<'b, ()>;
+
+    fn edge_references(self) -> Self::EdgeReferences {
+        self.graph.edge_references()
+    }
+}
+
+impl Visitable for DcgRef<'_> {
+    type Map = <Graph as Visitable>::Map;
+
+    fn visit_map(&self) -> Self::Map {
+        self.graph.visit_map()
+    }
+
+    fn reset_map(&self, map: &mut Self::Map) {
+        self.graph.reset_map(map)
+    }
+}
+
+impl NodeIndexable for DcgRef<'_> {
+    fn node_bound(&self) -> usize {
+        self.graph.node_bound()
+    }
+
+    fn to_index(&self, a: NodeIndex) -> usize {
+        a.index()
+    }
+
+    fn from_index(&self, i: usize) -> NodeIndex {
+        NodeIndex::new(i)
+    }
+}
+
+impl NodeCount for DcgRef<'_> {
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+}
@@ -0,0 +1,155 @@
+//! Temporary [`Buffer`]s over existing nodes, created with [`Dcg::watch_expr`] and removed when
+//! their [`WatchGuard`] is dropped.
+
+use std::ops::Deref;
+
+use petgraph::{graph::NodeIndex, Direction::Outgoing};
+
+use crate::{incremental::Incremental, Buffer, Dcg, Event, Inner};
+
+impl Dcg {
+    /// Creates a dirty [`Buffer`] of the value returned by `f`, adding incoming dependency edges
+    /// from `params`, to be removed from the [`Dcg`] when the returned [`WatchGuard`] is dropped.
+    ///
+    /// This suits ad-hoc expressions, e.g. typed into a debugger, evaluated repeatedly for a
+    /// while and then discarded. Creating the watch adds a node like [`buffer`](Dcg::buffer)
+    /// does, leaving every existing index as it was.
+    ///
+    /// Dropping the watch may free its node's index for the next node created, so a
+    /// [`NodeIndex`] kept from the watch must not be used after it is dropped: it would name that
+    /// node instead. See [`Event::NodeRemoved`]. Handles, unlike indices, detect this; see
+    /// [`StaleHandle`](crate::DcgError::StaleHandle).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let b = buffer!(dcg, a => a + 1);
+    /// let watch = dcg.watch_expr(&(a.clone(), b.clone()), {
+    ///     let (a, b) = (a.clone(), b.clone());
+    ///     move || a.read() * b.read()
+    /// });
+    ///
+    /// assert_eq!(watch.read(), 2);
+    /// a.write(2);
+    /// assert_eq!(watch.read(), 6);
+    /// drop(watch);
+    /// assert_eq!((dcg.node_count(), dcg.edge_count()), (2, 1));
+    /// ```
+    pub fn watch_expr<P, F, T>(&self, params: &P, f: F) -> WatchGuard<'_, T>
+    where
+        P: Incremental,
+        F: Fn() -> T + 'static,
+        T: Clone + 'static,
+    {
+        WatchGuard {
+            dcg: self,
+            buffer: Some(self.buffer(params, f)),
+        }
+    }
+}
+
+/// A [`Buffer`] created by [`Dcg::watch_expr`], dereferencing to it.
+///
+/// Dropping the guard removes the [`Buffer`]'s node and its edges from the [`Dcg`], along with
+/// everything recorded about it, leaving every other node's index as it was. If the watch still
+/// has dependents or an evaluation in progress, the node is instead only detached from its
+/// dependencies, as by [`retire`](Dcg::retire), and stays in the [`Dcg`].
+///
+/// Once removed, the node's index is reused by the next node created, so any [`NodeIndex`]
+/// kept from the watch, e.g. by a subscriber or a snapshot, then aliases that node. Clones of
+/// the [`Buffer`] don't: reading them panics with
+/// [`StaleHandle`](crate::DcgError::StaleHandle).
+pub struct WatchGuard<'a, T> {
+    dcg: &'a Dcg,
+    buffer: Option<Buffer<T>>,
+}
+
+impl<T> Deref for WatchGuard<'_, T> {
+    type Target = Buffer<T>;
+
+    fn deref(&self) -> &Buffer<T> {
+        self.buffer
+            .as_ref()
+            .expect("a watch holds its buffer until dropped")
+    }
+}
+
+impl<T> Drop for WatchGuard<'_, T> {
+    fn drop(&mut self) {
+        let Some(buffer) = self.buffer.take() else {
+            return;
+        };
+        let idx = buffer.node().idx;
+        let removable = self.dcg.inner.evaluating.borrow().is_empty() && {
+            let graph = self.dcg.inner.graph.borrow();
+            graph.neighbors_directed(idx, Outgoing).next().is_none()
+        };
+        drop(buffer);
+        self.dcg.detach(idx);
+        if removable {
+            self.dcg.inner.graph.borrow_mut().remove_node(idx);
+            self.dcg.inner.forget(idx);
+            *self
+                .dcg
+                .inner
+                .generations
+                .borrow_mut()
+                .entry(idx)
+                .or_default() += 1;
+            self.dcg.inner.emit(Event::NodeRemoved { idx });
+        }
+    }
+}
+
+impl Inner {
+    /// Discards everything recorded about the node at `idx`, as it's being removed.
+    fn forget(&self, idx: NodeIndex) {
+        self.kinds.borrow_mut().remove(&idx);
+        self.names.borrow_mut().remove(&idx);
+        #[cfg(feature = "serde")]
+        self.persisted.borrow_mut().remove(&idx);
+        self.value_labels.borrow_mut().remove(&idx);
+        self.edge_labels
+            .borrow_mut()
+            .retain(|&(from, to), _| from != idx && to != idx);
+        self.weak_edges
+            .borrow_mut()
+            .retain(|&(from, to)| from != idx && to != idx);
+        for nodes in self.tags.borrow_mut().values_mut() {
+            nodes.remove(&idx);
+        }
+        self.tags.borrow_mut().retain(|_, nodes| !nodes.is_empty());
+        for set in [
+            &self.eager,
+            &self.cached,
+            &self.dynamic,
+            &self.frozen,
+            &self.unfinalized,
+            &self.pending,
+            &self.intercepted,
+            &self.skipped,
+        ] {
+            set.borrow_mut().remove(&idx);
+        }
+        self.verified.borrow_mut().remove(&idx);
+        self.errors.borrow_mut().remove(&idx);
+        self.sensitivities.borrow_mut().remove(&idx);
+        self.stability.borrow_mut().remove(&idx);
+        self.refreshers.borrow_mut().remove(&idx);
+        self.expiries.borrow_mut().remove(&idx);
+        self.recency.borrow_mut().forget(idx);
+        self.dirtied_hooks.borrow_mut().remove(&idx);
+        self.cache_stats.borrow_mut().remove(&idx);
+        self.costs.borrow_mut().remove(&idx);
+        #[cfg(feature = "timings")]
+        self.timings.borrow_mut().remove(&idx);
+        self.audit.borrow_mut().cleaned.remove(&idx);
+        self.last_reads.borrow_mut().remove(&idx);
+        self.versions.borrow_mut().remove(&idx);
+        self.undo.borrow_mut().forget(idx);
+    }
+}
@@ -0,0 +1,101 @@
+//! Weak dependencies, added with [`Dcg::add_weak_dependency`]: nodes read by a node without
+//! their changes dirtying it.
+
+use std::{hash::Hash, rc::Rc};
+
+use petgraph::graph::NodeIndex;
+
+use crate::{incremental::Incremental, Dcg, Memo, Node};
+
+impl Dcg {
+    /// Records that `node` reads `dependency` without depending on it, for example for logging.
+    ///
+    /// Writes to `dependency` don't dirty `node`, which keeps serving its cached value, and
+    /// `node` doesn't count towards `dependency`'s dependents when dirtying, sorting or cleaning
+    /// the [`Dcg`]. Exports show the dependency, drawn dashed with a hollow arrowhead in
+    /// [DOT](Dcg::to_dot). A [dynamic](Dcg::dynamic_buffer) `node` reading `dependency` doesn't
+    /// come to depend on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cachegrab::{Dcg, buffer, incremental::Incremental};
+    ///
+    /// let dcg = Dcg::default();
+    /// let a = dcg.var(1);
+    /// let verbose = dcg.var(false);
+    /// let b = dcg.buffer(&a, {
+    ///     let (a, verbose) = (a.clone(), verbose.clone());
+    ///     move || {
+    ///         if verbose.read() {
+    ///             println!("recomputing b");
+    ///         }
+    ///         a.read() + 1
+    ///     }
+    /// });
+    /// dcg.add_weak_dependency(b.node(), verbose.node());
+    ///
+    /// assert_eq!(b.read(), 2);
+    /// verbose.write(true);
+    /// assert!(b.is_clean());
+    /// assert_eq!(dcg.weak_dependencies(b.node()), vec![verbose.node().index()]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` or `dependency` belongs to another [`Dcg`].
+    pub fn add_weak_dependency(&self, node: &Node, dependency: &Node) {
+        assert!(
+            Rc::ptr_eq(&node.inner, &self.inner) && Rc::ptr_eq(&dependency.inner, &self.inner),
+            "weakly dependent nodes must belong to the Dcg"
+        );
+        self.inner
+            .weak_edges
+            .borrow_mut()
+            .insert((dependency.idx, node.idx));
+    }
+
+    /// Returns the nodes `node` [weakly depends](Dcg::add_weak_dependency) on, sorted.
+    pub fn weak_dependencies(&self, node: &Node) -> Vec<NodeIndex> {
+        let mut dependencies: Vec<_> = self
+            .inner
+            .weak_edges
+            .borrow()
+            .iter()
+            .filter(|&&(_, to)| to == node.idx)
+            .map(|&(from, _)| from)
+            .collect();
+        dependencies.sort();
+        dependencies
+    }
+
+    /// Creates a dirty [`Memo`] like [`memo`](Dcg::memo), which also
+    /// [weakly depends](Dcg::add_weak_dependency) on the nodes of `weak_params`.
+    ///
+    /// Only `params` key the [`Memo`]'s cache, so reading a different value from `weak_params`
+    /// in `f` doesn't cause a cache miss.
+    pub fn memo_with_weak<P, W, F, A, T>(&self, params: P, weak_params: &W, f: F) -> Memo<A, T>
+    where
+        P: Incremental<Output = A> + 'static,
+        W: Incremental,
+        A: Eq + Hash + 'static,
+        F: Fn() -> T + 'static,
+        T: Clone + 'static,
+    {
+        let memo = self.memo(params, f);
+        for dependency in weak_params.nodes() {
+            self.add_weak_dependency(memo.node(), dependency);
+        }
+        memo
+    }
+}
+
+impl Node {
+    /// Returns `true` if the node [weakly depends](Dcg::add_weak_dependency) on `dependency`.
+    pub(crate) fn weakly_depends_on(&self, dependency: NodeIndex) -> bool {
+        self.inner
+            .weak_edges
+            .borrow()
+            .contains(&(dependency, self.idx))
+    }
+}